@@ -13,3 +13,4 @@ pub const STREAM_COLORS: [&str; 33] = [
 // https://developer.gnome.org/hig-book/unstable/design-color.html.en
 pub const WARNING_COLOR: &str = "#D1940C";
 pub const ERROR_COLOR: &str = "#DF421E";
+pub const SUCCESS_COLOR: &str = "#4E9A06";
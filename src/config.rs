@@ -13,6 +13,8 @@ pub struct Config {
     pub prefer_dark_theme: bool,
     pub custom_tcpdump_buffer_size_kib: Option<usize>,
     pub tcpdump_use_pkexec_if_possible: bool,
+    // a folder of .proto files or a descriptor set, to decode gRPC messages
+    pub grpc_proto_path: Option<String>,
 }
 
 impl Config {
@@ -21,6 +23,7 @@ impl Config {
             prefer_dark_theme: false,
             custom_tcpdump_buffer_size_kib: Some(8192),
             tcpdump_use_pkexec_if_possible: true,
+            grpc_proto_path: None,
         }
     }
 
@@ -4,10 +4,11 @@ use crate::tshark_communication::{NetworkPort, TSharkPacket, TcpStreamId};
 use crate::widgets::win;
 use crate::BgFunc;
 use gtk::prelude::*;
+use std::any::Any;
 use std::net::IpAddr;
 use std::sync::mpsc;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct ClientServerInfo {
     // need to say who is the server. i have 50:50 chance
     // that the first message that was capture is from the
@@ -66,6 +67,11 @@ pub trait CustomStreamsStore {
     fn stream_message_count(&self, stream_id: TcpStreamId) -> Option<usize>;
     fn stream_summary_details(&self, stream_id: TcpStreamId) -> Option<&str>;
 
+    /// to reach the methods which are specific to a store, for instance
+    /// the PostgreSQL statement statistics or the HTTP messages
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
     fn add_to_stream(
         &mut self,
         stream_id: TcpStreamId,
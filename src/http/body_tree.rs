@@ -0,0 +1,412 @@
+// a tree representation of JSON and XML bodies, meant to be loaded lazily
+// in a gtk tree: we only list the children of a node when it's expanded.
+// Nodes are located by a JSON pointer (RFC 6901) for JSON, and by the
+// indexes of the children separated by slashes for XML.
+use itertools::Itertools;
+use std::collections::HashMap;
+
+const SUMMARY_MAX_CHARS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlNode {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<XmlNode>,
+}
+
+enum TreeKind {
+    Json,
+    Xml,
+}
+
+fn tree_kind(content_type: Option<&str>) -> Option<TreeKind> {
+    match content_type
+        .and_then(|c| c.split(';').next())
+        .map(str::trim)
+    {
+        Some("application/json") | Some("text/json") => Some(TreeKind::Json),
+        Some("application/xml") | Some("text/xml") => Some(TreeKind::Xml),
+        Some(ct) if ct.ends_with("+json") => Some(TreeKind::Json),
+        Some(ct) if ct.ends_with("+xml") => Some(TreeKind::Xml),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum BodyTree {
+    Json(serde_json::Value),
+    Xml(XmlNode),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeNode {
+    pub key: String,
+    pub summary: String,
+    pub has_children: bool,
+    pub locator: String,
+    // JSONPath or XPath
+    pub path: String,
+}
+
+impl BodyTree {
+    pub fn supports_content_type(content_type: Option<&str>) -> bool {
+        tree_kind(content_type).is_some()
+    }
+
+    /// the body is parsed in full, independently of the display truncation
+    pub fn parse(body: &str, content_type: Option<&str>) -> Option<BodyTree> {
+        match tree_kind(content_type)? {
+            TreeKind::Json => serde_json::from_str(body).ok().map(BodyTree::Json),
+            TreeKind::Xml => parse_xml(body).map(BodyTree::Xml),
+        }
+    }
+
+    pub fn root(&self) -> TreeNode {
+        match self {
+            BodyTree::Json(v) => json_node("$".to_string(), v, "".to_string(), "$".to_string()),
+            BodyTree::Xml(n) => xml_node(n, "0".to_string(), format!("/{}", n.name)),
+        }
+    }
+
+    pub fn children(&self, locator: &str, path: &str) -> Vec<TreeNode> {
+        match self {
+            BodyTree::Json(root) => match root.pointer(locator) {
+                Some(serde_json::Value::Object(map)) => map
+                    .iter()
+                    .map(|(k, v)| {
+                        json_node(
+                            k.clone(),
+                            v,
+                            format!("{}/{}", locator, k.replace('~', "~0").replace('/', "~1")),
+                            format!("{}{}", path, jsonpath_key(k)),
+                        )
+                    })
+                    .collect(),
+                Some(serde_json::Value::Array(items)) => items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        json_node(
+                            format!("[{}]", i),
+                            v,
+                            format!("{}/{}", locator, i),
+                            format!("{}[{}]", path, i),
+                        )
+                    })
+                    .collect(),
+                _ => vec![],
+            },
+            BodyTree::Xml(root) => {
+                let node = match xml_find(root, locator) {
+                    Some(n) => n,
+                    None => return vec![],
+                };
+                let attributes = node.attributes.iter().map(|(k, v)| TreeNode {
+                    key: format!("@{}", k),
+                    summary: summarize(v),
+                    has_children: false,
+                    locator: format!("{}/@{}", locator, k),
+                    path: format!("{}/@{}", path, k),
+                });
+                let name_counts = node.children.iter().counts_by(|c| c.name.as_str());
+                let mut name_positions = HashMap::new();
+                let elements = node.children.iter().enumerate().map(|(i, child)| {
+                    let position = name_positions.entry(child.name.as_str()).or_insert(0);
+                    *position += 1;
+                    // xpath indexes are 1-based, and only needed if there are several siblings with the same name
+                    let child_path = if name_counts[child.name.as_str()] > 1 {
+                        format!("{}/{}[{}]", path, child.name, position)
+                    } else {
+                        format!("{}/{}", path, child.name)
+                    };
+                    xml_node(child, format!("{}/{}", locator, i), child_path)
+                });
+                attributes.chain(elements).collect()
+            }
+        }
+    }
+
+    /// the complete value of the node, for copying
+    pub fn value_text(&self, locator: &str) -> Option<String> {
+        match self {
+            BodyTree::Json(root) => root.pointer(locator).map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                _ => serde_json::to_string_pretty(v).unwrap_or_default(),
+            }),
+            BodyTree::Xml(root) => match locator.rsplit_once("/@") {
+                Some((element, attr)) => xml_find(root, element).and_then(|n| {
+                    n.attributes
+                        .iter()
+                        .find(|(k, _)| k == attr)
+                        .map(|(_, v)| v.clone())
+                }),
+                None => xml_find(root, locator).map(|n| {
+                    if n.children.is_empty() {
+                        n.text.clone()
+                    } else {
+                        xml_serialize(n)
+                    }
+                }),
+            },
+        }
+    }
+}
+
+fn jsonpath_key(key: &str) -> String {
+    let is_identifier = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if is_identifier {
+        format!(".{}", key)
+    } else {
+        format!("['{}']", key.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+fn json_node(key: String, v: &serde_json::Value, locator: String, path: String) -> TreeNode {
+    let (summary, has_children) = match v {
+        serde_json::Value::Object(map) => (format!("{{{} fields}}", map.len()), !map.is_empty()),
+        serde_json::Value::Array(items) => (format!("[{} items]", items.len()), !items.is_empty()),
+        serde_json::Value::String(s) => (summarize(&format!("\"{}\"", s)), false),
+        _ => (v.to_string(), false),
+    };
+    TreeNode {
+        key,
+        summary,
+        has_children,
+        locator,
+        path,
+    }
+}
+
+fn xml_node(n: &XmlNode, locator: String, path: String) -> TreeNode {
+    TreeNode {
+        key: n.name.clone(),
+        summary: if n.children.is_empty() {
+            summarize(&n.text)
+        } else {
+            format!("{} children", n.children.len())
+        },
+        has_children: !n.children.is_empty() || !n.attributes.is_empty(),
+        locator,
+        path,
+    }
+}
+
+fn summarize(s: &str) -> String {
+    let single_line = s.split_whitespace().join(" ");
+    if single_line.chars().count() > SUMMARY_MAX_CHARS {
+        format!(
+            "{}…",
+            single_line
+                .chars()
+                .take(SUMMARY_MAX_CHARS)
+                .collect::<String>()
+        )
+    } else {
+        single_line
+    }
+}
+
+/// "0" is the root, "0/2/1" the second child of the third child of the root
+fn xml_find<'a>(root: &'a XmlNode, locator: &str) -> Option<&'a XmlNode> {
+    let mut indexes = locator.split('/');
+    if indexes.next() != Some("0") {
+        return None;
+    }
+    indexes.try_fold(root, |node, idx| {
+        idx.parse::<usize>().ok().and_then(|i| node.children.get(i))
+    })
+}
+
+/// in one pass, so that "&amp;lt;" becomes "&lt;" and not "<".
+/// We leave the unknown references as they are.
+fn xml_unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        match rest
+            .find(';')
+            .and_then(|end| Some((end, xml_reference_char(&rest[1..end])?)))
+        {
+            Some((end, c)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// the predefined entities, and the numeric character references
+/// such as &#233; or &#x20AC; https://www.w3.org/TR/xml/#sec-references
+fn xml_reference_char(reference: &str) -> Option<char> {
+    match reference {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "amp" => Some('&'),
+        _ => {
+            let code = match reference.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => reference.strip_prefix('#').and_then(|d| d.parse().ok()),
+            };
+            code.and_then(char::from_u32)
+        }
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_serialize(n: &XmlNode) -> String {
+    let attrs: String = n
+        .attributes
+        .iter()
+        .map(|(k, v)| format!(" {}=\"{}\"", k, xml_escape(v)))
+        .collect();
+    format!(
+        "<{}{}>{}{}</{}>",
+        n.name,
+        attrs,
+        xml_escape(&n.text),
+        n.children.iter().map(xml_serialize).join(""),
+        n.name
+    )
+}
+
+fn qualified_name(prefix: &str, local: &str) -> String {
+    if prefix.is_empty() {
+        local.to_string()
+    } else {
+        format!("{}:{}", prefix, local)
+    }
+}
+
+fn parse_xml(xml: &str) -> Option<XmlNode> {
+    let mut stack: Vec<XmlNode> = vec![];
+    let mut root = None;
+    for token in xmlparser::Tokenizer::from(xml) {
+        match token.ok()? {
+            xmlparser::Token::ElementStart { prefix, local, .. } => stack.push(XmlNode {
+                name: qualified_name(&prefix, &local),
+                attributes: vec![],
+                text: "".to_string(),
+                children: vec![],
+            }),
+            xmlparser::Token::Attribute {
+                prefix,
+                local,
+                value,
+                ..
+            } => stack
+                .last_mut()?
+                .attributes
+                .push((qualified_name(&prefix, &local), xml_unescape(&value))),
+            xmlparser::Token::ElementEnd {
+                end: xmlparser::ElementEnd::Open,
+                ..
+            } => {}
+            xmlparser::Token::ElementEnd { .. } => {
+                let node = stack.pop()?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => root = Some(node),
+                }
+            }
+            xmlparser::Token::Text { text } => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(xml_unescape(&text).trim());
+                }
+            }
+            xmlparser::Token::Cdata { text, .. } => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    root
+}
+
+#[test]
+fn should_list_json_children_lazily() {
+    let tree = BodyTree::parse(
+        r#"{"items": [{"id": 1, "a/b": "x"}], "my key": null}"#,
+        Some("application/json; charset=utf-8"),
+    )
+    .unwrap();
+    let root = tree.root();
+    assert_eq!("{2 fields}", root.summary);
+    let children = tree.children(&root.locator, &root.path);
+    assert_eq!(
+        vec!["$.items", "$['my key']"],
+        children.iter().map(|c| c.path.as_str()).collect::<Vec<_>>()
+    );
+    let items = tree.children(&children[0].locator, &children[0].path);
+    assert_eq!("[0]", items[0].key);
+    let item_fields = tree.children(&items[0].locator, &items[0].path);
+    assert_eq!("$.items[0]['a/b']", item_fields[0].path);
+    assert_eq!("/items/0/a~1b", item_fields[0].locator);
+    assert_eq!(
+        Some("x".to_string()),
+        tree.value_text(&item_fields[0].locator)
+    );
+}
+
+#[test]
+fn should_list_xml_children_with_xpath() {
+    let tree = BodyTree::parse(
+        r#"<?xml version="1.0"?><list kind="a&amp;b"><item>1</item><item>2 &lt; 3</item><other/></list>"#,
+        Some("text/xml"),
+    )
+    .unwrap();
+    let root = tree.root();
+    let children = tree.children(&root.locator, &root.path);
+    assert_eq!(
+        vec![
+            "/list/@kind",
+            "/list/item[1]",
+            "/list/item[2]",
+            "/list/other"
+        ],
+        children.iter().map(|c| c.path.as_str()).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some("a&b".to_string()),
+        tree.value_text(&children[0].locator)
+    );
+    assert_eq!(
+        Some("2 < 3".to_string()),
+        tree.value_text(&children[2].locator)
+    );
+    assert_eq!(
+        Some(
+            "<list kind=\"a&amp;b\"><item>1</item><item>2 &lt; 3</item><other></other></list>"
+                .to_string()
+        ),
+        tree.value_text(&root.locator)
+    );
+}
+
+#[test]
+fn should_decode_xml_character_references() {
+    assert_eq!(
+        "café 20€ <&> &amp; &bogus; &#xZZ; & done",
+        xml_unescape("caf&#233; 20&#x20AC; &lt;&amp;&gt; &amp;amp; &bogus; &#xZZ; & done")
+    );
+}
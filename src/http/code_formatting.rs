@@ -1,5 +1,47 @@
 pub const BODY_TRUNCATE_LIMIT_BYTES: usize = 128 * 1024;
 
+#[derive(Debug, PartialEq, Eq)]
+enum CodeKind {
+    Xml,
+    Json,
+    NdJson,
+    Html,
+    JavaScript,
+    Css,
+    Yaml,
+    GraphQl,
+    FormUrlEncoded,
+}
+
+fn code_kind(content_type: &str) -> Option<CodeKind> {
+    match content_type.trim().to_ascii_lowercase().as_str() {
+        "application/xml" | "text/xml" => Some(CodeKind::Xml),
+        "application/json" | "text/json" => Some(CodeKind::Json),
+        "application/x-ndjson"
+        | "application/ndjson"
+        | "application/jsonl"
+        | "application/x-jsonlines" => Some(CodeKind::NdJson),
+        "text/html" | "application/xhtml+xml" => Some(CodeKind::Html),
+        "application/javascript"
+        | "text/javascript"
+        | "application/x-javascript"
+        | "application/ecmascript"
+        | "text/ecmascript" => Some(CodeKind::JavaScript),
+        "text/css" => Some(CodeKind::Css),
+        "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => {
+            Some(CodeKind::Yaml)
+        }
+        "application/graphql" => Some(CodeKind::GraphQl),
+        "application/x-www-form-urlencoded" => Some(CodeKind::FormUrlEncoded),
+        // structured syntax suffixes, https://datatracker.ietf.org/doc/html/rfc6839
+        // for instance application/problem+json, application/soap+xml
+        ct if ct.ends_with("+json") => Some(CodeKind::Json),
+        ct if ct.ends_with("+xml") => Some(CodeKind::Xml),
+        ct if ct.ends_with("+yaml") => Some(CodeKind::Yaml),
+        _ => None,
+    }
+}
+
 pub fn highlight_indent_truncate(
     do_format: bool,
     body: &str,
@@ -18,13 +60,21 @@ pub fn highlight_indent_truncate(
     } else {
         body
     };
-    match content_type_first_part {
-        Some("application/xml") | Some("text/xml") if do_format => {
-            highlight_indent_xml(truncated_body)
+    match content_type_first_part.and_then(code_kind) {
+        Some(CodeKind::Xml) if do_format => highlight_indent_xml(truncated_body),
+        Some(CodeKind::Json) if do_format => highlight_indent_json(truncated_body),
+        Some(CodeKind::NdJson) if do_format => highlight_indent_ndjson(truncated_body)
+            .unwrap_or_else(|| glib::markup_escape_text(truncated_body).to_string()),
+        Some(CodeKind::Html) if do_format => highlight_indent_html(truncated_body),
+        Some(CodeKind::JavaScript) if do_format => {
+            highlight_indent_code(truncated_body, &JAVASCRIPT_RULES)
         }
-        Some("application/json") | Some("text/json") if do_format => {
-            highlight_indent_json(truncated_body)
+        Some(CodeKind::Css) if do_format => highlight_indent_code(truncated_body, &CSS_RULES),
+        Some(CodeKind::Yaml) if do_format => highlight_yaml(truncated_body),
+        Some(CodeKind::GraphQl) if do_format => {
+            highlight_indent_code(truncated_body, &GRAPHQL_RULES)
         }
+        Some(CodeKind::FormUrlEncoded) if do_format => highlight_form_urlencoded(truncated_body),
         _ => glib::markup_escape_text(truncated_body).to_string(),
     }
 }
@@ -150,21 +200,46 @@ fn highlight_indent_xml(xml: &str) -> String {
 
 fn highlight_indent_json(json: &str) -> String {
     if let Ok(val) = serde_json::from_str(json) {
-        highlight_indent_json_value(&val, 0)
+        match graphql_request(&val) {
+            Some(markup) => markup,
+            None => highlight_indent_json_value(&val, 0),
+        }
     } else {
         // also support NDJSON -- Newline-Delimited JSON
         // one JSON object on each line, not wrapped in arrays.
-        if let Ok(ndjson_fmt) = json.lines().try_fold("".to_string(), |sofar, cur| {
-            let val = serde_json::from_str(cur)?;
-            let r: Result<String, serde_json::Error> =
-                Ok(sofar + &highlight_indent_json_value(&val, 0) + "\n");
-            r
-        }) {
-            ndjson_fmt
-        } else {
-            glib::markup_escape_text(json).to_string()
-        }
+        highlight_indent_ndjson(json).unwrap_or_else(|| glib::markup_escape_text(json).to_string())
+    }
+}
+
+fn highlight_indent_ndjson(json: &str) -> Option<String> {
+    json.lines()
+        .filter(|l| !l.trim().is_empty())
+        .try_fold("".to_string(), |sofar, cur| {
+            let val = serde_json::from_str(cur).ok()?;
+            Some(sofar + &highlight_indent_json_value(&val, 0) + "\n")
+        })
+}
+
+/// GraphQL over HTTP: {"query": "...", "variables": {...}, "operationName": "..."}
+/// we display the query formatted, rather than as a JSON string.
+fn graphql_request(val: &serde_json::Value) -> Option<String> {
+    let fields = val.as_object()?;
+    let query = fields.get("query")?.as_str()?;
+    if !fields
+        .keys()
+        .all(|k| ["query", "variables", "operationName", "extensions"].contains(&k.as_str()))
+    {
+        return None;
     }
+    let mut result = highlight_indent_code(query, &GRAPHQL_RULES);
+    for (k, v) in fields.iter().filter(|(k, v)| *k != "query" && !v.is_null()) {
+        result.push_str(&format!(
+            "\n\n<i>{}</i>: {}",
+            k,
+            highlight_indent_json_value(v, 0)
+        ));
+    }
+    Some(result)
 }
 
 fn highlight_indent_json_value(v: &serde_json::Value, indent_depth: usize) -> String {
@@ -211,6 +286,459 @@ fn highlight_indent_json_value(v: &serde_json::Value, indent_depth: usize) -> St
     }
 }
 
+struct SyntaxRules {
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    // besides alphanumeric characters and '_'
+    extra_word_chars: &'static str,
+    break_on_semicolon: bool,
+    // css: a word followed by ':' inside a block is a property name
+    highlight_properties: bool,
+    // graphql: the fields of a selection set are separated by whitespace
+    newline_between_fields: bool,
+}
+
+const JAVASCRIPT_RULES: SyntaxRules = SyntaxRules {
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "let",
+        "new",
+        "null",
+        "of",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typeof",
+        "undefined",
+        "var",
+        "void",
+        "while",
+        "yield",
+    ],
+    line_comment: Some("//"),
+    extra_word_chars: "$",
+    break_on_semicolon: true,
+    highlight_properties: false,
+    newline_between_fields: false,
+};
+
+const CSS_RULES: SyntaxRules = SyntaxRules {
+    keywords: &["!important"],
+    line_comment: None,
+    extra_word_chars: "-!@",
+    break_on_semicolon: true,
+    highlight_properties: true,
+    newline_between_fields: false,
+};
+
+const GRAPHQL_RULES: SyntaxRules = SyntaxRules {
+    keywords: &[
+        "query",
+        "mutation",
+        "subscription",
+        "fragment",
+        "on",
+        "true",
+        "false",
+        "null",
+    ],
+    line_comment: Some("#"),
+    extra_word_chars: "$@",
+    break_on_semicolon: false,
+    highlight_properties: false,
+    newline_between_fields: true,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+enum CodeToken<'a> {
+    Str(&'a str),
+    Comment(&'a str),
+    Word(&'a str),
+    Punct(char),
+    // the bool is true if there was an empty line
+    Newline(bool),
+}
+
+/// the tokens, and whether they were preceded by whitespace
+fn tokenize_code<'a>(code: &'a str, rules: &SyntaxRules) -> Vec<(CodeToken<'a>, bool)> {
+    let is_word_char =
+        |c: char| c.is_alphanumeric() || c == '_' || rules.extra_word_chars.contains(c);
+    let mut tokens = vec![];
+    let mut space_before = false;
+    let mut idx = 0;
+    while let Some(c) = code[idx..].chars().next() {
+        let rest = &code[idx..];
+        let (token, len) = if c.is_whitespace() {
+            let len = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            let newlines = rest[..len].matches('\n').count();
+            if newlines == 0 {
+                space_before = true;
+                idx += len;
+                continue;
+            }
+            (CodeToken::Newline(newlines > 1), len)
+        } else if c == '"' || c == '\'' || c == '`' {
+            let mut escaped = false;
+            let len = rest[1..]
+                .char_indices()
+                .find(|(_, ch)| {
+                    let is_end = !escaped && *ch == c;
+                    escaped = !escaped && *ch == '\\';
+                    is_end
+                })
+                .map(|(i, _)| i + 2)
+                .unwrap_or(rest.len());
+            (CodeToken::Str(&rest[..len]), len)
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let len = comment.find("*/").map(|i| i + 4).unwrap_or(rest.len());
+            (CodeToken::Comment(&rest[..len]), len)
+        } else if rules
+            .line_comment
+            .filter(|lc| rest.starts_with(lc))
+            .is_some()
+        {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            (CodeToken::Comment(&rest[..len]), len)
+        } else if is_word_char(c) {
+            let len = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+            (CodeToken::Word(&rest[..len]), len)
+        } else {
+            (CodeToken::Punct(c), c.len_utf8())
+        };
+        tokens.push((token, space_before));
+        space_before = false;
+        idx += len;
+    }
+    tokens
+}
+
+/// a simple formatter for brace-delimited languages: we break lines after
+/// blocks open and close, and after statements, and re-indent everything.
+/// That's enough to make minified code readable.
+fn highlight_indent_code(code: &str, rules: &SyntaxRules) -> String {
+    let tokens = tokenize_code(code, rules);
+    let mut result = String::new();
+    let mut depth = 0;
+    let mut paren_depth = 0;
+    let mut at_line_start = true;
+    let mut break_after_block = false;
+    let mut prev_token: Option<&CodeToken> = None;
+    let newline = |result: &mut String, at_line_start: &mut bool| {
+        if !*at_line_start {
+            result.push('\n');
+            *at_line_start = true;
+        }
+    };
+    for (idx, (token, space_before)) in tokens.iter().enumerate() {
+        let next_token = tokens.get(idx + 1).map(|(t, _)| t);
+        let mut space_before = *space_before;
+        if break_after_block {
+            break_after_block = false;
+            let continues_statement = matches!(
+                token,
+                CodeToken::Punct(')' | ']' | ',' | ';' | '.')
+                    | CodeToken::Word("else" | "catch" | "finally" | "while")
+            );
+            if !continues_statement {
+                newline(&mut result, &mut at_line_start);
+            } else if matches!(token, CodeToken::Word(_)) {
+                // "} else {"
+                space_before = true;
+            }
+        }
+        if rules.newline_between_fields && depth > 0 && paren_depth == 0 {
+            let prev_ends_field = match prev_token {
+                Some(CodeToken::Word(w)) => *w != "on",
+                Some(CodeToken::Punct(')')) | Some(CodeToken::Str(_)) => true,
+                _ => false,
+            };
+            // a new field, or a fragment spread: "...on Type"
+            let starts_field = match token {
+                CodeToken::Word(w) => !w.starts_with('@'),
+                CodeToken::Punct('.') => true,
+                _ => false,
+            };
+            if prev_ends_field && starts_field {
+                newline(&mut result, &mut at_line_start);
+            }
+        }
+        if let CodeToken::Punct('}') = token {
+            newline(&mut result, &mut at_line_start);
+            depth = std::cmp::max(depth, 1) - 1;
+        }
+        let markup = match token {
+            CodeToken::Newline(blank_line) => {
+                if !at_line_start {
+                    newline(&mut result, &mut at_line_start);
+                    if *blank_line {
+                        result.push('\n');
+                    }
+                }
+                prev_token = Some(token);
+                continue;
+            }
+            CodeToken::Comment(c) => format!("<i>{}</i>", glib::markup_escape_text(c)),
+            CodeToken::Word(w) if rules.keywords.contains(w) => {
+                format!("<b>{}</b>", glib::markup_escape_text(w))
+            }
+            CodeToken::Word(w)
+                if rules.highlight_properties
+                    && depth > 0
+                    && next_token == Some(&CodeToken::Punct(':')) =>
+            {
+                format!("<b>{}</b>", glib::markup_escape_text(w))
+            }
+            CodeToken::Word(w) | CodeToken::Str(w) => glib::markup_escape_text(w).to_string(),
+            CodeToken::Punct(p) => glib::markup_escape_text(&p.to_string()).to_string(),
+        };
+        if at_line_start {
+            result.push_str(&"  ".repeat(depth));
+        } else if space_before || matches!(token, CodeToken::Punct('{')) {
+            result.push(' ');
+        }
+        result.push_str(&markup);
+        at_line_start = false;
+        match token {
+            CodeToken::Punct('{') => {
+                depth += 1;
+                newline(&mut result, &mut at_line_start);
+            }
+            CodeToken::Punct('}') => break_after_block = true,
+            CodeToken::Punct('(' | '[') => paren_depth += 1,
+            CodeToken::Punct(')' | ']') => paren_depth = std::cmp::max(paren_depth, 1) - 1,
+            CodeToken::Punct(';') if rules.break_on_semicolon && paren_depth == 0 => {
+                newline(&mut result, &mut at_line_start)
+            }
+            CodeToken::Comment(c) if !c.starts_with("/*") => {
+                newline(&mut result, &mut at_line_start)
+            }
+            _ => {}
+        }
+        prev_token = Some(token);
+    }
+    result.trim_end().to_string()
+}
+
+const HTML_VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// HTML is often not well-formed XML (void elements, unquoted attributes...),
+/// so we can't use the XML tokenizer.
+fn highlight_indent_html(html: &str) -> String {
+    let mut result = "".to_string();
+    let mut indent = 0;
+    let mut has_text = false;
+    let mut rest = html.trim_start_matches('\u{feff}');
+    let push_line = |result: &mut String, indent: usize, contents: &str| {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(&"  ".repeat(indent));
+        result.push_str(contents);
+    };
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+            push_line(
+                &mut result,
+                indent,
+                &format!("<i>{}</i>", glib::markup_escape_text(&rest[..end])),
+            );
+            rest = &rest[end..];
+            has_text = false;
+        } else if rest.starts_with('<')
+            && rest.len() > 1
+            && !rest[1..].starts_with(char::is_whitespace)
+        {
+            let end = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+            let tag = &rest[..end];
+            rest = &rest[end..];
+            let is_closing = tag.starts_with("</");
+            let name_start = if is_closing { 2 } else { 1 };
+            let name_len = tag[name_start..]
+                .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .unwrap_or(tag.len() - name_start);
+            let name = &tag[name_start..name_start + name_len];
+            let markup = format!(
+                "&lt;{}<b>{}</b>{}",
+                if is_closing { "/" } else { "" },
+                glib::markup_escape_text(name),
+                glib::markup_escape_text(&tag[name_start + name_len..])
+            );
+            let lower_name = name.to_ascii_lowercase();
+            if is_closing {
+                indent = std::cmp::max(indent, 1) - 1;
+                if has_text {
+                    result.push_str(&markup);
+                } else {
+                    push_line(&mut result, indent, &markup);
+                }
+                has_text = false;
+                continue;
+            }
+            push_line(&mut result, indent, &markup);
+            has_text = false;
+            let is_void = name.starts_with('!')
+                || name.starts_with('?')
+                || tag.ends_with("/>")
+                || HTML_VOID_ELEMENTS.contains(&lower_name.as_str());
+            if is_void {
+                continue;
+            }
+            indent += 1;
+            if lower_name == "script" || lower_name == "style" {
+                // raw text, until the closing tag
+                let closing = format!("</{}", lower_name);
+                let end = rest
+                    .to_ascii_lowercase()
+                    .find(&closing)
+                    .unwrap_or(rest.len());
+                let contents = rest[..end].trim();
+                if !contents.is_empty() {
+                    let rules = if lower_name == "script" {
+                        &JAVASCRIPT_RULES
+                    } else {
+                        &CSS_RULES
+                    };
+                    for line in highlight_indent_code(contents, rules).lines() {
+                        push_line(&mut result, indent, line);
+                    }
+                }
+                rest = &rest[end..];
+            }
+        } else {
+            // the text can start with a multi-byte character
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == '<')
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            let text = rest[..end].split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() {
+                result.push_str(&glib::markup_escape_text(&text));
+                has_text = true;
+            }
+            rest = &rest[end..];
+        }
+    }
+    result
+}
+
+/// YAML is indentation-sensitive, we don't re-indent it, just highlight
+/// the keys and comments.
+fn highlight_yaml(yaml: &str) -> String {
+    yaml.lines()
+        .map(|line| {
+            let (code, comment) = split_yaml_comment(line);
+            let trimmed = code.trim_start();
+            let after_dash = trimmed
+                .strip_prefix("- ")
+                .map(|r| r.trim_start())
+                .unwrap_or(trimmed);
+            let prefix_len = code.len() - after_dash.len();
+            let quote = after_dash
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'');
+            let key_len = if let Some(q) = quote {
+                after_dash[1..]
+                    .find(q)
+                    .map(|i| i + 2)
+                    .filter(|i| after_dash[*i..].starts_with(':'))
+            } else {
+                after_dash
+                    .find(':')
+                    .filter(|i| {
+                        let after = &after_dash[i + 1..];
+                        after.is_empty() || after.starts_with(' ')
+                    })
+                    .filter(|i| !after_dash[..*i].contains(['{', '[', ' ']))
+            };
+            let mut result = match key_len {
+                Some(len) => format!(
+                    "{}<b>{}</b>{}",
+                    glib::markup_escape_text(&code[..prefix_len]),
+                    glib::markup_escape_text(&after_dash[..len]),
+                    glib::markup_escape_text(&after_dash[len..])
+                ),
+                None if trimmed == "---" || trimmed == "..." => {
+                    format!("<b>{}</b>", glib::markup_escape_text(code))
+                }
+                None => glib::markup_escape_text(code).to_string(),
+            };
+            if let Some(c) = comment {
+                result.push_str(&format!("<i>{}</i>", glib::markup_escape_text(c)));
+            }
+            result
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// a comment starts with '#', at the beginning of the line or after a space,
+/// outside of quotes
+fn split_yaml_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if prev.is_whitespace() => return (&line[..i], Some(&line[i..])),
+            _ => {}
+        }
+        prev = c;
+    }
+    (line, None)
+}
+
+fn highlight_form_urlencoded(body: &str) -> String {
+    body.trim()
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (name, value) = p.split_once('=').unwrap_or((p, ""));
+            format!(
+                "<b>{}</b> = {}",
+                glib::markup_escape_text(&super::structured_data::url_decode(name)),
+                glib::markup_escape_text(&super::structured_data::url_decode(value))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[test]
 fn simple_xml_indent() {
     assert_eq!(
@@ -265,3 +793,90 @@ fn simple_ndjson_indent() {
         highlight_indent_json("{\"key\": 1}\n{\"key2\": 2}")
     );
 }
+
+#[test]
+fn should_handle_structured_syntax_suffixes() {
+    assert_eq!(Some(CodeKind::Json), code_kind("application/problem+json"));
+    assert_eq!(Some(CodeKind::Json), code_kind("application/vnd.api+json"));
+    assert_eq!(Some(CodeKind::Xml), code_kind("application/soap+xml"));
+    assert_eq!(None, code_kind("application/octet-stream"));
+    assert_eq!(
+        "{\n  \"<b>a</b>\": 1\n}",
+        highlight_indent_truncate(
+            true,
+            "{\"a\":1}",
+            Some("application/problem+json; charset=utf-8")
+        )
+    );
+}
+
+#[test]
+fn should_indent_minified_javascript() {
+    assert_eq!(
+        "<b>function</b> f(a) {\n  <b>if</b>(a) {\n    <b>return</b> &quot;}&quot;;\n  } <b>else</b> {\n    g(<b>function</b>() {\n      <i>// done</i>\n      x++;\n    });\n  }\n}",
+        highlight_indent_code(
+            "function f(a){if(a){return \"}\";}else{g(function(){// done\nx++;});}}",
+            &JAVASCRIPT_RULES
+        )
+    );
+}
+
+#[test]
+fn should_indent_css() {
+    assert_eq!(
+        "a:hover, .b {\n  <b>color</b>:red <b>!important</b>;\n  <b>font-size</b>: 12px\n}\n<i>/* x */</i>",
+        highlight_indent_code("a:hover, .b{color:red !important;font-size: 12px}/* x */", &CSS_RULES)
+    );
+}
+
+#[test]
+fn should_indent_graphql_query() {
+    assert_eq!(
+        "<b>query</b> Q($id: ID!) {\n  user(id: $id) {\n    name\n    ... <b>on</b> Admin {\n      level\n    }\n  }\n}\n\n<i>variables</i>: {\n  \"<b>id</b>\": 1\n}",
+        highlight_indent_json(
+            r#"{"query": "query Q($id: ID!) { user(id: $id) { name ... on Admin { level } } }", "variables": {"id": 1}}"#
+        )
+    );
+}
+
+#[test]
+fn should_indent_html() {
+    assert_eq!(
+        "&lt;<b>!DOCTYPE</b> html&gt;\n&lt;<b>html</b>&gt;\n  &lt;<b>head</b>&gt;\n    &lt;<b>meta</b> charset=&quot;utf-8&quot;&gt;\n    &lt;<b>script</b>&gt;\n      a();\n    &lt;/<b>script</b>&gt;\n  &lt;/<b>head</b>&gt;\n  &lt;<b>body</b>&gt;\n    &lt;<b>p</b>&gt;hello world&lt;/<b>p</b>&gt;\n    <i>&lt;!-- c --&gt;</i>\n  &lt;/<b>body</b>&gt;\n&lt;/<b>html</b>&gt;",
+        highlight_indent_html(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><script>a();</script></head><body><p>hello\n world</p><!-- c --></body></html>"
+        )
+    );
+}
+
+#[test]
+fn should_indent_html_with_non_ascii_text() {
+    assert_eq!(
+        "&lt;<b>p</b>&gt;élan&lt;/<b>p</b>&gt;\n&lt;<b>p</b>&gt;日本語&lt;/<b>p</b>&gt;\n&lt;<b>p</b>&gt;😀 ok&lt;/<b>p</b>&gt;",
+        highlight_indent_html("\u{feff}<p>élan</p><p>日本語</p><p>😀 ok</p>")
+    );
+}
+
+#[test]
+fn should_highlight_yaml() {
+    assert_eq!(
+        "<b>---</b>\n<b>key</b>: value <i># comment</i>\n<b>list</b>:\n  - <b>name</b>: &quot;a # b&quot;\n  - plain",
+        highlight_yaml("---\nkey: value # comment\nlist:\n  - name: \"a # b\"\n  - plain")
+    );
+}
+
+#[test]
+fn should_highlight_form_and_ndjson() {
+    assert_eq!(
+        "<b>a b</b> = c&amp;d\n<b>e</b> = ",
+        highlight_indent_truncate(
+            true,
+            "a+b=c%26d&e=",
+            Some("application/x-www-form-urlencoded")
+        )
+    );
+    assert_eq!(
+        "{\n  \"<b>key</b>\": 1\n}\n[]\n",
+        highlight_indent_truncate(true, "{\"key\": 1}\n\n[]", Some("application/x-ndjson"))
+    );
+}
@@ -0,0 +1,641 @@
+// http://www.softwareishard.com/blog/har-12-spec/
+// export of the HTTP exchanges we parsed to the HAR format which browser
+// devtools understand, and import of HAR files: we convert the HAR entries
+// to packets, which then go through the normal HTTP parsing.
+// HAR has no trailers or interim responses, we export them in custom
+// fields (the spec reserves the names starting with an underscore for that).
+use super::http_streams_store;
+use super::http_streams_store::{HttpBody, HttpMessageData, HttpRequestResponseData};
+use super::request_export;
+use super::structured_data;
+use crate::custom_streams_store::ClientServerInfo;
+use crate::http::tshark_http::{HttpType, TSharkHttp};
+use crate::tshark_communication::{
+    NetworkPort, TSharkPacket, TSharkPacketBasicInfo, TcpSeqNumber, TcpStreamId,
+};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+
+#[cfg(test)]
+use {
+    crate::custom_streams_store::CustomStreamsStore,
+    crate::http::http_streams_store::{HttpStreamsStore, InterimResponse},
+    chrono::NaiveDate,
+};
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct HarLog {
+    version: String,
+    creator: HarCreator,
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct HarCreator {
+    name: String,
+    version: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default, rename_all = "camelCase")]
+struct HarEntry {
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: HarCache,
+    timings: HarTimings,
+    #[serde(rename = "serverIPAddress", skip_serializing_if = "Option::is_none")]
+    server_ip_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connection: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default, rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<HarNameValue>,
+    headers: Vec<HarNameValue>,
+    query_string: Vec<HarNameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<HarPostData>,
+    headers_size: i64,
+    body_size: i64,
+    #[serde(rename = "_trailers", skip_serializing_if = "Vec::is_empty")]
+    trailers: Vec<HarNameValue>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default, rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<HarNameValue>,
+    headers: Vec<HarNameValue>,
+    content: HarContent,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+    #[serde(rename = "_trailers", skip_serializing_if = "Vec::is_empty")]
+    trailers: Vec<HarNameValue>,
+    // 1xx responses such as 103 Early Hints, before this response
+    #[serde(rename = "_interimResponses", skip_serializing_if = "Vec::is_empty")]
+    interim_responses: Vec<HarInterimResponse>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+#[serde(default)]
+struct HarInterimResponse {
+    status: u16,
+    headers: Vec<HarNameValue>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+#[serde(default)]
+struct HarNameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default, rename_all = "camelCase")]
+struct HarPostData {
+    mime_type: String,
+    text: String,
+    // not in the HAR 1.2 spec, but firefox & others use it for binary bodies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default, rename_all = "camelCase")]
+struct HarContent {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct HarCache {}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct HarTimings {
+    blocked: f64,
+    dns: f64,
+    connect: f64,
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+const HAR_BASE64_ENCODING: &str = "base64";
+
+/// The entries are sorted by request time. We only have the timestamps
+/// of the request and of the response, so the whole duration is
+/// reported as waiting time.
+pub fn export_har(
+    exchanges: &[(TcpStreamId, ClientServerInfo, &HttpMessageData)],
+) -> Result<String, serde_json::Error> {
+    let mut with_request: Vec<_> = exchanges
+        .iter()
+        .filter_map(|(stream_id, cs, msg)| {
+            msg.request
+                .as_ref()
+                .map(|rq| (*stream_id, cs, rq, msg.response.as_ref()))
+        })
+        .collect();
+    with_request.sort_by_key(|(_, _, rq, _)| rq.timestamp);
+    let har = Har {
+        log: HarLog {
+            version: "1.2".to_string(),
+            creator: HarCreator {
+                name: "Hotwire".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            entries: with_request
+                .into_iter()
+                .map(|(stream_id, cs, rq, rs)| export_entry(stream_id, cs, rq, rs))
+                .collect(),
+        },
+    };
+    serde_json::to_string_pretty(&har)
+}
+
+fn export_entry(
+    stream_id: TcpStreamId,
+    client_server: &ClientServerInfo,
+    request: &HttpRequestResponseData,
+    response: Option<&HttpRequestResponseData>,
+) -> HarEntry {
+    let time = response
+        .and_then(|rs| (rs.timestamp - request.timestamp).num_microseconds())
+        .map_or(0.0, |us| us as f64 / 1000.0);
+    HarEntry {
+        started_date_time: format_timestamp(request.timestamp),
+        time,
+        request: export_request(client_server, request),
+        response: response.map(export_response).unwrap_or_default(),
+        cache: HarCache {},
+        timings: HarTimings {
+            blocked: -1.0,
+            dns: -1.0,
+            connect: -1.0,
+            send: 0.0,
+            wait: time,
+            receive: 0.0,
+        },
+        server_ip_address: Some(client_server.server_ip.to_string()),
+        connection: Some(stream_id.to_string()),
+    }
+}
+
+fn export_request(
+    client_server: &ClientServerInfo,
+    request: &HttpRequestResponseData,
+) -> HarRequest {
+    let request_line = request_export::parse_request_line(request);
+    let post_data = body_contents(request).map(|(text, encoding, _size)| HarPostData {
+        mime_type: request.content_type.clone().unwrap_or_default(),
+        text,
+        encoding: encoding.map(|e| e.to_string()),
+    });
+    HarRequest {
+        method: request_line.method.to_string(),
+        url: request_export::request_url(request, client_server),
+        http_version: request_line.http_version.to_string(),
+        cookies: structured_data::request_cookies(request)
+            .iter()
+            .map(|(name, value)| name_value(name, value))
+            .collect(),
+        headers: export_headers(&request.headers),
+        query_string: structured_data::query_params(request)
+            .iter()
+            .map(|(name, value)| name_value(name, value))
+            .collect(),
+        post_data,
+        headers_size: -1,
+        body_size: body_size(&request.body),
+        trailers: export_headers(&request.trailers),
+    }
+}
+
+/// the status, status text and HTTP version
+fn parse_status_line<'a>(
+    first_line: &'a str,
+    headers: &[(String, String)],
+) -> (u16, &'a str, &'a str) {
+    match http_streams_store::get_http_header_value(headers, ":status") {
+        // http2
+        Some(status) => (status.parse().unwrap_or(0), "", "HTTP/2.0"),
+        None => {
+            let mut parts = first_line.splitn(3, ' ');
+            let http_version = parts.next().unwrap_or("HTTP/1.1");
+            let status = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+            (status, parts.next().unwrap_or(""), http_version)
+        }
+    }
+}
+
+fn export_response(response: &HttpRequestResponseData) -> HarResponse {
+    let (status, status_text, http_version) =
+        parse_status_line(&response.first_line, &response.headers);
+    let contents = body_contents(response);
+    HarResponse {
+        status,
+        status_text: status_text.to_string(),
+        http_version: http_version.to_string(),
+        cookies: structured_data::response_cookies(response)
+            .iter()
+            .map(|c| name_value(&c.name, &c.value))
+            .collect(),
+        headers: export_headers(&response.headers),
+        content: HarContent {
+            size: contents.as_ref().map_or(0, |(_, _, size)| *size as i64),
+            mime_type: response.content_type.clone().unwrap_or_default(),
+            encoding: contents
+                .as_ref()
+                .and_then(|(_, encoding, _)| encoding.map(|e| e.to_string())),
+            text: contents.map(|(text, _, _)| text),
+        },
+        redirect_url: http_streams_store::get_http_header_value(&response.headers, "Location")
+            .cloned()
+            .unwrap_or_default(),
+        headers_size: -1,
+        body_size: body_size(&response.body),
+        trailers: export_headers(&response.trailers),
+        interim_responses: response
+            .interim_responses
+            .iter()
+            .map(|r| HarInterimResponse {
+                status: parse_status_line(&r.first_line, &r.headers).0,
+                headers: export_headers(&r.headers),
+            })
+            .collect(),
+    }
+}
+
+fn export_headers(headers: &[(String, String)]) -> Vec<HarNameValue> {
+    headers.iter().map(|(k, v)| name_value(k, v)).collect()
+}
+
+fn name_value(name: &str, value: &str) -> HarNameValue {
+    HarNameValue {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// the decompressed body, as text if possible, otherwise in base64.
+/// Also returns the size of the decompressed body.
+fn body_contents(r: &HttpRequestResponseData) -> Option<(String, Option<&'static str>, usize)> {
+    match (&r.body, r.body_as_str()) {
+        (HttpBody::Missing, _) => None,
+        (_, Some(text)) => Some((text.to_string(), None, text.len())),
+        (HttpBody::Binary(bytes), None) => {
+            let decoded = r
+                .content_encoding
+                .decode(bytes)
+                .unwrap_or_else(|| bytes.clone());
+            Some((
+                base64::encode(&decoded),
+                Some(HAR_BASE64_ENCODING),
+                decoded.len(),
+            ))
+        }
+        (HttpBody::Text(_), None) => None,
+    }
+}
+
+fn body_size(body: &HttpBody) -> i64 {
+    match body {
+        HttpBody::Text(s) => s.len() as i64,
+        HttpBody::Binary(b) => b.len() as i64,
+        HttpBody::Missing => 0,
+    }
+}
+
+/// tshark gives us timestamps in the local timezone
+fn format_timestamp(timestamp: NaiveDateTime) -> String {
+    match Local.from_local_datetime(&timestamp).earliest() {
+        Some(dt) => dt.to_rfc3339_opts(SecondsFormat::Millis, false),
+        None => Utc
+            .from_utc_datetime(&timestamp)
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+    }
+}
+
+/// Each HAR entry gives a request packet and a response packet. Entries
+/// which share a connection are put in the same TCP stream. We don't know
+/// the client IP, so we use the loopback address, the HAR file was
+/// recorded on the client.
+pub fn parse_har(contents: &str) -> Result<Vec<TSharkPacket>, String> {
+    let har: Har =
+        serde_json::from_str(contents).map_err(|e| format!("Invalid HAR file: {}", e))?;
+    let mut stream_ids: HashMap<String, TcpStreamId> = HashMap::new();
+    let mut packets = vec![];
+    for (idx, entry) in har.log.entries.into_iter().enumerate() {
+        let next_stream_id = TcpStreamId(idx as u32);
+        let tcp_stream_id = match &entry.connection {
+            Some(c) => *stream_ids.entry(c.clone()).or_insert(next_stream_id),
+            None => next_stream_id,
+        };
+        let (scheme, authority, target) = split_url(&entry.request.url)
+            .ok_or_else(|| format!("Invalid URL in HAR file: {}", entry.request.url))?;
+        let (host, port) = split_authority(authority);
+        let port = port.unwrap_or(if scheme.eq_ignore_ascii_case("https") {
+            443
+        } else {
+            80
+        });
+        let server_ip = entry
+            .server_ip_address
+            .as_deref()
+            .map(|ip| ip.trim_start_matches('[').trim_end_matches(']'))
+            .or_else(|| Some(host.trim_start_matches('[').trim_end_matches(']')))
+            .and_then(|ip| ip.parse().ok())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let request_time = DateTime::parse_from_rfc3339(&entry.started_date_time)
+            .map_err(|e| {
+                format!(
+                    "Invalid startedDateTime in HAR file: {}: {}",
+                    entry.started_date_time, e
+                )
+            })?
+            .with_timezone(&Local)
+            .naive_local();
+        let request_info = TSharkPacketBasicInfo {
+            frame_time: request_time,
+            ip_src: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            ip_dst: server_ip,
+            tcp_seq_number: TcpSeqNumber(idx as u32),
+            tcp_stream_id,
+            port_src: NetworkPort(0),
+            port_dst: NetworkPort(port),
+        };
+        let request = &entry.request;
+        let post_data = request.post_data.as_ref();
+        packets.push(har_packet(
+            request_info,
+            TSharkHttp {
+                http_type: Some(HttpType::Request),
+                http_host: Some(host.to_string()),
+                first_line: format!("{} {} {}", request.method, target, request.http_version)
+                    .trim_end()
+                    .to_string(),
+                other_lines: import_headers(&request.headers),
+                body: post_data
+                    .map(|p| import_body(&p.text, p.encoding.as_deref()))
+                    .transpose()?,
+                content_type: post_data
+                    .map(|p| p.mime_type.clone())
+                    .filter(|m| !m.is_empty()),
+            },
+        ));
+        // status 0 means that we didn't get a response
+        let response = &entry.response;
+        if response.status != 0 {
+            let response_info = TSharkPacketBasicInfo {
+                frame_time: request_time + Duration::microseconds((entry.time * 1000.0) as i64),
+                ip_src: request_info.ip_dst,
+                ip_dst: request_info.ip_src,
+                port_src: request_info.port_dst,
+                port_dst: request_info.port_src,
+                ..request_info
+            };
+            packets.push(har_packet(
+                response_info,
+                TSharkHttp {
+                    http_type: Some(HttpType::Response),
+                    http_host: None,
+                    first_line: format!(
+                        "{} {} {}",
+                        response.http_version, response.status, response.status_text
+                    )
+                    .trim()
+                    .to_string(),
+                    other_lines: import_headers(&response.headers),
+                    body: response
+                        .content
+                        .text
+                        .as_ref()
+                        .map(|t| import_body(t, response.content.encoding.as_deref()))
+                        .transpose()?,
+                    content_type: Some(response.content.mime_type.clone())
+                        .filter(|m| !m.is_empty()),
+                },
+            ));
+        }
+    }
+    Ok(packets)
+}
+
+fn har_packet(basic_info: TSharkPacketBasicInfo, http: TSharkHttp) -> TSharkPacket {
+    TSharkPacket {
+        basic_info,
+        http: Some(vec![http]),
+        http2: None,
+        pgsql: None,
+        websocket: None,
+        tcp_payload: None,
+        is_malformed: false,
+    }
+}
+
+/// the same format as tshark gives us
+fn import_headers(headers: &[HarNameValue]) -> String {
+    headers
+        .iter()
+        .map(|h| format!("{}: {}\r\n", h.name, h.value))
+        .collect()
+}
+
+fn import_body(text: &str, encoding: Option<&str>) -> Result<Vec<u8>, String> {
+    match encoding {
+        Some(HAR_BASE64_ENCODING) => {
+            base64::decode(text).map_err(|e| format!("Invalid base64 body in HAR file: {}", e))
+        }
+        _ => Ok(text.as_bytes().to_vec()),
+    }
+}
+
+/// returns the scheme, the authority and the path with the query string
+fn split_url(url: &str) -> Option<(&str, &str, &str)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let rest = rest.split('#').next().unwrap_or(rest);
+    Some(match rest.find('/') {
+        Some(idx) => (scheme, &rest[..idx], &rest[idx..]),
+        None => (scheme, rest, "/"),
+    })
+}
+
+fn split_authority(authority: &str) -> (&str, Option<u16>) {
+    let host_port_separator = if authority.starts_with('[') {
+        // ipv6
+        authority.find("]:").map(|idx| idx + 1)
+    } else {
+        authority.rfind(':')
+    };
+    match host_port_separator {
+        Some(idx) => (&authority[..idx], authority[idx + 1..].parse().ok()),
+        None => (authority, None),
+    }
+}
+
+#[test]
+fn should_export_har() {
+    let request = HttpRequestResponseData {
+        content_type: Some("application/json".to_string()),
+        ..HttpRequestResponseData::new(
+            TcpStreamId(4),
+            TcpSeqNumber(1),
+            NaiveDate::from_ymd(2021, 3, 18).and_hms_milli(12, 8, 13, 0),
+            "POST /search?q=hello%20world&page=2 HTTP/1.1".to_string(),
+            vec![
+                ("Host".to_string(), "example.com".to_string()),
+                ("Cookie".to_string(), "session=abc; theme=dark".to_string()),
+            ],
+            HttpBody::Text("{\"a\":1}".to_string()),
+        )
+    };
+    let response = HttpRequestResponseData {
+        tcp_seq_number: TcpSeqNumber(2),
+        timestamp: NaiveDate::from_ymd(2021, 3, 18).and_hms_milli(12, 8, 13, 250),
+        first_line: "HTTP/1.1 404 Not Found".to_string(),
+        headers: vec![("Set-Cookie".to_string(), "id=42; Path=/".to_string())],
+        body: HttpBody::Binary(vec![0, 159, 146, 150]),
+        content_type: Some("image/png".to_string()),
+        interim_responses: vec![InterimResponse {
+            first_line: "HTTP/1.1 103 Early Hints".to_string(),
+            headers: vec![("Link".to_string(), "</a.css>; rel=preload".to_string())],
+        }],
+        trailers: vec![("Server-Timing".to_string(), "db;dur=53".to_string())],
+        ..request.clone()
+    };
+    let msg = HttpMessageData {
+        http_stream_id: 0,
+        request: Some(request),
+        response: Some(response),
+        http2_frames: vec![],
+    };
+    let cs = ClientServerInfo {
+        server_ip: "10.0.0.1".parse().unwrap(),
+        server_port: NetworkPort(8080),
+        client_ip: "10.0.0.2".parse().unwrap(),
+    };
+    let json = export_har(&[(TcpStreamId(4), cs, &msg)]).unwrap();
+    // the request has no trailers
+    assert_eq!(1, json.matches("\"_trailers\"").count());
+    let har: Har = serde_json::from_str(&json).unwrap();
+    let entry = &har.log.entries[0];
+    assert_eq!(250.0, entry.time);
+    assert_eq!(250.0, entry.timings.wait);
+    assert_eq!(Some("10.0.0.1"), entry.server_ip_address.as_deref());
+    assert_eq!("POST", entry.request.method);
+    assert_eq!(
+        "http://example.com/search?q=hello%20world&page=2",
+        entry.request.url
+    );
+    assert_eq!(
+        vec![name_value("q", "hello world"), name_value("page", "2")],
+        entry.request.query_string
+    );
+    assert_eq!(
+        vec![name_value("session", "abc"), name_value("theme", "dark")],
+        entry.request.cookies
+    );
+    assert_eq!(
+        Some("{\"a\":1}"),
+        entry.request.post_data.as_ref().map(|p| p.text.as_str())
+    );
+    assert_eq!(404, entry.response.status);
+    assert_eq!("Not Found", entry.response.status_text);
+    assert_eq!(vec![name_value("id", "42")], entry.response.cookies);
+    assert_eq!(Some("AJ+Slg=="), entry.response.content.text.as_deref());
+    assert_eq!(Some("base64"), entry.response.content.encoding.as_deref());
+    assert_eq!(4, entry.response.content.size);
+    assert_eq!(
+        vec![name_value("Server-Timing", "db;dur=53")],
+        entry.response.trailers
+    );
+    assert_eq!(
+        vec![HarInterimResponse {
+            status: 103,
+            headers: vec![name_value("Link", "</a.css>; rel=preload")],
+        }],
+        entry.response.interim_responses
+    );
+}
+
+#[test]
+fn should_import_har() {
+    let packets = parse_har(
+        r#"{"log": {"version": "1.2", "entries": [{
+              "startedDateTime": "2021-03-18T12:08:13.000+01:00",
+              "time": 120.5,
+              "serverIPAddress": "[2001:db8::1]",
+              "connection": "1234",
+              "request": {
+                  "method": "GET", "url": "https://example.com/a?b=c", "httpVersion": "HTTP/1.1",
+                  "headers": [{"name": "Host", "value": "example.com"}]
+              },
+              "response": {
+                  "status": 200, "statusText": "OK", "httpVersion": "HTTP/1.1",
+                  "headers": [{"name": "Content-Type", "value": "image/png"}],
+                  "content": {"size": 2, "mimeType": "image/png", "text": "AJ8=", "encoding": "base64"}
+              }
+          }, {
+              "startedDateTime": "2021-03-18T12:08:14.000+01:00",
+              "time": 0,
+              "request": {"method": "GET", "url": "https://example.com/failed", "httpVersion": "HTTP/1.1"},
+              "response": {"status": 0}
+          }]}}"#,
+    )
+    .unwrap();
+    assert_eq!(3, packets.len());
+    let mut store = HttpStreamsStore::default();
+    for packet in packets {
+        let stream_id = packet.basic_info.tcp_stream_id;
+        store.add_to_stream(stream_id, packet).unwrap();
+    }
+    for stream_id in store.tcp_stream_ids() {
+        store.finish_stream(stream_id).unwrap();
+    }
+    let cs = store.stream_client_server(TcpStreamId(0)).unwrap();
+    assert_eq!("2001:db8::1".parse::<IpAddr>().unwrap(), cs.server_ip);
+    assert_eq!(NetworkPort(443), cs.server_port);
+    assert_eq!(
+        Some("example.com"),
+        store.stream_summary_details(TcpStreamId(0))
+    );
+    let messages = store.stream_http_messages(TcpStreamId(0)).unwrap();
+    let request = messages[0].request.as_ref().unwrap();
+    let response = messages[0].response.as_ref().unwrap();
+    assert_eq!("GET /a?b=c HTTP/1.1", request.first_line);
+    assert_eq!("HTTP/1.1 200 OK", response.first_line);
+    assert_eq!(HttpBody::Binary(vec![0, 159]), response.body);
+    assert_eq!(
+        120,
+        (response.timestamp - request.timestamp).num_milliseconds()
+    );
+    let failed = store.stream_http_messages(TcpStreamId(1)).unwrap();
+    assert_eq!(None, failed[0].response);
+}
@@ -0,0 +1,212 @@
+// the HTTP2 frames which don't carry headers or data. They're here rather
+// than in the http2 module because HttpMessageData refers to them.
+use crate::custom_streams_store::ClientServerInfo;
+use crate::tshark_communication::NetworkPort;
+use chrono::NaiveDateTime;
+use std::net::IpAddr;
+
+// past that, we only count the connection events
+const MAX_CONNECTION_EVENTS: usize = 1000;
+
+/// the frames which don't carry headers or data for the message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Http2ControlFrame {
+    RstStream {
+        error_code: u32,
+    },
+    GoAway {
+        last_stream_id: u32,
+        error_code: u32,
+        debug_data: Option<String>,
+    },
+    Settings {
+        is_ack: bool,
+        settings: Vec<(u16, u32)>,
+    },
+    WindowUpdate {
+        increment: u32,
+    },
+    PushPromise {
+        promised_stream_id: u32,
+        headers: Vec<(String, String)>,
+    },
+}
+
+impl Http2ControlFrame {
+    pub fn frame_type_name(&self) -> &'static str {
+        match self {
+            Http2ControlFrame::RstStream { .. } => "RST_STREAM",
+            Http2ControlFrame::GoAway { .. } => "GOAWAY",
+            Http2ControlFrame::Settings { .. } => "SETTINGS",
+            Http2ControlFrame::WindowUpdate { .. } => "WINDOW_UPDATE",
+            Http2ControlFrame::PushPromise { .. } => "PUSH_PROMISE",
+        }
+    }
+
+    /// the error code of RST_STREAM and GOAWAY frames, if it's not NO_ERROR
+    pub fn error_code(&self) -> Option<u32> {
+        match self {
+            Http2ControlFrame::RstStream { error_code }
+            | Http2ControlFrame::GoAway { error_code, .. } => Some(*error_code).filter(|c| *c != 0),
+            _ => None,
+        }
+    }
+
+    pub fn details(&self) -> String {
+        match self {
+            Http2ControlFrame::RstStream { error_code } => error_code_desc(*error_code),
+            Http2ControlFrame::GoAway {
+                last_stream_id,
+                error_code,
+                debug_data,
+            } => {
+                let mut desc = format!(
+                    "{}, last stream {}",
+                    error_code_desc(*error_code),
+                    last_stream_id
+                );
+                if let Some(d) = debug_data {
+                    desc.push_str(&format!(": {}", d));
+                }
+                desc
+            }
+            Http2ControlFrame::Settings { is_ack: true, .. } => "ACK".to_string(),
+            Http2ControlFrame::Settings { settings, .. } => settings
+                .iter()
+                .map(|(id, v)| format!("{}={}", setting_name(*id), v))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Http2ControlFrame::WindowUpdate { increment } => format!("+{} bytes", increment),
+            Http2ControlFrame::PushPromise {
+                promised_stream_id,
+                headers,
+            } => {
+                let header = |name| {
+                    headers
+                        .iter()
+                        .find(|(k, _)| k == name)
+                        .map(|(_, v)| v.as_str())
+                        .unwrap_or("-")
+                };
+                format!(
+                    "stream {}: {} {}",
+                    promised_stream_id,
+                    header(":method"),
+                    header(":path")
+                )
+            }
+        }
+    }
+}
+
+/// https://tools.ietf.org/html/rfc7540#section-7
+pub fn error_code_desc(code: u32) -> String {
+    let name = match code {
+        0x0 => "NO_ERROR",
+        0x1 => "PROTOCOL_ERROR",
+        0x2 => "INTERNAL_ERROR",
+        0x3 => "FLOW_CONTROL_ERROR",
+        0x4 => "SETTINGS_TIMEOUT",
+        0x5 => "STREAM_CLOSED",
+        0x6 => "FRAME_SIZE_ERROR",
+        0x7 => "REFUSED_STREAM",
+        0x8 => "CANCEL",
+        0x9 => "COMPRESSION_ERROR",
+        0xa => "CONNECT_ERROR",
+        0xb => "ENHANCE_YOUR_CALM",
+        0xc => "INADEQUATE_SECURITY",
+        0xd => "HTTP_1_1_REQUIRED",
+        _ => return format!("error code {}", code),
+    };
+    format!("{} ({})", name, code)
+}
+
+/// https://tools.ietf.org/html/rfc7540#section-6.5.2
+fn setting_name(id: u16) -> String {
+    match id {
+        0x1 => "HEADER_TABLE_SIZE".to_string(),
+        0x2 => "ENABLE_PUSH".to_string(),
+        0x3 => "MAX_CONCURRENT_STREAMS".to_string(),
+        0x4 => "INITIAL_WINDOW_SIZE".to_string(),
+        0x5 => "MAX_FRAME_SIZE".to_string(),
+        0x6 => "MAX_HEADER_LIST_SIZE".to_string(),
+        0x8 => "ENABLE_CONNECT_PROTOCOL".to_string(),
+        _ => format!("SETTING_{}", id),
+    }
+}
+
+/// a HTTP2 frame which doesn't carry headers or data, with the packet it came in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Http2FrameEvent {
+    pub timestamp: NaiveDateTime,
+    // 0 for connection-level frames
+    pub http2_stream_id: u32,
+    pub src_ip: IpAddr,
+    pub src_port: NetworkPort,
+    pub frame: Http2ControlFrame,
+}
+
+impl Http2FrameEvent {
+    pub fn is_from_server(&self, client_server: &ClientServerInfo) -> bool {
+        self.src_ip == client_server.server_ip && self.src_port == client_server.server_port
+    }
+}
+
+/// the WINDOW_UPDATE frames that a peer sent, on all the streams of the connection.
+/// There are too many of them to list them one by one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowUpdateTotal {
+    pub last_timestamp: NaiveDateTime,
+    pub src_ip: IpAddr,
+    pub src_port: NetworkPort,
+    pub frame_count: usize,
+    pub total_increment: u64,
+}
+
+impl WindowUpdateTotal {
+    pub fn is_from_server(&self, client_server: &ClientServerInfo) -> bool {
+        self.src_ip == client_server.server_ip && self.src_port == client_server.server_port
+    }
+}
+
+/// the RST_STREAM, GOAWAY, SETTINGS... frames of a connection
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Http2ConnectionEvents {
+    // in order, up to MAX_CONNECTION_EVENTS
+    pub events: Vec<Http2FrameEvent>,
+    pub omitted_event_count: usize,
+    pub window_updates: Vec<WindowUpdateTotal>,
+}
+
+impl Http2ConnectionEvents {
+    pub fn add(&mut self, event: &Http2FrameEvent) {
+        if let Http2ControlFrame::WindowUpdate { increment } = event.frame {
+            match self
+                .window_updates
+                .iter_mut()
+                .find(|w| w.src_ip == event.src_ip && w.src_port == event.src_port)
+            {
+                Some(total) => {
+                    total.last_timestamp = event.timestamp;
+                    total.frame_count += 1;
+                    total.total_increment += increment as u64;
+                }
+                None => self.window_updates.push(WindowUpdateTotal {
+                    last_timestamp: event.timestamp,
+                    src_ip: event.src_ip,
+                    src_port: event.src_port,
+                    frame_count: 1,
+                    total_increment: increment as u64,
+                }),
+            }
+        } else if self.events.len() < MAX_CONNECTION_EVENTS {
+            self.events.push(event.clone());
+        } else {
+            self.omitted_event_count += 1;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty() && self.window_updates.is_empty()
+    }
+}
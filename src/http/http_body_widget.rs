@@ -1,6 +1,12 @@
+use super::body_tree::BodyTree;
 use super::code_formatting;
 use super::http_streams_store;
 use super::http_streams_store::{HttpBody, HttpRequestResponseData};
+use super::structured_data;
+use crate::http2::grpc;
+use crate::http2::proto_schema::ConfiguredProtoSchema;
+use crate::widgets::hex_viewer;
+use crate::widgets::hex_viewer::{FileType, HexViewer};
 use crate::widgets::win;
 use crate::BgFunc;
 use gdk_pixbuf::prelude::*;
@@ -9,10 +15,20 @@ use relm::Widget;
 use relm_derive::{widget, Msg};
 use std::borrow::Cow;
 use std::sync::mpsc;
+use std::sync::Arc;
 
 const TEXT_CONTENTS_STACK_NAME: &str = "text";
 const IMAGE_CONTENTS_STACK_NAME: &str = "image";
 const BINARY_CONTENTS_STACK_NAME: &str = "binary";
+const TREE_CONTENTS_STACK_NAME: &str = "tree";
+const GRPC_CONTENTS_STACK_NAME: &str = "grpc";
+
+const TREE_COL_KEY: u32 = 0;
+const TREE_COL_SUMMARY: u32 = 1;
+const TREE_COL_LOCATOR: u32 = 2;
+const TREE_COL_PATH: u32 = 3;
+// a fake child, so that gtk displays the expander of nodes whose children we didn't load yet
+const TREE_COL_IS_PLACEHOLDER: u32 = 4;
 
 const KNOWN_CONTENT_TYPE_PREFIXES: &[(&str, &str)] = &[
     ("image/", "image"),
@@ -29,23 +45,45 @@ pub struct SavedBodyData {
 #[derive(Msg, Debug)]
 pub enum Msg {
     FormatCodeChanged(bool),
+    GrpcSchemaChanged(Option<Arc<ConfiguredProtoSchema>>),
     RequestResponseChanged {
         http_data: Option<HttpRequestResponseData>,
         request_first_line_if_response: Option<String>,
     },
     SaveContents,
+    DisplayAsTree(bool),
+    GotBodyTree(usize, Option<BodyTree>),
+    BodyTreeRowExpanded(gtk::TreePath),
+    BodyTreeSelectionChanged,
+    CopyBodyTreeValue,
+    CopyBodyTreePath,
 }
 
 pub struct Model {
     win_msg_sender: relm::StreamHandle<win::Msg>,
+    bg_sender: mpsc::Sender<BgFunc>,
 
     format_code: bool,
     data: Option<HttpRequestResponseData>,
 
     request_first_line_if_response: Option<String>,
 
+    display_as_tree: bool,
+    // incremented when the data changes, so that we can drop
+    // the trees parsed in the background for a previous body
+    body_tree_data_id: usize,
+    body_tree: Option<BodyTree>,
+    body_tree_store: gtk::TreeStore,
+    selected_tree_path: String,
+
+    grpc_schema: Option<Arc<ConfiguredProtoSchema>>,
+    grpc_markup: String,
+
     _saved_body_channel: relm::Channel<SavedBodyData>,
     saved_body_sender: relm::Sender<SavedBodyData>,
+
+    _body_tree_channel: relm::Channel<(usize, Option<BodyTree>)>,
+    body_tree_sender: relm::Sender<(usize, Option<BodyTree>)>,
 }
 
 #[widget]
@@ -56,13 +94,31 @@ impl Widget for HttpBodyWidget {
                 .label("The message body is too large, has been truncated for display")
                 .build(),
         );
+        self.init_body_treeview();
+    }
+
+    fn init_body_treeview(&self) {
+        let tv = &self.widgets.body_treeview;
+        for (title, col_idx) in &[("Key", TREE_COL_KEY), ("Value", TREE_COL_SUMMARY)] {
+            let col = gtk::builders::TreeViewColumnBuilder::new()
+                .title(title)
+                .resizable(true)
+                .build();
+            let cell_txt = gtk::builders::CellRendererTextBuilder::new()
+                .ellipsize(pango::EllipsizeMode::End)
+                .build();
+            col.pack_start(&cell_txt, true);
+            col.add_attribute(&cell_txt, "text", *col_idx as i32);
+            tv.append_column(&col);
+        }
+        tv.set_model(Some(&self.model.body_tree_store));
     }
 
     fn model(
-        _relm: &relm::Relm<Self>,
+        relm: &relm::Relm<Self>,
         params: (relm::StreamHandle<win::Msg>, mpsc::Sender<BgFunc>),
     ) -> Model {
-        let (win_msg_sender, _bg_sender) = params;
+        let (win_msg_sender, bg_sender) = params;
         let (_saved_body_channel, saved_body_sender) = {
             let win_stream = win_msg_sender.clone();
             relm::Channel::new(move |d: SavedBodyData| {
@@ -72,13 +128,35 @@ impl Widget for HttpBodyWidget {
                 ))
             })
         };
+        let (_body_tree_channel, body_tree_sender) = {
+            let stream = relm.stream().clone();
+            relm::Channel::new(move |(data_id, tree)| {
+                stream.emit(Msg::GotBodyTree(data_id, tree));
+            })
+        };
         Model {
             win_msg_sender,
+            bg_sender,
             format_code: true,
             data: None,
             request_first_line_if_response: None,
+            display_as_tree: false,
+            body_tree_data_id: 0,
+            body_tree: None,
+            body_tree_store: gtk::TreeStore::new(&[
+                String::static_type(), // key
+                String::static_type(), // value summary
+                String::static_type(), // locator
+                String::static_type(), // JSONPath/XPath
+                bool::static_type(),   // is placeholder
+            ]),
+            selected_tree_path: "".to_string(),
+            grpc_schema: None,
+            grpc_markup: "".to_string(),
             _saved_body_channel,
             saved_body_sender,
+            _body_tree_channel,
+            body_tree_sender,
         }
     }
 
@@ -88,6 +166,17 @@ impl Widget for HttpBodyWidget {
             Msg::FormatCodeChanged(format_code) => {
                 self.model.format_code = format_code;
             }
+            Msg::GrpcSchemaChanged(schema) => {
+                self.model.grpc_schema = schema;
+                if grpc::is_grpc(
+                    self.model
+                        .data
+                        .as_ref()
+                        .and_then(|d| d.content_type.as_deref()),
+                ) {
+                    self.display_grpc_messages();
+                }
+            }
             Msg::RequestResponseChanged {
                 http_data,
                 request_first_line_if_response,
@@ -98,12 +187,19 @@ impl Widget for HttpBodyWidget {
                 // child after that, if needed.
                 self.model.data = http_data.clone();
                 self.model.request_first_line_if_response = request_first_line_if_response;
+                self.model.body_tree_data_id += 1;
+                self.model.body_tree = None;
 
                 // need to try to decode as string.. the content-type may not be
                 // populated or be too exotic, and binary contents don't mean much
                 // if the data is encoded as brotli, gzip and so on
                 let is_data_str = http_data.as_ref().and_then(|d| d.body_as_str()).is_some();
 
+                if grpc::is_grpc(http_data.as_ref().and_then(|d| d.content_type.as_deref())) {
+                    self.display_grpc_messages();
+                    return;
+                }
+
                 match (
                     &http_data.as_ref().and_then(|d| d.content_type.as_deref()),
                     &http_data.as_ref().map(|d| &d.body),
@@ -114,7 +210,10 @@ impl Widget for HttpBodyWidget {
                     {
                         self.display_image(bytes);
                     }
-                    (_, _, false) => {
+                    (_, body, false) => {
+                        self.streams.hex_viewer.emit(hex_viewer::Msg::DisplayBytes(
+                            body.map(Self::body_bytes).unwrap_or_default().to_vec(),
+                        ));
                         self.widgets
                             .contents_stack
                             .set_visible_child_name(BINARY_CONTENTS_STACK_NAME);
@@ -129,12 +228,55 @@ impl Widget for HttpBodyWidget {
                                 .len()
                                 > code_formatting::BODY_TRUNCATE_LIMIT_BYTES,
                         );
-                        self.widgets
-                            .contents_stack
-                            .set_visible_child_name(TEXT_CONTENTS_STACK_NAME);
+                        if self.model.display_as_tree && self.body_tree_supported() {
+                            self.display_body_tree();
+                        } else {
+                            self.widgets
+                                .contents_stack
+                                .set_visible_child_name(TEXT_CONTENTS_STACK_NAME);
+                        }
+                    }
+                }
+            }
+            Msg::DisplayAsTree(as_tree) => {
+                self.model.display_as_tree = as_tree;
+                if as_tree {
+                    self.display_body_tree();
+                } else {
+                    self.widgets
+                        .contents_stack
+                        .set_visible_child_name(TEXT_CONTENTS_STACK_NAME);
+                }
+            }
+            Msg::GotBodyTree(data_id, tree) => {
+                if data_id == self.model.body_tree_data_id {
+                    self.model.body_tree = tree;
+                    if self.model.display_as_tree {
+                        self.show_body_tree();
                     }
                 }
             }
+            Msg::BodyTreeRowExpanded(path) => {
+                self.load_body_tree_children(&path);
+            }
+            Msg::BodyTreeSelectionChanged => {
+                self.model.selected_tree_path = self
+                    .selected_body_tree_column(TREE_COL_PATH)
+                    .unwrap_or_default();
+            }
+            Msg::CopyBodyTreePath => {
+                if let Some(path) = self.selected_body_tree_column(TREE_COL_PATH) {
+                    self.copy_to_clipboard(&path);
+                }
+            }
+            Msg::CopyBodyTreeValue => {
+                if let Some(value) = self
+                    .selected_body_tree_column(TREE_COL_LOCATOR)
+                    .and_then(|l| self.model.body_tree.as_ref()?.value_text(&l))
+                {
+                    self.copy_to_clipboard(&value);
+                }
+            }
             Msg::SaveContents => {
                 let dialog = gtk::builders::FileChooserNativeBuilder::new()
                     .action(gtk::FileChooserAction::Save)
@@ -157,11 +299,11 @@ impl Widget for HttpBodyWidget {
                         .send(SavedBodyData {
                             error_msg: std::fs::write(
                                 target_fname,
-                                match self.model.data.as_ref().map(|d| &d.body) {
-                                    Some(HttpBody::Binary(ref bytes)) => bytes,
-                                    Some(HttpBody::Text(ref txt)) => txt.as_bytes(),
-                                    _ => &[],
-                                },
+                                self.model
+                                    .data
+                                    .as_ref()
+                                    .map(|d| Self::body_bytes(&d.body))
+                                    .unwrap_or_default(),
                             )
                             .err()
                             .map(|e| e.to_string()),
@@ -172,6 +314,191 @@ impl Widget for HttpBodyWidget {
         }
     }
 
+    fn body_bytes(body: &HttpBody) -> &[u8] {
+        match body {
+            HttpBody::Binary(bytes) => bytes,
+            HttpBody::Text(txt) => txt.as_bytes(),
+            HttpBody::Missing => &[],
+        }
+    }
+
+    fn display_grpc_messages(&mut self) {
+        let data = match &self.model.data {
+            Some(d) => d,
+            None => return,
+        };
+        let mut markup = String::new();
+        let schema = match self.model.grpc_schema.as_deref() {
+            Some(ConfiguredProtoSchema {
+                path,
+                schema: Err(e),
+            }) => {
+                markup.push_str(&format!(
+                    "<i>Failed loading the protobuf definitions from {}: {}</i>\n\n",
+                    glib::markup_escape_text(path),
+                    glib::markup_escape_text(e)
+                ));
+                None
+            }
+            Some(ConfiguredProtoSchema { schema: Ok(s), .. }) => Some(s),
+            None => None,
+        };
+        let messages = grpc::describe_messages(
+            self.model
+                .request_first_line_if_response
+                .as_deref()
+                .unwrap_or(&data.first_line),
+            self.model.request_first_line_if_response.is_some(),
+            &data.headers,
+            Self::body_bytes(&data.body),
+            schema,
+        );
+        for (title, contents) in messages {
+            markup.push_str(&format!(
+                "<b>{}</b>\n{}\n",
+                glib::markup_escape_text(&title),
+                glib::markup_escape_text(&contents)
+            ));
+        }
+        self.model.grpc_markup = markup;
+        self.widgets
+            .contents_stack
+            .set_visible_child_name(GRPC_CONTENTS_STACK_NAME);
+    }
+
+    fn body_tree_supported(&self) -> bool {
+        BodyTree::supports_content_type(
+            self.model
+                .data
+                .as_ref()
+                .and_then(|d| d.content_type.as_deref()),
+        )
+    }
+
+    /// the body is parsed once, in the background, then we keep the tree
+    fn display_body_tree(&mut self) {
+        if self.model.body_tree.is_some() {
+            self.show_body_tree();
+            return;
+        }
+        let (body, content_type) = match &self.model.data {
+            Some(d) => (
+                d.body_as_str().unwrap_or_default().into_owned(),
+                d.content_type.clone(),
+            ),
+            None => return,
+        };
+        let data_id = self.model.body_tree_data_id;
+        let sender = self.model.body_tree_sender.clone();
+        self.model
+            .bg_sender
+            .send(BgFunc::new(move || {
+                sender
+                    .send((data_id, BodyTree::parse(&body, content_type.as_deref())))
+                    .unwrap();
+            }))
+            .unwrap();
+    }
+
+    /// we parsed the whole body, but only add the root node to the gtk tree.
+    /// The other nodes are added when their parent is expanded.
+    fn show_body_tree(&mut self) {
+        let store = &self.model.body_tree_store;
+        store.clear();
+        self.model.selected_tree_path = "".to_string();
+        let root = match &self.model.body_tree {
+            Some(tree) => tree.root(),
+            None => {
+                self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                    Some("Failed parsing the body to display it as a tree".to_string()),
+                    win::InfobarOptions::ShowCloseButton,
+                ));
+                self.model.display_as_tree = false;
+                self.widgets
+                    .contents_stack
+                    .set_visible_child_name(TEXT_CONTENTS_STACK_NAME);
+                return;
+            }
+        };
+        let root_iter = Self::add_body_tree_node(store, None, &root);
+        self.widgets
+            .contents_stack
+            .set_visible_child_name(TREE_CONTENTS_STACK_NAME);
+        if let Some(root_path) = store.path(&root_iter) {
+            self.widgets.body_treeview.expand_row(&root_path, false);
+        }
+    }
+
+    fn add_body_tree_node(
+        store: &gtk::TreeStore,
+        parent: Option<&gtk::TreeIter>,
+        node: &super::body_tree::TreeNode,
+    ) -> gtk::TreeIter {
+        let iter = store.insert_with_values(
+            parent,
+            None,
+            &[
+                (TREE_COL_KEY, &node.key),
+                (TREE_COL_SUMMARY, &node.summary),
+                (TREE_COL_LOCATOR, &node.locator),
+                (TREE_COL_PATH, &node.path),
+                (TREE_COL_IS_PLACEHOLDER, &false),
+            ],
+        );
+        if node.has_children {
+            store.insert_with_values(Some(&iter), None, &[(TREE_COL_IS_PLACEHOLDER, &true)]);
+        }
+        iter
+    }
+
+    fn load_body_tree_children(&self, path: &gtk::TreePath) {
+        let store = &self.model.body_tree_store;
+        let (tree, iter) = match (&self.model.body_tree, store.iter(path)) {
+            (Some(t), Some(i)) => (t, i),
+            _ => return,
+        };
+        let placeholder = match store.iter_children(Some(&iter)) {
+            Some(c)
+                if store
+                    .value(&c, TREE_COL_IS_PLACEHOLDER as i32)
+                    .get::<bool>()
+                    == Ok(true) =>
+            {
+                c
+            }
+            // already loaded
+            _ => return,
+        };
+        let column_str = |col: u32| {
+            store
+                .value(&iter, col as i32)
+                .get::<String>()
+                .unwrap_or_default()
+        };
+        for child in tree.children(&column_str(TREE_COL_LOCATOR), &column_str(TREE_COL_PATH)) {
+            Self::add_body_tree_node(store, Some(&iter), &child);
+        }
+        store.remove(&placeholder);
+    }
+
+    fn selected_body_tree_column(&self, col: u32) -> Option<String> {
+        self.widgets
+            .body_treeview
+            .selection()
+            .selected()
+            .and_then(|(model, iter)| model.value(&iter, col as i32).get::<String>().ok())
+    }
+
+    fn copy_to_clipboard(&self, contents: &str) {
+        if let Some(clip) = gtk::Clipboard::default(&self.widgets.body_treeview.display()) {
+            clip.set_text(contents);
+            self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                Some("Copied to the clipboard".to_string()),
+                win::InfobarOptions::TimeLimitedWithCloseButton,
+            ));
+        }
+    }
+
     fn display_image(&self, bytes: &[u8]) {
         let loader = gdk_pixbuf::PixbufLoader::new();
         let r = loader.write(bytes);
@@ -194,11 +521,7 @@ impl Widget for HttpBodyWidget {
             .and_then(|d| {
                 http_streams_store::get_http_header_value(&d.headers, "Content-Disposition")
             })
-            .and_then(|d| {
-                d.strip_prefix("attachment: filename=\"")
-                    .and_then(|f| f.strip_suffix('"'))
-                    .map(|f| f.to_string())
-            });
+            .and_then(|d| structured_data::header_param(d, "filename"));
         attachment_name
             .or_else(|| {
                 self.model
@@ -213,6 +536,13 @@ impl Widget for HttpBodyWidget {
                     .and_then(|d| d.content_type.as_ref())
                     .and_then(|ct| Self::filename_from_binary_content_type(ct))
             })
+            .or_else(|| {
+                self.model
+                    .data
+                    .as_ref()
+                    .and_then(|d| FileType::guess(Self::body_bytes(&d.body)))
+                    .map(|t| format!("data.{}", t.extension()))
+            })
             .unwrap_or_else(|| "data.bin".to_string())
     }
 
@@ -272,6 +602,13 @@ impl Widget for HttpBodyWidget {
                    name: Some(TEXT_CONTENTS_STACK_NAME)
                },
                orientation: gtk::Orientation::Vertical,
+               gtk::Button {
+                   label: "Display as tree",
+                   halign: gtk::Align::Start,
+                   visible: BodyTree::supports_content_type(
+                       self.model.data.as_ref().and_then(|d| d.content_type.as_deref())),
+                   clicked => Msg::DisplayAsTree(true),
+               },
                #[name="too_long_header"]
                gtk::Box {
                    #[name="too_long_infobar"]
@@ -297,6 +634,45 @@ impl Widget for HttpBodyWidget {
                    },
                }
            },
+           gtk::Box {
+               child: {
+                   name: Some(TREE_CONTENTS_STACK_NAME)
+               },
+               orientation: gtk::Orientation::Vertical,
+               spacing: 5,
+               gtk::Box {
+                   spacing: 5,
+                   gtk::Button {
+                       label: "Display as text",
+                       clicked => Msg::DisplayAsTree(false),
+                   },
+                   gtk::Label {
+                       label: &self.model.selected_tree_path,
+                       hexpand: true,
+                       xalign: 0.0,
+                       selectable: true,
+                       ellipsize: pango::EllipsizeMode::Middle,
+                   },
+                   gtk::Button {
+                       label: "Copy path",
+                       sensitive: !self.model.selected_tree_path.is_empty(),
+                       clicked => Msg::CopyBodyTreePath,
+                   },
+                   gtk::Button {
+                       label: "Copy value",
+                       sensitive: !self.model.selected_tree_path.is_empty(),
+                       clicked => Msg::CopyBodyTreeValue,
+                   },
+               },
+               gtk::ScrolledWindow {
+                   height_request: 400,
+                   #[name="body_treeview"]
+                   gtk::TreeView {
+                       row_expanded(_, _, path) => Msg::BodyTreeRowExpanded(path.clone()),
+                       cursor_changed => Msg::BodyTreeSelectionChanged,
+                   },
+               },
+           },
            gtk::Box {
                child: {
                    name: Some(IMAGE_CONTENTS_STACK_NAME)
@@ -320,9 +696,9 @@ impl Widget for HttpBodyWidget {
                    name: Some(BINARY_CONTENTS_STACK_NAME)
                },
                orientation: gtk::Orientation::Vertical,
-               gtk::Label {
-                   text: "Body contents are binary data",
-                   halign: gtk::Align::Start,
+               spacing: 5,
+               #[name="hex_viewer"]
+               HexViewer(self.model.win_msg_sender.clone()) {
                },
                gtk::Button {
                    always_show_image: true,
@@ -332,6 +708,26 @@ impl Widget for HttpBodyWidget {
                    label: "Save body contents",
                    halign: gtk::Align::Start,
                }
+           },
+           gtk::Box {
+               child: {
+                   name: Some(GRPC_CONTENTS_STACK_NAME)
+               },
+               orientation: gtk::Orientation::Vertical,
+               spacing: 5,
+               gtk::Label {
+                   markup: &self.model.grpc_markup,
+                   xalign: 0.0,
+                   selectable: true,
+               },
+               gtk::Button {
+                   always_show_image: true,
+                   image: Some(&gtk::Image::from_icon_name(
+                        Some("document-save-symbolic"), gtk::IconSize::Menu)),
+                   button_press_event(_, _) => (Msg::SaveContents, Inhibit(false)),
+                   label: "Save body contents",
+                   halign: gtk::Align::Start,
+               }
            }
        }
     }
@@ -1,26 +1,55 @@
+use super::auth_info;
+use super::auth_info::{AuthInfo, Jwt};
+use super::http2_frames::{Http2ConnectionEvents, Http2FrameEvent};
 use super::http_body_widget;
 use super::http_body_widget::HttpBodyWidget;
-use super::http_streams_store::{HttpMessageData, HttpRequestResponseData};
+use super::http_replay_window::HttpReplayWindow;
+use super::http_streams_store::{
+    HttpBody, HttpMessageData, HttpRequestResponseData, InterimResponse,
+};
+use super::request_export;
+use super::request_export::RequestExportFormat;
+use super::server_sent_events::ServerSentEvent;
+use super::structured_data;
+use super::structured_data::{MultipartPart, SetCookie};
+use crate::colors;
+use crate::custom_streams_store::ClientServerInfo;
+use crate::http2::proto_schema::ConfiguredProtoSchema;
 use crate::icons::Icon;
 use crate::tshark_communication::TcpStreamId;
 use crate::widgets::comm_info_header;
 use crate::widgets::comm_info_header::CommInfoHeader;
 use crate::widgets::win;
 use crate::BgFunc;
+use chrono::NaiveDateTime;
 use gtk::prelude::*;
 use itertools::Itertools;
-use relm::Widget;
+use relm::{ContainerWidget, Widget};
 use relm_derive::{widget, Msg};
 use std::borrow::Cow;
 use std::net::IpAddr;
 use std::sync::mpsc;
+use std::sync::Arc;
 
 #[derive(Msg, Debug)]
 pub enum Msg {
-    DisplayDetails(mpsc::Sender<BgFunc>, IpAddr, TcpStreamId, HttpMessageData),
+    DisplayDetails(
+        mpsc::Sender<BgFunc>,
+        ClientServerInfo,
+        TcpStreamId,
+        HttpMessageData,
+    ),
+    // http2 only, sent after DisplayDetails
+    DisplayConnectionEvents(Http2ConnectionEvents),
+    // live captures: the events an event stream got since we displayed it
+    AppendServerSentEvents(Vec<ServerSentEvent>),
+    GrpcSchemaChanged(Option<Arc<ConfiguredProtoSchema>>),
     RemoveFormatToggled,
     CopyContentsClick,
+    CopyRequestAs(RequestExportFormat),
+    ReplayRequest,
     ToggleDisplayPassword,
+    JwtSecretChanged,
 }
 
 pub struct Model {
@@ -28,9 +57,27 @@ pub struct Model {
     bg_sender: mpsc::Sender<BgFunc>,
     stream_id: TcpStreamId,
     client_ip: IpAddr,
+    client_server: Option<ClientServerInfo>,
     data: HttpMessageData,
     basic_auth_username: Option<String>,
     basic_auth_password: Option<String>,
+    auth_infos: Vec<AuthInfo>,
+    auth_info_markup: String,
+    has_hs256_jwt: bool,
+    jwt_verification_markup: String,
+    replay_win: Option<relm::Component<HttpReplayWindow>>,
+    grpc_schema: Option<Arc<ConfiguredProtoSchema>>,
+    request_query_params: Vec<(String, String)>,
+    request_form_fields: Vec<(String, String)>,
+    request_cookies: Vec<(String, String)>,
+    response_cookies: Vec<SetCookie>,
+    has_multipart_parts: bool,
+    multipart_body_components: Vec<relm::Component<HttpBodyWidget>>,
+    has_server_sent_events: bool,
+    events_store: gtk::ListStore,
+    http2_frames_markup: String,
+    has_connection_events: bool,
+    connection_events_store: gtk::ListStore,
 
     options_popover: gtk::Popover,
     format_contents_btn: gtk::CheckButton,
@@ -62,8 +109,6 @@ impl Widget for HttpCommEntry {
             .label("Copy to clipboard")
             .build();
         popover_box.add(&copy_to_clipboard_lbl);
-        popover_box.show_all();
-
         relm::connect!(
             relm,
             copy_to_clipboard_lbl,
@@ -71,6 +116,28 @@ impl Widget for HttpCommEntry {
             Msg::CopyContentsClick
         );
 
+        for (label, format) in &[
+            ("Copy request as curl", RequestExportFormat::Curl),
+            ("Copy request as HTTPie", RequestExportFormat::Httpie),
+            ("Copy request as raw HTTP", RequestExportFormat::RawHttp),
+        ] {
+            let copy_request_btn = gtk::builders::ButtonBuilder::new().label(label).build();
+            popover_box.add(&copy_request_btn);
+            let format = *format;
+            relm::connect!(
+                relm,
+                copy_request_btn,
+                connect_clicked(_),
+                Msg::CopyRequestAs(format)
+            );
+        }
+        let replay_btn = gtk::builders::ButtonBuilder::new()
+            .label("Replay request...")
+            .build();
+        popover_box.add(&replay_btn);
+        relm::connect!(relm, replay_btn, connect_clicked(_), Msg::ReplayRequest);
+        popover_box.show_all();
+
         let options_popover = gtk::builders::PopoverBuilder::new()
             .child(&popover_box)
             .build();
@@ -116,33 +183,210 @@ impl Widget for HttpCommEntry {
             data,
             stream_id,
             client_ip,
+            client_server: None,
             format_contents_btn,
             options_popover,
             format_request_response: true,
             basic_auth_username: None,
             basic_auth_password: None,
+            auth_infos: vec![],
+            auth_info_markup: "".to_string(),
+            has_hs256_jwt: false,
+            jwt_verification_markup: "".to_string(),
+            replay_win: None,
+            grpc_schema: None,
+            request_query_params: vec![],
+            request_form_fields: vec![],
+            request_cookies: vec![],
+            response_cookies: vec![],
+            has_multipart_parts: false,
+            multipart_body_components: vec![],
+            has_server_sent_events: false,
+            events_store: gtk::ListStore::new(&[
+                String::static_type(), // timestamp
+                String::static_type(), // event type
+                String::static_type(), // event id
+                String::static_type(), // data
+                String::static_type(), // retry
+            ]),
+            http2_frames_markup: "".to_string(),
+            has_connection_events: false,
+            connection_events_store: gtk::ListStore::new(&[
+                String::static_type(), // timestamp
+                String::static_type(), // sent by
+                u32::static_type(),    // http2 stream id
+                String::static_type(), // frame type
+                String::static_type(), // details
+            ]),
         }
     }
 
-    fn update_basic_auth_data(&mut self, message_data: &HttpMessageData) {
-        let empty = vec![];
-        let mut req_headers = message_data
-            .request
-            .as_ref()
-            .map(|r| &r.headers)
-            .unwrap_or(&empty)
-            .iter();
-        let auth_prefix = "Basic ";
-        let auth_header = req_headers
-            .find(|(k, v)| k.to_ascii_lowercase() == "authorization" && v.starts_with(auth_prefix))
-            .map(|(_k, v)| &v[(auth_prefix.len())..])
-            .and_then(|s| base64::decode(s).ok())
-            .and_then(|s| String::from_utf8(s).ok())
-            .and_then(|s| {
-                s.split_once(':')
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-            });
-        if let Some((k, v)) = auth_header {
+    fn init_view(&mut self) {
+        Self::init_text_columns(
+            &self.widgets.events_treeview,
+            &["Timestamp", "Event", "ID", "Data", "Retry"],
+            3,
+        );
+        self.widgets
+            .events_treeview
+            .set_model(Some(&self.model.events_store));
+        Self::init_text_columns(
+            &self.widgets.connection_events_treeview,
+            &["Timestamp", "Sent by", "Stream", "Frame", "Details"],
+            4,
+        );
+        self.widgets
+            .connection_events_treeview
+            .set_model(Some(&self.model.connection_events_store));
+    }
+
+    /// the expanding column is ellipsized
+    fn init_text_columns(tv: &gtk::TreeView, titles: &[&str], expand_idx: usize) {
+        for (idx, title) in titles.iter().enumerate() {
+            let is_expand = idx == expand_idx;
+            let col = gtk::builders::TreeViewColumnBuilder::new()
+                .title(title)
+                .expand(is_expand)
+                .resizable(true)
+                .build();
+            let cell_txt = gtk::builders::CellRendererTextBuilder::new()
+                .ellipsize(if is_expand {
+                    pango::EllipsizeMode::End
+                } else {
+                    pango::EllipsizeMode::None
+                })
+                .build();
+            col.pack_start(&cell_txt, true);
+            col.add_attribute(&cell_txt, "text", idx as i32);
+            tv.append_column(&col);
+        }
+    }
+
+    fn frame_sender_desc(is_from_server: Option<bool>) -> &'static str {
+        match is_from_server {
+            Some(true) => "server",
+            Some(false) => "client",
+            None => "-",
+        }
+    }
+
+    /// the HTTP2 frames about this message, such as RST_STREAM
+    fn format_http2_frames(
+        frames: &[Http2FrameEvent],
+        client_server: Option<&ClientServerInfo>,
+    ) -> String {
+        frames
+            .iter()
+            .map(|f| {
+                let desc = format!(
+                    "<b>{}</b> from the {} at {}: {}",
+                    f.frame.frame_type_name(),
+                    Self::frame_sender_desc(client_server.map(|cs| f.is_from_server(cs))),
+                    f.timestamp,
+                    glib::markup_escape_text(&f.frame.details())
+                );
+                if f.frame.error_code().is_some() {
+                    format!(
+                        "<span foreground=\"{}\">{}</span>",
+                        colors::ERROR_COLOR,
+                        desc
+                    )
+                } else {
+                    desc
+                }
+            })
+            .join("\n")
+    }
+
+    fn update_connection_events(&mut self, events: &Http2ConnectionEvents) {
+        let ls = &self.model.connection_events_store;
+        let client_server = self.model.client_server.as_ref();
+        ls.clear();
+        for evt in &events.events {
+            ls.insert_with_values(
+                None,
+                &[
+                    (0, &evt.timestamp.to_string()),
+                    (
+                        1,
+                        &Self::frame_sender_desc(client_server.map(|cs| evt.is_from_server(cs))),
+                    ),
+                    (2, &evt.http2_stream_id),
+                    (3, &evt.frame.frame_type_name()),
+                    (4, &evt.frame.details()),
+                ],
+            );
+        }
+        if events.omitted_event_count > 0 {
+            ls.insert_with_values(
+                None,
+                &[(
+                    4,
+                    &format!("{} more events not displayed", events.omitted_event_count),
+                )],
+            );
+        }
+        for total in &events.window_updates {
+            ls.insert_with_values(
+                None,
+                &[
+                    (0, &total.last_timestamp.to_string()),
+                    (
+                        1,
+                        &Self::frame_sender_desc(client_server.map(|cs| total.is_from_server(cs))),
+                    ),
+                    (3, &"WINDOW_UPDATE"),
+                    (
+                        4,
+                        &format!(
+                            "{} frames on all the streams, +{} bytes in total",
+                            total.frame_count, total.total_increment
+                        ),
+                    ),
+                ],
+            );
+        }
+        self.model.has_connection_events = !events.is_empty();
+    }
+
+    /// the events of text/event-stream responses, one row per event
+    fn update_server_sent_events(&mut self, response: Option<&HttpRequestResponseData>) {
+        self.model.events_store.clear();
+        self.model.has_server_sent_events = false;
+        self.append_server_sent_events(
+            response
+                .map(|r| r.server_sent_events.as_slice())
+                .unwrap_or(&[]),
+        );
+    }
+
+    fn append_server_sent_events(&mut self, events: &[ServerSentEvent]) {
+        let ls = &self.model.events_store;
+        for evt in events {
+            ls.insert_with_values(
+                None,
+                &[
+                    (0, &evt.timestamp.to_string()),
+                    (1, &evt.event_type()),
+                    (2, &evt.id.as_deref().unwrap_or("")),
+                    (3, &evt.data.replace('\n', " ")),
+                    (
+                        4,
+                        &evt.retry.map(|r| format!("{} ms", r)).unwrap_or_default(),
+                    ),
+                ],
+            );
+        }
+        self.model.has_server_sent_events |= !events.is_empty();
+    }
+
+    fn update_auth_data(&mut self, message_data: &HttpMessageData) {
+        let auth_infos = auth_info::parse_auth_infos(message_data);
+        let basic_auth = auth_infos.iter().find_map(|a| match a {
+            AuthInfo::Basic { username, password } => Some((username.clone(), password.clone())),
+            _ => None,
+        });
+        if let Some((k, v)) = basic_auth {
             self.model.basic_auth_username = Some(k);
             self.model.basic_auth_password = Some(v);
         } else {
@@ -153,6 +397,241 @@ impl Widget for HttpCommEntry {
         self.widgets
             .basic_auth_info
             .set_visible(self.model.basic_auth_username.is_some());
+        self.model.auth_info_markup = Self::format_auth_infos(
+            &auth_infos,
+            message_data.request.as_ref().map(|r| r.timestamp),
+        );
+        self.model.has_hs256_jwt = auth_infos
+            .iter()
+            .filter_map(Self::auth_info_jwt)
+            .any(|(_, jwt)| jwt.algorithm() == Some("HS256"));
+        self.model.auth_infos = auth_infos;
+        self.refresh_jwt_verification();
+    }
+
+    fn auth_info_jwt(auth_info: &AuthInfo) -> Option<(String, &Jwt)> {
+        match auth_info {
+            AuthInfo::Bearer { jwt: Some(jwt), .. } => Some(("Bearer token".to_string(), jwt)),
+            AuthInfo::CookieJwt { cookie_name, jwt } => {
+                Some((format!("Cookie {}", cookie_name), jwt))
+            }
+            _ => None,
+        }
+    }
+
+    fn refresh_jwt_verification(&mut self) {
+        let secret = self.widgets.jwt_secret_entry.text();
+        self.model.jwt_verification_markup = if secret.is_empty() {
+            "".to_string()
+        } else {
+            self.model
+                .auth_infos
+                .iter()
+                .filter_map(Self::auth_info_jwt)
+                .filter_map(|(title, jwt)| {
+                    jwt.verify_hs256(secret.as_bytes()).map(|valid| {
+                        format!(
+                            "{}: <span foreground=\"{}\">{}</span>",
+                            glib::markup_escape_text(&title),
+                            if valid {
+                                colors::SUCCESS_COLOR
+                            } else {
+                                colors::ERROR_COLOR
+                            },
+                            if valid {
+                                "valid signature"
+                            } else {
+                                "invalid signature"
+                            }
+                        )
+                    })
+                })
+                .join("\n")
+        };
+    }
+
+    /// basic authentication has its own display, with the password hidden by default
+    fn format_auth_infos(auth_infos: &[AuthInfo], request_time: Option<NaiveDateTime>) -> String {
+        auth_infos
+            .iter()
+            .filter_map(|auth_info| match auth_info {
+                AuthInfo::Basic { .. } => None,
+                AuthInfo::Bearer { token, jwt: None } => Some(format!(
+                    "<b>Bearer token</b>: {}",
+                    glib::markup_escape_text(token)
+                )),
+                AuthInfo::Digest { params } => Some(format!(
+                    "<b>HTTP Digest Authentication</b>\n{}",
+                    params
+                        .iter()
+                        .map(|(k, v)| format!(
+                            "    {}: {}",
+                            glib::markup_escape_text(k),
+                            glib::markup_escape_text(v)
+                        ))
+                        .join("\n")
+                )),
+                _ => Self::auth_info_jwt(auth_info)
+                    .map(|(title, jwt)| Self::format_jwt(&title, jwt, request_time)),
+            })
+            .join("\n\n")
+    }
+
+    fn format_jwt(title: &str, jwt: &Jwt, request_time: Option<NaiveDateTime>) -> String {
+        let claims = jwt
+            .claims
+            .iter()
+            .map(|(k, v)| {
+                let mut line = format!(
+                    "    {}: {}",
+                    glib::markup_escape_text(k),
+                    glib::markup_escape_text(&v.to_string())
+                );
+                if let Some(date) = Some(k.as_str())
+                    .filter(|k| ["exp", "iat", "nbf"].contains(k))
+                    .and_then(|k| jwt.date_claim(k))
+                {
+                    line.push_str(&format!(" ({})", date));
+                }
+                if k == "exp" && request_time.filter(|t| jwt.is_expired_at(*t)).is_some() {
+                    line.push_str(&format!(
+                        " <span foreground=\"{}\"><b>expired when the request was sent</b></span>",
+                        colors::ERROR_COLOR
+                    ));
+                }
+                line
+            })
+            .join("\n");
+        format!(
+            "<b>{}</b> (JWT, {})\n    <i>header</i>: {}\n{}",
+            glib::markup_escape_text(title),
+            glib::markup_escape_text(jwt.algorithm().unwrap_or("no algorithm")),
+            glib::markup_escape_text(&serde_json::Value::Object(jwt.header.clone()).to_string()),
+            claims
+        )
+    }
+
+    /// each part gets its own body widget, so it can be displayed
+    /// and saved like a request body.
+    fn update_multipart_parts(&mut self, request: Option<&HttpRequestResponseData>) {
+        let multipart_box = &self.widgets.multipart_box;
+        for child in multipart_box.children() {
+            multipart_box.remove(&child);
+        }
+        self.model.multipart_body_components.clear();
+        let (request, parts) = match request {
+            Some(r) => (r, structured_data::multipart_parts(r)),
+            None => {
+                self.model.has_multipart_parts = false;
+                return;
+            }
+        };
+        self.model.has_multipart_parts = !parts.is_empty();
+        for part in parts {
+            let header_box = gtk::builders::BoxBuilder::new().spacing(10).build();
+            header_box.add(
+                &gtk::builders::LabelBuilder::new()
+                    .use_markup(true)
+                    .selectable(true)
+                    .label(&Self::multipart_part_title(&part))
+                    .build(),
+            );
+            let save_btn = gtk::builders::ButtonBuilder::new()
+                .always_show_image(true)
+                .image(&gtk::Image::from_icon_name(
+                    Some("document-save-symbolic"),
+                    gtk::IconSize::Menu,
+                ))
+                .label("Save part")
+                .build();
+            header_box.add(&save_btn);
+            header_box.show_all();
+            multipart_box.add(&header_box);
+            let headers_label = gtk::builders::LabelBuilder::new()
+                .label(&Self::format_headers(&part.headers))
+                .xalign(0.0)
+                .selectable(true)
+                .build();
+            headers_label.show();
+            multipart_box.add(&headers_label);
+
+            let component = multipart_box.add_widget::<HttpBodyWidget>((
+                self.model.win_msg_sender.clone(),
+                self.model.bg_sender.clone(),
+            ));
+            component.emit(http_body_widget::Msg::FormatCodeChanged(
+                self.model.format_request_response,
+            ));
+            component.emit(http_body_widget::Msg::GrpcSchemaChanged(
+                self.model.grpc_schema.clone(),
+            ));
+            component.emit(http_body_widget::Msg::RequestResponseChanged {
+                http_data: Some(HttpRequestResponseData {
+                    content_type: part.content_type,
+                    ..HttpRequestResponseData::new(
+                        request.tcp_stream_no,
+                        request.tcp_seq_number,
+                        request.timestamp,
+                        "".to_string(),
+                        part.headers,
+                        part.body,
+                    )
+                }),
+                request_first_line_if_response: None,
+            });
+            let stream = component.stream().clone();
+            save_btn.connect_clicked(move |_| stream.emit(http_body_widget::Msg::SaveContents));
+            self.model.multipart_body_components.push(component);
+        }
+    }
+
+    fn multipart_part_title(part: &MultipartPart) -> String {
+        let name = format!(
+            "<b>{}</b>",
+            glib::markup_escape_text(part.name.as_deref().unwrap_or("Unnamed part"))
+        );
+        match &part.filename {
+            Some(f) => format!("{} ({})", name, glib::markup_escape_text(f)),
+            None => name,
+        }
+    }
+
+    fn format_name_values(name_values: &[(String, String)]) -> String {
+        name_values
+            .iter()
+            .map(|(k, v)| {
+                format!(
+                    "<b>{}</b>: {}",
+                    glib::markup_escape_text(k),
+                    glib::markup_escape_text(v)
+                )
+            })
+            .join("\n")
+    }
+
+    fn format_set_cookies(cookies: &[SetCookie]) -> String {
+        cookies
+            .iter()
+            .map(|c| {
+                let attributes = c
+                    .attributes
+                    .iter()
+                    .map(|(k, v)| {
+                        if v.is_empty() {
+                            k.to_string()
+                        } else {
+                            format!("{}={}", k, v)
+                        }
+                    })
+                    .join("; ");
+                format!(
+                    "<b>{}</b>: {}\n    <i>{}</i>",
+                    glib::markup_escape_text(&c.name),
+                    glib::markup_escape_text(&c.value),
+                    glib::markup_escape_text(&attributes)
+                )
+            })
+            .join("\n")
     }
 
     fn refresh_display_password(&mut self) {
@@ -167,8 +646,33 @@ impl Widget for HttpCommEntry {
     fn update(&mut self, event: Msg) {
         // dbg!(&event);
         match event {
-            Msg::DisplayDetails(.., client_ip, stream_id, message_data) => {
-                self.update_basic_auth_data(&message_data);
+            Msg::DisplayDetails(.., client_server, stream_id, message_data) => {
+                let client_ip = client_server.client_ip;
+                self.model.client_server = Some(client_server);
+                self.update_auth_data(&message_data);
+                let request = message_data.request.as_ref();
+                self.model.request_query_params = request
+                    .map(structured_data::query_params)
+                    .unwrap_or_default();
+                self.model.request_form_fields = request
+                    .map(structured_data::form_fields)
+                    .unwrap_or_default();
+                self.model.request_cookies = request
+                    .map(structured_data::request_cookies)
+                    .unwrap_or_default();
+                self.model.response_cookies = message_data
+                    .response
+                    .as_ref()
+                    .map(structured_data::response_cookies)
+                    .unwrap_or_default();
+                self.update_multipart_parts(request);
+                self.update_server_sent_events(message_data.response.as_ref());
+                self.model.http2_frames_markup = Self::format_http2_frames(
+                    &message_data.http2_frames,
+                    self.model.client_server.as_ref(),
+                );
+                // http2 sends them afterwards
+                self.update_connection_events(&Http2ConnectionEvents::default());
                 self.model.data = message_data;
                 self.streams
                     .comm_info_header
@@ -193,6 +697,25 @@ impl Widget for HttpCommEntry {
                             .map(|r| r.first_line.clone()),
                     });
             }
+            Msg::DisplayConnectionEvents(events) => {
+                self.update_connection_events(&events);
+            }
+            Msg::GrpcSchemaChanged(schema) => {
+                self.model.grpc_schema = schema;
+                let msg =
+                    || http_body_widget::Msg::GrpcSchemaChanged(self.model.grpc_schema.clone());
+                self.streams.request_body.emit(msg());
+                self.streams.response_body.emit(msg());
+                for component in &self.model.multipart_body_components {
+                    component.emit(msg());
+                }
+            }
+            Msg::AppendServerSentEvents(events) => {
+                self.append_server_sent_events(&events);
+                if let Some(response) = self.model.data.response.as_mut() {
+                    response.server_sent_events.extend(events);
+                }
+            }
             Msg::RemoveFormatToggled => {
                 self.model.format_request_response = self.model.format_contents_btn.is_active();
                 self.streams
@@ -205,21 +728,28 @@ impl Widget for HttpCommEntry {
                     .emit(http_body_widget::Msg::FormatCodeChanged(
                         self.model.format_request_response,
                     ));
+                for component in &self.model.multipart_body_components {
+                    component.emit(http_body_widget::Msg::FormatCodeChanged(
+                        self.model.format_request_response,
+                    ));
+                }
             }
             Msg::CopyContentsClick => {
                 if let Some(clip) =
                     gtk::Clipboard::default(&self.widgets.comm_info_header.display())
                 {
                     let format_reqresp = |r: &HttpRequestResponseData| {
-                        format!(
+                        let mut contents = format!(
                             "{}\n{}\n\n{}",
                             r.first_line,
-                            r.headers
-                                .iter()
-                                .map(|(k, v)| format!("{}: {}", k, v))
-                                .join("\n"),
+                            Self::format_headers(&r.headers),
                             r.body_as_str().unwrap_or(Cow::Borrowed(""))
-                        )
+                        );
+                        if !r.trailers.is_empty() {
+                            contents.push_str("\n\n");
+                            contents.push_str(&Self::format_headers(&r.trailers));
+                        }
+                        contents
                     };
                     let clip_contents = format!(
                         "{}\n-------\n{}",
@@ -246,12 +776,148 @@ impl Widget for HttpCommEntry {
                 }
                 self.model.options_popover.popdown();
             }
+            Msg::CopyRequestAs(format) => {
+                self.model.options_popover.popdown();
+                if let Err(e) = self.copy_request_as(format) {
+                    self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                        Some(format!("Error exporting the request: {}", e)),
+                        win::InfobarOptions::ShowCloseButton,
+                    ));
+                }
+            }
+            Msg::ReplayRequest => {
+                self.model.options_popover.popdown();
+                self.display_replay_window();
+            }
             Msg::ToggleDisplayPassword => {
                 self.refresh_display_password();
             }
+            Msg::JwtSecretChanged => {
+                self.refresh_jwt_verification();
+            }
+        }
+    }
+
+    /// binary contents can't go through the clipboard: we save the binary
+    /// request body to a file that the command refers to, and for the raw
+    /// HTTP export, the whole request.
+    fn copy_request_as(&self, format: RequestExportFormat) -> std::io::Result<()> {
+        let (request, client_server) =
+            match (&self.model.data.request, self.model.client_server.as_ref()) {
+                (Some(r), Some(cs)) => (r, cs),
+                _ => return Ok(()),
+            };
+        let binary_body = match &request.body {
+            HttpBody::Binary(bytes) => Some(bytes),
+            _ => None,
+        };
+        let save_path = if binary_body.is_some() {
+            let (title, default_name) = match format {
+                RequestExportFormat::RawHttp => ("Save the request to...", "request.http"),
+                _ => ("Save the request body to...", "request_body.bin"),
+            };
+            match Self::ask_save_path(title, default_name) {
+                Some(p) => Some(p),
+                // the user cancelled
+                None => return Ok(()),
+            }
+        } else {
+            None
+        };
+        let save_path_str = save_path.as_ref().map(|p| p.to_string_lossy());
+        let clip_contents = match (format, binary_body) {
+            (RequestExportFormat::Curl, _) => {
+                request_export::to_curl(request, client_server, save_path_str.as_deref())
+            }
+            (RequestExportFormat::Httpie, _) => {
+                request_export::to_httpie(request, client_server, save_path_str.as_deref())
+            }
+            (RequestExportFormat::RawHttp, None) => {
+                String::from_utf8_lossy(&request_export::to_raw_http(request)).to_string()
+            }
+            (RequestExportFormat::RawHttp, Some(_)) => {
+                std::fs::write(
+                    save_path.as_ref().unwrap(),
+                    request_export::to_raw_http(request),
+                )?;
+                self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                    Some(format!("Saved the request to {}", save_path_str.unwrap())),
+                    win::InfobarOptions::TimeLimitedWithCloseButton,
+                ));
+                return Ok(());
+            }
+        };
+        if let (Some(path), Some(bytes)) = (save_path.as_ref(), binary_body) {
+            std::fs::write(path, bytes)?;
+        }
+        if let Some(clip) = gtk::Clipboard::default(&self.widgets.comm_info_header.display()) {
+            clip.set_text(&clip_contents);
+            self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                Some("Copied to the clipboard".to_string()),
+                win::InfobarOptions::TimeLimitedWithCloseButton,
+            ));
+        }
+        Ok(())
+    }
+
+    fn display_replay_window(&mut self) {
+        let (request, client_server) =
+            match (&self.model.data.request, self.model.client_server.as_ref()) {
+                (Some(r), Some(cs)) => (r, cs),
+                _ => return,
+            };
+        self.model.replay_win = Some(
+            relm::init::<HttpReplayWindow>((
+                self.model.win_msg_sender.clone(),
+                self.model.bg_sender.clone(),
+                *client_server,
+                request.clone(),
+                self.model.data.response.clone(),
+                self.model.grpc_schema.clone(),
+            ))
+            .expect("Error initializing the replay window"),
+        );
+        let replay_win = self.model.replay_win.as_ref().unwrap().widget();
+        if let Some(parent) = self
+            .widgets
+            .comm_info_header
+            .toplevel()
+            .and_then(|w| w.downcast::<gtk::Window>().ok())
+        {
+            replay_win.set_transient_for(Some(&parent));
+        }
+        replay_win.set_position(gtk::WindowPosition::CenterOnParent);
+        replay_win.show();
+    }
+
+    fn ask_save_path(title: &str, default_name: &str) -> Option<std::path::PathBuf> {
+        let dialog = gtk::builders::FileChooserNativeBuilder::new()
+            .action(gtk::FileChooserAction::Save)
+            .title(title)
+            .do_overwrite_confirmation(true)
+            .modal(true)
+            .build();
+        dialog.set_current_name(default_name);
+        if dialog.run() == gtk::ResponseType::Accept {
+            dialog.filename()
+        } else {
+            None
         }
     }
 
+    fn format_interim_responses(interim_responses: &[InterimResponse]) -> String {
+        interim_responses
+            .iter()
+            .map(|r| {
+                format!(
+                    "<b>{}</b>\n{}",
+                    glib::markup_escape_text(&r.first_line),
+                    glib::markup_escape_text(&Self::format_headers(&r.headers))
+                )
+            })
+            .join("\n\n")
+    }
+
     fn format_headers(headers: &[(String, String)]) -> String {
         headers
             .iter()
@@ -286,15 +952,78 @@ impl Widget for HttpCommEntry {
                         line_wrap: true,
                         wrap_mode: pango::WrapMode::Char,
                     },
-                    gtk::Label {
-                        label: self.model.data.request.as_ref()
-                                                    .map(|r| &r.headers[..])
-                                                    .map(Self::format_headers)
-                                                    .as_deref()
-                                                    .unwrap_or(""),
-                        xalign: 0.0,
-                        selectable: true,
-                        line_wrap: true,
+                    gtk::Notebook {
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Headers")
+                            },
+                            label: self.model.data.request.as_ref()
+                                                        .map(|r| &r.headers[..])
+                                                        .map(Self::format_headers)
+                                                        .as_deref()
+                                                        .unwrap_or(""),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Trailers")
+                            },
+                            visible: self.model.data.request.as_ref().filter(|r| !r.trailers.is_empty()).is_some(),
+                            label: self.model.data.request.as_ref()
+                                                        .map(|r| &r.trailers[..])
+                                                        .map(Self::format_headers)
+                                                        .as_deref()
+                                                        .unwrap_or(""),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Query")
+                            },
+                            visible: !self.model.request_query_params.is_empty(),
+                            markup: &Self::format_name_values(&self.model.request_query_params),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Form")
+                            },
+                            visible: !self.model.request_form_fields.is_empty(),
+                            markup: &Self::format_name_values(&self.model.request_form_fields),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        #[name="multipart_box"]
+                        gtk::Box {
+                            child: {
+                                tab_label: Some("Multipart")
+                            },
+                            visible: self.model.has_multipart_parts,
+                            orientation: gtk::Orientation::Vertical,
+                            spacing: 10,
+                        },
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Cookies")
+                            },
+                            visible: !self.model.request_cookies.is_empty(),
+                            markup: &Self::format_name_values(&self.model.request_cookies),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
                     },
                     #[name="basic_auth_info"]
                     gtk::Box {
@@ -332,6 +1061,46 @@ impl Widget for HttpCommEntry {
                             halign: gtk::Align::End,
                         },
                     },
+                    gtk::Box {
+                        orientation: gtk::Orientation::Vertical,
+                        spacing: 5,
+                        visible: !self.model.auth_info_markup.is_empty(),
+                        gtk::Box {
+                            spacing: 5,
+                            gtk::Image {
+                                icon_name: Some(Icon::LOCK.name()),
+                                icon_size: gtk::IconSize::SmallToolbar,
+                                valign: gtk::Align::Start,
+                            },
+                            gtk::Label {
+                                markup: &self.model.auth_info_markup,
+                                xalign: 0.0,
+                                selectable: true,
+                                line_wrap: true,
+                                wrap_mode: pango::WrapMode::Char,
+                            },
+                        },
+                        gtk::Box {
+                            spacing: 5,
+                            visible: self.model.has_hs256_jwt,
+                            #[style_class="label"]
+                            gtk::Label {
+                                label: "HS256 secret",
+                            },
+                            #[name="jwt_secret_entry"]
+                            gtk::Entry {
+                                hexpand: true,
+                                visibility: false,
+                                placeholder_text: Some("Paste the secret to verify the JWT signatures"),
+                                changed => Msg::JwtSecretChanged,
+                            },
+                        },
+                        gtk::Label {
+                            markup: &self.model.jwt_verification_markup,
+                            visible: !self.model.jwt_verification_markup.is_empty(),
+                            xalign: 0.0,
+                        },
+                    },
                     #[name="request_body"]
                     HttpBodyWidget((self.model.win_msg_sender.clone(), self.model.bg_sender.clone())),
                 }
@@ -347,25 +1116,104 @@ impl Widget for HttpCommEntry {
                     spacing: 10,
                     #[style_class="http_first_line"]
                     gtk::Label {
-                        label: self.model.data.response.as_ref().map(|r| r.first_line.as_str()).unwrap_or("Missing response info"),
+                        label: self.model.data.response.as_ref().map(|r| r.first_line.clone())
+                                   .or_else(|| self.model.data.missing_response_reason())
+                                   .as_deref()
+                                   .unwrap_or("Missing response info"),
                         xalign: 0.0,
                         selectable: true,
                         line_wrap: true,
                         wrap_mode: pango::WrapMode::Char,
                     },
                     gtk::Label {
-                        label: self.model.data.response.as_ref()
-                                                    .map(|r| &r.headers[..])
-                                                    .map(Self::format_headers)
-                                                    .as_deref()
-                                                    .unwrap_or(""),
+                        markup: &self.model.http2_frames_markup,
+                        visible: !self.model.http2_frames_markup.is_empty(),
                         xalign: 0.0,
                         selectable: true,
                         line_wrap: true,
                     },
+                    gtk::Notebook {
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Headers")
+                            },
+                            label: self.model.data.response.as_ref()
+                                                        .map(|r| &r.headers[..])
+                                                        .map(Self::format_headers)
+                                                        .as_deref()
+                                                        .unwrap_or(""),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Trailers")
+                            },
+                            visible: self.model.data.response.as_ref().filter(|r| !r.trailers.is_empty()).is_some(),
+                            label: self.model.data.response.as_ref()
+                                                        .map(|r| &r.trailers[..])
+                                                        .map(Self::format_headers)
+                                                        .as_deref()
+                                                        .unwrap_or(""),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Interim responses")
+                            },
+                            visible: self.model.data.response.as_ref().filter(|r| !r.interim_responses.is_empty()).is_some(),
+                            markup: &self.model.data.response.as_ref()
+                                                        .map(|r| Self::format_interim_responses(&r.interim_responses))
+                                                        .unwrap_or_default(),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        gtk::Label {
+                            child: {
+                                tab_label: Some("Cookies")
+                            },
+                            visible: !self.model.response_cookies.is_empty(),
+                            markup: &Self::format_set_cookies(&self.model.response_cookies),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                            line_wrap: true,
+                        },
+                        #[name="events_treeview"]
+                        gtk::TreeView {
+                            child: {
+                                tab_label: Some("Events")
+                            },
+                            visible: self.model.has_server_sent_events,
+                        },
+                    },
                     #[name="response_body"]
                     HttpBodyWidget((self.model.win_msg_sender.clone(), self.model.bg_sender.clone())),
                 }
+            },
+            gtk::Box {
+                orientation: gtk::Orientation::Vertical,
+                spacing: 10,
+                visible: self.model.has_connection_events,
+                gtk::Separator {},
+                gtk::Expander {
+                    expanded: false,
+                    label: Some("HTTP2 connection events"),
+                    gtk::ScrolledWindow {
+                        margin_start: 10,
+                        margin_end: 10,
+                        height_request: 250,
+                        #[name="connection_events_treeview"]
+                        gtk::TreeView {},
+                    },
+                },
             }
         }
     }
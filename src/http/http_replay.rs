@@ -0,0 +1,310 @@
+// re-send a captured request, to its original host or to another
+// base URL (for instance a local instance of the server), and compare
+// the response we get with the captured one.
+use super::http_streams_store;
+use super::http_streams_store::{ContentEncoding, HttpBody, HttpRequestResponseData};
+use super::request_export;
+use chrono::Local;
+use similar::{ChangeTag, TextDiff};
+use std::time::Duration;
+
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(30);
+
+// reqwest computes these from the URL and the body,
+// and the hop-by-hop ones don't make sense for a new connection.
+const SKIPPED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "te",
+    "upgrade",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Captured(String),
+    Replayed(String),
+}
+
+/// "http://localhost:8080" or "http://localhost:8080/api" replace the scheme
+/// and authority of the captured URL, the path and query string are preserved.
+pub fn replay_url(captured_url: &str, base_url_override: Option<&str>) -> String {
+    let base = match base_url_override.map(str::trim).filter(|b| !b.is_empty()) {
+        Some(b) => b.trim_end_matches('/'),
+        None => return captured_url.to_string(),
+    };
+    let path_and_query = &captured_url[base_url(captured_url).len()..];
+    format!(
+        "{}{}",
+        base,
+        if path_and_query.is_empty() {
+            "/"
+        } else {
+            path_and_query
+        }
+    )
+}
+
+/// the scheme and authority: "https://example.com:8443"
+pub fn base_url(url: &str) -> &str {
+    let authority_start = url.find("://").map(|idx| idx + 3).unwrap_or(0);
+    match url[authority_start..].find('/') {
+        Some(idx) => &url[..authority_start + idx],
+        None => url,
+    }
+}
+
+/// we send the body we stored, which may have been decompressed or converted
+/// to UTF-8: the headers must match it. reqwest sets the connection headers.
+fn replayed_headers(request: &HttpRequestResponseData) -> Vec<(String, String)> {
+    request_export::headers_for_body(request)
+        .into_iter()
+        .filter(|(k, _)| !SKIPPED_HEADERS.contains(&k.to_ascii_lowercase().as_str()))
+        .collect()
+}
+
+/// blocking, to be called from the background thread.
+pub fn replay_request(
+    request: &HttpRequestResponseData,
+    url: &str,
+) -> Result<HttpRequestResponseData, String> {
+    let method = reqwest::Method::from_bytes(
+        request_export::parse_request_line(request)
+            .method
+            .as_bytes(),
+    )
+    .map_err(|e| e.to_string())?;
+    let client = reqwest::blocking::Client::builder()
+        // we want to compare with the captured response, not with the target of the redirect
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(REPLAY_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut builder = client.request(method, url);
+    for (k, v) in replayed_headers(request) {
+        builder = builder.header(k.as_str(), v.as_str());
+    }
+    match &request.body {
+        HttpBody::Text(txt) => builder = builder.body(txt.clone()),
+        HttpBody::Binary(bytes) => builder = builder.body(bytes.clone()),
+        HttpBody::Missing => {}
+    }
+    let response = builder.send().map_err(|e| e.to_string())?;
+    let first_line = format!("{:?} {}", response.version(), response.status());
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.as_str().to_string(),
+                String::from_utf8_lossy(v.as_bytes()).to_string(),
+            )
+        })
+        .collect();
+    let content_type = http_streams_store::get_http_header_value(&headers, "Content-Type").cloned();
+    let content_encoding = ContentEncoding::parse_from_str(
+        &http_streams_store::get_http_header_value(&headers, "Content-Encoding")
+            .map(|s| s.as_str()),
+    );
+    let bytes = response.bytes().map_err(|e| e.to_string())?.to_vec();
+    let body = if bytes.is_empty() {
+        HttpBody::Missing
+    } else if content_encoding == ContentEncoding::Plain {
+        http_streams_store::parse_body(Some(bytes), content_type.as_deref())
+    } else {
+        // body_as_str() will decode it
+        HttpBody::Binary(bytes)
+    };
+    Ok(HttpRequestResponseData {
+        content_encoding,
+        ..HttpRequestResponseData::new(
+            request.tcp_stream_no,
+            request.tcp_seq_number,
+            Local::now().naive_local(),
+            first_line,
+            headers,
+            body,
+        )
+    })
+}
+
+/// header names are case-insensitive and their order is not significant
+pub fn diff_headers(captured: &[(String, String)], replayed: &[(String, String)]) -> Vec<DiffLine> {
+    let header_lines = |headers: &[(String, String)]| {
+        let mut lines: Vec<_> = headers
+            .iter()
+            .map(|(k, v)| format!("{}: {}\n", k.to_ascii_lowercase(), v))
+            .collect();
+        lines.sort();
+        lines.concat()
+    };
+    diff_lines(&header_lines(captured), &header_lines(replayed))
+}
+
+/// JSON bodies are compared pretty-printed, binary bodies only by their contents.
+pub fn diff_bodies(
+    captured: &HttpRequestResponseData,
+    replayed: &HttpRequestResponseData,
+) -> Vec<DiffLine> {
+    match (diff_text(captured), diff_text(replayed)) {
+        (Some(c), Some(r)) => diff_lines(&c, &r),
+        _ => {
+            let (c, r) = (body_bytes(&captured.body), body_bytes(&replayed.body));
+            if c == r {
+                vec![DiffLine::Same(describe_body(&captured.body, c.len()))]
+            } else {
+                vec![
+                    DiffLine::Captured(describe_body(&captured.body, c.len())),
+                    DiffLine::Replayed(describe_body(&replayed.body, r.len())),
+                ]
+            }
+        }
+    }
+}
+
+fn diff_text(data: &HttpRequestResponseData) -> Option<String> {
+    if data.body == HttpBody::Missing {
+        return Some("".to_string());
+    }
+    let txt = data.body_as_str()?;
+    Some(
+        match serde_json::from_str::<serde_json::Value>(&txt)
+            .ok()
+            .and_then(|v| serde_json::to_string_pretty(&v).ok())
+        {
+            Some(pretty) => pretty,
+            None => txt.to_string(),
+        },
+    )
+}
+
+fn body_bytes(body: &HttpBody) -> &[u8] {
+    match body {
+        HttpBody::Text(txt) => txt.as_bytes(),
+        HttpBody::Binary(bytes) => bytes,
+        HttpBody::Missing => &[],
+    }
+}
+
+fn describe_body(body: &HttpBody, len: usize) -> String {
+    match body {
+        HttpBody::Missing => "(no body)".to_string(),
+        _ => format!("(binary body, {} bytes)", len),
+    }
+}
+
+pub fn diff_lines(captured: &str, replayed: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(captured, replayed)
+        .iter_all_changes()
+        .map(|change| {
+            let line = change.value().trim_end_matches('\n').to_string();
+            match change.tag() {
+                ChangeTag::Equal => DiffLine::Same(line),
+                ChangeTag::Delete => DiffLine::Captured(line),
+                ChangeTag::Insert => DiffLine::Replayed(line),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn should_compute_replay_url() {
+    assert_eq!(
+        "https://example.com/items?id=1",
+        replay_url("https://example.com/items?id=1", None)
+    );
+    assert_eq!(
+        "http://localhost:8080/api/items?id=1",
+        replay_url(
+            "https://example.com/items?id=1",
+            Some(" http://localhost:8080/api/ ")
+        )
+    );
+    assert_eq!(
+        "http://localhost:8080/",
+        replay_url("https://example.com", Some("http://localhost:8080"))
+    );
+    assert_eq!(
+        "https://example.com:8443",
+        base_url("https://example.com:8443/items")
+    );
+}
+
+#[test]
+fn should_replay_headers_matching_the_stored_body() {
+    let request = HttpRequestResponseData::test_data(
+        "POST /a HTTP/1.1",
+        &[
+            ("Host", "example.com"),
+            ("Content-Encoding", "gzip"),
+            ("Content-Length", "20"),
+            ("Content-Type", "text/plain; charset=ISO-8859-1"),
+        ],
+        HttpBody::Text("café".to_string()),
+    );
+    assert_eq!(
+        vec![(
+            "Content-Type".to_string(),
+            "text/plain; charset=utf-8".to_string()
+        )],
+        replayed_headers(&request)
+    );
+}
+
+#[test]
+fn should_diff_headers_and_json_bodies() {
+    let captured = HttpRequestResponseData::test_data(
+        "HTTP/1.1 200 OK",
+        &[("Content-Type", "application/json"), ("X-Id", "1")],
+        HttpBody::Text("{\"a\":1,\"b\":2}".to_string()),
+    );
+    let replayed = HttpRequestResponseData::test_data(
+        "HTTP/1.1 200 OK",
+        &[("x-id", "1"), ("content-type", "application/json")],
+        HttpBody::Text("{\"a\":1,\"b\":3}".to_string()),
+    );
+    assert_eq!(
+        vec![
+            DiffLine::Same("content-type: application/json".to_string()),
+            DiffLine::Same("x-id: 1".to_string()),
+        ],
+        diff_headers(&captured.headers, &replayed.headers)
+    );
+    assert_eq!(
+        vec![
+            DiffLine::Same("{".to_string()),
+            DiffLine::Same("  \"a\": 1,".to_string()),
+            DiffLine::Captured("  \"b\": 2".to_string()),
+            DiffLine::Replayed("  \"b\": 3".to_string()),
+            DiffLine::Same("}".to_string()),
+        ],
+        diff_bodies(&captured, &replayed)
+    );
+}
+
+#[test]
+fn should_diff_binary_bodies() {
+    let captured = HttpRequestResponseData::test_data(
+        "HTTP/1.1 200 OK",
+        &[],
+        HttpBody::Binary(vec![0xff, 0, 1]),
+    );
+    assert_eq!(
+        vec![DiffLine::Same("(binary body, 3 bytes)".to_string())],
+        diff_bodies(&captured, &captured.clone())
+    );
+    assert_eq!(
+        vec![
+            DiffLine::Captured("(binary body, 3 bytes)".to_string()),
+            DiffLine::Replayed("(no body)".to_string()),
+        ],
+        diff_bodies(
+            &captured,
+            &HttpRequestResponseData::test_data("HTTP/1.1 200 OK", &[], HttpBody::Missing)
+        )
+    );
+}
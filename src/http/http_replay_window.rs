@@ -0,0 +1,319 @@
+use super::http_body_widget;
+use super::http_body_widget::HttpBodyWidget;
+use super::http_replay;
+use super::http_replay::DiffLine;
+use super::http_streams_store::HttpRequestResponseData;
+use super::request_export;
+use crate::colors;
+use crate::custom_streams_store::ClientServerInfo;
+use crate::http2::proto_schema::ConfiguredProtoSchema;
+use crate::win;
+use crate::BgFunc;
+use gtk::prelude::*;
+use itertools::Itertools;
+use relm::Widget;
+use relm_derive::{widget, Msg};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+type ReplayResult = Result<HttpRequestResponseData, String>;
+
+#[derive(Msg)]
+pub enum Msg {
+    Send,
+    GotReplayResult(ReplayResult),
+}
+
+pub struct Model {
+    win_msg_sender: relm::StreamHandle<win::Msg>,
+    bg_sender: mpsc::Sender<BgFunc>,
+    request: HttpRequestResponseData,
+    captured_response: Option<HttpRequestResponseData>,
+    captured_url: String,
+    replayed_response: Option<ReplayResult>,
+    is_sending: bool,
+    grpc_schema: Option<Arc<ConfiguredProtoSchema>>,
+
+    _replay_channel: relm::Channel<ReplayResult>,
+    replay_sender: relm::Sender<ReplayResult>,
+}
+
+#[widget]
+impl Widget for HttpReplayWindow {
+    fn init_view(&mut self) {
+        self.widgets
+            .base_url_entry
+            .set_text(http_replay::base_url(&self.model.captured_url));
+        for body in [
+            &self.components.captured_body,
+            &self.components.replayed_body,
+        ] {
+            body.emit(http_body_widget::Msg::GrpcSchemaChanged(
+                self.model.grpc_schema.clone(),
+            ));
+        }
+        self.components
+            .captured_body
+            .emit(http_body_widget::Msg::RequestResponseChanged {
+                http_data: self.model.captured_response.clone(),
+                request_first_line_if_response: Some(self.model.request.first_line.clone()),
+            });
+    }
+
+    fn model(
+        relm: &relm::Relm<Self>,
+        params: (
+            relm::StreamHandle<win::Msg>,
+            mpsc::Sender<BgFunc>,
+            ClientServerInfo,
+            HttpRequestResponseData,
+            Option<HttpRequestResponseData>,
+            Option<Arc<ConfiguredProtoSchema>>,
+        ),
+    ) -> Model {
+        let (win_msg_sender, bg_sender, client_server, request, captured_response, grpc_schema) =
+            params;
+        let (_replay_channel, replay_sender) = {
+            let stream = relm.stream().clone();
+            relm::Channel::new(move |r: ReplayResult| {
+                stream.emit(Msg::GotReplayResult(r));
+            })
+        };
+        let captured_url = request_export::request_url(&request, &client_server);
+        Model {
+            win_msg_sender,
+            bg_sender,
+            request,
+            captured_response,
+            captured_url,
+            replayed_response: None,
+            is_sending: false,
+            grpc_schema,
+            _replay_channel,
+            replay_sender,
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Send if self.model.is_sending => {}
+            Msg::Send => {
+                let url = http_replay::replay_url(
+                    &self.model.captured_url,
+                    Some(self.widgets.base_url_entry.text().as_str()),
+                );
+                let request = self.model.request.clone();
+                let sender = self.model.replay_sender.clone();
+                self.model.is_sending = true;
+                // not on the bg_sender thread: the request can block until
+                // the timeout, and would delay all the other background tasks
+                thread::spawn(move || {
+                    // fails if the window was closed in the meantime
+                    let _ = sender.send(http_replay::replay_request(&request, &url));
+                });
+            }
+            Msg::GotReplayResult(r) => {
+                self.model.is_sending = false;
+                self.model.replayed_response = Some(r);
+                self.components
+                    .replayed_body
+                    .emit(http_body_widget::Msg::RequestResponseChanged {
+                        http_data: Self::replayed(&self.model.replayed_response).cloned(),
+                        request_first_line_if_response: Some(self.model.request.first_line.clone()),
+                    });
+            }
+        }
+    }
+
+    fn first_line(data: Option<&HttpRequestResponseData>) -> &str {
+        data.map(|r| r.first_line.as_str()).unwrap_or("")
+    }
+
+    fn headers(data: Option<&HttpRequestResponseData>) -> String {
+        data.map(|r| {
+            r.headers
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .join("\n")
+        })
+        .unwrap_or_default()
+    }
+
+    fn replayed(replayed_response: &Option<ReplayResult>) -> Option<&HttpRequestResponseData> {
+        match replayed_response {
+            Some(Ok(r)) => Some(r),
+            _ => None,
+        }
+    }
+
+    fn status_markup(is_sending: bool, replayed_response: &Option<ReplayResult>) -> String {
+        match replayed_response {
+            _ if is_sending => "Sending the request...".to_string(),
+            Some(Err(e)) => format!(
+                "<span foreground=\"{}\">Error replaying the request: {}</span>",
+                colors::ERROR_COLOR,
+                glib::markup_escape_text(e)
+            ),
+            _ => "".to_string(),
+        }
+    }
+
+    fn diff_markup(
+        captured_response: &Option<HttpRequestResponseData>,
+        replayed_response: &Option<ReplayResult>,
+    ) -> String {
+        let (captured, replayed) = match (captured_response, Self::replayed(replayed_response)) {
+            (Some(c), Some(r)) => (c, r),
+            _ => return "".to_string(),
+        };
+        let format_lines = |lines: Vec<DiffLine>| {
+            lines
+                .iter()
+                .map(|l| match l {
+                    DiffLine::Same(s) => format!("  {}", glib::markup_escape_text(s)),
+                    DiffLine::Captured(s) => format!(
+                        "<span foreground=\"{}\">- {}</span>",
+                        colors::ERROR_COLOR,
+                        glib::markup_escape_text(s)
+                    ),
+                    DiffLine::Replayed(s) => format!(
+                        "<span foreground=\"{}\">+ {}</span>",
+                        colors::SUCCESS_COLOR,
+                        glib::markup_escape_text(s)
+                    ),
+                })
+                .join("\n")
+        };
+        format!(
+            "<b>Status</b>\n{}\n\n<b>Headers</b>\n{}\n\n<b>Body</b>\n{}",
+            format_lines(http_replay::diff_lines(
+                &captured.first_line,
+                &replayed.first_line
+            )),
+            format_lines(http_replay::diff_headers(
+                &captured.headers,
+                &replayed.headers
+            )),
+            format_lines(http_replay::diff_bodies(captured, replayed))
+        )
+    }
+
+    view! {
+        #[name="replay_window"]
+        gtk::Window {
+            titlebar: view! {
+                gtk::HeaderBar {
+                    title: Some("Replay request"),
+                    subtitle: Some(self.model.request.first_line.as_str()),
+                    show_close_button: true,
+                }
+            },
+            default_width: 1100,
+            default_height: 750,
+            gtk::Box {
+                orientation: gtk::Orientation::Vertical,
+                margin_top: 10,
+                margin_start: 10,
+                margin_end: 10,
+                margin_bottom: 10,
+                spacing: 10,
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 5,
+                    gtk::Label {
+                        label: "Target base URL",
+                    },
+                    #[name="base_url_entry"]
+                    gtk::Entry {
+                        hexpand: true,
+                        activate => Msg::Send,
+                    },
+                    gtk::Button {
+                        label: "Send",
+                        sensitive: !self.model.is_sending,
+                        clicked => Msg::Send,
+                    },
+                    gtk::Spinner {
+                        active: self.model.is_sending,
+                    },
+                },
+                gtk::Label {
+                    markup: &Self::status_markup(self.model.is_sending, &self.model.replayed_response),
+                    xalign: 0.0,
+                    selectable: true,
+                    line_wrap: true,
+                },
+                gtk::Paned {
+                    orientation: gtk::Orientation::Vertical,
+                    vexpand: true,
+                    wide_handle: true,
+                    position: 450,
+                    gtk::Box {
+                        orientation: gtk::Orientation::Horizontal,
+                        homogeneous: true,
+                        spacing: 10,
+                        gtk::ScrolledWindow {
+                            gtk::Box {
+                                orientation: gtk::Orientation::Vertical,
+                                spacing: 10,
+                                #[style_class="label"]
+                                gtk::Label {
+                                    label: "Captured response",
+                                    xalign: 0.0,
+                                },
+                                #[style_class="http_first_line"]
+                                gtk::Label {
+                                    label: Self::first_line(self.model.captured_response.as_ref()),
+                                    xalign: 0.0,
+                                    selectable: true,
+                                },
+                                gtk::Label {
+                                    label: &Self::headers(self.model.captured_response.as_ref()),
+                                    xalign: 0.0,
+                                    selectable: true,
+                                    line_wrap: true,
+                                },
+                                #[name="captured_body"]
+                                HttpBodyWidget((self.model.win_msg_sender.clone(), self.model.bg_sender.clone())),
+                            },
+                        },
+                        gtk::ScrolledWindow {
+                            gtk::Box {
+                                orientation: gtk::Orientation::Vertical,
+                                spacing: 10,
+                                #[style_class="label"]
+                                gtk::Label {
+                                    label: "Replayed response",
+                                    xalign: 0.0,
+                                },
+                                #[style_class="http_first_line"]
+                                gtk::Label {
+                                    label: Self::first_line(Self::replayed(&self.model.replayed_response)),
+                                    xalign: 0.0,
+                                    selectable: true,
+                                },
+                                gtk::Label {
+                                    label: &Self::headers(Self::replayed(&self.model.replayed_response)),
+                                    xalign: 0.0,
+                                    selectable: true,
+                                    line_wrap: true,
+                                },
+                                #[name="replayed_body"]
+                                HttpBodyWidget((self.model.win_msg_sender.clone(), self.model.bg_sender.clone())),
+                            },
+                        },
+                    },
+                    gtk::ScrolledWindow {
+                        gtk::Label {
+                            markup: &Self::diff_markup(&self.model.captured_response, &self.model.replayed_response),
+                            xalign: 0.0,
+                            yalign: 0.0,
+                            selectable: true,
+                        },
+                    },
+                },
+            }
+        }
+    }
+}
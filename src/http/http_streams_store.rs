@@ -1,9 +1,16 @@
+use super::http2_frames;
+use super::http2_frames::{Http2ControlFrame, Http2FrameEvent};
 use super::http_details_widget;
 use super::http_details_widget::HttpCommEntry;
+use super::server_sent_events;
+use super::server_sent_events::{EventStreamParser, ServerSentEvent};
+use super::structured_data;
 use crate::colors;
 use crate::custom_streams_store;
 use crate::custom_streams_store::{ClientServerInfo, CustomStreamsStore};
 use crate::http::tshark_http::HttpType;
+use crate::http2::grpc;
+use crate::http2::proto_schema::ConfiguredProtoSchema;
 use crate::icons::Icon;
 use crate::search_expr;
 use crate::search_expr::SearchCriteria;
@@ -11,9 +18,12 @@ use crate::tshark_communication::{NetworkPort, TSharkPacket, TcpSeqNumber, TcpSt
 use crate::widgets::win;
 use crate::BgFunc;
 use chrono::NaiveDateTime;
-use flate2::read::GzDecoder;
+use encoding_rs::Encoding;
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
 use gtk::prelude::*;
+use regex::bytes;
 use relm::ContainerWidget;
+use std::any::Any;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::VecDeque;
@@ -22,13 +32,14 @@ use std::net::IpAddr;
 use std::str;
 use std::str::FromStr;
 use std::sync::mpsc;
+use std::sync::Arc;
 use strum::VariantNames;
 use strum_macros::{EnumString, EnumVariantNames};
 
 #[cfg(test)]
 use {
     crate::custom_streams_store::common_tests_parse_stream,
-    crate::tshark_communication::parse_test_xml_no_wrapper, chrono::NaiveDate,
+    crate::tshark_communication::parse_test_xml_no_wrapper, chrono::NaiveDate, chrono::Timelike,
 };
 
 lazy_static! {
@@ -37,6 +48,14 @@ lazy_static! {
     // in the stream I get the real hostname, I ignore it because I "already got"
     // the hostname. So filter out these and ignore them.
     static ref IP_ONLY_CHARS: Vec<char> = "0123456789.:".chars().collect();
+
+    static ref CONTENT_TYPE_CHARSET: bytes::Regex =
+        bytes::Regex::new(r#"(?i)charset\s*=\s*"?([^";\s]+)"#).unwrap();
+    // <meta charset="..."> <meta http-equiv="Content-Type" content="text/html; charset=...">
+    // and <?xml version="1.0" encoding="..."?>
+    static ref DECLARATION_CHARSET: bytes::Regex =
+        bytes::Regex::new(r#"(?i)(?:<meta[^>]+charset|<\?xml[^>]+encoding)\s*=\s*["']?([\w.:-]+)"#)
+            .unwrap();
 }
 
 #[derive(Default)]
@@ -63,6 +82,57 @@ impl HttpStreamsStore {
         let msg = stream.messages.get(msg_idx)?;
         Some((msg, stream.client_server?))
     }
+
+    /// the HTTP exchanges of the stream, for the HAR export for instance
+    pub fn stream_http_messages(&self, stream_id: TcpStreamId) -> Option<&[HttpMessageData]> {
+        self.streams.get(&stream_id).map(|s| s.messages.as_slice())
+    }
+
+    pub fn set_grpc_schema(&self, schema: Option<Arc<ConfiguredProtoSchema>>) {
+        self.component
+            .as_ref()
+            .unwrap()
+            .stream()
+            .emit(http_details_widget::Msg::GrpcSchemaChanged(schema));
+    }
+
+    /// the details widget displays the first `displayed_count` events already
+    pub fn display_new_server_sent_events(
+        &self,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+        displayed_count: usize,
+    ) {
+        if let Some(events) = self
+            .get_msg_info(stream_id, msg_idx)
+            .and_then(|(m, _)| m.response.as_ref())
+            .and_then(|r| r.server_sent_events.get(displayed_count..))
+        {
+            self.component.as_ref().unwrap().stream().emit(
+                http_details_widget::Msg::AppendServerSentEvents(events.to_vec()),
+            );
+        }
+    }
+
+    /// the connection switched to another protocol (WebSocket): remove the
+    /// stream, and hand over its client/server info and its exchanges so far,
+    /// including the requests which are still waiting for a response
+    pub fn remove_upgraded_stream(
+        &mut self,
+        stream_id: TcpStreamId,
+    ) -> Option<(Option<ClientServerInfo>, Vec<HttpMessageData>)> {
+        let stream = self.streams.remove(&stream_id)?;
+        let mut messages = stream.messages;
+        messages.extend(stream.stream_globals.cur_requests.into_iter().map(|req| {
+            HttpMessageData {
+                http_stream_id: 0,
+                request: Some(req),
+                response: None,
+                http2_frames: vec![],
+            }
+        }));
+        Some((stream.client_server, messages))
+    }
 }
 
 #[derive(Debug, Default)]
@@ -71,6 +141,10 @@ pub struct HttpStreamGlobals {
 
     tcp_leftover_payload: Option<(TcpSeqNumber, NaiveDateTime, Vec<u8>)>,
 
+    // text/event-stream responses may never complete while we capture,
+    // we display them as soon as we have their headers and add the events as they come
+    live_event_stream: Option<LiveEventStream>,
+
     // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Server
     // will put the description of the Server http header in the summary details
     // if we can't get the hostname. Don't store it directly in the summary details,
@@ -80,6 +154,31 @@ pub struct HttpStreamGlobals {
     server_info: Option<String>,
 }
 
+#[derive(Debug)]
+struct LiveEventStream {
+    msg_idx: usize,
+    dechunker: Option<ChunkedBodyDecoder>,
+    parser: EventStreamParser,
+}
+
+impl LiveEventStream {
+    fn feed(&mut self, timestamp: NaiveDateTime, bytes: &[u8]) -> (String, Vec<ServerSentEvent>) {
+        match &mut self.dechunker {
+            Some(dechunker) => {
+                let body = dechunker.feed(bytes);
+                self.parser.feed(timestamp, &body)
+            }
+            None => self.parser.feed(timestamp, bytes),
+        }
+    }
+
+    /// a chunked response is complete after the last chunk,
+    /// otherwise it lasts until the connection is closed
+    fn is_done(&self) -> bool {
+        self.dechunker.as_ref().filter(|d| d.is_done).is_some()
+    }
+}
+
 impl HttpStreamGlobals {
     fn http_resp_from_tcp_if_any(
         &mut self,
@@ -107,13 +206,8 @@ impl HttpStreamGlobals {
         Some((&data[0..idx], &data[(idx + crlf2.len())..]))
     }
 
-    fn parse_as_http(
-        seq: TcpSeqNumber,
-        dt: NaiveDateTime,
-        data: Vec<u8>,
-        tcp_stream_id: TcpStreamId,
-    ) -> Option<HttpRequestResponseData> {
-        let (headers, raw_body) = Self::get_headers_body(&data)?;
+    /// the first line and the headers
+    fn parse_header_lines(headers: &[u8]) -> Option<(String, Vec<(String, String)>)> {
         let header_lines: Vec<_> = str::from_utf8(headers).ok()?.lines().collect();
         let first_line = header_lines.first()?.to_string();
         let headers = header_lines[1..]
@@ -123,23 +217,86 @@ impl HttpStreamGlobals {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
             })
             .collect::<Option<Vec<_>>>()?;
+        Some((first_line, headers))
+    }
+
+    fn is_chunked(headers: &[(String, String)]) -> bool {
+        get_http_header_value(headers, "Transfer-Encoding")
+            .filter(|te| te.to_ascii_lowercase().contains("chunked"))
+            .is_some()
+    }
+
+    /// tshark waits for the end of the response to reassemble it, which never
+    /// comes for event streams in a live capture. Meanwhile we get the TCP payload:
+    /// start the event stream as soon as it contains the response headers.
+    fn event_stream_from_tcp_if_any(
+        &mut self,
+        tcp_stream_id: TcpStreamId,
+        msg_idx: usize,
+    ) -> Option<HttpRequestResponseData> {
+        let (_, _, data) = self.tcp_leftover_payload.as_ref()?;
+        let (headers, raw_body) = Self::get_headers_body(data)?;
+        let body_start = data.len() - raw_body.len();
+        let (first_line, headers) = Self::parse_header_lines(headers)?;
+        let content_type = get_http_header_value(&headers, "Content-Type").cloned();
+        let content_encoding = ContentEncoding::parse_from_str(
+            &get_http_header_value(&headers, "Content-Encoding").map(|s| s.as_str()),
+        );
+        if !server_sent_events::is_event_stream(content_type.as_deref())
+            || content_encoding != ContentEncoding::Plain
+        {
+            return None;
+        }
+        let (seq, dt, data) = self.tcp_leftover_payload.take()?;
+        let mut live = LiveEventStream {
+            msg_idx,
+            dechunker: Some(ChunkedBodyDecoder::default()).filter(|_| Self::is_chunked(&headers)),
+            parser: EventStreamParser::default(),
+        };
+        let (text, server_sent_events) = live.feed(dt, &data[body_start..]);
+        self.live_event_stream = Some(live);
+        Some(HttpRequestResponseData {
+            content_encoding,
+            server_sent_events,
+            ..HttpRequestResponseData::new(
+                tcp_stream_id,
+                seq,
+                dt,
+                first_line,
+                headers,
+                HttpBody::Text(text),
+            )
+        })
+    }
+
+    fn parse_as_http(
+        seq: TcpSeqNumber,
+        dt: NaiveDateTime,
+        data: Vec<u8>,
+        tcp_stream_id: TcpStreamId,
+    ) -> Option<HttpRequestResponseData> {
+        let (headers, raw_body) = Self::get_headers_body(&data)?;
+        let (first_line, headers) = Self::parse_header_lines(headers)?;
         let content_type = get_http_header_value(&headers, "Content-Type").map(|s| s.to_string());
         let content_encoding = ContentEncoding::parse_from_str(
             &get_http_header_value(&headers, "Content-Encoding").map(|s| s.as_str()),
         );
-        let body = match str::from_utf8(raw_body) {
-            Ok(txt) => HttpBody::Text(txt.to_string()),
-            _ => HttpBody::Binary(raw_body.to_vec()),
+        let is_chunked = Self::is_chunked(&headers);
+        let raw_body = if is_chunked {
+            Cow::Owned(dechunk_body(raw_body))
+        } else {
+            Cow::Borrowed(raw_body)
+        };
+        // if the body is compressed, body_as_str() will decode it
+        let body = match content_encoding {
+            ContentEncoding::Plain => {
+                parse_body(Some(raw_body.into_owned()), content_type.as_deref())
+            }
+            _ => HttpBody::Binary(raw_body.into_owned()),
         };
         Some(HttpRequestResponseData {
-            tcp_stream_no: tcp_stream_id,
-            tcp_seq_number: seq,
-            timestamp: dt,
-            first_line,
-            headers,
-            content_type,
             content_encoding,
-            body,
+            ..HttpRequestResponseData::new(tcp_stream_id, seq, dt, first_line, headers, body)
         })
     }
 }
@@ -178,6 +335,12 @@ pub enum HttpStringFilterKeys {
     ReqBody,
     #[strum(serialize = "http.resp_body")]
     RespBody,
+    #[strum(serialize = "http.query_param")]
+    QueryParam,
+    #[strum(serialize = "http.form_field")]
+    FormField,
+    #[strum(serialize = "http.cookie")]
+    Cookie,
 }
 
 fn get_http_message<'a, 'b>(
@@ -223,6 +386,14 @@ impl CustomStreamsStore for HttpStreamsStore {
             .and_then(|s| s.summary_details.as_deref())
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn stream_client_server(&self, stream_id: TcpStreamId) -> Option<ClientServerInfo> {
         self.streams.get(&stream_id).and_then(|s| s.client_server)
     }
@@ -320,16 +491,75 @@ impl CustomStreamsStore for HttpStreamsStore {
                             server_port: srv_port,
                         });
                     }
-                    stream.messages.push(HttpMessageData {
-                        http_stream_id: 0,
-                        request: stream.stream_globals.cur_requests.pop_front(),
-                        response: Some(r),
-                    });
+                    match stream.stream_globals.live_event_stream.take() {
+                        Some(mut live)
+                            if server_sent_events::is_event_stream(r.content_type.as_deref()) =>
+                        {
+                            // tshark reassembled the event stream response which
+                            // we displayed as we were receiving it
+                            if let (Some(resp), HttpBody::Text(full_body)) = (
+                                stream
+                                    .messages
+                                    .get_mut(live.msg_idx)
+                                    .and_then(|m| m.response.as_mut()),
+                                &r.body,
+                            ) {
+                                let (text, events) = live
+                                    .parser
+                                    .feed_reassembled(r.timestamp, full_body.as_bytes());
+                                server_sent_events::append_to_body(&mut resp.body, &text);
+                                resp.server_sent_events.extend(events);
+                            }
+                        }
+                        _ => {
+                            let mut r = r;
+                            r.server_sent_events = server_sent_events::parse_body_events(
+                                r.timestamp,
+                                r.content_type.as_deref(),
+                                &r.body,
+                            );
+                            stream.messages.push(HttpMessageData {
+                                http_stream_id: 0,
+                                request: stream.stream_globals.cur_requests.pop_front(),
+                                response: Some(r),
+                                http2_frames: vec![],
+                            });
+                        }
+                    }
                 }
                 ReqRespInfo {
                     req_resp: RequestOrResponse::ResponseBytes(date, seq, bytes),
                     ..
                 } => {
+                    let bytes = match stream.stream_globals.live_event_stream.as_mut() {
+                        Some(live) => {
+                            let (text, events) = live.feed(date, &bytes);
+                            if let Some(resp) = stream
+                                .messages
+                                .get_mut(live.msg_idx)
+                                .and_then(|m| m.response.as_mut())
+                            {
+                                server_sent_events::append_to_body(&mut resp.body, &text);
+                                resp.server_sent_events.extend(events);
+                            }
+                            if !live.is_done() {
+                                continue;
+                            }
+                            // the bytes after the end of the event stream
+                            // belong to the next response
+                            let leftover = live
+                                .dechunker
+                                .as_mut()
+                                .map(|d| std::mem::take(&mut d.pending))
+                                .unwrap_or_default();
+                            stream.stream_globals.live_event_stream = None;
+                            if leftover.is_empty() {
+                                continue;
+                            }
+                            leftover
+                        }
+                        None => bytes,
+                    };
                     stream.stream_globals.tcp_leftover_payload = Some(
                         if let Some(mut payload_sofar) =
                             stream.stream_globals.tcp_leftover_payload.take()
@@ -340,6 +570,18 @@ impl CustomStreamsStore for HttpStreamsStore {
                             (seq, date, bytes)
                         },
                     );
+                    let msg_idx = stream.messages.len();
+                    if let Some(r) = stream
+                        .stream_globals
+                        .event_stream_from_tcp_if_any(stream_id, msg_idx)
+                    {
+                        stream.messages.push(HttpMessageData {
+                            http_stream_id: 0,
+                            request: stream.stream_globals.cur_requests.pop_front(),
+                            response: Some(r),
+                            http2_frames: vec![],
+                        });
+                    }
                 }
             };
         }
@@ -357,6 +599,7 @@ impl CustomStreamsStore for HttpStreamsStore {
                 http_stream_id: 0,
                 request: Some(req),
                 response: globals.http_resp_from_tcp_if_any(stream_id),
+                http2_frames: vec![],
             });
         }
         if stream.summary_details.is_none() && stream.stream_globals.server_info.is_some() {
@@ -434,6 +677,7 @@ impl CustomStreamsStore for HttpStreamsStore {
                 http_stream_id: 0,
                 request: None,
                 response: None,
+                http2_frames: vec![],
             },
             overlay.unwrap().clone(),
             bg_sender,
@@ -451,7 +695,7 @@ impl CustomStreamsStore for HttpStreamsStore {
             self.component.as_ref().unwrap().stream().emit(
                 http_details_widget::Msg::DisplayDetails(
                     bg_sender,
-                    client_server.client_ip,
+                    client_server,
                     stream_id,
                     http_msg.clone(),
                 ),
@@ -560,6 +804,51 @@ pub fn http_matches_filter(
                             .is_some()
                     })
                 }
+                HttpStringFilterKeys::QueryParam => get_http_message(streams, model, iter)
+                    .and_then(|http_msg| http_msg.request.as_ref())
+                    .filter(|r| {
+                        name_values_match(&structured_data::query_params(r), filter_val)
+                    })
+                    .is_some(),
+                HttpStringFilterKeys::FormField => get_http_message(streams, model, iter)
+                    .and_then(|http_msg| http_msg.request.as_ref())
+                    .filter(|r| {
+                        name_values_match(&structured_data::form_fields(r), filter_val)
+                            || structured_data::multipart_parts(r).iter().any(|p| {
+                                p.name
+                                    .as_deref()
+                                    .filter(|n| n.to_lowercase().contains(filter_val))
+                                    .is_some()
+                                    || matches!(&p.body, HttpBody::Text(t) if t.to_lowercase().contains(filter_val))
+                            })
+                    })
+                    .is_some(),
+                HttpStringFilterKeys::Cookie => {
+                    get_http_message(streams, model, iter)
+                        .filter(|http_msg| {
+                            http_msg
+                                .request
+                                .as_ref()
+                                .filter(|r| {
+                                    name_values_match(
+                                        &structured_data::request_cookies(r),
+                                        filter_val,
+                                    )
+                                })
+                                .is_some()
+                                || http_msg
+                                    .response
+                                    .as_ref()
+                                    .filter(|r| {
+                                        structured_data::response_cookies(r).iter().any(|c| {
+                                            c.name.to_lowercase().contains(filter_val)
+                                                || c.value.to_lowercase().contains(filter_val)
+                                        })
+                                    })
+                                    .is_some()
+                        })
+                        .is_some()
+                }
             }
         }
         (_, Ok(filter_key), SearchCriteria::GreaterThan(filter_val, decimals)) => {
@@ -624,6 +913,12 @@ pub fn http_matches_filter(
     }
 }
 
+fn name_values_match(name_values: &[(String, String)], filter_val: &str) -> bool {
+    name_values.iter().any(|(k, v)| {
+        k.to_lowercase().contains(filter_val) || v.to_lowercase().contains(filter_val)
+    })
+}
+
 fn body_size_matches<F>(
     streams: &HashMap<TcpStreamId, &Vec<HttpMessageData>>,
     model: &gtk::TreeModel,
@@ -757,6 +1052,7 @@ pub fn http_populate_treeview(
                 .unwrap_or("Missing request info")
                 .to_value(),
         );
+        let missing_response_reason = http.missing_response_reason();
         ls.set_value(
             &iter,
             1,
@@ -764,6 +1060,7 @@ pub fn http_populate_treeview(
                 .response
                 .as_ref()
                 .map(|r| r.first_line.as_str())
+                .or(missing_response_reason.as_deref())
                 .unwrap_or("Missing response info")
                 .to_value(),
         );
@@ -803,21 +1100,25 @@ pub fn http_populate_treeview(
                 .to_value(),
         );
         let str_is_numbers_only = |s: &&str| s.chars().all(|c| c.is_numeric());
-        let resp_code: Option<u16> = http
-            .response
-            .as_ref()
-            .and_then(|r| {
-                r.first_line
-                    .split_ascii_whitespace()
-                    .find(str_is_numbers_only)
-            })
-            .and_then(|s| s.parse().ok());
+        let resp_code: Option<u16> = http.response.as_ref().and_then(|r| {
+            // gRPC errors come with a HTTP 200 status
+            grpc::grpc_status(&r.trailers)
+                .or_else(|| grpc::grpc_status(&r.headers))
+                .map(|(code, _)| grpc::http_equivalent_status(code))
+                .or_else(|| {
+                    r.first_line
+                        .split_ascii_whitespace()
+                        .find(str_is_numbers_only)
+                        .and_then(|s| s.parse().ok())
+                })
+        });
         ls.set_value(
             &iter,
             12,
             &match resp_code {
                 Some(r) if (400..500).contains(&r) => colors::WARNING_COLOR.to_value(),
                 Some(r) if (500..600).contains(&r) => colors::ERROR_COLOR.to_value(),
+                None if missing_response_reason.is_some() => colors::ERROR_COLOR.to_value(),
                 _ => None::<&str>.to_value(),
             },
         );
@@ -885,26 +1186,78 @@ pub struct HttpRequestResponseData {
     pub body: HttpBody,
     pub content_type: Option<String>,
     pub content_encoding: ContentEncoding,
+    // for text/event-stream responses
+    pub server_sent_events: Vec<ServerSentEvent>,
+    // 1xx responses such as 103 Early Hints, before the final response.
+    // only used for http2, always empty for http1
+    pub interim_responses: Vec<InterimResponse>,
+    // the headers after the body. only used for http2, always empty for http1
+    pub trailers: Vec<(String, String)>,
+}
+
+#[cfg(test)]
+impl HttpRequestResponseData {
+    /// a request or response for the tests. The content type comes from the headers.
+    pub fn test_data(
+        first_line: &str,
+        headers: &[(&str, &str)],
+        body: HttpBody,
+    ) -> HttpRequestResponseData {
+        let headers: Vec<_> = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        HttpRequestResponseData::new(
+            TcpStreamId(1),
+            TcpSeqNumber(1),
+            NaiveDate::from_ymd(2021, 3, 18).and_hms(12, 8, 13),
+            first_line.to_string(),
+            headers,
+            body,
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterimResponse {
+    pub first_line: String,
+    pub headers: Vec<(String, String)>,
 }
 
 impl HttpRequestResponseData {
+    /// the content type comes from the headers. Callers override the
+    /// other fields when they have them
+    pub fn new(
+        tcp_stream_no: TcpStreamId,
+        tcp_seq_number: TcpSeqNumber,
+        timestamp: NaiveDateTime,
+        first_line: String,
+        headers: Vec<(String, String)>,
+        body: HttpBody,
+    ) -> HttpRequestResponseData {
+        HttpRequestResponseData {
+            tcp_stream_no,
+            tcp_seq_number,
+            timestamp,
+            first_line,
+            content_type: get_http_header_value(&headers, "Content-Type").cloned(),
+            headers,
+            body,
+            content_encoding: ContentEncoding::Plain,
+            server_sent_events: vec![],
+            interim_responses: vec![],
+            trailers: vec![],
+        }
+    }
+
     pub fn body_as_str(&self) -> Option<Cow<str>> {
         match (&self.body, &self.content_encoding) {
             (HttpBody::Text(s), _) => Some(Cow::Borrowed(s)), // tshark will do some decoding for us... could have text even if the encoding is gzip
-            (HttpBody::Binary(bytes), ContentEncoding::Brotli) => {
-                let mut r = String::new();
-                brotli::Decompressor::new(&bytes[..], 4096)
-                    .read_to_string(&mut r)
-                    .ok()
-                    .map(|_| Cow::Owned(r))
-            }
-            (HttpBody::Binary(bytes), ContentEncoding::Gzip) => {
-                // not sure we really need the gzip. i think tshark always decodes gzip for us
-                // (doesn't do it for brotli!!)
-                let mut d = GzDecoder::new(&bytes[..]);
-                let mut r = String::new();
-                d.read_to_string(&mut r).ok().map(|_| Cow::Owned(r))
-            }
+            (HttpBody::Binary(_), ContentEncoding::Plain) => None,
+            (HttpBody::Binary(bytes), encoding) => encoding
+                .decode(bytes)
+                .and_then(|d| decode_text(&d, self.content_type.as_deref()))
+                .map(Cow::Owned),
             _ => None,
         }
     }
@@ -915,17 +1268,165 @@ pub enum ContentEncoding {
     Plain,
     Gzip,
     Brotli,
-    // TODO deflate
+    Deflate,
+    Zstd,
+    // "gzip, br": the encodings in the order they were applied
+    Multiple(Vec<ContentEncoding>),
 }
 
 impl ContentEncoding {
     pub fn parse_from_str(input: &Option<&str>) -> ContentEncoding {
-        match input {
-            Some("br") => ContentEncoding::Brotli,
-            Some("gzip") => ContentEncoding::Gzip,
-            _ => ContentEncoding::Plain,
+        let mut encodings: Vec<_> = input
+            .unwrap_or("")
+            .split(',')
+            .map(|e| match e.trim().to_ascii_lowercase().as_str() {
+                "br" => ContentEncoding::Brotli,
+                "gzip" | "x-gzip" => ContentEncoding::Gzip,
+                "deflate" => ContentEncoding::Deflate,
+                "zstd" => ContentEncoding::Zstd,
+                _ => ContentEncoding::Plain,
+            })
+            .filter(|e| *e != ContentEncoding::Plain)
+            .collect();
+        match encodings.len() {
+            0 => ContentEncoding::Plain,
+            1 => encodings.remove(0),
+            _ => ContentEncoding::Multiple(encodings),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let mut r = vec![];
+        match self {
+            ContentEncoding::Plain => r.extend_from_slice(bytes),
+            ContentEncoding::Brotli => {
+                brotli::Decompressor::new(bytes, 4096)
+                    .read_to_end(&mut r)
+                    .ok()?;
+            }
+            ContentEncoding::Gzip => {
+                // not sure we really need the gzip. i think tshark always decodes gzip for us
+                // (doesn't do it for brotli!!)
+                GzDecoder::new(bytes).read_to_end(&mut r).ok()?;
+            }
+            ContentEncoding::Deflate => {
+                // the spec says zlib, but some servers send raw deflate
+                if ZlibDecoder::new(bytes).read_to_end(&mut r).is_err() {
+                    r.clear();
+                    DeflateDecoder::new(bytes).read_to_end(&mut r).ok()?;
+                }
+            }
+            ContentEncoding::Zstd => {
+                zstd::stream::read::Decoder::new(bytes)
+                    .ok()?
+                    .read_to_end(&mut r)
+                    .ok()?;
+            }
+            ContentEncoding::Multiple(encodings) => {
+                // undo the encodings in the reverse order they were applied
+                return encodings
+                    .iter()
+                    .rev()
+                    .try_fold(bytes.to_vec(), |data, encoding| encoding.decode(&data));
+            }
+        }
+        Some(r)
+    }
+}
+
+/// Decodes a chunked body as we get it from packets, see dechunk_body()
+/// Once it's done, `pending` holds the bytes following the body.
+#[derive(Debug, Default)]
+struct ChunkedBodyDecoder {
+    pending: Vec<u8>,
+    chunk_left: usize,
+    expect_crlf: bool,
+    // after the last chunk, until the empty line
+    in_trailers: bool,
+    is_done: bool,
+}
+
+impl ChunkedBodyDecoder {
+    fn feed(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut result = vec![];
+        if self.is_done {
+            return result;
         }
+        self.pending.extend_from_slice(bytes);
+        loop {
+            if self.chunk_left > 0 {
+                let len = self.chunk_left.min(self.pending.len());
+                if len == 0 {
+                    break;
+                }
+                result.extend(self.pending.drain(..len));
+                self.chunk_left -= len;
+                self.expect_crlf = self.chunk_left == 0;
+            } else if self.expect_crlf {
+                if self.pending.len() < 2 {
+                    break;
+                }
+                self.pending.drain(..2);
+                self.expect_crlf = false;
+            } else {
+                let line_end = match self.pending.windows(2).position(|w| w == b"\r\n") {
+                    Some(e) => e,
+                    None => break,
+                };
+                if self.in_trailers {
+                    self.pending.drain(..line_end + 2);
+                    if line_end == 0 {
+                        self.is_done = true;
+                        break;
+                    }
+                    continue;
+                }
+                let size_str = str::from_utf8(&self.pending[..line_end]).unwrap_or("");
+                // ignore chunk extensions
+                match usize::from_str_radix(size_str.split(';').next().unwrap_or("").trim(), 16) {
+                    Ok(0) => {
+                        self.in_trailers = true;
+                        self.pending.drain(..line_end + 2);
+                    }
+                    Err(_) => {
+                        self.is_done = true;
+                        self.pending.clear();
+                        break;
+                    }
+                    Ok(size) => {
+                        self.chunk_left = size;
+                        self.pending.drain(..line_end + 2);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-4.1
+/// chunk sizes in hex, followed by the chunk data, until a zero-sized chunk.
+/// If the data is truncated, we return what we could decode.
+fn dechunk_body(raw_body: &[u8]) -> Vec<u8> {
+    let mut result = vec![];
+    let mut rest = raw_body;
+    while let Some(line_end) = rest.windows(2).position(|w| w == b"\r\n") {
+        let size_str = str::from_utf8(&rest[..line_end]).unwrap_or("");
+        // ignore chunk extensions
+        let size = match usize::from_str_radix(size_str.split(';').next().unwrap_or("").trim(), 16)
+        {
+            Ok(s) => s,
+            Err(_) => break,
+        };
+        if size == 0 {
+            break;
+        }
+        let data = &rest[line_end + 2..];
+        let chunk_len = size.min(data.len());
+        result.extend_from_slice(&data[..chunk_len]);
+        rest = data.get(chunk_len + 2..).unwrap_or(&[]);
     }
+    result
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -933,6 +1434,29 @@ pub struct HttpMessageData {
     pub http_stream_id: u32, // only used for http2. always 0 for http1
     pub request: Option<HttpRequestResponseData>,
     pub response: Option<HttpRequestResponseData>,
+    // RST_STREAM, PUSH_PROMISE... only used for http2. always empty for http1
+    pub http2_frames: Vec<Http2FrameEvent>,
+}
+
+impl HttpMessageData {
+    /// why we have no response: the stream was reset, or the connection
+    /// was closed with a GOAWAY before the response
+    pub fn missing_response_reason(&self) -> Option<String> {
+        if self.response.is_some() {
+            return None;
+        }
+        self.http2_frames.iter().rev().find_map(|f| match f.frame {
+            Http2ControlFrame::RstStream { error_code } => Some(format!(
+                "Stream reset: {}",
+                http2_frames::error_code_desc(error_code)
+            )),
+            Http2ControlFrame::GoAway { error_code, .. } => Some(format!(
+                "Connection closed (GOAWAY): {}",
+                http2_frames::error_code_desc(error_code)
+            )),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -961,16 +1485,59 @@ struct ReqRespInfo {
     host: Option<String>,
 }
 
-fn parse_body(body: Option<Vec<u8>>, _headers: &[(String, String)]) -> HttpBody {
-    body.map(|d| {
-        str::from_utf8(&d)
-            .ok()
-            .map(|s| HttpBody::Text(s.to_string()))
-            .unwrap_or_else(|| HttpBody::Binary(d))
+pub fn parse_body(body: Option<Vec<u8>>, content_type: Option<&str>) -> HttpBody {
+    body.map(|d| match decode_text(&d, content_type) {
+        Some(txt) => HttpBody::Text(txt),
+        None => HttpBody::Binary(d),
     })
     .unwrap_or(HttpBody::Missing)
 }
 
+/// tshark may give us the body still compressed, or decompressed even though
+/// the headers say it's compressed. Returns the encoding of the body we got.
+/// We must not decode a compressed body as text: some charsets such as
+/// latin1 accept any bytes.
+pub fn parse_encoded_body(
+    body: Option<Vec<u8>>,
+    content_type: Option<&str>,
+    content_encoding: ContentEncoding,
+) -> (HttpBody, ContentEncoding) {
+    match body {
+        Some(d)
+            if content_encoding != ContentEncoding::Plain
+                && content_encoding.decode(&d).is_some() =>
+        {
+            (HttpBody::Binary(d), content_encoding)
+        }
+        _ => (parse_body(body, content_type), ContentEncoding::Plain),
+    }
+}
+
+/// the body is text if it's valid UTF-8, or if it starts with a BOM,
+/// or if we can decode it with the charset of the Content-Type header or
+/// of the HTML/XML declaration.
+fn decode_text(data: &[u8], content_type: Option<&str>) -> Option<String> {
+    if let Ok(txt) = str::from_utf8(data) {
+        return Some(txt.to_string());
+    }
+    let (encoding, data) = match Encoding::for_bom(data) {
+        Some((encoding, bom_length)) => (encoding, &data[bom_length..]),
+        None => {
+            let charset = content_type
+                .and_then(|ct| CONTENT_TYPE_CHARSET.captures(ct.as_bytes()))
+                .or_else(|| {
+                    // the declaration must be at the beginning of the document
+                    let start = &data[..data.len().min(1024)];
+                    DECLARATION_CHARSET.captures(start)
+                })?;
+            (Encoding::for_label(&charset[1])?, data)
+        }
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(data)
+        .map(|txt| txt.into_owned())
+}
+
 fn parse_request_response(
     comm: TSharkPacket,
     server_ip_port_if_known: Option<(IpAddr, NetworkPort)>,
@@ -989,18 +1556,28 @@ fn parse_request_response(
         Some(http_list) => {
             for http in http_list.into_iter() {
                 let http_headers = parse_headers(&http.other_lines);
+                let (body, content_encoding) = parse_encoded_body(
+                    http.body,
+                    http.content_type.as_deref(),
+                    ContentEncoding::parse_from_str(
+                        &get_http_header_value(&http_headers, "Content-Encoding")
+                            .map(|s| s.as_str()),
+                    ),
+                );
                 let http_type = http.http_type.or(ip_port_req_resp_guess);
                 match http_type {
                     Some(HttpType::Request) => reqresp.push(ReqRespInfo {
                         req_resp: RequestOrResponse::Request(HttpRequestResponseData {
-                            tcp_stream_no: comm.basic_info.tcp_stream_id,
-                            tcp_seq_number: comm.basic_info.tcp_seq_number,
-                            timestamp: comm.basic_info.frame_time,
-                            body: parse_body(http.body, &http_headers),
-                            first_line: http.first_line,
-                            headers: http_headers,
                             content_type: http.content_type,
-                            content_encoding: ContentEncoding::Plain, // not sure whether maybe tshark decodes before us...
+                            content_encoding,
+                            ..HttpRequestResponseData::new(
+                                comm.basic_info.tcp_stream_id,
+                                comm.basic_info.tcp_seq_number,
+                                comm.basic_info.frame_time,
+                                http.first_line,
+                                http_headers,
+                                body,
+                            )
                         }),
                         port_dst: comm.basic_info.port_dst,
                         ip_dst,
@@ -1009,14 +1586,16 @@ fn parse_request_response(
                     }),
                     Some(HttpType::Response) => reqresp.push(ReqRespInfo {
                         req_resp: RequestOrResponse::Response(HttpRequestResponseData {
-                            tcp_stream_no: comm.basic_info.tcp_stream_id,
-                            tcp_seq_number: comm.basic_info.tcp_seq_number,
-                            timestamp: comm.basic_info.frame_time,
-                            body: parse_body(http.body, &http_headers),
-                            first_line: http.first_line,
-                            headers: http_headers,
                             content_type: http.content_type,
-                            content_encoding: ContentEncoding::Plain, // not sure whether maybe tshark decodes before us...
+                            content_encoding,
+                            ..HttpRequestResponseData::new(
+                                comm.basic_info.tcp_stream_id,
+                                comm.basic_info.tcp_seq_number,
+                                comm.basic_info.frame_time,
+                                http.first_line,
+                                http_headers,
+                                body,
+                            )
                         }),
                         port_dst: comm.basic_info.port_src,
                         ip_dst: ip_src,
@@ -1129,6 +1708,9 @@ fn should_parse_plain_request_response() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                server_sent_events: vec![],
+                interim_responses: vec![],
+                trailers: vec![],
             }),
             response: Some(HttpRequestResponseData {
                 tcp_stream_no: TcpStreamId(0),
@@ -1139,7 +1721,11 @@ fn should_parse_plain_request_response() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                server_sent_events: vec![],
+                interim_responses: vec![],
+                trailers: vec![],
             }),
+            http2_frames: vec![],
         },
         HttpMessageData {
             http_stream_id: 0,
@@ -1152,8 +1738,12 @@ fn should_parse_plain_request_response() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                server_sent_events: vec![],
+                interim_responses: vec![],
+                trailers: vec![],
             }),
             response: None,
+            http2_frames: vec![],
         },
     ];
     assert_eq!(expected, parsed);
@@ -1210,6 +1800,9 @@ fn should_add_non_http_tcp_stream_at_end_of_exchange() {
             body: HttpBody::Missing,
             content_type: None,
             content_encoding: ContentEncoding::Plain,
+            server_sent_events: vec![],
+            interim_responses: vec![],
+            trailers: vec![],
         }),
         response: Some(HttpRequestResponseData {
             tcp_stream_no: TcpStreamId(1),
@@ -1223,11 +1816,278 @@ fn should_add_non_http_tcp_stream_at_end_of_exchange() {
             body: HttpBody::Text("{\"attributes\":{\"description\":\"\",\"kibanaSavedObjectMeta\":{\"searchSourceJSON\":\"{\\\"filter\\\":[],\\\"query\\\":{\\\"language\\\":\\\"kuery\\\",\\\"query\\\":\\\"\\\"}}\"},\"title\":\"System Navigat".to_string()),
             content_type: Some("application/ndjson".to_string()),
             content_encoding: ContentEncoding::Plain,
+            server_sent_events: vec![],
+            interim_responses: vec![],
+            trailers: vec![],
         }),
+        http2_frames: vec![],
     }];
     assert_eq!(expected, parsed);
 }
 
+#[test]
+fn should_dechunk_non_http_tcp_stream_at_end_of_exchange() {
+    let parsed = tests_parse_stream(parse_test_xml_no_wrapper(
+        r#"
+      <pdml>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.215.215.9" />
+              <field name="ip.dst" show="10.215.215.9" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="53092" />
+            <field name="tcp.dstport" show="80" />
+          </proto>
+          <proto name="http">
+            <field name="" show="GET /test"></field>
+            <field name="http.request.line" showname="Host: 192.168.1.1\r\n" hide="yes" size="22" pos="114" show="Host: 192.168.1.1" value="486f73743a203139322e3136382e38382e3230300d0a"/>
+          </proto>
+        </packet>
+
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.215.215.9" />
+              <field name="ip.dst" show="10.215.215.9" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+            <field name="tcp.payload" value="485454502f312e3120323030204f4b0d0a436f6e74656e742d547970653a20746578742f706c61696e0d0a5472616e736665722d456e636f64696e673a206368756e6b65640d0a0d0a350d0a68656c6c6f0d0a373b6578743d310d0a2c20776f726c640d0a300d0a0d0a" />
+          </proto>
+        </packet>
+      </pdml>
+        "#,
+    ))
+    .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(
+        HttpBody::Text("hello, world".to_string()),
+        parsed[0].response.as_ref().unwrap().body
+    );
+}
+
+#[test]
+fn should_parse_live_event_streams_as_packets_arrive() {
+    let packets = parse_test_xml_no_wrapper(
+        r#"
+      <pdml>
+        <packet>
+          <proto name="frame">
+            <field name="frame.time" show="Mar  5, 2021 08:49:52.000000000 CET"/>
+          </proto>
+          <proto name="ip">
+              <field name="ip.src" show="10.215.215.9" />
+              <field name="ip.dst" show="10.215.215.9" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="53092" />
+            <field name="tcp.dstport" show="80" />
+          </proto>
+          <proto name="http">
+            <field name="" show="GET /events"></field>
+            <field name="http.request.line" showname="Host: 192.168.1.1\r\n" hide="yes" size="22" pos="114" show="Host: 192.168.1.1" value="486f73743a203139322e3136382e38382e3230300d0a"/>
+          </proto>
+        </packet>
+
+        <packet>
+          <proto name="frame">
+            <field name="frame.time" show="Mar  5, 2021 08:49:53.000000000 CET"/>
+          </proto>
+          <proto name="ip">
+              <field name="ip.src" show="10.215.215.9" />
+              <field name="ip.dst" show="10.215.215.9" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+            <field name="tcp.payload" value="485454502f312e3120323030204f4b0d0a436f6e74656e742d547970653a20746578742f6576656e742d73747265616d0d0a5472616e736665722d456e636f64696e673a206368756e6b65640d0a0d0a31330d0a69643a20310a646174613a2066697273740a0a0d0a390d0a646174613a207365630d0a" />
+          </proto>
+        </packet>
+
+        <packet>
+          <proto name="frame">
+            <field name="frame.time" show="Mar  5, 2021 08:49:54.000000000 CET"/>
+          </proto>
+          <proto name="ip">
+              <field name="ip.src" show="10.215.215.9" />
+              <field name="ip.dst" show="10.215.215.9" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+            <field name="tcp.payload" value="350d0a6f6e640a0a0d0a31390d0a6576656e743a2070696e670a646174613a2074686972640a0a0d0a" />
+          </proto>
+        </packet>
+      </pdml>
+        "#,
+    )
+    .unwrap();
+    let mut parser = HttpStreamsStore::default();
+    let sid = TcpStreamId(1);
+    let mut message_counts = vec![];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+        message_counts.push(parser.stream_message_count(sid).unwrap());
+    }
+    // the response is there as soon as we got its headers
+    assert_eq!(vec![0, 1, 1], message_counts);
+    parser.finish_stream(sid).unwrap();
+    let messages = parser.stream_http_messages(sid).unwrap();
+    assert_eq!(1, messages.len());
+    assert_eq!(
+        "GET /events",
+        messages[0].request.as_ref().unwrap().first_line
+    );
+    let response = messages[0].response.as_ref().unwrap();
+    assert_eq!(
+        HttpBody::Text(
+            "id: 1\ndata: first\n\ndata: second\n\nevent: ping\ndata: third\n\n".to_string()
+        ),
+        response.body
+    );
+    assert_eq!(
+        vec![
+            (53, Some("1"), "message", "first"),
+            (54, Some("1"), "message", "second"),
+            (54, Some("1"), "ping", "third")
+        ],
+        response
+            .server_sent_events
+            .iter()
+            .map(|e| (
+                e.timestamp.second(),
+                e.id.as_deref(),
+                e.event_type(),
+                e.data.as_str()
+            ))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn should_decode_deflate_zstd_and_stacked_encodings() {
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    let body = b"{\"hello\": \"world\"}";
+    let zlib = {
+        let mut e = ZlibEncoder::new(vec![], Compression::default());
+        e.write_all(body).unwrap();
+        e.finish().unwrap()
+    };
+    let raw_deflate = {
+        let mut e = DeflateEncoder::new(vec![], Compression::default());
+        e.write_all(body).unwrap();
+        e.finish().unwrap()
+    };
+    let zstd = zstd::encode_all(&body[..], 0).unwrap();
+    let gzip_then_br = {
+        let mut e = GzEncoder::new(vec![], Compression::default());
+        e.write_all(body).unwrap();
+        let gzipped = e.finish().unwrap();
+        let mut br = vec![];
+        brotli::CompressorWriter::new(&mut br, 4096, 5, 22)
+            .write_all(&gzipped)
+            .unwrap();
+        br
+    };
+    for (encoding, bytes) in [
+        ("deflate", zlib),
+        ("deflate", raw_deflate),
+        ("zstd", zstd),
+        ("gzip, br", gzip_then_br),
+    ] {
+        let data = HttpRequestResponseData {
+            content_encoding: ContentEncoding::parse_from_str(&Some(encoding)),
+            ..HttpRequestResponseData::new(
+                TcpStreamId(0),
+                TcpSeqNumber(0),
+                NaiveDate::from_ymd(1970, 1, 1).and_hms(0, 0, 0),
+                "HTTP/1.1 200 OK".to_string(),
+                vec![],
+                HttpBody::Binary(bytes),
+            )
+        };
+        assert_eq!(
+            Some("{\"hello\": \"world\"}"),
+            data.body_as_str().as_deref(),
+            "{}",
+            encoding
+        );
+    }
+}
+
+#[test]
+fn should_not_decode_compressed_bodies_as_text() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    let latin1 = b"caf\xe9".to_vec();
+    let gzipped = {
+        let mut e = GzEncoder::new(vec![], Compression::default());
+        e.write_all(&latin1).unwrap();
+        e.finish().unwrap()
+    };
+    let content_type = Some("text/plain; charset=iso-8859-1");
+    let (body, content_encoding) =
+        parse_encoded_body(Some(gzipped.clone()), content_type, ContentEncoding::Gzip);
+    assert_eq!(
+        (HttpBody::Binary(gzipped), ContentEncoding::Gzip),
+        (body.clone(), content_encoding.clone())
+    );
+    let data = HttpRequestResponseData {
+        content_type: content_type.map(|ct| ct.to_string()),
+        content_encoding,
+        ..HttpRequestResponseData::test_data("HTTP/1.1 200 OK", &[], body)
+    };
+    assert_eq!(Some("café"), data.body_as_str().as_deref());
+    // tshark already decompressed it
+    assert_eq!(
+        (HttpBody::Text("café".to_string()), ContentEncoding::Plain),
+        parse_encoded_body(Some(latin1), content_type, ContentEncoding::Gzip)
+    );
+}
+
+#[test]
+fn should_decode_bodies_using_their_charset() {
+    assert_eq!(
+        HttpBody::Text("café".to_string()),
+        parse_body(
+            Some(b"caf\xe9".to_vec()),
+            Some("text/plain; charset=ISO-8859-1")
+        )
+    );
+    assert_eq!(
+        HttpBody::Text("5 €".to_string()),
+        parse_body(
+            Some(b"5 \x80".to_vec()),
+            Some("text/plain;charset=\"windows-1252\"")
+        )
+    );
+    assert_eq!(
+        HttpBody::Text("<meta charset=\"Shift_JIS\">日本".to_string()),
+        parse_body(
+            Some(b"<meta charset=\"Shift_JIS\">\x93\xfa\x96\x7b".to_vec()),
+            Some("text/html")
+        )
+    );
+    assert_eq!(
+        HttpBody::Text("<?xml version=\"1.0\" encoding=\"iso-8859-1\"?><a>é</a>".to_string()),
+        parse_body(
+            Some(b"<?xml version=\"1.0\" encoding=\"iso-8859-1\"?><a>\xe9</a>".to_vec()),
+            None
+        )
+    );
+    assert_eq!(
+        HttpBody::Text("hi".to_string()),
+        parse_body(Some(b"\xff\xfeh\x00i\x00".to_vec()), None)
+    );
+    assert_eq!(
+        HttpBody::Binary(b"\x89PNG\xff".to_vec()),
+        parse_body(Some(b"\x89PNG\xff".to_vec()), Some("image/png"))
+    );
+}
+
 #[test]
 fn should_parse_http11_pipelining() {
     let parsed = tests_parse_stream(parse_test_xml_no_wrapper(
@@ -1298,6 +2158,9 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                server_sent_events: vec![],
+                interim_responses: vec![],
+                trailers: vec![],
             }),
             response: Some(HttpRequestResponseData {
                 tcp_stream_no: TcpStreamId(0),
@@ -1308,7 +2171,11 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                server_sent_events: vec![],
+                interim_responses: vec![],
+                trailers: vec![],
             }),
+            http2_frames: vec![],
         },
         HttpMessageData {
             http_stream_id: 0,
@@ -1321,6 +2188,9 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                server_sent_events: vec![],
+                interim_responses: vec![],
+                trailers: vec![],
             }),
             response: Some(HttpRequestResponseData {
                 tcp_stream_no: TcpStreamId(0),
@@ -1331,8 +2201,94 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                server_sent_events: vec![],
+                interim_responses: vec![],
+                trailers: vec![],
             }),
+            http2_frames: vec![],
         },
     ];
     assert_eq!(expected, parsed);
 }
+
+#[test]
+fn should_parse_the_response_after_a_complete_live_event_stream() {
+    let request = |time: &str, path: &str| {
+        format!(
+            r#"
+        <packet>
+          <proto name="frame">
+            <field name="frame.time" show="Mar  5, 2021 08:49:{}.000000000 CET"/>
+          </proto>
+          <proto name="ip">
+              <field name="ip.src" show="10.215.215.9" />
+              <field name="ip.dst" show="10.215.215.9" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="53092" />
+            <field name="tcp.dstport" show="80" />
+          </proto>
+          <proto name="http">
+            <field name="" show="GET {}"></field>
+            <field name="http.request.line" showname="Host: 192.168.1.1\r\n" hide="yes" size="22" pos="114" show="Host: 192.168.1.1" value="486f73743a203139322e3136382e38382e3230300d0a"/>
+          </proto>
+        </packet>"#,
+            time, path
+        )
+    };
+    let response_bytes = |time: &str, payload: &str| {
+        format!(
+            r#"
+        <packet>
+          <proto name="frame">
+            <field name="frame.time" show="Mar  5, 2021 08:49:{}.000000000 CET"/>
+          </proto>
+          <proto name="ip">
+              <field name="ip.src" show="10.215.215.9" />
+              <field name="ip.dst" show="10.215.215.9" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+            <field name="tcp.payload" value="{}" />
+          </proto>
+        </packet>"#,
+            time, payload
+        )
+    };
+    let packets = parse_test_xml_no_wrapper(&format!(
+        "<pdml>{}{}{}{}</pdml>",
+        request("50", "/events"),
+        request("51", "/more-events"),
+        // the headers and a first event
+        response_bytes("52", "485454502f312e3120323030204f4b0d0a436f6e74656e742d547970653a20746578742f6576656e742d73747265616d0d0a5472616e736665722d456e636f64696e673a206368756e6b65640d0a0d0a31330d0a69643a20310a646174613a2066697273740a0a0d0a"),
+        // the last chunk, then the next response, with an event
+        response_bytes("53", "300d0a0d0a485454502f312e3120323030204f4b0d0a436f6e74656e742d547970653a20746578742f6576656e742d73747265616d0d0a5472616e736665722d456e636f64696e673a206368756e6b65640d0a0d0a650d0a646174613a207365636f6e640a0a0d0a"),
+    ))
+    .unwrap();
+    let mut parser = HttpStreamsStore::default();
+    let sid = TcpStreamId(1);
+    let mut message_counts = vec![];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+        message_counts.push(parser.stream_message_count(sid).unwrap());
+    }
+    assert_eq!(vec![0, 0, 1, 2], message_counts);
+    let messages = parser.stream_http_messages(sid).unwrap();
+    let events = |idx: usize| {
+        messages[idx]
+            .response
+            .as_ref()
+            .unwrap()
+            .server_sent_events
+            .iter()
+            .map(|e| e.data.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        "GET /more-events",
+        messages[1].request.as_ref().unwrap().first_line
+    );
+    assert_eq!(vec!["first"], events(0));
+    assert_eq!(vec!["second"], events(1));
+}
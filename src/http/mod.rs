@@ -1,5 +1,14 @@
-mod code_formatting;
+mod auth_info;
+mod body_tree;
+pub mod code_formatting;
+pub mod har;
+pub mod http2_frames;
 mod http_body_widget;
 pub mod http_details_widget;
+mod http_replay;
+mod http_replay_window;
 pub mod http_streams_store;
+mod request_export;
+pub mod server_sent_events;
+mod structured_data;
 pub mod tshark_http;
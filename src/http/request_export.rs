@@ -0,0 +1,399 @@
+// export of HTTP requests as curl or httpie commands, or as raw HTTP/1.1.
+// For HTTP2 we rebuild the request line from the pseudo-headers.
+// curl and httpie can't send trailers, only the raw HTTP export has them.
+use super::http_streams_store;
+use super::http_streams_store::{ContentEncoding, HttpBody, HttpRequestResponseData};
+use crate::custom_streams_store::ClientServerInfo;
+use crate::tshark_communication::NetworkPort;
+use itertools::Itertools;
+use std::net::SocketAddr;
+
+#[derive(Debug, Clone, Copy)]
+pub enum RequestExportFormat {
+    Curl,
+    Httpie,
+    RawHttp,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RequestLine<'a> {
+    pub method: &'a str,
+    pub target: &'a str,
+    pub http_version: &'a str,
+}
+
+pub fn parse_request_line(request: &HttpRequestResponseData) -> RequestLine<'_> {
+    let header = |name| http_streams_store::get_http_header_value(&request.headers, name);
+    match header(":method") {
+        // http2
+        Some(method) => RequestLine {
+            method,
+            target: header(":path").map(|p| p.as_str()).unwrap_or("/"),
+            http_version: "HTTP/2.0",
+        },
+        None => {
+            let mut parts = request.first_line.split_ascii_whitespace();
+            RequestLine {
+                method: parts.next().unwrap_or("GET"),
+                target: parts.next().unwrap_or("/"),
+                http_version: parts.next().unwrap_or("HTTP/1.1"),
+            }
+        }
+    }
+}
+
+/// the host comes from the Host or :authority headers, if we don't have them
+/// we fallback to the server IP
+pub fn request_url(request: &HttpRequestResponseData, client_server: &ClientServerInfo) -> String {
+    let target = parse_request_line(request).target;
+    if target.starts_with("http://") || target.starts_with("https://") {
+        // request to a proxy
+        return target.to_string();
+    }
+    let header = |name| http_streams_store::get_http_header_value(&request.headers, name);
+    let scheme = header(":scheme").map(|s| s.as_str()).unwrap_or(
+        if client_server.server_port == NetworkPort(443) {
+            "https"
+        } else {
+            "http"
+        },
+    );
+    let authority = header(":authority").or_else(|| header("Host")).map_or_else(
+        || SocketAddr::new(client_server.server_ip, client_server.server_port.as_u16()).to_string(),
+        |a| a.to_string(),
+    );
+    format!("{}://{}{}", scheme, authority, target)
+}
+
+/// the headers, adapted to the body we export: tshark can give us
+/// decompressed bodies, and we store text bodies in UTF-8, whatever
+/// their original charset. The pseudo-headers of HTTP2 are left out.
+pub fn headers_for_body(request: &HttpRequestResponseData) -> Vec<(String, String)> {
+    let is_text = matches!(request.body, HttpBody::Text(_));
+    // content_encoding is the encoding of the body we stored: Plain if tshark
+    // decompressed it, whatever the Content-Encoding header says
+    let is_decompressed = is_text || request.content_encoding == ContentEncoding::Plain;
+    request
+        .headers
+        .iter()
+        .filter(|(k, _)| !k.starts_with(':'))
+        .filter(|(k, _)| !is_decompressed || !k.eq_ignore_ascii_case("Content-Encoding"))
+        .map(|(k, v)| {
+            if is_text && k.eq_ignore_ascii_case("Content-Type") {
+                (k.clone(), with_utf8_charset(v))
+            } else {
+                (k.clone(), v.clone())
+            }
+        })
+        .collect()
+}
+
+fn with_utf8_charset(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .map(|param| {
+            if param
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("charset=")
+            {
+                " charset=utf-8"
+            } else {
+                param
+            }
+        })
+        .join(";")
+}
+
+/// curl and httpie compute the content length themselves
+fn exported_headers(request: &HttpRequestResponseData) -> Vec<(String, String)> {
+    headers_for_body(request)
+        .into_iter()
+        .filter(|(k, _)| !k.eq_ignore_ascii_case("Content-Length"))
+        .collect()
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// binary bodies are not put in the command, rather they're read from
+/// `binary_body_path`, where the caller saved them.
+pub fn to_curl(
+    request: &HttpRequestResponseData,
+    client_server: &ClientServerInfo,
+    binary_body_path: Option<&str>,
+) -> String {
+    let request_line = parse_request_line(request);
+    let mut parts = vec![format!(
+        "curl {}",
+        shell_quote(&request_url(request, client_server))
+    )];
+    let has_body = !matches!(request.body, HttpBody::Missing);
+    // curl defaults to GET, or POST if there is a body
+    let default_method = if has_body { "POST" } else { "GET" };
+    if request_line.method != default_method {
+        parts.push(format!("-X {}", request_line.method));
+    }
+    if request_line.http_version == "HTTP/2.0" {
+        parts.push("--http2".to_string());
+    }
+    for (k, v) in exported_headers(request) {
+        parts.push(format!("-H {}", shell_quote(&format!("{}: {}", k, v))));
+    }
+    if http_streams_store::get_http_header_value(&request.headers, "Accept-Encoding").is_some() {
+        parts.push("--compressed".to_string());
+    }
+    match &request.body {
+        HttpBody::Text(txt) => parts.push(format!("--data-raw {}", shell_quote(txt))),
+        HttpBody::Binary(_) => parts.push(format!(
+            "--data-binary {}",
+            shell_quote(&format!("@{}", binary_body_path.unwrap_or("body.bin")))
+        )),
+        HttpBody::Missing => {}
+    }
+    parts.join(" \\\n  ")
+}
+
+pub fn to_httpie(
+    request: &HttpRequestResponseData,
+    client_server: &ClientServerInfo,
+    binary_body_path: Option<&str>,
+) -> String {
+    let request_line = parse_request_line(request);
+    let mut parts = vec![format!(
+        "http {} {}",
+        request_line.method,
+        shell_quote(&request_url(request, client_server))
+    )];
+    for (k, v) in exported_headers(request) {
+        // 'Header;' is the httpie syntax for an empty header value
+        parts.push(shell_quote(&if v.is_empty() {
+            format!("{};", k)
+        } else {
+            format!("{}:{}", k, v)
+        }));
+    }
+    match &request.body {
+        HttpBody::Text(txt) => parts.push(format!("--raw {}", shell_quote(txt))),
+        HttpBody::Binary(_) => parts.push(format!(
+            "< {}",
+            shell_quote(binary_body_path.unwrap_or("body.bin"))
+        )),
+        HttpBody::Missing => {}
+    }
+    parts.join(" \\\n  ")
+}
+
+/// The headers and body as we captured them, except that we put back
+/// a chunked body, which we store de-chunked, in a single chunk.
+/// The body may have been decompressed or converted to UTF-8, in that
+/// case the Content-Encoding, Content-Type and Content-Length headers
+/// are updated to match it. HTTP/1.1 only has trailers for chunked
+/// bodies, so HTTP2 requests with trailers are exported chunked.
+pub fn to_raw_http(request: &HttpRequestResponseData) -> Vec<u8> {
+    let request_line = parse_request_line(request);
+    let body: &[u8] = match &request.body {
+        HttpBody::Text(txt) => txt.as_bytes(),
+        HttpBody::Binary(bytes) => bytes,
+        HttpBody::Missing => &[],
+    };
+    let mut headers = headers_for_body(request);
+    let is_http2 = request_line.http_version == "HTTP/2.0";
+    if is_http2 {
+        if let Some(authority) =
+            http_streams_store::get_http_header_value(&request.headers, ":authority")
+        {
+            headers.insert(0, ("Host".to_string(), authority.clone()));
+        }
+    }
+    let mut is_chunked =
+        http_streams_store::get_http_header_value(&request.headers, "Transfer-Encoding")
+            .filter(|te| te.to_ascii_lowercase().contains("chunked"))
+            .is_some();
+    if is_http2 && !request.trailers.is_empty() {
+        headers.retain(|(k, _)| !k.eq_ignore_ascii_case("Content-Length"));
+        headers.push(("Transfer-Encoding".to_string(), "chunked".to_string()));
+        is_chunked = true;
+    }
+    if !is_chunked {
+        match headers
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case("Content-Length"))
+        {
+            Some((_, len)) => *len = body.len().to_string(),
+            None if !body.is_empty() => {
+                headers.push(("Content-Length".to_string(), body.len().to_string()))
+            }
+            None => {}
+        }
+    }
+    let mut result = format!(
+        "{} {} {}\r\n{}\r\n",
+        request_line.method,
+        request_line.target,
+        if is_http2 {
+            "HTTP/1.1"
+        } else {
+            request_line.http_version
+        },
+        headers
+            .iter()
+            .map(|(k, v)| format!("{}: {}\r\n", k, v))
+            .join("")
+    )
+    .into_bytes();
+    if is_chunked {
+        if !body.is_empty() {
+            result.extend_from_slice(format!("{:x}\r\n", body.len()).as_bytes());
+            result.extend_from_slice(body);
+            result.extend_from_slice(b"\r\n");
+        }
+        result.extend_from_slice(b"0\r\n");
+        for (k, v) in &request.trailers {
+            result.extend_from_slice(format!("{}: {}\r\n", k, v).as_bytes());
+        }
+        result.extend_from_slice(b"\r\n");
+    } else {
+        result.extend_from_slice(body);
+    }
+    result
+}
+
+#[cfg(test)]
+fn test_client_server() -> ClientServerInfo {
+    ClientServerInfo {
+        server_ip: "10.0.0.1".parse().unwrap(),
+        server_port: NetworkPort(443),
+        client_ip: "10.0.0.2".parse().unwrap(),
+    }
+}
+
+#[test]
+fn should_export_http1_request_as_curl_and_httpie() {
+    let request = HttpRequestResponseData::test_data(
+        "PUT /items/1 HTTP/1.1",
+        &[
+            ("Host", "example.com:8080"),
+            ("Content-Length", "11"),
+            ("X-Empty", ""),
+        ],
+        HttpBody::Text("{\"a\":\"b'c\"}".to_string()),
+    );
+    assert_eq!(
+        "curl 'https://example.com:8080/items/1' \\\n  -X PUT \\\n  -H 'Host: example.com:8080' \\\n  -H 'X-Empty: ' \\\n  --data-raw '{\"a\":\"b'\\''c\"}'",
+        to_curl(&request, &test_client_server(), None)
+    );
+    assert_eq!(
+        "http PUT 'https://example.com:8080/items/1' \\\n  'Host:example.com:8080' \\\n  'X-Empty;' \\\n  --raw '{\"a\":\"b'\\''c\"}'",
+        to_httpie(&request, &test_client_server(), None)
+    );
+}
+
+#[test]
+fn should_export_http2_request_with_binary_body() {
+    let request = HttpRequestResponseData::test_data(
+        "POST /upload",
+        &[
+            (":method", "POST"),
+            (":scheme", "https"),
+            (":authority", "example.com"),
+            (":path", "/upload"),
+            ("content-type", "application/octet-stream"),
+        ],
+        HttpBody::Binary(vec![0, 1, 2]),
+    );
+    assert_eq!(
+        "curl 'https://example.com/upload' \\\n  --http2 \\\n  -H 'content-type: application/octet-stream' \\\n  --data-binary '@/tmp/body.bin'",
+        to_curl(&request, &test_client_server(), Some("/tmp/body.bin"))
+    );
+    assert_eq!(
+        b"POST /upload HTTP/1.1\r\nHost: example.com\r\ncontent-type: application/octet-stream\r\nContent-Length: 3\r\n\r\n\x00\x01\x02".to_vec(),
+        to_raw_http(&request)
+    );
+}
+
+#[test]
+fn should_rechunk_raw_http_body() {
+    let request = HttpRequestResponseData::test_data(
+        "POST /a HTTP/1.1",
+        &[("Host", "example.com"), ("Transfer-Encoding", "chunked")],
+        HttpBody::Text("hello world".to_string()),
+    );
+    assert_eq!(
+        "POST /a HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\nb\r\nhello world\r\n0\r\n\r\n",
+        String::from_utf8(to_raw_http(&request)).unwrap()
+    );
+}
+
+#[test]
+fn should_rewrite_headers_to_match_decoded_body() {
+    let request = HttpRequestResponseData::test_data(
+        "POST /a HTTP/1.1",
+        &[
+            ("Host", "example.com"),
+            ("Content-Encoding", "gzip"),
+            ("Content-Length", "20"),
+            ("Content-Type", "text/plain; charset=ISO-8859-1"),
+        ],
+        HttpBody::Text("café".to_string()),
+    );
+    assert_eq!(
+        "POST /a HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\nContent-Type: text/plain; charset=utf-8\r\n\r\ncafé",
+        String::from_utf8(to_raw_http(&request)).unwrap()
+    );
+    assert_eq!(
+        "curl 'https://example.com/a' \\\n  -H 'Host: example.com' \\\n  -H 'Content-Type: text/plain; charset=utf-8' \\\n  --data-raw 'café'",
+        to_curl(&request, &test_client_server(), None)
+    );
+}
+
+#[test]
+fn should_export_http2_trailers_in_a_chunked_body() {
+    let mut request = HttpRequestResponseData::test_data(
+        "POST /upload",
+        &[
+            (":method", "POST"),
+            (":path", "/upload"),
+            (":authority", "example.com"),
+            ("content-length", "5"),
+        ],
+        HttpBody::Text("hello".to_string()),
+    );
+    request.trailers = vec![("checksum".to_string(), "abc".to_string())];
+    assert_eq!(
+        "POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\nchecksum: abc\r\n\r\n",
+        String::from_utf8(to_raw_http(&request)).unwrap()
+    );
+}
+
+#[test]
+fn should_keep_the_content_encoding_of_compressed_bodies() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    let gzipped = {
+        let mut e = GzEncoder::new(vec![], Compression::default());
+        e.write_all(b"hello").unwrap();
+        e.finish().unwrap()
+    };
+    let headers = [("Host", "example.com"), ("Content-Encoding", "gzip")];
+    // like the tshark HTTP parsing does it
+    let request = |body| {
+        let (body, content_encoding) =
+            http_streams_store::parse_encoded_body(Some(body), None, ContentEncoding::Gzip);
+        HttpRequestResponseData {
+            content_encoding,
+            ..HttpRequestResponseData::test_data("POST /a HTTP/1.1", &headers, body)
+        }
+    };
+    let mut expected =
+        format!("POST /a HTTP/1.1\r\nHost: example.com\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n", gzipped.len()).into_bytes();
+    expected.extend_from_slice(&gzipped);
+    assert_eq!(expected, to_raw_http(&request(gzipped)));
+    // tshark decompressed it
+    assert_eq!(
+        "POST /a HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello",
+        String::from_utf8(to_raw_http(&request(b"hello".to_vec()))).unwrap()
+    );
+}
@@ -0,0 +1,265 @@
+// https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+use super::http_streams_store::HttpBody;
+use chrono::NaiveDateTime;
+
+#[cfg(test)]
+use chrono::NaiveDate;
+
+// when tshark reassembles a whole event stream body, we compare the
+// beginning of it with the beginning of what we already parsed
+const HEAD_BYTES_COMPARED: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerSentEvent {
+    // the timestamp of the packet which completed the event
+    pub timestamp: NaiveDateTime,
+    // the last event id, which is inherited by the following events
+    pub id: Option<String>,
+    // None means the default "message" event type
+    pub event: Option<String>,
+    pub data: String,
+    pub retry: Option<u32>,
+}
+
+impl ServerSentEvent {
+    pub fn event_type(&self) -> &str {
+        self.event.as_deref().unwrap_or("message")
+    }
+}
+
+pub fn is_event_stream(content_type: Option<&str>) -> bool {
+    content_type
+        .and_then(|ct| ct.split(';').next())
+        .filter(|ct| ct.trim().eq_ignore_ascii_case("text/event-stream"))
+        .is_some()
+}
+
+/// the body text grows as the events are received
+pub fn append_to_body(body: &mut HttpBody, text: &str) {
+    match body {
+        HttpBody::Text(s) => s.push_str(text),
+        HttpBody::Missing => *body = HttpBody::Text(text.to_string()),
+        // couldn't decode the beginning of the body, the events are still there
+        HttpBody::Binary(_) => {}
+    }
+}
+
+/// the events of a body we got in one piece
+pub fn parse_body_events(
+    timestamp: NaiveDateTime,
+    content_type: Option<&str>,
+    body: &HttpBody,
+) -> Vec<ServerSentEvent> {
+    match body {
+        HttpBody::Text(txt) if is_event_stream(content_type) => {
+            EventStreamParser::default()
+                .feed(timestamp, txt.as_bytes())
+                .1
+        }
+        _ => vec![],
+    }
+}
+
+/// Parses an event stream incrementally, as we get its bytes from packets.
+#[derive(Debug, Default)]
+pub struct EventStreamParser {
+    head: Vec<u8>,
+    received_len: usize,
+    line: Vec<u8>,
+    after_cr: bool,
+    got_line: bool,
+    last_event_id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+    retry: Option<u32>,
+}
+
+impl EventStreamParser {
+    /// returns the text of the lines which were completed by these bytes,
+    /// and the events which were completed by these bytes.
+    pub fn feed(
+        &mut self,
+        timestamp: NaiveDateTime,
+        bytes: &[u8],
+    ) -> (String, Vec<ServerSentEvent>) {
+        if self.head.len() < HEAD_BYTES_COMPARED {
+            let missing = HEAD_BYTES_COMPARED - self.head.len();
+            self.head
+                .extend_from_slice(&bytes[..missing.min(bytes.len())]);
+        }
+        self.received_len += bytes.len();
+        let mut text = String::new();
+        let mut events = vec![];
+        for &b in bytes {
+            match b {
+                b'\n' if self.after_cr => {
+                    // CRLF, we already handled the line at the CR
+                    self.after_cr = false;
+                    text.push('\n');
+                }
+                b'\r' | b'\n' => {
+                    self.after_cr = b == b'\r';
+                    let line_bytes = std::mem::take(&mut self.line);
+                    let line = String::from_utf8_lossy(&line_bytes);
+                    text.push_str(&line);
+                    text.push(b as char);
+                    // the stream may start with a BOM
+                    let line = if self.got_line {
+                        &line
+                    } else {
+                        line.trim_start_matches('\u{feff}')
+                    };
+                    self.got_line = true;
+                    if let Some(evt) = self.process_line(timestamp, line) {
+                        events.push(evt);
+                    }
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.line.push(b);
+                }
+            }
+        }
+        (text, events)
+    }
+
+    /// tshark may give us the whole body once it reassembled it,
+    /// or only the last part. Parse only what we didn't get yet.
+    pub fn feed_reassembled(
+        &mut self,
+        timestamp: NaiveDateTime,
+        body: &[u8],
+    ) -> (String, Vec<ServerSentEvent>) {
+        if self.received_len > 0 && body.len() >= self.received_len && body.starts_with(&self.head)
+        {
+            let received_len = self.received_len;
+            self.feed(timestamp, &body[received_len..])
+        } else {
+            self.feed(timestamp, body)
+        }
+    }
+
+    fn process_line(&mut self, timestamp: NaiveDateTime, line: &str) -> Option<ServerSentEvent> {
+        if line.is_empty() {
+            // dispatch the event
+            return match self.data.take() {
+                Some(data) => Some(ServerSentEvent {
+                    timestamp,
+                    id: self.last_event_id.clone(),
+                    event: self.event.take(),
+                    data,
+                    retry: self.retry.take(),
+                }),
+                None => {
+                    self.event = None;
+                    None
+                }
+            };
+        }
+        if line.starts_with(':') {
+            // comment, often used as keepalive
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((f, v)) => (f, v.strip_prefix(' ').unwrap_or(v)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(d) => {
+                    d.push('\n');
+                    d.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => {
+                self.last_event_id = Some(value.to_string()).filter(|v| !v.is_empty())
+            }
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok()
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+fn test_timestamp(second: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd(2021, 3, 18).and_hms_nano(12, 0, second, 0)
+}
+
+#[test]
+fn should_parse_events_split_between_packets() {
+    let mut parser = EventStreamParser::default();
+    let (text1, events1) = parser.feed(
+        test_timestamp(1),
+        b"\xef\xbb\xbf: keepalive\r\nretry: 3000\r\nid: 1\r\nevent: update\r\ndata: {\"a\":\r\ndata:1}\r\n\r\ndata: sec",
+    );
+    assert_eq!(
+        ": keepalive\r\nretry: 3000\r\nid: 1\r\nevent: update\r\ndata: {\"a\":\r\ndata:1}\r\n\r\n",
+        text1.trim_start_matches('\u{feff}')
+    );
+    assert_eq!(
+        vec![ServerSentEvent {
+            timestamp: test_timestamp(1),
+            id: Some("1".to_string()),
+            event: Some("update".to_string()),
+            data: "{\"a\":\n1}".to_string(),
+            retry: Some(3000),
+        }],
+        events1
+    );
+    let (text2, events2) = parser.feed(test_timestamp(2), b"ond\n\nid\ndata\n\n");
+    assert_eq!("data: second\n\nid\ndata\n\n", text2);
+    assert_eq!(
+        vec![
+            ServerSentEvent {
+                timestamp: test_timestamp(2),
+                id: Some("1".to_string()),
+                event: None,
+                data: "second".to_string(),
+                retry: None,
+            },
+            ServerSentEvent {
+                timestamp: test_timestamp(2),
+                id: None,
+                event: None,
+                data: "".to_string(),
+                retry: None,
+            }
+        ],
+        events2
+    );
+    assert_eq!("message", events2[0].event_type());
+}
+
+#[test]
+fn should_only_parse_the_new_part_of_reassembled_bodies() {
+    let mut parser = EventStreamParser::default();
+    parser.feed(test_timestamp(1), b"data: first\n\nda");
+    let (text, events) = parser.feed_reassembled(
+        test_timestamp(2),
+        b"data: first\n\ndata: second\n\ndata: third\n\n",
+    );
+    assert_eq!("data: second\n\ndata: third\n\n", text);
+    assert_eq!(
+        vec!["second", "third"],
+        events.iter().map(|e| e.data.as_str()).collect::<Vec<_>>()
+    );
+    // not the whole body, just the end of it
+    let (_, events) = parser.feed_reassembled(test_timestamp(3), b"data: fourth\n\n");
+    assert_eq!(
+        vec!["fourth"],
+        events.iter().map(|e| e.data.as_str()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn should_recognize_event_stream_content_types() {
+    assert!(is_event_stream(Some("text/event-stream")));
+    assert!(is_event_stream(Some("Text/Event-Stream; charset=utf-8")));
+    assert!(!is_event_stream(Some("text/plain")));
+    assert!(!is_event_stream(None));
+}
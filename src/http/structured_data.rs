@@ -0,0 +1,337 @@
+// parsing of the structured parts of HTTP messages: the query string,
+// url-encoded and multipart forms, and cookies.
+use super::http_streams_store;
+use super::http_streams_store::{HttpBody, HttpRequestResponseData};
+use super::request_export;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetCookie {
+    pub name: String,
+    pub value: String,
+    // Path, Expires, HttpOnly (with an empty value)...
+    pub attributes: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipartPart {
+    pub name: Option<String>,
+    pub filename: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub content_type: Option<String>,
+    pub body: HttpBody,
+}
+
+pub fn url_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'+' => result.push(b' '),
+            b'%' => match input
+                .get(idx + 1..idx + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                Some(b) => {
+                    result.push(b);
+                    idx += 2;
+                }
+                None => result.push(b'%'),
+            },
+            b => result.push(b),
+        }
+        idx += 1;
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+fn parse_url_encoded(input: &str) -> Vec<(String, String)> {
+    input
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (name, value) = p.split_once('=').unwrap_or((p, ""));
+            (url_decode(name), url_decode(value))
+        })
+        .collect()
+}
+
+pub fn query_params(request: &HttpRequestResponseData) -> Vec<(String, String)> {
+    let target = request_export::parse_request_line(request).target;
+    let query = target.split_once('?').map(|(_, q)| q).unwrap_or("");
+    // drop a fragment, if the client sent one
+    parse_url_encoded(query.split_once('#').map(|(q, _)| q).unwrap_or(query))
+}
+
+fn content_type_is(data: &HttpRequestResponseData, expected: &str) -> bool {
+    data.content_type
+        .as_deref()
+        .and_then(|ct| ct.split(';').next())
+        .filter(|ct| ct.trim().eq_ignore_ascii_case(expected))
+        .is_some()
+}
+
+pub fn form_fields(request: &HttpRequestResponseData) -> Vec<(String, String)> {
+    if !content_type_is(request, "application/x-www-form-urlencoded") {
+        return vec![];
+    }
+    request
+        .body_as_str()
+        .map(|b| parse_url_encoded(b.trim()))
+        .unwrap_or_default()
+}
+
+/// a parameter of a header such as Content-Type or Content-Disposition:
+/// `form-data; name="field1"; filename="a.txt"`
+pub fn header_param(header_value: &str, param: &str) -> Option<String> {
+    split_header_params(header_value)
+        .into_iter()
+        .skip(1)
+        .filter_map(|p| p.trim().split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case(param))
+        .map(|(_, v)| unquote(v.trim()))
+}
+
+/// split on ';', except inside quoted strings
+fn split_header_params(header_value: &str) -> Vec<&str> {
+    let mut result = vec![];
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut is_escaped = false;
+    for (idx, c) in header_value.char_indices() {
+        match c {
+            _ if is_escaped => is_escaped = false,
+            '\\' if in_quotes => is_escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                result.push(&header_value[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(&header_value[start..]);
+    result
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc7230#section-3.2.6
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(quoted) => {
+            let mut result = String::with_capacity(quoted.len());
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => result.extend(chars.next()),
+                    _ => result.push(c),
+                }
+            }
+            result
+        }
+        None => value.to_string(),
+    }
+}
+
+pub fn request_cookies(request: &HttpRequestResponseData) -> Vec<(String, String)> {
+    request
+        .headers
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("Cookie"))
+        .flat_map(|(_, v)| v.split(';'))
+        .filter_map(|c| c.trim().split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+pub fn response_cookies(response: &HttpRequestResponseData) -> Vec<SetCookie> {
+    response
+        .headers
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("Set-Cookie"))
+        .filter_map(|(_, v)| {
+            let mut parts = v.split(';');
+            let (name, value) = parts.next()?.trim().split_once('=')?;
+            Some(SetCookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                attributes: parts
+                    .map(|a| a.trim())
+                    .filter(|a| !a.is_empty())
+                    .map(|a| {
+                        let (k, v) = a.split_once('=').unwrap_or((a, ""));
+                        (k.to_string(), v.to_string())
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc7578
+pub fn multipart_parts(request: &HttpRequestResponseData) -> Vec<MultipartPart> {
+    let boundary = match request
+        .content_type
+        .as_deref()
+        .filter(|_| content_type_is(request, "multipart/form-data"))
+        .and_then(|ct| header_param(ct, "boundary"))
+    {
+        Some(b) => b,
+        None => return vec![],
+    };
+    let body: &[u8] = match &request.body {
+        HttpBody::Text(txt) => txt.as_bytes(),
+        HttpBody::Binary(bytes) => bytes,
+        HttpBody::Missing => return vec![],
+    };
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = vec![];
+    let mut pos = match find_bytes(body, &delimiter) {
+        Some(p) => p + delimiter.len(),
+        None => return vec![],
+    };
+    // the last delimiter is followed by "--"
+    while !body[pos..].starts_with(b"--") {
+        let end = match find_bytes(&body[pos..], &delimiter) {
+            Some(p) => pos + p,
+            None => break,
+        };
+        parts.push(parse_part(strip_crlf(&body[pos..end])));
+        pos = end + delimiter.len();
+    }
+    parts
+}
+
+fn strip_crlf(part: &[u8]) -> &[u8] {
+    let part = part
+        .strip_prefix(b"\r\n")
+        .or_else(|| part.strip_prefix(b"\n"))
+        .unwrap_or(part);
+    part.strip_suffix(b"\r\n")
+        .or_else(|| part.strip_suffix(b"\n"))
+        .unwrap_or(part)
+}
+
+fn parse_part(part: &[u8]) -> MultipartPart {
+    let (headers_bytes, body) = match find_bytes(part, b"\r\n\r\n") {
+        Some(p) => (&part[..p], &part[p + 4..]),
+        None => match find_bytes(part, b"\n\n") {
+            Some(p) => (&part[..p], &part[p + 2..]),
+            None => (part, &[][..]),
+        },
+    };
+    let headers = http_streams_store::parse_headers(&String::from_utf8_lossy(headers_bytes));
+    let disposition = http_streams_store::get_http_header_value(&headers, "Content-Disposition");
+    let content_type = http_streams_store::get_http_header_value(&headers, "Content-Type").cloned();
+    MultipartPart {
+        name: disposition.and_then(|d| header_param(d, "name")),
+        filename: disposition.and_then(|d| header_param(d, "filename")),
+        body: http_streams_store::parse_body(Some(body.to_vec()), content_type.as_deref()),
+        content_type,
+        headers,
+    }
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+fn name_values(items: &[(&str, &str)]) -> Vec<(String, String)> {
+    items
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn should_parse_query_and_form_fields() {
+    let request = HttpRequestResponseData::test_data(
+        "POST /search?q=hello+world&lang=fr%2Dbe&empty HTTP/1.1",
+        &[
+            (
+                "Content-Type",
+                "application/x-www-form-urlencoded; charset=UTF-8",
+            ),
+            ("Cookie", "session=abc; theme=dark"),
+        ],
+        HttpBody::Text("user=j%C3%A9r%C3%B4me&remember=on\r\n".to_string()),
+    );
+    assert_eq!(
+        name_values(&[("q", "hello world"), ("lang", "fr-be"), ("empty", "")]),
+        query_params(&request)
+    );
+    assert_eq!(
+        name_values(&[("user", "jérôme"), ("remember", "on")]),
+        form_fields(&request)
+    );
+    assert_eq!(
+        name_values(&[("session", "abc"), ("theme", "dark")]),
+        request_cookies(&request)
+    );
+}
+
+#[test]
+fn should_parse_set_cookie_attributes() {
+    let response = HttpRequestResponseData::test_data(
+        "HTTP/1.1 200 OK",
+        &[("Set-Cookie", "id=42; Path=/; HttpOnly")],
+        HttpBody::Missing,
+    );
+    assert_eq!(
+        vec![SetCookie {
+            name: "id".to_string(),
+            value: "42".to_string(),
+            attributes: name_values(&[("Path", "/"), ("HttpOnly", "")]),
+        }],
+        response_cookies(&response)
+    );
+}
+
+#[test]
+fn should_parse_multipart_parts() {
+    let request = HttpRequestResponseData::test_data(
+        "POST /upload HTTP/1.1",
+        &[("Content-Type", "multipart/form-data; boundary=\"XyZ\"")],
+        HttpBody::Binary(
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nmy file\r\n--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n\xff\x00\x01\r\n--XyZ--\r\n"
+                .to_vec(),
+        ),
+    );
+    assert_eq!(
+        vec![
+            MultipartPart {
+                name: Some("title".to_string()),
+                filename: None,
+                headers: name_values(&[("Content-Disposition", "form-data; name=\"title\"")]),
+                content_type: None,
+                body: HttpBody::Text("my file".to_string()),
+            },
+            MultipartPart {
+                name: Some("file".to_string()),
+                filename: Some("a.bin".to_string()),
+                headers: name_values(&[
+                    (
+                        "Content-Disposition",
+                        "form-data; name=\"file\"; filename=\"a.bin\""
+                    ),
+                    ("Content-Type", "application/octet-stream")
+                ]),
+                content_type: Some("application/octet-stream".to_string()),
+                body: HttpBody::Binary(vec![0xff, 0, 1]),
+            },
+        ],
+        multipart_parts(&request)
+    );
+}
+
+#[test]
+fn should_read_quoted_header_params() {
+    let disposition = r#"form-data; name="file"; filename="a;b \"c\".txt"; size=3"#;
+    assert_eq!(
+        Some("a;b \"c\".txt".to_string()),
+        header_param(disposition, "filename")
+    );
+    assert_eq!(Some("3".to_string()), header_param(disposition, "size"));
+    assert_eq!(Some("file".to_string()), header_param(disposition, "name"));
+    assert_eq!(None, header_param(disposition, "form-data"));
+}
@@ -2,7 +2,7 @@ use crate::tshark_communication;
 use quick_xml::events::Event;
 use std::io::BufRead;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum HttpType {
     Request,
     Response,
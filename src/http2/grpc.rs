@@ -0,0 +1,255 @@
+// https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md
+use super::proto_schema::ProtoSchema;
+use super::protobuf;
+use crate::http::http_streams_store;
+use crate::http::http_streams_store::ContentEncoding;
+
+// compressed flag + message length
+const MESSAGE_PREFIX_LEN: usize = 5;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GrpcFrame {
+    pub compressed: bool,
+    pub payload: Vec<u8>,
+}
+
+pub fn is_grpc(content_type: Option<&str>) -> bool {
+    content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase())
+        .filter(|ct| ct == "application/grpc" || ct.starts_with("application/grpc+"))
+        .is_some()
+}
+
+/// a HTTP2 stream can contain many messages (streaming RPCs).
+/// Returns the frames we could parse, and whether the body was
+/// cut in the middle of a frame.
+pub fn parse_frames(body: &[u8]) -> (Vec<GrpcFrame>, bool) {
+    let mut frames = vec![];
+    let mut rest = body;
+    while !rest.is_empty() {
+        if rest.len() < MESSAGE_PREFIX_LEN {
+            return (frames, true);
+        }
+        let len = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+        let payload = match rest.get(MESSAGE_PREFIX_LEN..MESSAGE_PREFIX_LEN + len) {
+            Some(p) => p,
+            None => return (frames, true),
+        };
+        frames.push(GrpcFrame {
+            compressed: rest[0] & 1 == 1,
+            payload: payload.to_vec(),
+        });
+        rest = &rest[MESSAGE_PREFIX_LEN + len..];
+    }
+    (frames, false)
+}
+
+/// the grpc-status and grpc-message trailers (or headers, for trailers-only responses)
+pub fn grpc_status(headers: &[(String, String)]) -> Option<(u32, Option<String>)> {
+    let code = http_streams_store::get_http_header_value(headers, "grpc-status")?
+        .trim()
+        .parse()
+        .ok()?;
+    let message = http_streams_store::get_http_header_value(headers, "grpc-message")
+        .map(|m| percent_decode(m))
+        .filter(|m| !m.is_empty());
+    Some((code, message))
+}
+
+/// https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
+pub fn status_name(code: u32) -> &'static str {
+    match code {
+        0 => "OK",
+        1 => "CANCELLED",
+        2 => "UNKNOWN",
+        3 => "INVALID_ARGUMENT",
+        4 => "DEADLINE_EXCEEDED",
+        5 => "NOT_FOUND",
+        6 => "ALREADY_EXISTS",
+        7 => "PERMISSION_DENIED",
+        8 => "RESOURCE_EXHAUSTED",
+        9 => "FAILED_PRECONDITION",
+        10 => "ABORTED",
+        11 => "OUT_OF_RANGE",
+        12 => "UNIMPLEMENTED",
+        13 => "INTERNAL",
+        14 => "UNAVAILABLE",
+        15 => "DATA_LOSS",
+        16 => "UNAUTHENTICATED",
+        _ => "UNKNOWN",
+    }
+}
+
+/// the HTTP status code matching a gRPC status, so that we can color
+/// gRPC errors like HTTP errors.
+/// https://github.com/googleapis/googleapis/blob/master/google/rpc/code.proto
+pub fn http_equivalent_status(code: u32) -> u16 {
+    match code {
+        0 => 200,
+        1 => 499,
+        3 | 9 | 11 => 400,
+        4 => 504,
+        5 => 404,
+        6 | 10 => 409,
+        7 => 403,
+        8 => 429,
+        12 => 501,
+        14 => 503,
+        16 => 401,
+        _ => 500,
+    }
+}
+
+pub fn status_description(code: u32, message: Option<&str>) -> String {
+    match message {
+        Some(msg) => format!("gRPC {} ({}): {}", status_name(code), code, msg),
+        None => format!("gRPC {} ({})", status_name(code), code),
+    }
+}
+
+/// grpc-message is percent-encoded
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut result = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let decoded = Some(bytes[i])
+            .filter(|b| *b == b'%')
+            .and_then(|_| bytes.get(i + 1..i + 3))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match decoded {
+            Some(b) => {
+                result.push(b);
+                i += 3;
+            }
+            None => {
+                result.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// the method path from the request first line: "POST /package.Service/Method"
+fn method_path(request_first_line: &str) -> Option<&str> {
+    request_first_line.split(' ').nth(1)
+}
+
+/// the title and text format description of every message of the body
+pub fn describe_messages(
+    request_first_line: &str,
+    is_response: bool,
+    headers: &[(String, String)],
+    body: &[u8],
+    schema: Option<&ProtoSchema>,
+) -> Vec<(String, String)> {
+    let message_type = schema
+        .zip(method_path(request_first_line))
+        .and_then(|(s, path)| s.method(path))
+        .map(|m| {
+            if is_response {
+                m.output_type.as_str()
+            } else {
+                m.input_type.as_str()
+            }
+        });
+    let type_desc = message_type
+        .map(|t| format!(" ({})", t))
+        .unwrap_or_default();
+    let encoding = ContentEncoding::parse_from_str(
+        &http_streams_store::get_http_header_value(headers, "grpc-encoding").map(|s| s.as_str()),
+    );
+    let (frames, is_truncated) = parse_frames(body);
+    let mut result: Vec<_> = frames
+        .iter()
+        .enumerate()
+        .map(|(idx, frame)| {
+            let title = format!("Message {}{}", idx + 1, type_desc);
+            let payload = if frame.compressed {
+                encoding.decode(&frame.payload)
+            } else {
+                Some(frame.payload.clone())
+            };
+            let contents = match payload {
+                None => "Failed decompressing the message".to_string(),
+                Some(p) => match protobuf::decode_message(&p) {
+                    Some(fields) => protobuf::format_message(&fields, schema, message_type),
+                    None => format!("Not a protobuf message: {}", hex::encode(&p)),
+                },
+            };
+            (title, contents)
+        })
+        .collect();
+    if is_truncated {
+        result.push((
+            "Incomplete message".to_string(),
+            "The body ends in the middle of a gRPC message".to_string(),
+        ));
+    }
+    result
+}
+
+#[cfg(test)]
+fn test_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0];
+    frame.extend((payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[test]
+fn should_parse_streaming_frames() {
+    let mut body = test_frame(&protobuf::test_encode_varint_field(1, 1));
+    body.extend(test_frame(&[]));
+    body.extend(test_frame(&protobuf::test_encode_varint_field(1, 2)));
+    body.extend(&[0, 0, 0, 0, 9, 8]);
+    let (frames, is_truncated) = parse_frames(&body);
+    assert!(is_truncated);
+    assert_eq!(
+        vec![vec![8, 1], vec![], vec![8, 2]],
+        frames.into_iter().map(|f| f.payload).collect::<Vec<_>>()
+    );
+    let described = describe_messages("POST /pkg.Svc/Get", false, &[], &body, None);
+    assert_eq!(
+        vec![
+            ("Message 1".to_string(), "1 (varint): 1\n".to_string()),
+            ("Message 2".to_string(), "".to_string()),
+            ("Message 3".to_string(), "1 (varint): 2\n".to_string()),
+            (
+                "Incomplete message".to_string(),
+                "The body ends in the middle of a gRPC message".to_string()
+            ),
+        ],
+        described
+    );
+}
+
+#[test]
+fn should_read_grpc_status() {
+    let headers = vec![
+        (":status".to_string(), "200".to_string()),
+        ("grpc-status".to_string(), "5".to_string()),
+        (
+            "grpc-message".to_string(),
+            "order%20not%20found: %E2%9C%97".to_string(),
+        ),
+    ];
+    let (code, message) = grpc_status(&headers).unwrap();
+    assert_eq!(
+        "gRPC NOT_FOUND (5): order not found: ✗",
+        status_description(code, message.as_deref())
+    );
+    assert_eq!(404, http_equivalent_status(code));
+    assert_eq!(None, grpc_status(&headers[..1]));
+}
+
+#[test]
+fn should_recognize_grpc_content_types() {
+    assert!(is_grpc(Some("application/grpc")));
+    assert!(is_grpc(Some("application/grpc+proto")));
+    assert!(!is_grpc(Some("application/grpc-web")));
+    assert!(!is_grpc(None));
+}
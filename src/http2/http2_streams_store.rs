@@ -1,9 +1,14 @@
 use crate::custom_streams_store::{ClientServerInfo, CustomStreamsStore};
+use crate::http::http2_frames::{Http2ConnectionEvents, Http2ControlFrame, Http2FrameEvent};
 use crate::http::http_details_widget::{self, HttpCommEntry};
 use crate::http::http_streams_store;
 use crate::http::http_streams_store::{
-    ContentEncoding, HttpBody, HttpMessageData, HttpRequestResponseData,
+    ContentEncoding, HttpMessageData, HttpRequestResponseData, InterimResponse,
 };
+use crate::http::server_sent_events;
+use crate::http::server_sent_events::EventStreamParser;
+use crate::http2::grpc;
+use crate::http2::proto_schema::ConfiguredProtoSchema;
 use crate::http2::tshark_http2::TSharkHttp2Message;
 use crate::icons;
 use crate::search_expr;
@@ -12,15 +17,17 @@ use crate::widgets::win;
 use crate::BgFunc;
 use chrono::NaiveDateTime;
 use relm::ContainerWidget;
+use std::any::Any;
 use std::collections::HashMap;
-use std::str;
 use std::sync::mpsc;
+use std::sync::Arc;
 use strum::VariantNames;
 
 #[cfg(test)]
 use {
     crate::custom_streams_store::common_tests_parse_stream,
-    crate::tshark_communication::parse_test_xml, chrono::NaiveDate,
+    crate::http::http_streams_store::HttpBody, crate::tshark_communication::parse_test_xml,
+    chrono::NaiveDate, chrono::Timelike,
 };
 
 #[derive(Default)]
@@ -29,6 +36,7 @@ pub struct Http2StreamData {
     pub client_server: Option<ClientServerInfo>,
     pub messages: Vec<HttpMessageData>,
     pub summary_details: Option<String>,
+    pub connection_events: Http2ConnectionEvents,
 }
 
 #[derive(Default)]
@@ -47,6 +55,37 @@ impl Http2StreamsStore {
         let msg = stream.messages.get(msg_idx)?;
         Some((msg, stream.client_server?))
     }
+
+    /// the HTTP exchanges of the stream, for the HAR export for instance
+    pub fn stream_http_messages(&self, stream_id: TcpStreamId) -> Option<&[HttpMessageData]> {
+        self.streams.get(&stream_id).map(|s| s.messages.as_slice())
+    }
+
+    pub fn set_grpc_schema(&self, schema: Option<Arc<ConfiguredProtoSchema>>) {
+        self.component
+            .as_ref()
+            .unwrap()
+            .stream()
+            .emit(http_details_widget::Msg::GrpcSchemaChanged(schema));
+    }
+
+    /// the details widget displays the first `displayed_count` events already
+    pub fn display_new_server_sent_events(
+        &self,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+        displayed_count: usize,
+    ) {
+        if let Some(events) = self
+            .get_msg_info(stream_id, msg_idx)
+            .and_then(|(m, _)| m.response.as_ref())
+            .and_then(|r| r.server_sent_events.get(displayed_count..))
+        {
+            self.component.as_ref().unwrap().stream().emit(
+                http_details_widget::Msg::AppendServerSentEvents(events.to_vec()),
+            );
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -54,11 +93,18 @@ pub struct Http2StreamProcessedContents {
     cur_request: Option<HttpRequestResponseData>,
     unfinished_basic_info: Option<TSharkPacketBasicInfo>,
     unfinished_stream_messages: Vec<TSharkHttp2Message>,
+    // a text/event-stream response we're displaying as we receive it:
+    // its message index and the parser for its DATA frames
+    live_event_stream: Option<(usize, EventStreamParser)>,
+    // control frames for this stream, until we can attach them to the message
+    frames: Vec<Http2FrameEvent>,
 }
 
 #[derive(Debug, Default)]
 pub struct Http2StreamGlobals {
     pub messages_per_stream: HashMap<u32, Http2StreamProcessedContents>,
+    // the streams which are still open when the connection ends were cut by the GOAWAY
+    pub goaway: Option<Http2FrameEvent>,
 }
 
 impl CustomStreamsStore for Http2StreamsStore {
@@ -108,6 +154,14 @@ impl CustomStreamsStore for Http2StreamsStore {
             .and_then(|s| s.summary_details.as_deref())
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn add_to_stream(
         &mut self,
         stream_id: TcpStreamId,
@@ -122,7 +176,19 @@ impl CustomStreamsStore for Http2StreamsStore {
             return Ok(None);
         }
         let http2 = new_packet.http2.unwrap();
-        for http2_msg in http2 {
+        for mut http2_msg in http2 {
+            if let Some(frame) = http2_msg.control_frame.take() {
+                let event = Http2FrameEvent {
+                    timestamp: cur_msg.frame_time,
+                    http2_stream_id: http2_msg.stream_id,
+                    src_ip: cur_msg.ip_src,
+                    src_port: cur_msg.port_src,
+                    frame,
+                };
+                stream.connection_events.add(&event);
+                handle_control_frame(stream, cur_msg, event);
+                continue;
+            }
             if http2_msg.is_end_stream {
                 let http2_stream_id = http2_msg.stream_id;
                 // got all the elements of the message, add it to the result
@@ -134,7 +200,24 @@ impl CustomStreamsStore for Http2StreamsStore {
                         cur_request: None,
                         unfinished_basic_info: Some(cur_msg),
                         unfinished_stream_messages: vec![],
+                        live_event_stream: None,
+                        frames: vec![],
                     });
+                if let Some((msg_idx, mut parser)) = stream_messages.live_event_stream.take() {
+                    // the end of an event stream we displayed as we were receiving it
+                    if let (Some(data), Some(resp)) = (
+                        &http2_msg.data,
+                        stream
+                            .messages
+                            .get_mut(msg_idx)
+                            .and_then(|m| m.response.as_mut()),
+                    ) {
+                        let (text, events) = parser.feed_reassembled(cur_msg.frame_time, data);
+                        server_sent_events::append_to_body(&mut resp.body, &text);
+                        resp.server_sent_events.extend(events);
+                    }
+                    continue;
+                }
                 stream_messages.unfinished_stream_messages.push(http2_msg);
                 let (http_msg, msg_type) = prepare_http_message(
                     cur_msg.tcp_stream_id,
@@ -164,6 +247,8 @@ impl CustomStreamsStore for Http2StreamsStore {
                                 cur_request: Some(http_msg),
                                 unfinished_basic_info: Some(cur_msg),
                                 unfinished_stream_messages: vec![],
+                                live_event_stream: None,
+                                frames: stream_messages.frames,
                             },
                         );
                     }
@@ -179,11 +264,13 @@ impl CustomStreamsStore for Http2StreamsStore {
                             http_stream_id: http2_stream_id,
                             request: stream_messages.cur_request,
                             response: Some(http_msg),
+                            http2_frames: stream_messages.frames,
                         });
                     }
                 }
             } else {
                 // collecting more elements for this message
+                let http2_msg_stream_id = http2_msg.stream_id;
                 let stream_msgs_entry = stream
                     .stream_globals
                     .messages_per_stream
@@ -192,8 +279,50 @@ impl CustomStreamsStore for Http2StreamsStore {
                         cur_request: None,
                         unfinished_basic_info: Some(cur_msg),
                         unfinished_stream_messages: vec![],
+                        live_event_stream: None,
+                        frames: vec![],
+                    });
+                if let Some((msg_idx, parser)) = &mut stream_msgs_entry.live_event_stream {
+                    // more events for an event stream we're displaying
+                    if let (Some(data), Some(resp)) = (
+                        &http2_msg.data,
+                        stream
+                            .messages
+                            .get_mut(*msg_idx)
+                            .and_then(|m| m.response.as_mut()),
+                    ) {
+                        let (text, events) = parser.feed(cur_msg.frame_time, data);
+                        server_sent_events::append_to_body(&mut resp.body, &text);
+                        resp.server_sent_events.extend(events);
+                    }
+                } else if is_event_stream_response(&http2_msg.headers) {
+                    // event streams may never end while we capture, display the
+                    // response now and add the events as we get them
+                    stream_msgs_entry.unfinished_stream_messages.push(http2_msg);
+                    let (http_msg, _) = prepare_http_message(
+                        cur_msg.tcp_stream_id,
+                        cur_msg.tcp_seq_number,
+                        cur_msg.frame_time,
+                        std::mem::take(&mut stream_msgs_entry.unfinished_stream_messages),
+                    );
+                    if stream.client_server.is_none() {
+                        stream.client_server = Some(ClientServerInfo {
+                            client_ip: cur_msg.ip_dst,
+                            server_ip: cur_msg.ip_src,
+                            server_port: cur_msg.port_src,
+                        });
+                    }
+                    stream.messages.push(HttpMessageData {
+                        http_stream_id: http2_msg_stream_id,
+                        request: stream_msgs_entry.cur_request.take(),
+                        response: Some(http_msg),
+                        http2_frames: std::mem::take(&mut stream_msgs_entry.frames),
                     });
-                stream_msgs_entry.unfinished_stream_messages.push(http2_msg);
+                    stream_msgs_entry.live_event_stream =
+                        Some((stream.messages.len() - 1, EventStreamParser::default()));
+                } else {
+                    stream_msgs_entry.unfinished_stream_messages.push(http2_msg);
+                }
             }
         }
         Ok(stream.client_server)
@@ -201,70 +330,17 @@ impl CustomStreamsStore for Http2StreamsStore {
 
     fn finish_stream(&mut self, stream_id: TcpStreamId) -> Result<(), String> {
         // flush all the incomplete messages as best we can
-        let mut stream = self
+        let stream = self
             .streams
             .get_mut(&stream_id)
             .ok_or("No data for stream")?;
         let globals = std::mem::take(&mut stream.stream_globals);
-        for (http2_stream_id, stream_contents) in globals.messages_per_stream {
-            let cur_msg = stream_contents.unfinished_basic_info.unwrap();
-            match (
-                stream_contents.cur_request,
-                stream_contents.unfinished_stream_messages,
-            ) {
-                (Some(r), leftover) if leftover.is_empty() => {
-                    stream.messages.push(HttpMessageData {
-                        http_stream_id: http2_stream_id,
-                        request: Some(r),
-                        response: None,
-                    })
-                }
-                (req, leftover) => {
-                    let (http_msg, msg_type) = prepare_http_message(
-                        cur_msg.tcp_stream_id,
-                        cur_msg.tcp_seq_number,
-                        cur_msg.frame_time,
-                        leftover,
-                    );
-                    match msg_type {
-                        MsgType::Request => {
-                            if stream.summary_details.is_none() {
-                                stream.summary_details = http_streams_store::get_http_header_value(
-                                    &http_msg.headers,
-                                    ":authority",
-                                )
-                                .map(|c| c.to_string());
-                            }
-                            if stream.client_server.is_none() {
-                                stream.client_server = Some(ClientServerInfo {
-                                    client_ip: cur_msg.ip_src,
-                                    server_ip: cur_msg.ip_dst,
-                                    server_port: cur_msg.port_dst,
-                                });
-                            }
-                            stream.messages.push(HttpMessageData {
-                                http_stream_id: http2_stream_id,
-                                request: Some(http_msg),
-                                response: None,
-                            });
-                        }
-                        MsgType::Response => {
-                            if stream.client_server.is_none() {
-                                stream.client_server = Some(ClientServerInfo {
-                                    client_ip: cur_msg.ip_dst,
-                                    server_ip: cur_msg.ip_src,
-                                    server_port: cur_msg.port_src,
-                                });
-                            }
-                            stream.messages.push(HttpMessageData {
-                                http_stream_id: http2_stream_id,
-                                request: req,
-                                response: Some(http_msg),
-                            });
-                        }
-                    }
-                }
+        for (http2_stream_id, mut stream_contents) in globals.messages_per_stream {
+            if let Some(goaway) = &globals.goaway {
+                // the connection was closed before we got the whole message
+                stream_contents.frames.push(goaway.clone());
             }
+            flush_stream_contents(stream, http2_stream_id, stream_contents);
         }
         Ok(())
     }
@@ -311,6 +387,7 @@ impl CustomStreamsStore for Http2StreamsStore {
                 http_stream_id: 0,
                 request: None,
                 response: None,
+                http2_frames: vec![],
             },
             overlay.unwrap().clone(),
             bg_sender,
@@ -351,14 +428,19 @@ impl CustomStreamsStore for Http2StreamsStore {
         msg_idx: usize,
     ) {
         if let Some((http_msg, client_server)) = self.get_msg_info(stream_id, msg_idx) {
-            self.component.as_ref().unwrap().stream().emit(
-                http_details_widget::Msg::DisplayDetails(
-                    bg_sender,
-                    client_server.client_ip,
-                    stream_id,
-                    http_msg.clone(),
-                ),
-            )
+            let component_stream = self.component.as_ref().unwrap().stream();
+            component_stream.emit(http_details_widget::Msg::DisplayDetails(
+                bg_sender,
+                client_server,
+                stream_id,
+                http_msg.clone(),
+            ));
+            component_stream.emit(http_details_widget::Msg::DisplayConnectionEvents(
+                self.streams
+                    .get(&stream_id)
+                    .map(|s| s.connection_events.clone())
+                    .unwrap_or_default(),
+            ));
         }
     }
 }
@@ -368,75 +450,289 @@ enum MsgType {
     Response,
 }
 
+fn handle_control_frame(
+    stream: &mut Http2StreamData,
+    cur_msg: TSharkPacketBasicInfo,
+    event: Http2FrameEvent,
+) {
+    let http2_stream_id = event.http2_stream_id;
+    match &event.frame {
+        Http2ControlFrame::RstStream { .. } => {
+            // we won't get any more of this message
+            match stream
+                .stream_globals
+                .messages_per_stream
+                .remove(&http2_stream_id)
+            {
+                Some(mut contents) => {
+                    contents.frames.push(event);
+                    flush_stream_contents(stream, http2_stream_id, contents);
+                }
+                None => attach_frame_to_message(stream, event),
+            }
+        }
+        Http2ControlFrame::GoAway { last_stream_id, .. } => {
+            // the streams after the last stream id were not processed, and never will be.
+            // The last stream id is about the streams the receiver of the GOAWAY opened:
+            // odd ids are opened by the client, even ids by the server (push)
+            // https://tools.ietf.org/html/rfc7540#section-6.8
+            let receiver_stream_parity = match stream.client_server {
+                Some(cs) if event.is_from_server(&cs) => Some(1),
+                Some(_) => Some(0),
+                None => None,
+            };
+            let mut refused_stream_ids: Vec<_> = stream
+                .stream_globals
+                .messages_per_stream
+                .keys()
+                .filter(|id| *id > last_stream_id && Some(*id % 2) == receiver_stream_parity)
+                .copied()
+                .collect();
+            refused_stream_ids.sort_unstable();
+            for refused_stream_id in refused_stream_ids {
+                if let Some(mut contents) = stream
+                    .stream_globals
+                    .messages_per_stream
+                    .remove(&refused_stream_id)
+                {
+                    contents.frames.push(event.clone());
+                    flush_stream_contents(stream, refused_stream_id, contents);
+                }
+            }
+            stream.stream_globals.goaway = Some(event);
+        }
+        Http2ControlFrame::PushPromise {
+            promised_stream_id,
+            headers,
+        } => {
+            // the server will send the response to this request on the promised stream
+            let (request, _) = prepare_http_message(
+                cur_msg.tcp_stream_id,
+                cur_msg.tcp_seq_number,
+                cur_msg.frame_time,
+                vec![TSharkHttp2Message {
+                    headers: headers.clone(),
+                    data: None,
+                    stream_id: *promised_stream_id,
+                    is_end_stream: true,
+                    starts_header_block: true,
+                    control_frame: None,
+                }],
+            );
+            stream.stream_globals.messages_per_stream.insert(
+                *promised_stream_id,
+                Http2StreamProcessedContents {
+                    cur_request: Some(request),
+                    unfinished_basic_info: Some(cur_msg),
+                    unfinished_stream_messages: vec![],
+                    live_event_stream: None,
+                    frames: vec![event],
+                },
+            );
+        }
+        // only in the connection events. WINDOW_UPDATEs are too many to list
+        // them with the messages
+        Http2ControlFrame::WindowUpdate { .. } | Http2ControlFrame::Settings { .. } => {}
+    }
+}
+
+fn attach_frame_to_message(stream: &mut Http2StreamData, event: Http2FrameEvent) {
+    match stream
+        .stream_globals
+        .messages_per_stream
+        .get_mut(&event.http2_stream_id)
+    {
+        Some(Http2StreamProcessedContents {
+            live_event_stream: Some((msg_idx, _)),
+            ..
+        }) => {
+            if let Some(msg) = stream.messages.get_mut(*msg_idx) {
+                msg.http2_frames.push(event);
+            }
+        }
+        Some(contents) => contents.frames.push(event),
+        None => {
+            // a frame for a stream we're done with, for instance a RST_STREAM
+            // after the response
+            if let Some(msg) = stream
+                .messages
+                .iter_mut()
+                .rev()
+                .find(|m| m.http_stream_id == event.http2_stream_id)
+            {
+                msg.http2_frames.push(event);
+            }
+        }
+    }
+}
+
+/// add the message for a HTTP2 stream which we won't get more data for,
+/// as best we can
+fn flush_stream_contents(
+    stream: &mut Http2StreamData,
+    http2_stream_id: u32,
+    stream_contents: Http2StreamProcessedContents,
+) {
+    if let Some((msg_idx, _)) = stream_contents.live_event_stream {
+        // already displayed as we were receiving it
+        if let Some(msg) = stream.messages.get_mut(msg_idx) {
+            msg.http2_frames.extend(stream_contents.frames);
+        }
+        return;
+    }
+    let cur_msg = stream_contents.unfinished_basic_info.unwrap();
+    match (
+        stream_contents.cur_request,
+        stream_contents.unfinished_stream_messages,
+    ) {
+        (Some(r), leftover) if leftover.is_empty() => stream.messages.push(HttpMessageData {
+            http_stream_id: http2_stream_id,
+            request: Some(r),
+            response: None,
+            http2_frames: stream_contents.frames,
+        }),
+        (req, leftover) => {
+            let (http_msg, msg_type) = prepare_http_message(
+                cur_msg.tcp_stream_id,
+                cur_msg.tcp_seq_number,
+                cur_msg.frame_time,
+                leftover,
+            );
+            match msg_type {
+                MsgType::Request => {
+                    if stream.summary_details.is_none() {
+                        stream.summary_details = http_streams_store::get_http_header_value(
+                            &http_msg.headers,
+                            ":authority",
+                        )
+                        .map(|c| c.to_string());
+                    }
+                    if stream.client_server.is_none() {
+                        stream.client_server = Some(ClientServerInfo {
+                            client_ip: cur_msg.ip_src,
+                            server_ip: cur_msg.ip_dst,
+                            server_port: cur_msg.port_dst,
+                        });
+                    }
+                    stream.messages.push(HttpMessageData {
+                        http_stream_id: http2_stream_id,
+                        request: Some(http_msg),
+                        response: None,
+                        http2_frames: stream_contents.frames,
+                    });
+                }
+                MsgType::Response => {
+                    if stream.client_server.is_none() {
+                        stream.client_server = Some(ClientServerInfo {
+                            client_ip: cur_msg.ip_dst,
+                            server_ip: cur_msg.ip_src,
+                            server_port: cur_msg.port_src,
+                        });
+                    }
+                    stream.messages.push(HttpMessageData {
+                        http_stream_id: http2_stream_id,
+                        request: req,
+                        response: Some(http_msg),
+                        http2_frames: stream_contents.frames,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn is_event_stream_response(headers: &[(String, String)]) -> bool {
+    http_streams_store::get_http_header_value(headers, ":status").is_some()
+        && server_sent_events::is_event_stream(
+            http_streams_store::get_http_header_value(headers, "content-type").map(|s| s.as_str()),
+        )
+        && ContentEncoding::parse_from_str(
+            &http_streams_store::get_http_header_value(headers, "content-encoding")
+                .map(|s| s.as_str()),
+        ) == ContentEncoding::Plain
+}
+
 fn prepare_http_message(
     tcp_stream_no: TcpStreamId,
     tcp_seq_number: TcpSeqNumber,
     timestamp: NaiveDateTime,
     http2_msgs: Vec<TSharkHttp2Message>,
 ) -> (HttpRequestResponseData, MsgType) {
-    let (headers, data) = http2_msgs.into_iter().fold(
-        (vec![], None::<Vec<u8>>),
-        |(mut sofar_h, sofar_d), mut cur| {
-            sofar_h.append(&mut cur.headers);
-            let new_data = match (sofar_d, cur.data) {
-                (None, Some(d)) => Some(d),
-                (Some(_s), Some(n)) => {
-                    // when a http data transfer is split between multiple packets, it seems that tshark
-                    // recomposes data in the final packet, we don't need to combine it ourselves.
-                    // The "recombined" aspect was clearer in the tshark JSON as opposed to XML PDML.
-                    // => discarding previous messages, only keeping the last one.
-                    // TODO if this holds, I could make sure that we never load the incomplete data from
-                    // previous packets to memory, to reduce memory use
-                    Some(n)
-                }
-                (d, _) => d,
-            };
-            (sofar_h, new_data)
-        },
+    let mut header_blocks: Vec<Vec<(String, String)>> = vec![];
+    let mut data = None;
+    for mut cur in http2_msgs {
+        match header_blocks.last_mut() {
+            Some(block) if !cur.starts_header_block => {
+                // CONTINUATION frame
+                block.append(&mut cur.headers);
+            }
+            _ if cur.starts_header_block || !cur.headers.is_empty() => {
+                header_blocks.push(cur.headers)
+            }
+            _ => {}
+        }
+        if cur.data.is_some() {
+            // when a http data transfer is split between multiple packets, it seems that tshark
+            // recomposes data in the final packet, we don't need to combine it ourselves.
+            // The "recombined" aspect was clearer in the tshark JSON as opposed to XML PDML.
+            // => discarding previous messages, only keeping the last one.
+            // TODO if this holds, I could make sure that we never load the incomplete data from
+            // previous packets to memory, to reduce memory use
+            data = cur.data;
+        }
+    }
+    // every http2 response must contain a ":status" header
+    // https://tools.ietf.org/html/rfc7540#section-8.1.2.4
+    let is_response = header_blocks
+        .iter()
+        .any(|b| http_streams_store::get_http_header_value(b, ":status").is_some());
+    let MessageHeaders {
+        interim_responses,
+        headers,
+        trailers,
+    } = split_header_blocks(is_response, header_blocks);
+    let content_type = http_streams_store::get_http_header_value(&headers, "content-type").cloned();
+    let (body, content_encoding) = http_streams_store::parse_encoded_body(
+        data,
+        content_type.as_deref(),
+        ContentEncoding::parse_from_str(
+            &http_streams_store::get_http_header_value(&headers, "content-encoding")
+                .map(|s| s.as_str()),
+        ),
     );
-    let body = data
-        .map(|d| {
-            str::from_utf8(&d)
-                .ok()
-                .map(|s| HttpBody::Text(s.to_string()))
-                .unwrap_or_else(|| HttpBody::Binary(d))
-        })
-        .unwrap_or(HttpBody::Missing);
+    let server_sent_events =
+        server_sent_events::parse_body_events(timestamp, content_type.as_deref(), &body);
 
-    let (first_line, msg_type) =
-        if http_streams_store::get_http_header_value(&headers, ":status").is_none() {
-            // every http2 response must contain a ":status" header
-            // https://tools.ietf.org/html/rfc7540#section-8.1.2.4
-            // => this is a request
-            (
-                format!(
+    let (first_line, msg_type) = if !is_response {
+        // => this is a request
+        (
+            format!(
+                "{} {}",
+                http_streams_store::get_http_header_value(&headers, ":method")
+                    .map(|s| s.as_str())
+                    .unwrap_or("-"),
+                http_streams_store::get_http_header_value(&headers, ":path")
+                    .map(|s| s.as_str())
+                    .unwrap_or("-")
+            ),
+            MsgType::Request,
+        )
+    } else {
+        // this is a response
+        let status = response_status_line(&headers);
+        (
+            // gRPC sends the status in the trailers, except for trailers-only responses
+            match grpc::grpc_status(&trailers).or_else(|| grpc::grpc_status(&headers)) {
+                Some((code, message)) => format!(
                     "{} {}",
-                    http_streams_store::get_http_header_value(&headers, ":method")
-                        .map(|s| s.as_str())
-                        .unwrap_or("-"),
-                    http_streams_store::get_http_header_value(&headers, ":path")
-                        .map(|s| s.as_str())
-                        .unwrap_or("-")
+                    status,
+                    grpc::status_description(code, message.as_deref())
                 ),
-                MsgType::Request,
-            )
-        } else {
-            // this is a response
-            (
-                format!(
-                    "HTTP/2 status {}",
-                    http_streams_store::get_http_header_value(&headers, ":status")
-                        .map(|s| s.as_str())
-                        .unwrap_or("-"),
-                ),
-                MsgType::Response,
-            )
-        };
-    let content_type = http_streams_store::get_http_header_value(&headers, "content-type").cloned();
-    let content_encoding = ContentEncoding::parse_from_str(
-        &http_streams_store::get_http_header_value(&headers, "content-encoding")
-            .map(|s| s.as_str()),
-    );
+                None => status,
+            },
+            MsgType::Response,
+        )
+    };
     // if matches!(body, HttpBody::Binary(_)) {
     //     println!(
     //         "######### GOT BINARY BODY {:?} status {:?} path {:?}",
@@ -447,19 +743,82 @@ fn prepare_http_message(
     // }
     (
         HttpRequestResponseData {
-            tcp_stream_no,
-            tcp_seq_number,
-            timestamp,
-            first_line,
-            headers,
-            body,
-            content_type,
             content_encoding,
+            server_sent_events,
+            interim_responses,
+            trailers,
+            ..HttpRequestResponseData::new(
+                tcp_stream_no,
+                tcp_seq_number,
+                timestamp,
+                first_line,
+                headers,
+                body,
+            )
         },
         msg_type,
     )
 }
 
+fn response_status_line(headers: &[(String, String)]) -> String {
+    format!(
+        "HTTP/2 status {}",
+        http_streams_store::get_http_header_value(headers, ":status")
+            .map(|s| s.as_str())
+            .unwrap_or("-"),
+    )
+}
+
+struct MessageHeaders {
+    interim_responses: Vec<InterimResponse>,
+    headers: Vec<(String, String)>,
+    trailers: Vec<(String, String)>,
+}
+
+/// a message is made of 1xx interim responses (only for responses), the header block
+/// of the message, then the trailers, after the body.
+/// https://tools.ietf.org/html/rfc7540#section-8.1
+/// tshark gives us empty header blocks when it can't decode them (missing HPACK context,
+/// for instance if the capture started after the connection). We skip them, but then
+/// a block with no pseudo-headers can only be the trailers.
+fn split_header_blocks(
+    is_response: bool,
+    header_blocks: Vec<Vec<(String, String)>>,
+) -> MessageHeaders {
+    let mut interim_responses = vec![];
+    let mut headers = None;
+    let mut trailers = vec![];
+    let mut skipped_undecodable_block = false;
+    for mut block in header_blocks {
+        if block.is_empty() {
+            skipped_undecodable_block = true;
+            continue;
+        }
+        // https://tools.ietf.org/html/rfc7540#section-8.1.2.1
+        let has_pseudo_headers = block.iter().any(|(k, _)| k.starts_with(':'));
+        let is_interim = is_response
+            && headers.is_none()
+            && http_streams_store::get_http_header_value(&block, ":status")
+                .filter(|s| s.starts_with('1'))
+                .is_some();
+        if is_interim {
+            interim_responses.push(InterimResponse {
+                first_line: response_status_line(&block),
+                headers: block,
+            });
+        } else if headers.is_none() && (has_pseudo_headers || !skipped_undecodable_block) {
+            headers = Some(block);
+        } else {
+            trailers.append(&mut block);
+        }
+    }
+    MessageHeaders {
+        interim_responses,
+        headers: headers.unwrap_or_default(),
+        trailers,
+    }
+}
+
 #[cfg(test)]
 fn tests_parse_stream(
     packets: Result<Vec<TSharkPacket>, String>,
@@ -545,9 +904,497 @@ fn should_parse_simple_comm() {
             body: HttpBody::Missing,
             content_type: None,
             content_encoding: ContentEncoding::Plain,
+            server_sent_events: vec![],
+            interim_responses: vec![],
+            trailers: vec![],
         }),
         response: None,
+        http2_frames: vec![],
     }];
     assert_eq!(expected, parsed);
     // assert!(false);
 }
+
+#[cfg(test)]
+fn test_http2_headers_xml(headers: &[(&str, &str)]) -> String {
+    headers
+        .iter()
+        .map(|(k, v)| {
+            format!(
+                r#"<field name="http2.header" show="" value="">
+                     <field name="http2.header.name" show="{}"/>
+                     <field name="http2.header.value" show="{}"/>
+                   </field>"#,
+                k, v
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn test_http2_packet(
+    second: u32,
+    is_end_stream: bool,
+    headers: &[(&str, &str)],
+    data: Option<&str>,
+) -> TSharkPacket {
+    let data_xml = data
+        .map(|d| {
+            format!(
+                r#"<field name="http2.data.data" show="{}"/>"#,
+                hex::encode(d)
+            )
+        })
+        .unwrap_or_default();
+    test_http2_frame_packet(
+        second,
+        &format!(
+            r#"<field name="http2.flags.end_stream" show="{}"/>
+               <field name="http2.streamid" show="3"/>
+               {}
+               {}"#,
+            if is_end_stream { 1 } else { 0 },
+            test_http2_headers_xml(headers),
+            data_xml
+        ),
+    )
+}
+
+#[cfg(test)]
+fn test_http2_frame_packet(second: u32, stream_fields_xml: &str) -> TSharkPacket {
+    let mut packet = parse_test_xml(&format!(
+        r#"<proto name="http2">
+             <field name="http2.stream" show="" value="">
+               {}
+             </field>
+           </proto>"#,
+        stream_fields_xml
+    ))
+    .unwrap()
+    .remove(0);
+    packet.basic_info.frame_time = NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, second);
+    packet
+}
+
+#[cfg(test)]
+fn test_from_server(mut packet: TSharkPacket) -> TSharkPacket {
+    let info = &mut packet.basic_info;
+    std::mem::swap(&mut info.ip_src, &mut info.ip_dst);
+    std::mem::swap(&mut info.port_src, &mut info.port_dst);
+    packet
+}
+
+#[cfg(test)]
+fn test_http2_request_packet(second: u32, http2_stream_id: u32, path: &str) -> TSharkPacket {
+    test_http2_frame_packet(
+        second,
+        &format!(
+            r#"<field name="http2.type" show="1"/>
+               <field name="http2.flags.end_stream" show="1"/>
+               <field name="http2.streamid" show="{}"/>
+               {}"#,
+            http2_stream_id,
+            test_http2_headers_xml(&[(":method", "GET"), (":path", path)])
+        ),
+    )
+}
+
+#[test]
+fn should_parse_live_event_streams_before_the_end_of_stream() {
+    let mut parser = Http2StreamsStore::default();
+    let sid = TcpStreamId(1);
+    let packets = vec![
+        test_http2_packet(1, true, &[(":method", "GET"), (":path", "/events")], None),
+        test_http2_packet(
+            2,
+            false,
+            &[(":status", "200"), ("content-type", "text/event-stream")],
+            None,
+        ),
+        test_http2_packet(3, false, &[], Some("data: first\n\ndata: sec")),
+        test_http2_packet(4, false, &[], Some("ond\n\n")),
+        // tshark recombines the data of the whole stream in the last frame
+        test_http2_packet(
+            5,
+            true,
+            &[],
+            Some("data: first\n\ndata: second\n\nevent: end\ndata: third\n\n"),
+        ),
+    ];
+    let mut message_counts = vec![];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+        message_counts.push(parser.stream_message_count(sid).unwrap());
+    }
+    assert_eq!(vec![0, 1, 1, 1, 1], message_counts);
+    parser.finish_stream(sid).unwrap();
+    let messages = parser.stream_http_messages(sid).unwrap();
+    assert_eq!(1, messages.len());
+    assert_eq!(
+        "GET /events",
+        messages[0].request.as_ref().unwrap().first_line
+    );
+    let response = messages[0].response.as_ref().unwrap();
+    assert_eq!(
+        HttpBody::Text("data: first\n\ndata: second\n\nevent: end\ndata: third\n\n".to_string()),
+        response.body
+    );
+    assert_eq!(
+        vec![
+            (3, "message", "first"),
+            (4, "message", "second"),
+            (5, "end", "third")
+        ],
+        response
+            .server_sent_events
+            .iter()
+            .map(|e| (e.timestamp.second(), e.event_type(), e.data.as_str()))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn should_attach_rst_stream_and_goaway_to_messages() {
+    let mut parser = Http2StreamsStore::default();
+    let sid = TcpStreamId(1);
+    let packets = vec![
+        test_http2_frame_packet(
+            1,
+            r#"<field name="http2.type" show="4"/>
+               <field name="http2.streamid" show="0"/>
+               <field name="http2.settings" show="">
+                 <field name="http2.settings.id" show="3"/>
+                 <field name="http2.settings.max_concurrent_streams" show="100"/>
+               </field>
+               <field name="http2.settings" show="">
+                 <field name="http2.settings.id" show="4"/>
+                 <field name="http2.settings.initial_window_size" show="65535"/>
+               </field>"#,
+        ),
+        test_http2_request_packet(2, 1, "/cancelled"),
+        test_http2_request_packet(2, 3, "/slow"),
+        test_http2_request_packet(2, 5, "/refused"),
+        test_http2_frame_packet(
+            3,
+            r#"<field name="http2.type" show="3"/>
+               <field name="http2.streamid" show="1"/>
+               <field name="http2.rst_stream.error" show="8"/>"#,
+        ),
+        test_from_server(test_http2_frame_packet(
+            4,
+            r#"<field name="http2.type" show="7"/>
+               <field name="http2.streamid" show="0"/>
+               <field name="http2.goaway.last_stream_id" show="3"/>
+               <field name="http2.goaway.error" show="0"/>
+               <field name="http2.goaway.addata" show="62:79:65"/>"#,
+        )),
+    ];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+    }
+    let paths = |parser: &Http2StreamsStore| {
+        parser
+            .stream_http_messages(sid)
+            .unwrap()
+            .iter()
+            .map(|m| {
+                (
+                    m.request.as_ref().unwrap().first_line.clone(),
+                    m.missing_response_reason(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    // the stream after the GOAWAY last stream id is done right away
+    assert_eq!(
+        vec![
+            (
+                "GET /cancelled".to_string(),
+                Some("Stream reset: CANCEL (8)".to_string())
+            ),
+            (
+                "GET /refused".to_string(),
+                Some("Connection closed (GOAWAY): NO_ERROR (0)".to_string())
+            ),
+        ],
+        paths(&parser)
+    );
+    parser.finish_stream(sid).unwrap();
+    assert_eq!(
+        (
+            "GET /slow".to_string(),
+            Some("Connection closed (GOAWAY): NO_ERROR (0)".to_string())
+        ),
+        paths(&parser)[2]
+    );
+    assert_eq!(
+        vec![
+            (
+                0,
+                "SETTINGS",
+                "MAX_CONCURRENT_STREAMS=100, INITIAL_WINDOW_SIZE=65535".to_string()
+            ),
+            (1, "RST_STREAM", "CANCEL (8)".to_string()),
+            (0, "GOAWAY", "NO_ERROR (0), last stream 3: bye".to_string()),
+        ],
+        parser.streams[&sid]
+            .connection_events
+            .events
+            .iter()
+            .map(|e| (
+                e.http2_stream_id,
+                e.frame.frame_type_name(),
+                e.frame.details()
+            ))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn should_skip_undecodable_header_blocks() {
+    let mut parser = Http2StreamsStore::default();
+    let sid = TcpStreamId(1);
+    let frame = |second, is_end_stream, headers: &[(&str, &str)]| {
+        test_http2_frame_packet(
+            second,
+            &format!(
+                r#"<field name="http2.type" show="1"/>
+                   <field name="http2.flags.end_stream" show="{}"/>
+                   <field name="http2.streamid" show="1"/>
+                   {}"#,
+                if is_end_stream { 1 } else { 0 },
+                test_http2_headers_xml(headers)
+            ),
+        )
+    };
+    let packets = vec![
+        test_http2_request_packet(1, 1, "/download"),
+        // tshark couldn't decode this one
+        frame(2, false, &[]),
+        frame(3, false, &[(":status", "200")]),
+        frame(4, true, &[("x-checksum", "abc")]),
+    ];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+    }
+    let response = parser.stream_http_messages(sid).unwrap()[0]
+        .response
+        .clone()
+        .unwrap();
+    assert_eq!("HTTP/2 status 200", response.first_line);
+    assert_eq!(
+        vec![(":status".to_string(), "200".to_string())],
+        response.headers
+    );
+    assert_eq!(
+        vec![("x-checksum".to_string(), "abc".to_string())],
+        response.trailers
+    );
+}
+
+#[test]
+fn should_total_window_updates_instead_of_listing_them() {
+    let mut parser = Http2StreamsStore::default();
+    let sid = TcpStreamId(1);
+    let window_update = |second, http2_stream_id, increment| {
+        test_http2_frame_packet(
+            second,
+            &format!(
+                r#"<field name="http2.type" show="8"/>
+                   <field name="http2.streamid" show="{}"/>
+                   <field name="http2.window_update.window_size_increment" show="{}"/>"#,
+                http2_stream_id, increment
+            ),
+        )
+    };
+    let packets = vec![
+        test_http2_request_packet(1, 1, "/download"),
+        window_update(2, 0, 1000),
+        window_update(3, 1, 200),
+        window_update(4, 1, 30),
+    ];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+    }
+    parser.finish_stream(sid).unwrap();
+    let stream = &parser.streams[&sid];
+    assert!(stream.messages[0].http2_frames.is_empty());
+    assert!(stream.connection_events.events.is_empty());
+    assert_eq!(
+        vec![(3, 1230)],
+        stream
+            .connection_events
+            .window_updates
+            .iter()
+            .map(|w| (w.frame_count, w.total_increment))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn should_not_refuse_the_client_requests_on_a_client_goaway() {
+    let mut parser = Http2StreamsStore::default();
+    let sid = TcpStreamId(1);
+    let packets = vec![
+        test_http2_request_packet(1, 1, "/slow"),
+        test_http2_frame_packet(
+            2,
+            r#"<field name="http2.type" show="7"/>
+               <field name="http2.streamid" show="0"/>
+               <field name="http2.goaway.last_stream_id" show="0"/>
+               <field name="http2.goaway.error" show="0"/>"#,
+        ),
+        test_from_server(test_http2_frame_packet(
+            3,
+            &format!(
+                r#"<field name="http2.type" show="1"/>
+                   <field name="http2.flags.end_stream" show="1"/>
+                   <field name="http2.streamid" show="1"/>
+                   {}"#,
+                test_http2_headers_xml(&[(":status", "200")])
+            ),
+        )),
+    ];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+    }
+    parser.finish_stream(sid).unwrap();
+    let messages = parser.stream_http_messages(sid).unwrap();
+    assert_eq!(1, messages.len());
+    assert_eq!(
+        "GET /slow",
+        messages[0].request.as_ref().unwrap().first_line
+    );
+    assert_eq!(
+        "HTTP/2 status 200",
+        messages[0].response.as_ref().unwrap().first_line
+    );
+}
+
+#[test]
+fn should_pair_pushed_responses_with_the_push_promise() {
+    let mut parser = Http2StreamsStore::default();
+    let sid = TcpStreamId(1);
+    let packets = vec![
+        test_http2_request_packet(1, 1, "/index.html"),
+        test_http2_frame_packet(
+            2,
+            &format!(
+                r#"<field name="http2.type" show="5"/>
+                   <field name="http2.streamid" show="1"/>
+                   <field name="http2.push_promise.promised_stream_id" show="2"/>
+                   {}"#,
+                test_http2_headers_xml(&[(":method", "GET"), (":path", "/style.css")])
+            ),
+        ),
+        test_http2_frame_packet(
+            3,
+            &format!(
+                r#"<field name="http2.type" show="1"/>
+                   <field name="http2.flags.end_stream" show="1"/>
+                   <field name="http2.streamid" show="2"/>
+                   {}"#,
+                test_http2_headers_xml(&[(":status", "200")])
+            ),
+        ),
+    ];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+    }
+    let messages = parser.stream_http_messages(sid).unwrap();
+    assert_eq!(1, messages.len());
+    assert_eq!(2, messages[0].http_stream_id);
+    assert_eq!(
+        "GET /style.css",
+        messages[0].request.as_ref().unwrap().first_line
+    );
+    assert_eq!(
+        "HTTP/2 status 200",
+        messages[0].response.as_ref().unwrap().first_line
+    );
+    assert_eq!(
+        vec!["PUSH_PROMISE"],
+        messages[0]
+            .http2_frames
+            .iter()
+            .map(|f| f.frame.frame_type_name())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn should_separate_interim_responses_and_trailers() {
+    let mut parser = Http2StreamsStore::default();
+    let sid = TcpStreamId(1);
+    let frame = |second, frame_type, is_end_stream, headers: &[(&str, &str)]| {
+        test_http2_frame_packet(
+            second,
+            &format!(
+                r#"<field name="http2.type" show="{}"/>
+                   <field name="http2.flags.end_stream" show="{}"/>
+                   <field name="http2.streamid" show="1"/>
+                   {}"#,
+                frame_type,
+                if is_end_stream { 1 } else { 0 },
+                test_http2_headers_xml(headers)
+            ),
+        )
+    };
+    let packets = vec![
+        frame(
+            1,
+            1,
+            true,
+            &[(":method", "POST"), (":path", "/shop.v1.Orders/GetOrder")],
+        ),
+        frame(
+            2,
+            1,
+            false,
+            &[(":status", "103"), ("link", "/style.css; rel=preload")],
+        ),
+        frame(3, 1, false, &[(":status", "200")]),
+        // CONTINUATION
+        frame(3, 9, false, &[("content-type", "application/grpc")]),
+        frame(
+            4,
+            1,
+            true,
+            &[("grpc-status", "5"), ("grpc-message", "no such order")],
+        ),
+    ];
+    for packet in packets.into_iter() {
+        parser.add_to_stream(sid, packet).unwrap();
+    }
+    let messages = parser.stream_http_messages(sid).unwrap();
+    let response = messages[0].response.as_ref().unwrap();
+    assert_eq!(
+        "HTTP/2 status 200 gRPC NOT_FOUND (5): no such order",
+        response.first_line
+    );
+    assert_eq!(
+        vec![InterimResponse {
+            first_line: "HTTP/2 status 103".to_string(),
+            headers: vec![
+                (":status".to_string(), "103".to_string()),
+                ("link".to_string(), "/style.css; rel=preload".to_string())
+            ],
+        }],
+        response.interim_responses
+    );
+    assert_eq!(
+        vec![
+            (":status".to_string(), "200".to_string()),
+            ("content-type".to_string(), "application/grpc".to_string())
+        ],
+        response.headers
+    );
+    assert_eq!(Some("application/grpc"), response.content_type.as_deref());
+    assert_eq!(
+        vec![
+            ("grpc-status".to_string(), "5".to_string()),
+            ("grpc-message".to_string(), "no such order".to_string())
+        ],
+        response.trailers
+    );
+}
@@ -1,2 +1,5 @@
+pub mod grpc;
 pub mod http2_streams_store;
+pub mod proto_schema;
+mod protobuf;
 pub mod tshark_http2;
@@ -0,0 +1,902 @@
+// The protobuf definitions used to display gRPC messages with their field names.
+// We read either a folder of .proto files, or a descriptor set
+// (protoc --include_imports --descriptor_set_out=...)
+use super::protobuf;
+use super::protobuf::{ProtoField, WireValue};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarType {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Bool,
+    String,
+    Bytes,
+}
+
+impl ScalarType {
+    fn from_proto_name(name: &str) -> Option<ScalarType> {
+        Some(match name {
+            "double" => ScalarType::Double,
+            "float" => ScalarType::Float,
+            "int32" => ScalarType::Int32,
+            "int64" => ScalarType::Int64,
+            "uint32" => ScalarType::Uint32,
+            "uint64" => ScalarType::Uint64,
+            "sint32" => ScalarType::Sint32,
+            "sint64" => ScalarType::Sint64,
+            "fixed32" => ScalarType::Fixed32,
+            "fixed64" => ScalarType::Fixed64,
+            "sfixed32" => ScalarType::Sfixed32,
+            "sfixed64" => ScalarType::Sfixed64,
+            "bool" => ScalarType::Bool,
+            "string" => ScalarType::String,
+            "bytes" => ScalarType::Bytes,
+            _ => return None,
+        })
+    }
+
+    /// https://github.com/protocolbuffers/protobuf/blob/main/src/google/protobuf/descriptor.proto
+    /// FieldDescriptorProto.Type
+    fn from_descriptor_type(t: u64) -> Option<ScalarType> {
+        Some(match t {
+            1 => ScalarType::Double,
+            2 => ScalarType::Float,
+            3 => ScalarType::Int64,
+            4 => ScalarType::Uint64,
+            5 => ScalarType::Int32,
+            6 => ScalarType::Fixed64,
+            7 => ScalarType::Fixed32,
+            8 => ScalarType::Bool,
+            9 => ScalarType::String,
+            12 => ScalarType::Bytes,
+            13 => ScalarType::Uint32,
+            15 => ScalarType::Sfixed32,
+            16 => ScalarType::Sfixed64,
+            17 => ScalarType::Sint32,
+            18 => ScalarType::Sint64,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Scalar(ScalarType),
+    // fully qualified type names, without leading dot
+    Message(String),
+    Enum(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDef {
+    pub name: String,
+    pub field_type: FieldType,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MessageDef {
+    pub fields: HashMap<u32, FieldDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDef {
+    pub input_type: String,
+    pub output_type: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ProtoSchema {
+    messages: HashMap<String, MessageDef>,
+    enums: HashMap<String, HashMap<i32, String>>,
+    // by gRPC path: /package.Service/Method
+    methods: HashMap<String, MethodDef>,
+}
+
+/// the schema from the path of the configuration. We load it when the
+/// configuration changes, and the widgets displaying gRPC messages share it.
+#[derive(Debug)]
+pub struct ConfiguredProtoSchema {
+    pub path: String,
+    pub schema: Result<ProtoSchema, String>,
+}
+
+impl ConfiguredProtoSchema {
+    pub fn load(path: String) -> ConfiguredProtoSchema {
+        let schema = ProtoSchema::load(Path::new(&path));
+        ConfiguredProtoSchema { path, schema }
+    }
+}
+
+impl ProtoSchema {
+    pub fn message(&self, type_name: &str) -> Option<&MessageDef> {
+        self.messages.get(type_name.trim_start_matches('.'))
+    }
+
+    pub fn enum_value_name(&self, type_name: &str, number: i32) -> Option<&str> {
+        self.enums
+            .get(type_name.trim_start_matches('.'))?
+            .get(&number)
+            .map(|s| s.as_str())
+    }
+
+    pub fn method(&self, grpc_path: &str) -> Option<&MethodDef> {
+        self.methods.get(grpc_path)
+    }
+
+    /// a folder of .proto files (searched recursively), a single .proto file,
+    /// or a binary descriptor set
+    pub fn load(path: &Path) -> Result<ProtoSchema, String> {
+        let mut parsed = ParsedFiles::default();
+        if path.is_dir() {
+            parsed.parse_dir(path)?;
+        } else if path.extension().filter(|e| *e == "proto").is_some() {
+            parsed.parse_file(path)?;
+        } else {
+            let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            return Self::from_descriptor_set(&bytes);
+        }
+        Ok(parsed.resolve())
+    }
+
+    /// FileDescriptorSet: https://github.com/protocolbuffers/protobuf/blob/main/src/google/protobuf/descriptor.proto
+    pub fn from_descriptor_set(bytes: &[u8]) -> Result<ProtoSchema, String> {
+        let files = protobuf::decode_message(bytes).ok_or("Invalid protobuf descriptor set")?;
+        let mut schema = ProtoSchema::default();
+        for file in sub_messages(&files, 1) {
+            let package = string_field(&file, 2).unwrap_or_default();
+            for msg in sub_messages(&file, 4) {
+                schema.add_descriptor_message(&package, &msg);
+            }
+            for enm in sub_messages(&file, 5) {
+                schema.add_descriptor_enum(&package, &enm);
+            }
+            for service in sub_messages(&file, 6) {
+                let service_name =
+                    qualified_name(&package, &string_field(&service, 1).unwrap_or_default());
+                for method in sub_messages(&service, 2) {
+                    if let (Some(name), Some(input), Some(output)) = (
+                        string_field(&method, 1),
+                        string_field(&method, 2),
+                        string_field(&method, 3),
+                    ) {
+                        schema.methods.insert(
+                            format!("/{}/{}", service_name, name),
+                            MethodDef {
+                                input_type: input.trim_start_matches('.').to_string(),
+                                output_type: output.trim_start_matches('.').to_string(),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        Ok(schema)
+    }
+
+    /// DescriptorProto
+    fn add_descriptor_message(&mut self, scope: &str, msg: &[ProtoField]) {
+        let full_name = qualified_name(scope, &string_field(msg, 1).unwrap_or_default());
+        let mut message_def = MessageDef::default();
+        // FieldDescriptorProto
+        for field in sub_messages(msg, 2) {
+            let type_name = string_field(&field, 6)
+                .map(|t| t.trim_start_matches('.').to_string())
+                .unwrap_or_default();
+            let field_type = match varint_field(&field, 5) {
+                Some(10) | Some(11) => FieldType::Message(type_name),
+                Some(14) => FieldType::Enum(type_name),
+                Some(t) => match ScalarType::from_descriptor_type(t) {
+                    Some(s) => FieldType::Scalar(s),
+                    None => continue,
+                },
+                None => continue,
+            };
+            if let (Some(name), Some(number)) = (string_field(&field, 1), varint_field(&field, 3)) {
+                message_def
+                    .fields
+                    .insert(number as u32, FieldDef { name, field_type });
+            }
+        }
+        for nested in sub_messages(msg, 3) {
+            self.add_descriptor_message(&full_name, &nested);
+        }
+        for enm in sub_messages(msg, 4) {
+            self.add_descriptor_enum(&full_name, &enm);
+        }
+        self.messages.insert(full_name, message_def);
+    }
+
+    /// EnumDescriptorProto
+    fn add_descriptor_enum(&mut self, scope: &str, enm: &[ProtoField]) {
+        let full_name = qualified_name(scope, &string_field(enm, 1).unwrap_or_default());
+        let values = sub_messages(enm, 2)
+            .iter()
+            .filter_map(|v| Some((varint_field(v, 2)? as i32, string_field(v, 1)?)))
+            .collect();
+        self.enums.insert(full_name, values);
+    }
+}
+
+fn qualified_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn sub_messages(fields: &[ProtoField], number: u32) -> Vec<Vec<ProtoField>> {
+    fields
+        .iter()
+        .filter(|f| f.number == number)
+        .filter_map(|f| match &f.value {
+            WireValue::LengthDelimited(bytes) => protobuf::decode_message(bytes),
+            _ => None,
+        })
+        .collect()
+}
+
+fn string_field(fields: &[ProtoField], number: u32) -> Option<String> {
+    fields.iter().rev().find_map(|f| match &f.value {
+        WireValue::LengthDelimited(bytes) if f.number == number => {
+            str::from_utf8(bytes).ok().map(|s| s.to_string())
+        }
+        _ => None,
+    })
+}
+
+fn varint_field(fields: &[ProtoField], number: u32) -> Option<u64> {
+    fields.iter().rev().find_map(|f| match &f.value {
+        WireValue::Varint(v) if f.number == number => Some(*v),
+        _ => None,
+    })
+}
+
+/// a field as written in the .proto file, before we resolve its type name
+#[derive(Debug)]
+struct ParsedField {
+    name: String,
+    number: u32,
+    type_name: String,
+}
+
+#[derive(Debug)]
+struct ParsedMessage {
+    full_name: String,
+    fields: Vec<ParsedField>,
+}
+
+#[derive(Debug)]
+struct ParsedMethod {
+    path: String,
+    package: String,
+    input_type: String,
+    output_type: String,
+}
+
+#[derive(Debug, Default)]
+struct ParsedFiles {
+    messages: Vec<ParsedMessage>,
+    enums: HashMap<String, HashMap<i32, String>>,
+    methods: Vec<ParsedMethod>,
+}
+
+struct Tokens<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    /// https://developers.google.com/protocol-buffers/docs/reference/proto3-spec#lexical_elements
+    fn tokenize(contents: &'a str) -> Tokens<'a> {
+        let mut tokens = vec![];
+        let bytes = contents.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i];
+            if c.is_ascii_whitespace() {
+                i += 1;
+            } else if contents[i..].starts_with("//") {
+                i = contents[i..]
+                    .find('\n')
+                    .map(|e| i + e)
+                    .unwrap_or(bytes.len());
+            } else if contents[i..].starts_with("/*") {
+                i = contents[i + 2..]
+                    .find("*/")
+                    .map(|e| i + 2 + e + 2)
+                    .unwrap_or(bytes.len());
+            } else if c == b'"' || c == b'\'' {
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end] != c {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                let end = (end + 1).min(bytes.len());
+                tokens.push(&contents[i..end]);
+                i = end;
+            } else if c.is_ascii_alphanumeric() || c == b'_' || c == b'.' || c == b'-' {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || b"_.-+".contains(&bytes[i]))
+                {
+                    i += 1;
+                }
+                tokens.push(&contents[start..i]);
+            } else {
+                let len = contents[i..]
+                    .chars()
+                    .next()
+                    .map(|c| c.len_utf8())
+                    .unwrap_or(1);
+                tokens.push(&contents[i..i + len]);
+                i += len;
+            }
+        }
+        Tokens { tokens, pos: 0 }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let t = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        t
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == expected => Ok(()),
+            t => Err(format!(
+                "expected '{}', got '{}'",
+                expected,
+                t.unwrap_or("EOF")
+            )),
+        }
+    }
+
+    fn next_ident(&mut self) -> Result<&'a str, String> {
+        self.next()
+            .ok_or_else(|| "unexpected end of file".to_string())
+    }
+
+    /// skips an option, reserved or import statement, which may contain
+    /// aggregate values between braces
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(t) = self.next() {
+            match t {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                ";" if depth <= 0 => return,
+                _ => {}
+            }
+            if depth < 0 {
+                return;
+            }
+        }
+    }
+
+    fn skip_block(&mut self) {
+        let mut depth = 0;
+        while let Some(t) = self.next() {
+            match t {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// [deprecated = true, json_name = "x"]
+    fn skip_field_options(&mut self) {
+        if self.peek() == Some("[") {
+            while let Some(t) = self.next() {
+                if t == "]" {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl ParsedFiles {
+    fn parse_dir(&mut self, dir: &Path) -> Result<(), String> {
+        let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.is_dir() {
+                self.parse_dir(&path)?;
+            } else if path.extension().filter(|e| *e == "proto").is_some() {
+                self.parse_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_file(&mut self, path: &Path) -> Result<(), String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.parse_proto(&contents)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse_proto(&mut self, contents: &str) -> Result<(), String> {
+        let mut tokens = Tokens::tokenize(contents);
+        let mut package = "".to_string();
+        while let Some(t) = tokens.next() {
+            match t {
+                "package" => {
+                    package = tokens.next_ident()?.to_string();
+                    tokens.expect(";")?;
+                }
+                "message" => {
+                    let name = tokens.next_ident()?;
+                    self.parse_message(&mut tokens, &qualified_name(&package, name))?;
+                }
+                "enum" => {
+                    let name = tokens.next_ident()?;
+                    self.parse_enum(&mut tokens, &qualified_name(&package, name))?;
+                }
+                "service" => {
+                    let name = tokens.next_ident()?;
+                    self.parse_service(&mut tokens, &package, name)?;
+                }
+                "extend" => tokens.skip_block(),
+                ";" => {}
+                // syntax, import, option
+                _ => tokens.skip_statement(),
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_message(&mut self, tokens: &mut Tokens, full_name: &str) -> Result<(), String> {
+        tokens.expect("{")?;
+        let mut fields = vec![];
+        self.parse_message_body(tokens, full_name, &mut fields)?;
+        self.messages.push(ParsedMessage {
+            full_name: full_name.to_string(),
+            fields,
+        });
+        Ok(())
+    }
+
+    /// parses until the closing brace. Also used for oneof bodies,
+    /// whose fields belong to the enclosing message.
+    fn parse_message_body(
+        &mut self,
+        tokens: &mut Tokens,
+        full_name: &str,
+        fields: &mut Vec<ParsedField>,
+    ) -> Result<(), String> {
+        loop {
+            let t = tokens.next_ident()?;
+            match t {
+                "}" => return Ok(()),
+                ";" => {}
+                "message" => {
+                    let name = tokens.next_ident()?;
+                    self.parse_message(tokens, &qualified_name(full_name, name))?;
+                }
+                "enum" => {
+                    let name = tokens.next_ident()?;
+                    self.parse_enum(tokens, &qualified_name(full_name, name))?;
+                }
+                "oneof" => {
+                    tokens.next_ident()?;
+                    tokens.expect("{")?;
+                    self.parse_message_body(tokens, full_name, fields)?;
+                }
+                "option" | "reserved" | "extensions" => tokens.skip_statement(),
+                "extend" => tokens.skip_block(),
+                "map" => {
+                    // map<K, V> name = N; is a repeated message with key=1 and value=2
+                    tokens.expect("<")?;
+                    let key_type = tokens.next_ident()?;
+                    tokens.expect(",")?;
+                    let value_type = tokens.next_ident()?;
+                    tokens.expect(">")?;
+                    let name = tokens.next_ident()?;
+                    tokens.expect("=")?;
+                    let number = Self::parse_field_number(tokens)?;
+                    tokens.skip_field_options();
+                    tokens.expect(";")?;
+                    let entry_name = format!("{}Entry", Self::camel_case(name));
+                    self.messages.push(ParsedMessage {
+                        full_name: qualified_name(full_name, &entry_name),
+                        fields: vec![
+                            ParsedField {
+                                name: "key".to_string(),
+                                number: 1,
+                                type_name: key_type.to_string(),
+                            },
+                            ParsedField {
+                                name: "value".to_string(),
+                                number: 2,
+                                type_name: value_type.to_string(),
+                            },
+                        ],
+                    });
+                    fields.push(ParsedField {
+                        name: name.to_string(),
+                        number,
+                        type_name: entry_name,
+                    });
+                }
+                _ => {
+                    // [label] type name = number [options];
+                    let type_name = match t {
+                        "repeated" | "optional" | "required" => tokens.next_ident()?,
+                        _ => t,
+                    };
+                    let name = tokens.next_ident()?;
+                    tokens.expect("=")?;
+                    let number = Self::parse_field_number(tokens)?;
+                    if type_name == "group" {
+                        // proto2 groups: the name is the type, the field is lowercased
+                        self.parse_message(tokens, &qualified_name(full_name, name))?;
+                        fields.push(ParsedField {
+                            name: name.to_ascii_lowercase(),
+                            number,
+                            type_name: name.to_string(),
+                        });
+                        continue;
+                    }
+                    tokens.skip_field_options();
+                    tokens.expect(";")?;
+                    fields.push(ParsedField {
+                        name: name.to_string(),
+                        number,
+                        type_name: type_name.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn parse_field_number(tokens: &mut Tokens) -> Result<u32, String> {
+        let number = tokens.next_ident()?;
+        number
+            .parse()
+            .map_err(|_| format!("invalid field number: {}", number))
+    }
+
+    fn camel_case(name: &str) -> String {
+        let mut result = String::new();
+        let mut upper_next = true;
+        for c in name.chars() {
+            if c == '_' {
+                upper_next = true;
+            } else if upper_next {
+                result.push(c.to_ascii_uppercase());
+                upper_next = false;
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    fn parse_enum(&mut self, tokens: &mut Tokens, full_name: &str) -> Result<(), String> {
+        tokens.expect("{")?;
+        let mut values = HashMap::new();
+        loop {
+            match tokens.next_ident()? {
+                "}" => break,
+                ";" => {}
+                "option" | "reserved" => tokens.skip_statement(),
+                name => {
+                    tokens.expect("=")?;
+                    let number = tokens.next_ident()?;
+                    let number = number
+                        .parse()
+                        .map_err(|_| format!("invalid enum value: {}", number))?;
+                    tokens.skip_field_options();
+                    tokens.expect(";")?;
+                    values.insert(number, name.to_string());
+                }
+            }
+        }
+        self.enums.insert(full_name.to_string(), values);
+        Ok(())
+    }
+
+    fn parse_service(
+        &mut self,
+        tokens: &mut Tokens,
+        package: &str,
+        name: &str,
+    ) -> Result<(), String> {
+        let service_name = qualified_name(package, name);
+        tokens.expect("{")?;
+        loop {
+            match tokens.next_ident()? {
+                "}" => return Ok(()),
+                ";" => {}
+                "rpc" => {
+                    let method_name = tokens.next_ident()?;
+                    let input_type = Self::parse_rpc_type(tokens)?;
+                    tokens.expect("returns")?;
+                    let output_type = Self::parse_rpc_type(tokens)?;
+                    match tokens.next_ident()? {
+                        "{" => {
+                            // method options
+                            tokens.pos -= 1;
+                            tokens.skip_block();
+                        }
+                        ";" => {}
+                        t => return Err(format!("unexpected '{}' after rpc {}", t, method_name)),
+                    }
+                    self.methods.push(ParsedMethod {
+                        path: format!("/{}/{}", service_name, method_name),
+                        package: package.to_string(),
+                        input_type: input_type.to_string(),
+                        output_type: output_type.to_string(),
+                    });
+                }
+                _ => tokens.skip_statement(),
+            }
+        }
+    }
+
+    /// (stream Type)
+    fn parse_rpc_type<'a>(tokens: &mut Tokens<'a>) -> Result<&'a str, String> {
+        tokens.expect("(")?;
+        let mut t = tokens.next_ident()?;
+        if t == "stream" && tokens.peek() != Some(")") {
+            t = tokens.next_ident()?;
+        }
+        tokens.expect(")")?;
+        Ok(t)
+    }
+
+    /// https://developers.google.com/protocol-buffers/docs/proto3#packages_and_name_resolution
+    /// the type is searched in the innermost scope first, then in the enclosing scopes.
+    fn resolve_type_name(&self, scope: &str, type_name: &str) -> Option<String> {
+        let is_known =
+            |n: &str| self.enums.contains_key(n) || self.messages.iter().any(|m| m.full_name == n);
+        if let Some(absolute) = type_name.strip_prefix('.') {
+            return Some(absolute.to_string()).filter(|n| is_known(n));
+        }
+        let mut scope = scope;
+        loop {
+            let candidate = qualified_name(scope, type_name);
+            if is_known(&candidate) {
+                return Some(candidate);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rsplit_once('.').map(|(s, _)| s).unwrap_or("");
+        }
+    }
+
+    fn resolve(self) -> ProtoSchema {
+        let mut schema = ProtoSchema::default();
+        for msg in &self.messages {
+            let mut message_def = MessageDef::default();
+            for field in &msg.fields {
+                let field_type = match ScalarType::from_proto_name(&field.type_name) {
+                    Some(s) => FieldType::Scalar(s),
+                    None => match self.resolve_type_name(&msg.full_name, &field.type_name) {
+                        Some(n) if self.enums.contains_key(&n) => FieldType::Enum(n),
+                        Some(n) => FieldType::Message(n),
+                        // probably imported from a file we don't have
+                        None => continue,
+                    },
+                };
+                message_def.fields.insert(
+                    field.number,
+                    FieldDef {
+                        name: field.name.clone(),
+                        field_type,
+                    },
+                );
+            }
+            schema.messages.insert(msg.full_name.clone(), message_def);
+        }
+        for method in &self.methods {
+            if let (Some(input_type), Some(output_type)) = (
+                self.resolve_type_name(&method.package, &method.input_type),
+                self.resolve_type_name(&method.package, &method.output_type),
+            ) {
+                schema.methods.insert(
+                    method.path.clone(),
+                    MethodDef {
+                        input_type,
+                        output_type,
+                    },
+                );
+            }
+        }
+        schema.enums = self.enums;
+        schema
+    }
+}
+
+#[cfg(test)]
+fn test_parse_proto(contents: &str) -> ProtoSchema {
+    let mut parsed = ParsedFiles::default();
+    parsed.parse_proto(contents).unwrap();
+    parsed.resolve()
+}
+
+#[test]
+fn should_parse_proto_files() {
+    let schema = test_parse_proto(
+        r#"
+        syntax = "proto3";
+        // a comment
+        package shop.v1;
+        import "google/protobuf/timestamp.proto";
+        option java_package = "com.example.shop";
+
+        service Orders {
+            option (google.api.default_host) = "orders.example.com";
+            rpc GetOrder (GetOrderRequest) returns (Order) {
+                option (google.api.http) = { get: "/v1/orders/{id}" };
+            }
+            rpc WatchOrders (stream GetOrderRequest) returns (stream Order);
+        }
+
+        message GetOrderRequest {
+            string id = 1 [deprecated = true];
+        }
+
+        /* the order */
+        message Order {
+            enum Status {
+                UNKNOWN = 0;
+                SHIPPED = 2;
+            }
+            message Line {
+                string sku = 1;
+                uint32 quantity = 2;
+            }
+            string id = 1;
+            Status status = 2;
+            repeated Line lines = 3;
+            map<string, int64> tags = 4;
+            oneof payment {
+                string card = 5;
+                shop.v1.Order.Line free_gift = 6;
+            }
+            google.protobuf.Timestamp created = 7;
+            reserved 8, 9;
+        }
+        "#,
+    );
+    assert_eq!(
+        Some(&MethodDef {
+            input_type: "shop.v1.GetOrderRequest".to_string(),
+            output_type: "shop.v1.Order".to_string(),
+        }),
+        schema.method("/shop.v1.Orders/GetOrder")
+    );
+    assert!(schema.method("/shop.v1.Orders/WatchOrders").is_some());
+    let order = schema.message("shop.v1.Order").unwrap();
+    let field_desc = |n: u32| {
+        order
+            .fields
+            .get(&n)
+            .map(|f| (f.name.as_str(), &f.field_type))
+    };
+    assert_eq!(
+        Some((
+            "status",
+            &FieldType::Enum("shop.v1.Order.Status".to_string())
+        )),
+        field_desc(2)
+    );
+    assert_eq!(
+        Some((
+            "lines",
+            &FieldType::Message("shop.v1.Order.Line".to_string())
+        )),
+        field_desc(3)
+    );
+    assert_eq!(
+        Some((
+            "tags",
+            &FieldType::Message("shop.v1.Order.TagsEntry".to_string())
+        )),
+        field_desc(4)
+    );
+    assert_eq!(
+        Some((
+            "free_gift",
+            &FieldType::Message("shop.v1.Order.Line".to_string())
+        )),
+        field_desc(6)
+    );
+    // the imported type is unknown
+    assert_eq!(None, field_desc(7));
+    assert_eq!(
+        Some("SHIPPED"),
+        schema.enum_value_name("shop.v1.Order.Status", 2)
+    );
+}
+
+#[test]
+fn should_decode_messages_with_proto_file_names() {
+    let schema = test_parse_proto(
+        r#"
+        syntax = "proto3";
+        message Order {
+            enum Status { UNKNOWN = 0; SHIPPED = 2; }
+            message Line { string sku = 1; sint32 delta = 2; }
+            string id = 1;
+            Status status = 2;
+            repeated Line lines = 3;
+            repeated int32 counts = 4;
+        }
+        "#,
+    );
+    let mut line = protobuf::test_encode_len_field(1, b"abc");
+    line.extend(protobuf::test_encode_varint_field(2, 3)); // zigzag -2
+    let mut msg = protobuf::test_encode_len_field(1, b"o-1");
+    msg.extend(protobuf::test_encode_varint_field(2, 2));
+    msg.extend(protobuf::test_encode_len_field(3, &line));
+    msg.extend(protobuf::test_encode_len_field(4, &[1, 2]));
+    msg.extend(protobuf::test_encode_varint_field(9, 7));
+    let fields = protobuf::decode_message(&msg).unwrap();
+    assert_eq!(
+        "id: \"o-1\"\nstatus: SHIPPED\nlines {\n  sku: \"abc\"\n  delta: -2\n}\ncounts: 1\ncounts: 2\n9 (varint): 7\n",
+        protobuf::format_message(&fields, Some(&schema), Some("Order"))
+    );
+}
+
+#[test]
+fn should_read_descriptor_sets() {
+    use protobuf::{test_encode_len_field, test_encode_varint_field};
+    // FieldDescriptorProto: name=1, number=3, type=5, type_name=6
+    let mut field = test_encode_len_field(1, b"id");
+    field.extend(test_encode_varint_field(3, 1));
+    field.extend(test_encode_varint_field(5, 9));
+    let mut msg = test_encode_len_field(1, b"Req");
+    msg.extend(test_encode_len_field(2, &field));
+    let mut method = test_encode_len_field(1, b"Get");
+    method.extend(test_encode_len_field(2, b".pkg.Req"));
+    method.extend(test_encode_len_field(3, b".pkg.Req"));
+    let mut service = test_encode_len_field(1, b"Svc");
+    service.extend(test_encode_len_field(2, &method));
+    let mut file = test_encode_len_field(2, b"pkg");
+    file.extend(test_encode_len_field(4, &msg));
+    file.extend(test_encode_len_field(6, &service));
+    let schema = ProtoSchema::from_descriptor_set(&test_encode_len_field(1, &file)).unwrap();
+    assert_eq!(
+        Some(&MethodDef {
+            input_type: "pkg.Req".to_string(),
+            output_type: "pkg.Req".to_string(),
+        }),
+        schema.method("/pkg.Svc/Get")
+    );
+    assert_eq!(
+        Some(&FieldDef {
+            name: "id".to_string(),
+            field_type: FieldType::Scalar(ScalarType::String),
+        }),
+        schema.message("pkg.Req").unwrap().fields.get(&1)
+    );
+}
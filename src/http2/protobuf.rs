@@ -0,0 +1,416 @@
+// https://developers.google.com/protocol-buffers/docs/encoding
+use super::proto_schema::{FieldDef, FieldType, ProtoSchema, ScalarType};
+use std::fmt::Write;
+use std::str;
+
+// protect against stack overflows on hostile payloads
+const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireValue {
+    Varint(u64),
+    Fixed64(u64),
+    LengthDelimited(Vec<u8>),
+    Group(Vec<ProtoField>),
+    Fixed32(u32),
+}
+
+impl WireValue {
+    fn wire_type_desc(&self) -> &'static str {
+        match self {
+            WireValue::Varint(_) => "varint",
+            WireValue::Fixed64(_) => "i64",
+            WireValue::LengthDelimited(_) => "len",
+            WireValue::Group(_) => "group",
+            WireValue::Fixed32(_) => "i32",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtoField {
+    pub number: u32,
+    pub value: WireValue,
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut result = 0_u64;
+    for shift in (0..64).step_by(7) {
+        let b = *data.get(*pos)?;
+        *pos += 1;
+        result |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Some(result);
+        }
+    }
+    None
+}
+
+fn read_fixed<const N: usize>(data: &[u8], pos: &mut usize) -> Option<[u8; N]> {
+    let bytes = data.get(*pos..*pos + N)?;
+    *pos += N;
+    bytes.try_into().ok()
+}
+
+/// None if the bytes are not a valid protobuf message
+pub fn decode_message(data: &[u8]) -> Option<Vec<ProtoField>> {
+    let mut pos = 0;
+    let (fields, end_group) = decode_fields(data, &mut pos, 0)?;
+    if end_group.is_some() {
+        return None;
+    }
+    Some(fields)
+}
+
+/// decodes until the end of the data, or the end of the current group
+fn decode_fields(
+    data: &[u8],
+    pos: &mut usize,
+    depth: usize,
+) -> Option<(Vec<ProtoField>, Option<u32>)> {
+    if depth > MAX_NESTING_DEPTH {
+        return None;
+    }
+    let mut fields = vec![];
+    while *pos < data.len() {
+        let key = read_varint(data, pos)?;
+        let number = u32::try_from(key >> 3).ok().filter(|n| *n > 0)?;
+        let value = match key & 0x7 {
+            0 => WireValue::Varint(read_varint(data, pos)?),
+            1 => WireValue::Fixed64(u64::from_le_bytes(read_fixed(data, pos)?)),
+            2 => {
+                let len = usize::try_from(read_varint(data, pos)?).ok()?;
+                let bytes = data.get(*pos..pos.checked_add(len)?)?;
+                *pos += len;
+                WireValue::LengthDelimited(bytes.to_vec())
+            }
+            3 => match decode_fields(data, pos, depth + 1)? {
+                (group_fields, Some(end_number)) if end_number == number => {
+                    WireValue::Group(group_fields)
+                }
+                _ => return None,
+            },
+            4 => return Some((fields, Some(number))),
+            5 => WireValue::Fixed32(u32::from_le_bytes(read_fixed(data, pos)?)),
+            _ => return None,
+        };
+        fields.push(ProtoField { number, value });
+    }
+    Some((fields, None))
+}
+
+fn is_printable_text(bytes: &[u8]) -> Option<&str> {
+    str::from_utf8(bytes)
+        .ok()
+        .filter(|s| s.chars().all(|c| !c.is_control() || "\n\r\t".contains(c)))
+}
+
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    for &b in bytes {
+        match b {
+            b'"' => result.push_str("\\\""),
+            b'\\' => result.push_str("\\\\"),
+            b'\n' => result.push_str("\\n"),
+            b'\r' => result.push_str("\\r"),
+            b'\t' => result.push_str("\\t"),
+            0x20..=0x7e => result.push(b as char),
+            _ => {
+                let _ = write!(result, "\\{:03o}", b);
+            }
+        }
+    }
+    result
+}
+
+fn escape_str(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '"' => "\\\"".to_string(),
+            '\\' => "\\\\".to_string(),
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\t' => "\\t".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+/// a text format rendering of the message. Without a schema (or for the fields
+/// the schema doesn't know), we display the field numbers and wire types.
+pub fn format_message(
+    fields: &[ProtoField],
+    schema: Option<&ProtoSchema>,
+    message_type: Option<&str>,
+) -> String {
+    let mut result = String::new();
+    let message_def = schema.zip(message_type).and_then(|(s, t)| s.message(t));
+    format_fields(
+        &mut result,
+        fields,
+        schema.zip(message_def.map(|m| &m.fields)),
+        0,
+    );
+    result
+}
+
+fn format_fields(
+    result: &mut String,
+    fields: &[ProtoField],
+    schema_fields: Option<(&ProtoSchema, &std::collections::HashMap<u32, FieldDef>)>,
+    depth: usize,
+) {
+    for field in fields {
+        let field_def = schema_fields.and_then(|(s, f)| Some((s, f.get(&field.number)?)));
+        let formatted = field_def
+            .and_then(|(schema, def)| format_known_field(result, schema, def, &field.value, depth));
+        if formatted.is_none() {
+            format_raw_field(result, field, depth);
+        }
+    }
+}
+
+fn indent(result: &mut String, depth: usize) {
+    for _ in 0..depth {
+        result.push_str("  ");
+    }
+}
+
+fn format_raw_field(result: &mut String, field: &ProtoField, depth: usize) {
+    indent(result, depth);
+    let _ = write!(
+        result,
+        "{} ({})",
+        field.number,
+        field.value.wire_type_desc()
+    );
+    match &field.value {
+        WireValue::Varint(v) => {
+            let _ = writeln!(result, ": {}", v);
+        }
+        WireValue::Fixed64(v) => {
+            let _ = writeln!(result, ": 0x{:016x}", v);
+        }
+        WireValue::Fixed32(v) => {
+            let _ = writeln!(result, ": 0x{:08x}", v);
+        }
+        WireValue::Group(fields) => {
+            result.push_str(" {\n");
+            format_fields(result, fields, None, depth + 1);
+            indent(result, depth);
+            result.push_str("}\n");
+        }
+        WireValue::LengthDelimited(bytes) => {
+            // we can't know whether it's a string, a nested message or bytes
+            match (
+                is_printable_text(bytes),
+                Some(bytes)
+                    .filter(|b| !b.is_empty() && depth < MAX_NESTING_DEPTH)
+                    .and_then(|b| decode_message(b)),
+            ) {
+                (Some(txt), _) => {
+                    let _ = writeln!(result, ": \"{}\"", escape_str(txt));
+                }
+                (None, Some(fields)) => {
+                    result.push_str(" {\n");
+                    format_fields(result, &fields, None, depth + 1);
+                    indent(result, depth);
+                    result.push_str("}\n");
+                }
+                (None, None) => {
+                    let _ = writeln!(result, ": \"{}\"", escape_bytes(bytes));
+                }
+            }
+        }
+    }
+}
+
+/// None if the value doesn't match the field definition
+fn format_known_field(
+    result: &mut String,
+    schema: &ProtoSchema,
+    def: &FieldDef,
+    value: &WireValue,
+    depth: usize,
+) -> Option<()> {
+    match (&def.field_type, value) {
+        (FieldType::Message(type_name), WireValue::LengthDelimited(bytes))
+            if depth < MAX_NESTING_DEPTH =>
+        {
+            let fields = decode_message(bytes)?;
+            let message_def = schema.message(type_name);
+            indent(result, depth);
+            let _ = writeln!(result, "{} {{", def.name);
+            format_fields(
+                result,
+                &fields,
+                message_def.map(|m| (schema, &m.fields)),
+                depth + 1,
+            );
+            indent(result, depth);
+            result.push_str("}\n");
+        }
+        (FieldType::Message(type_name), WireValue::Group(fields)) => {
+            let message_def = schema.message(type_name);
+            indent(result, depth);
+            let _ = writeln!(result, "{} {{", def.name);
+            format_fields(
+                result,
+                fields,
+                message_def.map(|m| (schema, &m.fields)),
+                depth + 1,
+            );
+            indent(result, depth);
+            result.push_str("}\n");
+        }
+        (FieldType::Scalar(ScalarType::String), WireValue::LengthDelimited(bytes)) => {
+            indent(result, depth);
+            let _ = writeln!(
+                result,
+                "{}: \"{}\"",
+                def.name,
+                escape_str(str::from_utf8(bytes).ok()?)
+            );
+        }
+        (FieldType::Scalar(ScalarType::Bytes), WireValue::LengthDelimited(bytes)) => {
+            indent(result, depth);
+            let _ = writeln!(result, "{}: \"{}\"", def.name, escape_bytes(bytes));
+        }
+        (FieldType::Scalar(_) | FieldType::Enum(_), WireValue::LengthDelimited(bytes)) => {
+            // packed repeated field
+            let values = unpack(&def.field_type, bytes)?;
+            for v in values {
+                indent(result, depth);
+                let _ = writeln!(
+                    result,
+                    "{}: {}",
+                    def.name,
+                    format_scalar(schema, &def.field_type, &v)?
+                );
+            }
+        }
+        (field_type, v) => {
+            let formatted = format_scalar(schema, field_type, v)?;
+            indent(result, depth);
+            let _ = writeln!(result, "{}: {}", def.name, formatted);
+        }
+    }
+    Some(())
+}
+
+fn unpack(field_type: &FieldType, bytes: &[u8]) -> Option<Vec<WireValue>> {
+    let mut pos = 0;
+    let mut values = vec![];
+    while pos < bytes.len() {
+        values.push(match field_type {
+            FieldType::Scalar(ScalarType::Double | ScalarType::Fixed64 | ScalarType::Sfixed64) => {
+                WireValue::Fixed64(u64::from_le_bytes(read_fixed(bytes, &mut pos)?))
+            }
+            FieldType::Scalar(ScalarType::Float | ScalarType::Fixed32 | ScalarType::Sfixed32) => {
+                WireValue::Fixed32(u32::from_le_bytes(read_fixed(bytes, &mut pos)?))
+            }
+            _ => WireValue::Varint(read_varint(bytes, &mut pos)?),
+        });
+    }
+    Some(values)
+}
+
+fn format_scalar(
+    schema: &ProtoSchema,
+    field_type: &FieldType,
+    value: &WireValue,
+) -> Option<String> {
+    Some(match (field_type, value) {
+        (FieldType::Enum(type_name), WireValue::Varint(v)) => {
+            let number = *v as i32;
+            schema
+                .enum_value_name(type_name, number)
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| number.to_string())
+        }
+        (FieldType::Scalar(scalar), WireValue::Varint(v)) => match scalar {
+            ScalarType::Int32 => (*v as i32).to_string(),
+            ScalarType::Int64 => (*v as i64).to_string(),
+            ScalarType::Uint32 => (*v as u32).to_string(),
+            ScalarType::Uint64 => v.to_string(),
+            ScalarType::Sint32 | ScalarType::Sint64 => {
+                // zigzag encoding
+                ((*v >> 1) as i64 ^ -((*v & 1) as i64)).to_string()
+            }
+            ScalarType::Bool => (*v != 0).to_string(),
+            _ => return None,
+        },
+        (FieldType::Scalar(scalar), WireValue::Fixed64(v)) => match scalar {
+            ScalarType::Double => f64::from_bits(*v).to_string(),
+            ScalarType::Fixed64 => v.to_string(),
+            ScalarType::Sfixed64 => (*v as i64).to_string(),
+            _ => return None,
+        },
+        (FieldType::Scalar(scalar), WireValue::Fixed32(v)) => match scalar {
+            ScalarType::Float => f32::from_bits(*v).to_string(),
+            ScalarType::Fixed32 => v.to_string(),
+            ScalarType::Sfixed32 => (*v as i32).to_string(),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+pub fn test_encode_varint(mut v: u64) -> Vec<u8> {
+    let mut result = vec![];
+    loop {
+        let b = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            result.push(b);
+            return result;
+        }
+        result.push(b | 0x80);
+    }
+}
+
+#[cfg(test)]
+pub fn test_encode_len_field(number: u32, bytes: &[u8]) -> Vec<u8> {
+    let mut result = test_encode_varint(((number << 3) | 2) as u64);
+    result.extend(test_encode_varint(bytes.len() as u64));
+    result.extend_from_slice(bytes);
+    result
+}
+
+#[cfg(test)]
+pub fn test_encode_varint_field(number: u32, v: u64) -> Vec<u8> {
+    let mut result = test_encode_varint((number << 3) as u64);
+    result.extend(test_encode_varint(v));
+    result
+}
+
+#[test]
+fn should_decode_messages_without_schema() {
+    let mut nested = test_encode_varint_field(1, 150);
+    nested.extend(test_encode_len_field(2, b"\x00\xff"));
+    let mut msg = test_encode_len_field(1, b"hello \"world\"");
+    msg.extend(test_encode_len_field(2, &nested));
+    msg.extend([0x19, 1, 0, 0, 0, 0, 0, 0, 0]); // 3: fixed64
+    msg.extend([0x25, 1, 0, 0, 0]); // 4: fixed32
+    msg.extend([0x2b, 0x08, 0x01, 0x2c]); // 5: group containing 1: 1
+    let fields = decode_message(&msg).unwrap();
+    assert_eq!(
+        "1 (len): \"hello \\\"world\\\"\"\n\
+         2 (len) {\n  1 (varint): 150\n  2 (len): \"\\000\\377\"\n}\n\
+         3 (i64): 0x0000000000000001\n\
+         4 (i32): 0x00000001\n\
+         5 (group) {\n  1 (varint): 1\n}\n",
+        format_message(&fields, None, None)
+    );
+}
+
+#[test]
+fn should_reject_invalid_messages() {
+    // truncated length-delimited field
+    assert_eq!(None, decode_message(&[0x0a, 0x05, 0x61]));
+    // field number 0
+    assert_eq!(None, decode_message(&[0x00, 0x01]));
+    // unmatched end group
+    assert_eq!(None, decode_message(&[0x0c]));
+}
@@ -1,14 +1,26 @@
+use crate::http::http2_frames::Http2ControlFrame;
 use crate::tshark_communication;
 use quick_xml::events::Event;
 use std::fmt::Debug;
 use std::io::BufRead;
 
+// https://tools.ietf.org/html/rfc7540#section-6
+const FRAME_TYPE_HEADERS: u8 = 1;
+const FRAME_TYPE_RST_STREAM: u8 = 3;
+const FRAME_TYPE_SETTINGS: u8 = 4;
+const FRAME_TYPE_PUSH_PROMISE: u8 = 5;
+const FRAME_TYPE_GOAWAY: u8 = 7;
+const FRAME_TYPE_WINDOW_UPDATE: u8 = 8;
+
 #[derive(Debug)]
 pub struct TSharkHttp2Message {
     pub headers: Vec<(String, String)>,
     pub data: Option<Vec<u8>>,
     pub stream_id: u32,
     pub is_end_stream: bool,
+    // false for CONTINUATION frames, their headers belong to the previous header block
+    pub starts_header_block: bool,
+    pub control_frame: Option<Http2ControlFrame>,
 }
 
 pub fn parse_http2_info<B: BufRead>(
@@ -22,7 +34,11 @@ pub fn parse_http2_info<B: BufRead>(
                 let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
                 if name.as_deref() == Some(b"http2.stream")  {
                     let msg = parse_http2_stream(xml_reader)?;
-                    if !msg.headers.is_empty() || matches!(&msg.data, Some(v) if !v.is_empty()) {
+                    if !msg.headers.is_empty()
+                        || matches!(&msg.data, Some(v) if !v.is_empty())
+                        || msg.control_frame.is_some()
+                        || msg.starts_header_block
+                    {
                         streams.push(msg);
                     }
                 }
@@ -44,6 +60,15 @@ fn parse_http2_stream<B: BufRead>(
     let mut data = None;
     let mut stream_id = 0;
     let mut is_end_stream = false;
+    let mut frame_type = None;
+    let mut error_code = 0;
+    let mut is_ack = false;
+    let mut settings = vec![];
+    let mut cur_setting_id = None;
+    let mut last_stream_id = 0;
+    let mut debug_data = None;
+    let mut window_increment = 0;
+    let mut promised_stream_id = 0;
     let buf = &mut vec![];
     xml_event_loop!(xml_reader, buf,
         Ok(Event::Empty(ref e)) => {
@@ -60,6 +85,41 @@ fn parse_http2_stream<B: BufRead>(
                             tshark_communication::element_attr_val_number(e, b"show")?
                                 == Some(1);
                     }
+                    Some(b"http2.type") => {
+                        frame_type = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(b"http2.flags.ack.settings") => {
+                        is_ack =
+                            tshark_communication::element_attr_val_number(e, b"show")?
+                                == Some(1);
+                    }
+                    Some(b"http2.rst_stream.error") | Some(b"http2.goaway.error") => {
+                        error_code = element_number_or_default(e)?;
+                    }
+                    Some(b"http2.goaway.last_stream_id") => {
+                        last_stream_id = element_number_or_default(e)?;
+                    }
+                    Some(b"http2.goaway.addata") => {
+                        debug_data = tshark_communication::element_attr_val_string(e, b"show")?
+                            .and_then(|h| hex::decode(h.replace(':', "")).ok())
+                            .map(|d| String::from_utf8_lossy(&d).to_string())
+                            .filter(|d| !d.is_empty());
+                    }
+                    Some(b"http2.window_update.window_size_increment") => {
+                        window_increment = element_number_or_default(e)?;
+                    }
+                    Some(b"http2.push_promise.promised_stream_id") => {
+                        promised_stream_id = element_number_or_default(e)?;
+                    }
+                    Some(b"http2.settings.id") => {
+                        cur_setting_id = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(n) if n.starts_with(b"http2.settings.") => {
+                        // the value of the setting whose id we just read
+                        if let Some(id) = cur_setting_id.take() {
+                            settings.push((id, element_number_or_default(e)?));
+                        }
+                    }
                     Some(b"http2.data.data") => {
                         if let Some(data_hex) = tshark_communication::element_attr_val_string(e, b"show")? {
                             data = hex::decode(data_hex.replace(':', "")).ok();
@@ -83,11 +143,34 @@ fn parse_http2_stream<B: BufRead>(
             if e.name() == b"field" {
                 field_depth -= 1;
                 if field_depth < 0 {
+                    let control_frame = match frame_type {
+                        Some(FRAME_TYPE_RST_STREAM) => Some(Http2ControlFrame::RstStream { error_code }),
+                        Some(FRAME_TYPE_GOAWAY) => Some(Http2ControlFrame::GoAway {
+                            last_stream_id,
+                            error_code,
+                            debug_data,
+                        }),
+                        Some(FRAME_TYPE_SETTINGS) => Some(Http2ControlFrame::Settings { is_ack, settings }),
+                        Some(FRAME_TYPE_WINDOW_UPDATE) => Some(Http2ControlFrame::WindowUpdate {
+                            increment: window_increment,
+                        }),
+                        Some(FRAME_TYPE_PUSH_PROMISE) => Some(Http2ControlFrame::PushPromise {
+                            promised_stream_id,
+                            // the headers of the promised request, not of this stream
+                            headers: std::mem::take(&mut headers),
+                        }),
+                        _ => None,
+                    };
+                    // older tshark versions may not give us the frame type
+                    let starts_header_block = frame_type == Some(FRAME_TYPE_HEADERS)
+                        || (frame_type.is_none() && !headers.is_empty());
                     return Ok(TSharkHttp2Message {
                         headers,
                         data,
                         stream_id,
                         is_end_stream,
+                        starts_header_block,
+                        control_frame,
                     });
                 }
             }
@@ -95,6 +178,14 @@ fn parse_http2_stream<B: BufRead>(
     )
 }
 
+fn element_number_or_default<'a, F: std::str::FromStr + Default>(
+    e: &'a quick_xml::events::BytesStart<'a>,
+) -> Result<F, String> {
+    Ok(tshark_communication::element_attr_val_string(e, b"show")?
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default())
+}
+
 fn parse_http2_headers<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
 ) -> Result<Vec<(String, String)>, String> {
@@ -23,7 +23,8 @@ impl Icon {
     pub const PLSQL: Icon = Icon("plsql");
     pub const LOGIN: Icon = Icon("login");
     pub const COPY: Icon = Icon("copy");
-    pub const BOOKMARK: Icon = Icon("bookmark");
+    pub const BEGIN: Icon = Icon("begin");
+    pub const NOTIFY: Icon = Icon("notify");
     pub const OTHER: Icon = Icon("other");
     pub const ANGLE_DOUBLE_DOWN: Icon = Icon("angle-double-down");
     pub const SESSION: Icon = Icon("session");
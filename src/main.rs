@@ -20,6 +20,7 @@ mod widgets;
 pub mod http;
 pub mod http2;
 pub mod pgsql;
+pub mod websocket;
 
 #[macro_use]
 extern crate lazy_static;
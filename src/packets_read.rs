@@ -1,5 +1,6 @@
 use crate::config;
 use crate::config::Config;
+use crate::http::har;
 use crate::tshark_communication;
 use crate::tshark_communication::TSharkPacket;
 use crate::tshark_communication::TcpStreamId;
@@ -88,6 +89,24 @@ pub fn invoke_tshark(
     parse_pdml_stream(buf_reader, sender);
 }
 
+/// HAR files don't go through tshark, we convert them to packets ourselves
+pub fn read_har_file(fname: &Path, sender: relm::Sender<ParseInputStep>) {
+    let packets = std::fs::read_to_string(fname)
+        .map_err(|e| format!("Error reading the HAR file: {}", e))
+        .and_then(|contents| har::parse_har(&contents));
+    match packets {
+        Ok(packets) => {
+            for packet in packets {
+                sender
+                    .send(Ok(InputStep::Packet(Box::new(packet))))
+                    .unwrap();
+            }
+            sender.send(Ok(InputStep::Eof)).unwrap();
+        }
+        Err(e) => sender.send(Err(e)).unwrap(),
+    }
+}
+
 pub fn parse_pdml_stream<B: BufRead>(buf_reader: B, sender: relm::Sender<ParseInputStep>) {
     let mut xml_reader = quick_xml::Reader::from_reader(buf_reader);
     let mut buf = vec![];
@@ -1,3 +1,6 @@
+pub mod postgres_copy_data;
 pub mod postgres_details_widget;
+pub mod postgres_statement_stats;
 pub mod postgres_streams_store;
+pub mod postgres_value_decoding;
 pub mod tshark_pgsql;
@@ -0,0 +1,178 @@
+// https://www.postgresql.org/docs/12/sql-copy.html
+// we reassemble the rows of the CopyData messages, for the text and CSV
+// formats. The options (CSV, HEADER, DELIMITER, NULL) are in the COPY query.
+use regex::Regex;
+
+lazy_static! {
+    static ref COPY_COLUMNS: Regex =
+        Regex::new(r#"(?is)^\s*copy\s+[^\s(]+\s*\(([^)]*)\)"#).unwrap();
+    static ref CSV_OPTION: Regex = Regex::new(r"(?i)\bcsv\b").unwrap();
+    static ref HEADER_OPTION: Regex = Regex::new(r"(?i)\bheader\b").unwrap();
+    static ref DELIMITER_OPTION: Regex = Regex::new(r"(?i)\bdelimiter\s+(?:as\s+)?'(.)'").unwrap();
+    static ref NULL_OPTION: Regex = Regex::new(r"(?i)\bnull\s+(?:as\s+)?'([^']*)'").unwrap();
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PostgresCopyResultSet {
+    pub col_names: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+pub fn parse_copy_data(query: Option<&str>, data: &[u8]) -> PostgresCopyResultSet {
+    let query = query.unwrap_or("");
+    let contents = String::from_utf8_lossy(data);
+    let is_csv = CSV_OPTION.is_match(query);
+    let delimiter = DELIMITER_OPTION
+        .captures(query)
+        .and_then(|c| c[1].chars().next())
+        .unwrap_or(if is_csv { ',' } else { '\t' });
+    let null_str = NULL_OPTION.captures(query).map(|c| c[1].to_string());
+    let mut rows = if is_csv {
+        parse_csv_rows(&contents, delimiter, null_str.as_deref().unwrap_or(""))
+    } else {
+        parse_text_rows(&contents, delimiter, null_str.as_deref().unwrap_or("\\N"))
+    };
+    let mut col_names = COPY_COLUMNS
+        .captures(query)
+        .map(|c| {
+            c[1].split(',')
+                .map(|n| n.trim().trim_matches('"').to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if is_csv && HEADER_OPTION.is_match(query) && !rows.is_empty() {
+        let header = rows.remove(0);
+        col_names = header.into_iter().map(|h| h.unwrap_or_default()).collect();
+    }
+    let col_count = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    col_names.resize(col_count.max(col_names.len()), "Col".to_string());
+    for row in &mut rows {
+        row.resize(col_names.len(), None);
+    }
+    PostgresCopyResultSet { col_names, rows }
+}
+
+fn parse_text_rows(contents: &str, delimiter: char, null_str: &str) -> Vec<Vec<Option<String>>> {
+    contents
+        .lines()
+        // end-of-data marker, optional in the v3 protocol
+        .filter(|l| *l != "\\.")
+        .map(|line| {
+            line.split(delimiter)
+                .map(|field| {
+                    if field == null_str {
+                        None
+                    } else {
+                        Some(unescape_text_field(field))
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn unescape_text_field(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => result.push('\u{8}'),
+            Some('f') => result.push('\u{c}'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('v') => result.push('\u{b}'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// an unquoted empty field is a null, a quoted one is an empty string.
+/// quoted fields may contain the delimiter and newlines.
+fn parse_csv_rows(contents: &str, delimiter: char, null_str: &str) -> Vec<Vec<Option<String>>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut was_quoted = false;
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+    let finish_field = |field: &mut String, was_quoted: &mut bool, row: &mut Vec<_>| {
+        let val = std::mem::take(field);
+        row.push(if !*was_quoted && val == null_str {
+            None
+        } else {
+            Some(val)
+        });
+        *was_quoted = false;
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                was_quoted = true;
+            }
+            c if c == delimiter && !in_quotes => {
+                finish_field(&mut field, &mut was_quoted, &mut row);
+            }
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                finish_field(&mut field, &mut was_quoted, &mut row);
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || was_quoted || !row.is_empty() {
+        finish_field(&mut field, &mut was_quoted, &mut row);
+        rows.push(row);
+    }
+    rows
+}
+
+#[test]
+fn should_parse_text_copy_data() {
+    assert_eq!(
+        PostgresCopyResultSet {
+            col_names: vec!["id".to_string(), "name".to_string()],
+            rows: vec![
+                vec![Some("1".to_string()), Some("a\tb".to_string())],
+                vec![Some("2".to_string()), None],
+            ],
+        },
+        parse_copy_data(
+            Some("COPY items (id, name) FROM STDIN"),
+            b"1\ta\\tb\n2\t\\N\n\\.\n"
+        )
+    );
+}
+
+#[test]
+fn should_parse_csv_copy_data_with_header() {
+    assert_eq!(
+        PostgresCopyResultSet {
+            col_names: vec!["id".to_string(), "comment".to_string()],
+            rows: vec![
+                vec![
+                    Some("1".to_string()),
+                    Some("hello, \"world\"\nbye".to_string())
+                ],
+                vec![Some("2".to_string()), None],
+                vec![Some("3".to_string()), Some("".to_string())],
+            ],
+        },
+        parse_copy_data(
+            Some("copy items to stdout with (format csv, header)"),
+            b"id,comment\n1,\"hello, \"\"world\"\"\nbye\"\n2,\n3,\"\"\n"
+        )
+    );
+}
@@ -1,24 +1,33 @@
-use super::postgres_streams_store::PostgresMessageData;
-use crate::pgsql::tshark_pgsql::PostgresColType;
+use super::postgres_statement_stats;
+use super::postgres_statement_stats::{PostgresStatementSample, PostgresStatementStats};
+use super::postgres_streams_store::{PostgresLoginInfo, PostgresMessageData};
+use crate::colors;
+use crate::pgsql::tshark_pgsql::{PostgresColType, PostgresErrorInfo};
 use crate::tshark_communication::TcpStreamId;
 use crate::widgets::comm_info_header;
 use crate::widgets::comm_info_header::CommInfoHeader;
+use crate::widgets::hex_viewer;
+use crate::widgets::hex_viewer::HexViewer;
 use crate::widgets::win;
 use crate::BgFunc;
 use chrono::NaiveDateTime;
 use gtk::prelude::*;
 use itertools::Itertools;
 use regex::Regex;
-use relm::Widget;
+use relm::{ContainerWidget, Widget};
 use relm_derive::{widget, Msg};
 use std::borrow::Cow;
 use std::fs::File;
 use std::io::BufWriter;
 use std::io::Write;
+use std::iter;
 use std::net::IpAddr;
 use std::path::Path;
 use std::sync::mpsc;
 
+#[cfg(test)]
+use chrono::NaiveDate;
+
 pub struct Model {
     bg_sender: mpsc::Sender<BgFunc>,
     win_msg_sender: relm::StreamHandle<win::Msg>,
@@ -27,9 +36,13 @@ pub struct Model {
     data: PostgresMessageData,
     list_store: Option<gtk::ListStore>,
     syntax_highlight: Vec<(Regex, String)>,
+    hex_viewer: Option<relm::Component<HexViewer>>,
 
     _saved_resultset_channel: relm::Channel<Option<String>>, // None on success, or error message
     saved_resultset_sender: relm::Sender<Option<String>>,
+
+    _statement_stats_channel: relm::Channel<Vec<PostgresStatementStats>>,
+    statement_stats_sender: relm::Sender<Vec<PostgresStatementStats>>,
 }
 
 #[derive(Msg, Debug)]
@@ -41,6 +54,10 @@ pub enum Msg {
         PostgresMessageData,
     ),
     ExportResultSet,
+    DisplayStatementStats,
+    ComputeStatementStats(Vec<PostgresStatementSample>),
+    GotStatementStats(Vec<PostgresStatementStats>),
+    ResultsetRowActivated(gtk::TreePath, gtk::TreeViewColumn),
 }
 
 #[widget]
@@ -48,7 +65,7 @@ impl Widget for PostgresCommEntry {
     fn init_view(&mut self) {}
 
     fn model(
-        _relm: &relm::Relm<Self>,
+        relm: &relm::Relm<Self>,
         params: (
             TcpStreamId,
             IpAddr,
@@ -76,6 +93,12 @@ impl Widget for PostgresCommEntry {
                 }
             })
         };
+        let (_statement_stats_channel, statement_stats_sender) = {
+            let stream = relm.stream().clone();
+            relm::Channel::new(move |stats: Vec<PostgresStatementStats>| {
+                stream.emit(Msg::GotStatementStats(stats));
+            })
+        };
         Model {
             bg_sender,
             win_msg_sender,
@@ -84,9 +107,12 @@ impl Widget for PostgresCommEntry {
             client_ip,
             list_store: None,
             syntax_highlight: Self::prepare_syntax_highlight(),
+            hex_viewer: None,
 
             saved_resultset_sender,
             _saved_resultset_channel,
+            statement_stats_sender,
+            _statement_stats_channel,
         }
     }
 
@@ -225,34 +251,220 @@ impl Widget for PostgresCommEntry {
                     }
                 }
             }
+            Msg::DisplayStatementStats => {
+                // the store has the messages, it'll answer with ComputeStatementStats
+                self.model
+                    .win_msg_sender
+                    .emit(win::Msg::DisplayStatementStats(self.model.stream_id));
+            }
+            Msg::ComputeStatementStats(samples) => {
+                let s = self.model.statement_stats_sender.clone();
+                self.model
+                    .bg_sender
+                    .send(BgFunc::new(move || {
+                        s.send(postgres_statement_stats::compute_statement_stats(&samples))
+                            .unwrap()
+                    }))
+                    .unwrap();
+            }
+            Msg::GotStatementStats(stats) => {
+                self.display_statement_stats(&stats);
+            }
+            Msg::ResultsetRowActivated(path, col) => {
+                self.display_bytea_value(&path, &col);
+            }
         }
     }
 
-    fn fill_resultset(&mut self) {
-        let field_descs: Vec<_> = self
-            .model
-            .data
-            .resultset_col_types
+    /// bytea values are displayed as "\x" followed by the hex digits,
+    /// open them in a hex viewer
+    fn display_bytea_value(&mut self, path: &gtk::TreePath, col: &gtk::TreeViewColumn) {
+        let col_idx = match self
+            .widgets
+            .resultset
+            .columns()
             .iter()
-            .map(|t| match t {
-                // I'd love to "optimize" the liststore by storing ints as ints and not
-                // as strings. Sadly... https://gtk-rs.org/docs/glib/value/struct.Value.html
-                // "Some types (e.g. String and objects) support None values while others (e.g. numeric types) don't."
-                //
-                // And obviously I want to support 'null'. Therefore write all the columns as strings in the liststore.
+            .position(|c| c == col)
+        {
+            Some(idx)
+                if self.model.data.resultset_col_types.get(idx)
+                    == Some(&PostgresColType::ByteArray) =>
+            {
+                idx
+            }
+            _ => return,
+        };
+        let bytes = match self
+            .model
+            .list_store
+            .as_ref()
+            .and_then(|store| Some((store, store.iter(path)?)))
+            .and_then(|(store, iter)| store.value(&iter, col_idx as i32).get::<String>().ok())
+            .and_then(|val| hex::decode(val.strip_prefix("\\x")?).ok())
+        {
+            Some(b) => b,
+            None => return,
+        };
+        let dialog = gtk::builders::DialogBuilder::new()
+            .title(
+                self.model
+                    .data
+                    .resultset_col_names
+                    .get(col_idx)
+                    .map(|n| n.as_str())
+                    .unwrap_or("bytea"),
+            )
+            .default_width(800)
+            .default_height(500)
+            .build();
+        if let Some(parent) = self
+            .widgets
+            .resultset
+            .toplevel()
+            .and_then(|w| w.downcast::<gtk::Window>().ok())
+        {
+            dialog.set_transient_for(Some(&parent));
+        }
+        let component = dialog
+            .content_area()
+            .add_widget::<HexViewer>(self.model.win_msg_sender.clone());
+        component.emit(hex_viewer::Msg::DisplayBytes(bytes));
+        dialog.show_all();
+        self.model.hex_viewer = Some(component);
+    }
 
-                // PostgresColType::Bool => bool::static_type(),
-                // PostgresColType::Int2 | PostgresColType::Int4 => i32::static_type(),
-                // // PostgresColType::Int8 | PostgresColType::Timestamp => i64::static_type(),
-                // PostgresColType::Int8 => i64::static_type(),
-                _ => String::static_type(),
-            })
-            .collect();
-        let descs = if field_descs.is_empty() {
+    /// aggregated durations and row counts per normalized statement,
+    /// for all the streams to the server of the current message
+    fn display_statement_stats(&self, statement_stats: &[PostgresStatementStats]) {
+        let dialog = gtk::builders::DialogBuilder::new()
+            .title("Top queries")
+            .default_width(900)
+            .default_height(500)
+            .modal(true)
+            .build();
+        if let Some(parent) = self
+            .widgets
+            .resultset
+            .toplevel()
+            .and_then(|w| w.downcast::<gtk::Window>().ok())
+        {
+            dialog.set_transient_for(Some(&parent));
+        }
+        // the durations are displayed as strings with two decimals,
+        // and sorted on hidden f64 columns
+        let list_store = gtk::ListStore::new(&[
+            String::static_type(),
+            u32::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            u32::static_type(),
+            f64::static_type(),
+            f64::static_type(),
+            f64::static_type(),
+            f64::static_type(),
+        ]);
+        for stats in statement_stats {
+            list_store.insert_with_values(
+                None,
+                &[
+                    (0, &stats.statement.to_value()),
+                    (1, &(stats.count as u32).to_value()),
+                    (2, &format!("{:.2}", stats.total_duration_ms).to_value()),
+                    (3, &format!("{:.2}", stats.mean_duration_ms).to_value()),
+                    (4, &format!("{:.2}", stats.p95_duration_ms).to_value()),
+                    (5, &format!("{:.2}", stats.max_duration_ms).to_value()),
+                    (6, &(stats.total_rows as u32).to_value()),
+                    (7, &stats.total_duration_ms.to_value()),
+                    (8, &stats.mean_duration_ms.to_value()),
+                    (9, &stats.p95_duration_ms.to_value()),
+                    (10, &stats.max_duration_ms.to_value()),
+                ],
+            );
+        }
+        let tv = gtk::builders::TreeViewBuilder::new()
+            .model(&list_store)
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        for (idx, title) in [
+            "Statement",
+            "Count",
+            "Total (ms)",
+            "Mean (ms)",
+            "p95 (ms)",
+            "Max (ms)",
+            "Rows",
+        ]
+        .iter()
+        .enumerate()
+        {
+            let sort_idx = if (2..=5).contains(&idx) { idx + 5 } else { idx };
+            let col = gtk::builders::TreeViewColumnBuilder::new()
+                .title(title)
+                .resizable(true)
+                .sort_column_id(sort_idx as i32)
+                .expand(idx == 0)
+                .build();
+            let cell_r_txt = gtk::builders::CellRendererTextBuilder::new()
+                .ellipsize(if idx == 0 {
+                    pango::EllipsizeMode::End
+                } else {
+                    pango::EllipsizeMode::None
+                })
+                .build();
+            col.pack_start(&cell_r_txt, true);
+            col.add_attribute(&cell_r_txt, "text", idx as i32);
+            tv.append_column(&col);
+        }
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        scroll.add(&tv);
+        dialog.content_area().pack_start(&scroll, true, true, 0);
+        dialog.show_all();
+    }
+
+    /// the type of the hidden liststore column we sort on, for columns
+    /// where sorting on the display string would give the wrong order
+    fn sort_col_type(col_type: PostgresColType) -> Option<glib::Type> {
+        match col_type {
+            PostgresColType::Int2
+            | PostgresColType::Int4
+            | PostgresColType::Int8
+            | PostgresColType::Timestamp => Some(i64::static_type()),
+            PostgresColType::Float4 | PostgresColType::Float8 | PostgresColType::Numeric => {
+                Some(f64::static_type())
+            }
+            _ => None,
+        }
+    }
+
+    fn fill_resultset(&mut self) {
+        let col_types = &self.model.data.resultset_col_types;
+        // I'd love to "optimize" the liststore by storing ints as ints and not
+        // as strings. Sadly... https://gtk-rs.org/docs/glib/value/struct.Value.html
+        // "Some types (e.g. String and objects) support None values while others (e.g. numeric types) don't."
+        //
+        // And obviously I want to support 'null'. Therefore write all the columns as strings in the liststore,
+        // and for numeric columns add hidden typed columns after the displayed ones, for sorting.
+        // nulls are sorted first.
+        let mut sort_col_idxs = vec![];
+        let mut sort_col_descs = vec![];
+        for col_type in col_types {
+            if let Some(t) = Self::sort_col_type(*col_type) {
+                sort_col_idxs.push(Some(col_types.len() + sort_col_descs.len()));
+                sort_col_descs.push(t);
+            } else {
+                sort_col_idxs.push(None);
+            }
+        }
+        let descs = if col_types.is_empty() {
             // gtk really doesn't like if there are no columns (crashes or something like that)
             vec![String::static_type()]
         } else {
-            field_descs
+            let mut descs = vec![String::static_type(); col_types.len()];
+            descs.extend(sort_col_descs);
+            descs
         };
 
         let list_store = gtk::ListStore::new(&descs);
@@ -267,9 +479,15 @@ impl Widget for PostgresCommEntry {
             let cell_r_txt = gtk::CellRendererText::new();
             col1.pack_start(&cell_r_txt, true);
             col1.add_attribute(&cell_r_txt, "text", idx as i32);
+            col1.set_sort_column_id(sort_col_idxs.get(idx).copied().flatten().unwrap_or(idx) as i32);
             self.widgets.resultset.append_column(&col1);
         }
 
+        let set_sort_value = |iter: &gtk::TreeIter, col_idx: usize, val: &glib::Value| {
+            if let Some(sort_idx) = sort_col_idxs[col_idx] {
+                list_store.set_value(iter, sort_idx as u32, val);
+            }
+        };
         for row_idx in 0..self.model.data.resultset_row_count {
             let iter = list_store.append();
             Self::visit_resultset_row(
@@ -295,6 +513,7 @@ impl Widget for PostgresCommEntry {
                             .unwrap_or(Cow::Borrowed("null"))
                             .to_value(),
                     );
+                    set_sort_value(&iter, col_idx, &int_val.unwrap_or(i64::MIN).to_value());
                 },
                 |col_idx, str_val, _| {
                     list_store.set_value(
@@ -302,6 +521,15 @@ impl Widget for PostgresCommEntry {
                         col_idx as u32,
                         &str_val.unwrap_or("null").to_value(),
                     );
+                    // numeric is stored as a string, to preserve its precision
+                    set_sort_value(
+                        &iter,
+                        col_idx,
+                        &str_val
+                            .and_then(|s| s.parse::<f64>().ok())
+                            .unwrap_or(f64::MIN)
+                            .to_value(),
+                    );
                 },
                 |col_idx, datetime_val, _| {
                     list_store.set_value(
@@ -313,6 +541,22 @@ impl Widget for PostgresCommEntry {
                             .unwrap_or("null")
                             .to_value(),
                     );
+                    set_sort_value(
+                        &iter,
+                        col_idx,
+                        &datetime_val.map_or(i64::MIN, datetime_sort_key).to_value(),
+                    );
+                },
+                |col_idx, float_val, _| {
+                    list_store.set_value(
+                        &iter,
+                        col_idx as u32,
+                        &float_val
+                            .map(|v| Cow::Owned(v.to_string()))
+                            .unwrap_or(Cow::Borrowed("null"))
+                            .to_value(),
+                    );
+                    set_sort_value(&iter, col_idx, &float_val.unwrap_or(f64::MIN).to_value());
                 },
             );
         }
@@ -320,6 +564,7 @@ impl Widget for PostgresCommEntry {
         self.model.list_store = Some(list_store);
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn visit_resultset_row<D>(
         pg_message: &PostgresMessageData,
         row_idx: usize,
@@ -328,12 +573,14 @@ impl Widget for PostgresCommEntry {
         int_value_cb: impl Fn(usize, Option<i64>, &mut D),
         string_value_cb: impl Fn(usize, Option<&str>, &mut D),
         datetime_value_cb: impl Fn(usize, Option<NaiveDateTime>, &mut D),
+        float_value_cb: impl Fn(usize, Option<f64>, &mut D),
     ) {
         let mut bool_idx = 0;
         let mut int_idx = 0;
         let mut datetime_idx = 0;
         let mut bigint_idx = 0;
         let mut str_idx = 0;
+        let mut float_idx = 0;
         for (col_idx, col_type) in pg_message.resultset_col_types.iter().enumerate() {
             match col_type {
                 PostgresColType::Bool => {
@@ -368,6 +615,14 @@ impl Widget for PostgresCommEntry {
                     );
                     bigint_idx += 1;
                 }
+                PostgresColType::Float4 | PostgresColType::Float8 => {
+                    float_value_cb(
+                        col_idx,
+                        pg_message.resultset_float_cols[float_idx][row_idx],
+                        data,
+                    );
+                    float_idx += 1;
+                }
                 _ => {
                     string_value_cb(
                         col_idx,
@@ -428,6 +683,14 @@ impl Widget for PostgresCommEntry {
                             .unwrap_or("null"),
                     );
                 },
+                |col_idx, float_val, output| {
+                    write_separator(output, col_idx);
+                    if let Some(f) = float_val {
+                        output.push_str(&f.to_string());
+                    } else {
+                        output.push_str("null");
+                    }
+                },
             );
         }
         file.write_all(output.as_bytes())?;
@@ -435,6 +698,67 @@ impl Widget for PostgresCommEntry {
         Ok(())
     }
 
+    fn format_error_info(error_info: &PostgresErrorInfo, color: Option<&str>) -> String {
+        let mut title = format!(
+            "<b>{} {}</b>: {}",
+            glib::markup_escape_text(error_info.severity.as_deref().unwrap_or("")),
+            glib::markup_escape_text(error_info.code.as_deref().unwrap_or("")),
+            glib::markup_escape_text(error_info.message.as_deref().unwrap_or(""))
+        );
+        if let Some(c) = color {
+            title = format!("<span foreground=\"{}\">{}</span>", c, title);
+        }
+        let details = [
+            ("Detail", error_info.detail.clone()),
+            ("Hint", error_info.hint.clone()),
+            ("Position", error_info.position.map(|p| p.to_string())),
+        ];
+        iter::once(title)
+            .chain(details.iter().filter_map(|(desc, val)| {
+                val.as_ref()
+                    .map(|v| format!("<b>{}</b>: {}", desc, glib::markup_escape_text(v)))
+            }))
+            .join("\n")
+    }
+
+    fn format_login_info(login: &PostgresLoginInfo) -> String {
+        let auth_method = login.auth_method.map(|m| {
+            if login.sasl_mechanisms.is_empty() {
+                m.desc().to_string()
+            } else {
+                format!("{} ({})", m.desc(), login.sasl_mechanisms.join(", "))
+            }
+        });
+        let result = match login.authenticated {
+            Some(true) => "authenticated",
+            Some(false) => "failed",
+            None => "unknown",
+        };
+        let details = [
+            ("User", Some(login.username.clone())),
+            ("Database", Some(login.database.clone())),
+            ("Application", login.application.clone()),
+            ("Authentication", auth_method),
+            ("Result", Some(result.to_string())),
+            ("Backend PID", login.backend_pid.map(|p| p.to_string())),
+            ("Backend key", login.backend_key.map(|k| k.to_string())),
+        ];
+        let mut lines = details
+            .iter()
+            .filter_map(|(desc, val)| {
+                val.as_ref()
+                    .map(|v| format!("<b>{}</b>: {}", desc, glib::markup_escape_text(v)))
+            })
+            .collect::<Vec<_>>();
+        if login.is_cleartext_password() {
+            lines.push(format!(
+                "<span foreground=\"{}\"><b>The password was sent in cleartext</b></span>",
+                colors::ERROR_COLOR
+            ));
+        }
+        lines.join("\n")
+    }
+
     fn highlight_sql(highlight: &[(Regex, String)], query: &str) -> String {
         let result = glib::markup_escape_text(query).to_string();
         highlight.iter().fold(result, |sofar, (regex, repl)| {
@@ -450,8 +774,21 @@ impl Widget for PostgresCommEntry {
             margin_start: 10,
             margin_end: 10,
             spacing: 10,
-            #[name="comm_info_header"]
-            CommInfoHeader(self.model.client_ip.clone(), self.model.stream_id) {
+            gtk::Box {
+                orientation: gtk::Orientation::Horizontal,
+                #[name="comm_info_header"]
+                CommInfoHeader(self.model.client_ip.clone(), self.model.stream_id) {
+                },
+                gtk::Button {
+                    child: {
+                        pack_type: gtk::PackType::End,
+                    },
+                    always_show_image: true,
+                    image: Some(&gtk::Image::from_icon_name(
+                        Some("view-list-symbolic"), gtk::IconSize::Menu)),
+                    label: "Top queries...",
+                    clicked => Msg::DisplayStatementStats,
+                },
             },
             gtk::Paned {
                 orientation: gtk::Orientation::Vertical,
@@ -462,10 +799,19 @@ impl Widget for PostgresCommEntry {
                             markup: &Self::highlight_sql(
                                 &self.model.syntax_highlight,
                                 self.model.data.query.as_deref().unwrap_or("Failed retrieving the query string")),
+                            visible: self.model.data.login.is_none(),
                             line_wrap: true,
                             xalign: 0.0,
                             selectable: true,
                         },
+                        gtk::Label {
+                            markup: &self.model.data.login.as_ref()
+                                        .map(Self::format_login_info)
+                                        .unwrap_or_default(),
+                            visible: self.model.data.login.is_some(),
+                            xalign: 0.0,
+                            selectable: true,
+                        },
                         gtk::Label {
                             markup: &Itertools::intersperse(self.model.data.parameter_values
                                                     .iter()
@@ -476,6 +822,27 @@ impl Widget for PostgresCommEntry {
                             visible: !self.model.data.parameter_values.is_empty(),
                             xalign: 0.0,
                         },
+                        gtk::Label {
+                            markup: &self.model.data.error.as_ref()
+                                        .map(|e| Self::format_error_info(e, Some(colors::ERROR_COLOR)))
+                                        .unwrap_or_default(),
+                            visible: self.model.data.error.is_some(),
+                            margin_top: 10,
+                            line_wrap: true,
+                            selectable: true,
+                            xalign: 0.0,
+                        },
+                        gtk::Label {
+                            markup: &self.model.data.notices
+                                        .iter()
+                                        .map(|n| Self::format_error_info(n, None))
+                                        .join("\n\n"),
+                            visible: !self.model.data.notices.is_empty(),
+                            margin_top: 10,
+                            line_wrap: true,
+                            selectable: true,
+                            xalign: 0.0,
+                        },
                     }
                 },
                 gtk::Box {
@@ -507,7 +874,8 @@ impl Widget for PostgresCommEntry {
                         gtk::TreeView {
                             hexpand: true,
                             vexpand: true,
-                            visible: !self.model.data.resultset_row_count > 0
+                            visible: !self.model.data.resultset_row_count > 0,
+                            row_activated(_, path, col) => Msg::ResultsetRowActivated(path.clone(), col.clone()),
                         },
                     }
                 }
@@ -515,3 +883,20 @@ impl Widget for PostgresCommEntry {
         }
     }
 }
+
+/// microseconds, the precision of postgres timestamps. Nanoseconds would
+/// overflow outside of the years 1677-2262
+fn datetime_sort_key(datetime: NaiveDateTime) -> i64 {
+    datetime.timestamp() * 1_000_000 + datetime.timestamp_subsec_micros() as i64
+}
+
+#[test]
+fn should_sort_timestamps_far_from_the_epoch() {
+    let early = NaiveDate::from_ymd(1500, 6, 1).and_hms_micro(0, 0, 0, 1);
+    let now = NaiveDate::from_ymd(2021, 6, 1).and_hms_micro(0, 0, 0, 1);
+    let now_later = NaiveDate::from_ymd(2021, 6, 1).and_hms_micro(0, 0, 0, 2);
+    let far = NaiveDate::from_ymd(3000, 1, 1).and_hms(0, 0, 0);
+    assert!(datetime_sort_key(early) < datetime_sort_key(now));
+    assert!(datetime_sort_key(now) < datetime_sort_key(now_later));
+    assert!(datetime_sort_key(now_later) < datetime_sort_key(far));
+}
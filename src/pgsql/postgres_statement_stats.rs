@@ -0,0 +1,280 @@
+// aggregated statistics per normalized statement, in the spirit of
+// pg_stat_statements: literals are replaced by numbered parameters,
+// so that executions of the same statement with different values
+// are grouped together.
+use super::postgres_streams_store::PostgresMessageData;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostgresStatementStats {
+    pub statement: String,
+    pub count: usize,
+    pub total_duration_ms: f64,
+    pub mean_duration_ms: f64,
+    pub p95_duration_ms: f64,
+    pub max_duration_ms: f64,
+    pub total_rows: usize,
+}
+
+/// the data we need from a message for the statistics: we collect
+/// it on the GUI thread, and then compute the statistics in the background
+#[derive(Debug, Clone)]
+pub struct PostgresStatementSample {
+    query: String,
+    duration_ms: f64,
+    row_count: usize,
+}
+
+/// we only consider statements which completed, either through
+/// a CommandComplete or an error. That excludes logins and notifications.
+pub fn statement_samples<'a>(
+    messages: impl Iterator<Item = &'a PostgresMessageData>,
+) -> Vec<PostgresStatementSample> {
+    messages
+        .filter(|msg| msg.login.is_none() && (msg.command_tag.is_some() || msg.error.is_some()))
+        .filter_map(|msg| {
+            Some(PostgresStatementSample {
+                query: msg.query.as_deref()?.to_string(),
+                duration_ms: (msg.result_timestamp - msg.query_timestamp)
+                    .num_microseconds()
+                    .unwrap_or(0) as f64
+                    / 1000.0,
+                row_count: msg.resultset_row_count,
+            })
+        })
+        .collect()
+}
+
+pub fn compute_statement_stats(samples: &[PostgresStatementSample]) -> Vec<PostgresStatementStats> {
+    let mut by_statement: HashMap<String, (Vec<f64>, usize)> = HashMap::new();
+    for sample in samples {
+        let entry = by_statement
+            .entry(normalize_query(&sample.query))
+            .or_default();
+        entry.0.push(sample.duration_ms);
+        entry.1 += sample.row_count;
+    }
+    let mut result: Vec<_> = by_statement
+        .into_iter()
+        .map(|(statement, (mut durations, total_rows))| {
+            durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let count = durations.len();
+            let total_duration_ms = durations.iter().sum();
+            // nearest-rank percentile
+            let p95_idx = ((count as f64 * 0.95).ceil() as usize).max(1) - 1;
+            PostgresStatementStats {
+                statement,
+                count,
+                total_duration_ms,
+                mean_duration_ms: total_duration_ms / count as f64,
+                p95_duration_ms: durations[p95_idx],
+                max_duration_ms: durations[count - 1],
+                total_rows,
+            }
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        b.total_duration_ms
+            .partial_cmp(&a.total_duration_ms)
+            .unwrap()
+            .then_with(|| a.statement.cmp(&b.statement))
+    });
+    result
+}
+
+/// replace string, numeric and dollar-quoted literals by numbered
+/// parameters, continuing after the parameters already present in
+/// the query, and collapse whitespace.
+pub fn normalize_query(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut next_param = max_param_number(&chars) + 1;
+    let mut result = String::with_capacity(query.len());
+    let mut idx = 0;
+    let mut push_param = |result: &mut String| {
+        result.push_str(&format!("${}", next_param));
+        next_param += 1;
+    };
+    while idx < chars.len() {
+        let c = chars[idx];
+        let prev_is_ident = idx > 0 && is_ident_char(chars[idx - 1]);
+        match c {
+            '\'' => {
+                // E'' strings support backslash escapes. drop the E we already pushed
+                let is_escape_string = idx > 0
+                    && matches!(chars[idx - 1], 'e' | 'E')
+                    && (idx < 2 || !is_ident_char(chars[idx - 2]));
+                if is_escape_string {
+                    result.pop();
+                }
+                idx = skip_string_literal(&chars, idx, is_escape_string);
+                push_param(&mut result);
+                continue;
+            }
+            '"' => {
+                // quoted identifier, keep it
+                let end = skip_until(&chars, idx + 1, '"');
+                result.extend(&chars[idx..end]);
+                idx = end;
+                continue;
+            }
+            '$' if !prev_is_ident => {
+                if let Some(end) = skip_dollar_quoted(&chars, idx) {
+                    idx = end;
+                    push_param(&mut result);
+                    continue;
+                }
+                // an existing parameter, $1
+                result.push(c);
+                idx += 1;
+                while idx < chars.len() && chars[idx].is_ascii_digit() {
+                    result.push(chars[idx]);
+                    idx += 1;
+                }
+                continue;
+            }
+            c if c.is_ascii_digit() && !prev_is_ident => {
+                while idx < chars.len() && (chars[idx].is_ascii_alphanumeric() || chars[idx] == '.')
+                {
+                    idx += 1;
+                }
+                push_param(&mut result);
+                continue;
+            }
+            c if c.is_whitespace() => {
+                while idx < chars.len() && chars[idx].is_whitespace() {
+                    idx += 1;
+                }
+                if !result.is_empty() && idx < chars.len() {
+                    result.push(' ');
+                }
+                continue;
+            }
+            _ => result.push(c),
+        }
+        idx += 1;
+    }
+    result
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn max_param_number(chars: &[char]) -> usize {
+    let mut max = 0;
+    let mut idx = 0;
+    while idx < chars.len() {
+        if chars[idx] == '$' && (idx == 0 || !is_ident_char(chars[idx - 1])) {
+            let digits: String = chars[idx + 1..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            if let Ok(n) = digits.parse::<usize>() {
+                max = max.max(n);
+            }
+        }
+        idx += 1;
+    }
+    max
+}
+
+/// returns the index after the closing quote. Doubled quotes are escapes.
+fn skip_string_literal(chars: &[char], start: usize, backslash_escapes: bool) -> usize {
+    let mut idx = start + 1;
+    while idx < chars.len() {
+        if chars[idx] == '\'' {
+            if chars.get(idx + 1) == Some(&'\'') {
+                idx += 2;
+                continue;
+            }
+            return idx + 1;
+        }
+        if backslash_escapes && chars[idx] == '\\' {
+            idx += 1;
+        }
+        idx += 1;
+    }
+    chars.len()
+}
+
+fn skip_until(chars: &[char], start: usize, end_char: char) -> usize {
+    chars[start..]
+        .iter()
+        .position(|c| *c == end_char)
+        .map_or(chars.len(), |p| start + p + 1)
+}
+
+/// $$...$$ or $tag$...$tag$, returns the index after the closing tag
+fn skip_dollar_quoted(chars: &[char], start: usize) -> Option<usize> {
+    let tag_end = start
+        + 1
+        + chars[start + 1..]
+            .iter()
+            .position(|c| !(c.is_alphanumeric() || *c == '_'))?;
+    if chars[tag_end] != '$' || chars[start + 1..tag_end].iter().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let tag = &chars[start..=tag_end];
+    let mut idx = tag_end + 1;
+    while idx + tag.len() <= chars.len() {
+        if &chars[idx..idx + tag.len()] == tag {
+            return Some(idx + tag.len());
+        }
+        idx += 1;
+    }
+    Some(chars.len())
+}
+
+#[test]
+fn should_normalize_queries() {
+    assert_eq!(
+        "select * from items where id = $1 and name = $2 and price > $3",
+        normalize_query("select *\n  from items where id = 42 and name = 'it''s' and price > 1.5")
+    );
+    assert_eq!(
+        "update t2 set a = $2, b = $3 where id = $1",
+        normalize_query("update t2 set a = E'x\\'y', b = $$body$$ where id = $1")
+    );
+    assert_eq!(
+        "select \"col 1\" from t where x = $1",
+        normalize_query("select \"col 1\" from t where x = $tag$a$tag$")
+    );
+}
+
+#[test]
+fn should_aggregate_durations_of_queries_differing_in_literals() {
+    let sample = |query: &str, duration_ms, row_count| PostgresStatementSample {
+        query: query.to_string(),
+        duration_ms,
+        row_count,
+    };
+    let stats = compute_statement_stats(&[
+        sample("select * from t where id = 1", 10.0, 1),
+        sample("select * from t where name = 'bob'", 5.0, 0),
+        sample("select *\n from t where id = 22", 30.0, 2),
+        sample("select * from t where name = 'it''s'", 7.0, 1),
+    ]);
+    assert_eq!(
+        vec![
+            PostgresStatementStats {
+                statement: "select * from t where id = $1".to_string(),
+                count: 2,
+                total_duration_ms: 40.0,
+                mean_duration_ms: 20.0,
+                p95_duration_ms: 30.0,
+                max_duration_ms: 30.0,
+                total_rows: 3,
+            },
+            PostgresStatementStats {
+                statement: "select * from t where name = $1".to_string(),
+                count: 2,
+                total_duration_ms: 12.0,
+                mean_duration_ms: 6.0,
+                p95_duration_ms: 7.0,
+                max_duration_ms: 7.0,
+                total_rows: 1,
+            }
+        ],
+        stats
+    );
+}
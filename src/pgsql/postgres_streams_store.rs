@@ -1,10 +1,16 @@
+use super::postgres_copy_data;
 use super::postgres_details_widget;
 use super::postgres_details_widget::PostgresCommEntry;
+use super::postgres_statement_stats;
+use super::postgres_value_decoding;
 use crate::colors;
 use crate::custom_streams_store;
 use crate::custom_streams_store::{ClientServerInfo, CustomStreamsStore};
 use crate::icons::Icon;
-use crate::pgsql::tshark_pgsql::{PostgresColType, PostgresWireMessage};
+use crate::pgsql::tshark_pgsql::{
+    PostgresAuthRequest, PostgresColType, PostgresErrorInfo, PostgresFormat,
+    PostgresTransactionState, PostgresWireMessage,
+};
 use crate::search_expr;
 use crate::search_expr::SearchCriteria;
 use crate::tshark_communication::{TSharkPacket, TcpStreamId};
@@ -13,9 +19,11 @@ use crate::BgFunc;
 use chrono::{NaiveDateTime, Utc};
 use gtk::prelude::*;
 use relm::ContainerWidget;
+use std::any::Any;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::iter;
+use std::ops::Range;
 use std::str;
 use std::str::FromStr;
 use std::sync::mpsc;
@@ -34,6 +42,27 @@ pub struct PostgresStreamData {
     pub client_server: Option<ClientServerInfo>,
     pub messages: Vec<PostgresMessageData>,
     pub summary_details: Option<String>,
+    pub transaction_tracker: PostgresTransactionTracker,
+    databases: Vec<String>,
+    // the subset of the ParameterStatus values that we show in the summary
+    server_parameters: HashMap<&'static str, String>,
+}
+
+const SUMMARY_SERVER_PARAMETERS: [&str; 3] = ["server_version", "TimeZone", "application_name"];
+
+// COPY operations can transfer gigabytes, we only keep the beginning
+const MAX_COPY_DATA_BYTES: usize = 4 * 1024 * 1024;
+
+/// we follow transactions through the transaction status of the
+/// ReadyForQuery messages: 'I'dle, in 'T'ransaction, or 'E'rror
+#[derive(Default)]
+pub struct PostgresTransactionTracker {
+    transaction_count: u32,
+    // index of the first message of the transaction in progress
+    cur_transaction_start_idx: Option<usize>,
+    cur_transaction_failed: bool,
+    // messages from that index on were emitted since the last ReadyForQuery
+    message_count_at_ready: usize,
 }
 
 #[derive(Default)]
@@ -55,13 +84,25 @@ impl PostgresStreamsStore {
 }
 
 #[derive(EnumString, EnumVariantNames)]
-enum PostgresFilterKeys {
+enum PostgresNumericFilterKeys {
+    #[strum(serialize = "pg.rows_affected")]
+    RowsAffected,
+}
+
+#[derive(EnumString, EnumVariantNames)]
+enum PostgresStringFilterKeys {
     #[strum(serialize = "pg.query")]
     QueryString,
     #[strum(serialize = "pg.resultset")]
     ResultSet,
     #[strum(serialize = "pg.query_param")]
     QueryParamValue,
+    #[strum(serialize = "pg.error")]
+    Error,
+    #[strum(serialize = "pg.sqlstate")]
+    SqlState,
+    #[strum(serialize = "pg.transaction_status")]
+    TransactionStatus,
 }
 
 fn get_pg_message<'a, 'b>(
@@ -114,6 +155,14 @@ impl CustomStreamsStore for PostgresStreamsStore {
             .and_then(|s| s.summary_details.as_deref())
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn stream_client_server(&self, stream_id: TcpStreamId) -> Option<ClientServerInfo> {
         self.streams.get(&stream_id).and_then(|s| s.client_server)
     }
@@ -140,15 +189,9 @@ impl CustomStreamsStore for PostgresStreamsStore {
                         database: Some(ref database),
                         application,
                     } => {
-                        match stream.summary_details.as_ref() {
-                            None => {
-                                stream.summary_details = Some(database.to_string());
-                            }
-                            Some(other_db) if !other_db.contains(database) => {
-                                stream.summary_details =
-                                    Some(format!("{}, {}", other_db, database));
-                            }
-                            _ => {}
+                        if !stream.databases.contains(database) {
+                            stream.databases.push(database.to_string());
+                            refresh_summary_details(stream);
                         }
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
@@ -157,13 +200,9 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 server_port: new_packet.basic_info.port_dst,
                             });
                         }
+                        stream.stream_globals.cur_login_idx = Some(stream.messages.len());
                         stream.messages.push(PostgresMessageData {
-                            query: Some(Cow::Owned(format!(
-                                "LOGIN: user: {}, db: {}, app: {}",
-                                username,
-                                database,
-                                application.as_deref().unwrap_or("-")
-                            ))),
+                            query: None,
                             query_timestamp: timestamp,
                             result_timestamp: timestamp,
                             parameter_values: vec![],
@@ -174,7 +213,23 @@ impl CustomStreamsStore for PostgresStreamsStore {
                             resultset_bool_cols: vec![],
                             resultset_string_cols: vec![],
                             resultset_datetime_cols: vec![],
+                            resultset_float_cols: vec![],
                             resultset_col_types: vec![],
+                            error: None,
+                            notices: vec![],
+                            command_tag: None,
+                            rows_affected: None,
+                            transaction: None,
+                            login: Some(PostgresLoginInfo {
+                                username: username.clone(),
+                                database: database.clone(),
+                                application,
+                                auth_method: None,
+                                sasl_mechanisms: vec![],
+                                authenticated: None,
+                                backend_pid: None,
+                                backend_key: None,
+                            }),
                         });
                     }
                     PostgresWireMessage::Startup { .. } => {
@@ -209,7 +264,9 @@ impl CustomStreamsStore for PostgresStreamsStore {
                     }
                     PostgresWireMessage::Bind {
                         statement,
+                        parameter_formats,
                         parameter_lengths_and_vals,
+                        result_formats,
                     } => {
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
@@ -242,12 +299,21 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                     .iter()
                                     .chain(iter::repeat(&PostgresColType::Unknown)),
                             )
-                            .map(|((length, val), typ)| (*typ, decode_param(*typ, val, *length)))
+                            .enumerate()
+                            .map(|(idx, ((length, val), typ))| {
+                                let format = format_for_idx(&parameter_formats, idx);
+                                (*typ, decode_param(*typ, format, val, *length))
+                            })
                             .collect();
+                        // the RowDescription we may have received before the Bind,
+                        // in response to a statement Describe, doesn't know the
+                        // result formats yet. The ones from the Bind are authoritative.
+                        stream.stream_globals.cur_col_formats = result_formats;
                     }
                     PostgresWireMessage::RowDescription {
                         col_names,
                         col_types,
+                        col_formats,
                     } => {
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
@@ -258,6 +324,9 @@ impl CustomStreamsStore for PostgresStreamsStore {
                         }
                         stream.stream_globals.cur_col_names = col_names;
                         stream.stream_globals.cur_col_types = col_types;
+                        if !stream.stream_globals.was_bind {
+                            stream.stream_globals.cur_col_formats = col_formats;
+                        }
                         for col_type in &stream.stream_globals.cur_col_types {
                             match col_type {
                                 PostgresColType::Bool => {
@@ -272,6 +341,9 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 PostgresColType::Int8 => {
                                     stream.stream_globals.cur_rs_bigint_cols.push(vec![]);
                                 }
+                                PostgresColType::Float4 | PostgresColType::Float8 => {
+                                    stream.stream_globals.cur_rs_float_cols.push(vec![]);
+                                }
                                 _ => {
                                     stream.stream_globals.cur_rs_string_cols.push(vec![]);
                                 }
@@ -296,7 +368,52 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 .collect(),
                         )?;
                     }
-                    PostgresWireMessage::ReadyForQuery => {
+                    PostgresWireMessage::SimpleQuery { query } => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_dst,
+                                client_ip: new_packet.basic_info.ip_src,
+                                server_port: new_packet.basic_info.port_dst,
+                            });
+                        }
+                        stream.stream_globals.was_simple_query = true;
+                        stream.stream_globals.query_timestamp = Some(timestamp);
+                        stream.stream_globals.simple_query_statements = query
+                            .as_deref()
+                            .map(split_simple_query_statements)
+                            .unwrap_or_default();
+                        stream.stream_globals.cur_query_with_fallback = query;
+                    }
+                    PostgresWireMessage::CommandComplete { tag } => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
+                            });
+                        }
+                        // for the extended query protocol we emit the message on
+                        // ReadyForQuery, but a simple query can contain multiple
+                        // statements, each one with its own resultset.
+                        let globals = &mut stream.stream_globals;
+                        globals.cur_command_tag = tag;
+                        if globals.was_simple_query {
+                            let query = simple_query_cur_statement(globals);
+                            let query_timestamp = globals.query_timestamp.unwrap_or(timestamp);
+                            stream.messages.push(take_current_resultset(
+                                globals,
+                                query,
+                                query_timestamp,
+                                timestamp,
+                            ));
+                            globals.simple_query_completed_count += 1;
+                            // the next statement starts when this one completes
+                            globals.query_timestamp = Some(timestamp);
+                        }
+                    }
+                    PostgresWireMessage::ReadyForQuery { transaction_state } => {
+                        // the login is over, if it was in progress
+                        stream.stream_globals.cur_login_idx = None;
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
                                 server_ip: new_packet.basic_info.ip_src,
@@ -306,26 +423,127 @@ impl CustomStreamsStore for PostgresStreamsStore {
                         }
 
                         // reset all the globals, but keep known_statements
-                        let globals = std::mem::take(&mut stream.stream_globals);
-                        stream.stream_globals.known_statements = globals.known_statements;
-                        if globals.was_bind {
-                            stream.messages.push(PostgresMessageData {
-                                query: globals.cur_query_with_fallback.map(Cow::Owned),
-                                query_timestamp: globals.query_timestamp.unwrap(), // know it was populated since was_bind is true
-                                result_timestamp: timestamp,
-                                parameter_values: globals.cur_parameter_values,
-                                resultset_col_names: globals.cur_col_names,
-                                resultset_row_count: globals.cur_rs_row_count,
-                                resultset_bool_cols: globals.cur_rs_bool_cols,
-                                resultset_string_cols: globals.cur_rs_string_cols,
-                                resultset_int_cols: globals.cur_rs_int_cols,
-                                resultset_bigint_cols: globals.cur_rs_bigint_cols,
-                                resultset_datetime_cols: globals.cur_rs_datetime_cols,
-                                resultset_col_types: globals.cur_col_types,
+                        let mut globals = std::mem::take(&mut stream.stream_globals);
+                        stream.stream_globals.known_statements =
+                            std::mem::take(&mut globals.known_statements);
+                        if globals.was_simple_query
+                            && (globals.simple_query_completed_count == 0
+                                || globals.cur_error.is_some())
+                        {
+                            // a statement which failed, or a simple query with no CommandComplete
+                            // at all (empty query for instance): no message was emitted yet
+                            let query = simple_query_cur_statement(&globals);
+                            let query_timestamp = globals.query_timestamp.unwrap_or(timestamp);
+                            stream.messages.push(take_current_resultset(
+                                &mut globals,
+                                query,
+                                query_timestamp,
+                                timestamp,
+                            ));
+                        } else if globals.was_bind
+                            || globals.cur_error.is_some()
+                            || globals.is_unknown_copy
+                        {
+                            let query = globals.cur_query_with_fallback.take();
+                            let query_timestamp = globals.query_timestamp.unwrap_or(timestamp);
+                            stream.messages.push(take_current_resultset(
+                                &mut globals,
+                                query,
+                                query_timestamp,
+                                timestamp,
+                            ));
+                        }
+                        if let Some(state) = transaction_state {
+                            update_transactions(stream, state);
+                        }
+                        stream.transaction_tracker.message_count_at_ready = stream.messages.len();
+                    }
+                    PostgresWireMessage::ErrorResponse(error_info) => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
+                            });
+                        }
+                        match cur_login_message(stream) {
+                            Some(login_msg)
+                                if login_msg.login.as_ref().and_then(|l| l.authenticated)
+                                    != Some(true) =>
+                            {
+                                // the authentication failed
+                                if let Some(login) = login_msg.login.as_mut() {
+                                    login.authenticated = Some(false);
+                                }
+                                login_msg.error = Some(error_info);
+                                login_msg.result_timestamp = timestamp;
+                                stream.stream_globals.cur_login_idx = None;
+                            }
+                            _ => {
+                                stream.stream_globals.cur_error = Some(error_info);
+                            }
+                        }
+                    }
+                    PostgresWireMessage::NoticeResponse(notice_info) => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
+                            });
+                        }
+                        stream.stream_globals.cur_notices.push(notice_info);
+                    }
+                    PostgresWireMessage::AuthenticationRequest {
+                        request,
+                        sasl_mechanisms,
+                    } => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
                             });
                         }
+                        if let Some(login_msg) = cur_login_message(stream) {
+                            login_msg.result_timestamp = timestamp;
+                            if let Some(login) = login_msg.login.as_mut() {
+                                match request {
+                                    PostgresAuthRequest::Ok => login.authenticated = Some(true),
+                                    PostgresAuthRequest::SaslContinue
+                                    | PostgresAuthRequest::SaslFinal => {}
+                                    _ => login.auth_method = Some(request),
+                                }
+                                if !sasl_mechanisms.is_empty() {
+                                    login.sasl_mechanisms = sasl_mechanisms;
+                                }
+                            }
+                        }
                     }
-                    PostgresWireMessage::CopyData => {
+                    PostgresWireMessage::BackendKeyData { pid, key } => {
+                        if let Some(login) =
+                            cur_login_message(stream).and_then(|m| m.login.as_mut())
+                        {
+                            login.backend_pid = pid;
+                            login.backend_key = key;
+                        }
+                    }
+                    PostgresWireMessage::ParameterStatus {
+                        name: Some(name),
+                        value: Some(value),
+                    } => {
+                        if let Some(param) = SUMMARY_SERVER_PARAMETERS.iter().find(|p| **p == name)
+                        {
+                            stream.server_parameters.insert(*param, value);
+                            refresh_summary_details(stream);
+                        }
+                    }
+                    PostgresWireMessage::ParameterStatus { .. } => {}
+                    PostgresWireMessage::NotificationResponse {
+                        pid,
+                        channel,
+                        payload,
+                    } => {
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
                                 server_ip: new_packet.basic_info.ip_src,
@@ -334,7 +552,12 @@ impl CustomStreamsStore for PostgresStreamsStore {
                             });
                         }
                         stream.messages.push(PostgresMessageData {
-                            query: Some(Cow::Borrowed("COPY DATA")),
+                            query: Some(Cow::Owned(format!(
+                                "NOTIFICATION: channel: {}, payload: {}, pid: {}",
+                                channel.as_deref().unwrap_or("-"),
+                                payload.as_deref().unwrap_or("-"),
+                                pid.map_or_else(|| "-".to_string(), |p| p.to_string())
+                            ))),
                             query_timestamp: timestamp,
                             result_timestamp: timestamp,
                             parameter_values: vec![],
@@ -345,9 +568,43 @@ impl CustomStreamsStore for PostgresStreamsStore {
                             resultset_bool_cols: vec![],
                             resultset_string_cols: vec![],
                             resultset_datetime_cols: vec![],
+                            resultset_float_cols: vec![],
                             resultset_col_types: vec![],
+                            error: None,
+                            notices: vec![],
+                            command_tag: None,
+                            rows_affected: None,
+                            transaction: None,
+                            login: None,
                         });
                     }
+                    PostgresWireMessage::CopyInResponse { format }
+                    | PostgresWireMessage::CopyOutResponse { format } => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
+                            });
+                        }
+                        stream.stream_globals.copy_format = Some(format);
+                        stream.stream_globals.copy_data.clear();
+                        stream.stream_globals.copy_data_len = 0;
+                    }
+                    PostgresWireMessage::CopyData { data } => {
+                        // the data can come from the client (COPY FROM STDIN)
+                        // or from the server (COPY TO STDOUT)
+                        add_copy_data(&mut stream.stream_globals, &data);
+                    }
+                    PostgresWireMessage::CopyDone => {
+                        finish_copy(&mut stream.stream_globals);
+                    }
+                    PostgresWireMessage::CopyFail => {
+                        stream.stream_globals.copy_format = None;
+                        stream.stream_globals.copy_data.clear();
+                        stream.stream_globals.copy_data_len = 0;
+                        stream.stream_globals.is_unknown_copy = false;
+                    }
                 }
             }
         }
@@ -410,8 +667,19 @@ impl CustomStreamsStore for PostgresStreamsStore {
         let cell_r_txt = gtk::builders::CellRendererTextBuilder::new().build();
         result_col.pack_start(&cell_r_txt, true);
         result_col.add_attribute(&cell_r_txt, "text", 1);
+        result_col.add_attribute(&cell_r_txt, "foreground", 11);
         tv.append_column(&result_col);
 
+        let command_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Command")
+            .resizable(true)
+            .sort_column_id(13)
+            .build();
+        let cell_c_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        command_col.pack_start(&cell_c_txt, true);
+        command_col.add_attribute(&cell_c_txt, "text", 12);
+        tv.append_column(&command_col);
+
         let duration_col = gtk::builders::TreeViewColumnBuilder::new()
             .title("Duration")
             .resizable(true)
@@ -421,6 +689,17 @@ impl CustomStreamsStore for PostgresStreamsStore {
         duration_col.pack_start(&cell_d_txt, true);
         duration_col.add_attribute(&cell_d_txt, "text", 7);
         tv.append_column(&duration_col);
+
+        let transaction_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Transaction")
+            .resizable(true)
+            .sort_column_id(16)
+            .build();
+        let cell_tx_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        transaction_col.pack_start(&cell_tx_txt, true);
+        transaction_col.add_attribute(&cell_tx_txt, "text", 14);
+        transaction_col.add_attribute(&cell_tx_txt, "foreground", 15);
+        tv.append_column(&transaction_col);
     }
 
     fn get_empty_liststore(&self) -> gtk::ListStore {
@@ -436,6 +715,12 @@ impl CustomStreamsStore for PostgresStreamsStore {
             i64::static_type(),    // number of rows, for sorting
             String::static_type(), // query type: update, insert..
             String::static_type(), // stream color
+            String::static_type(), // result color (errors)
+            String::static_type(), // command tag (INSERT 0 5, UPDATE 12..)
+            i64::static_type(),    // rows affected, for sorting
+            String::static_type(), // transaction
+            String::static_type(), // transaction color (failed transactions)
+            i64::static_type(),    // transaction id, for sorting
         ])
     }
 
@@ -447,25 +732,19 @@ impl CustomStreamsStore for PostgresStreamsStore {
         item_count: usize,
     ) {
         let messages = &self.streams.get(&session_id).unwrap().messages;
+        refresh_transaction_rows(
+            ls,
+            session_id,
+            messages,
+            ended_transaction_rows_to_refresh(messages, start_idx),
+        );
         // println!("adding {} rows", messages.len());
         for (idx, postgres) in messages.iter().skip(start_idx).take(item_count).enumerate() {
             ls.insert_with_values(
                 None,
                 &[
-                    (
-                        0,
-                        &postgres
-                            .query
-                            .as_deref()
-                            .map(|q| if q.len() > 250 { &q[..250] } else { q })
-                            .unwrap_or("couldn't get query")
-                            .replace("\n", "")
-                            .to_value(),
-                    ),
-                    (
-                        1,
-                        &format!("{} rows", postgres.resultset_row_count).to_value(),
-                    ),
+                    (0, &get_message_desc(postgres).to_value()),
+                    (1, &get_result_desc(postgres).to_value()),
                     (
                         custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX,
                         &session_id.as_u32().to_value(),
@@ -492,13 +771,52 @@ impl CustomStreamsStore for PostgresStreamsStore {
                         .to_value(),
                     ),
                     (8, &(postgres.resultset_row_count as u32).to_value()),
-                    (9, &get_query_type_desc(&postgres.query).to_value()),
+                    (
+                        9,
+                        &postgres
+                            .command_tag
+                            .as_deref()
+                            .and_then(get_command_tag_type_desc)
+                            .or_else(|| postgres.login.as_ref().map(|_| "login"))
+                            .unwrap_or_else(|| get_query_type_desc(&postgres.query))
+                            .to_value(),
+                    ),
                     (
                         10,
                         &colors::STREAM_COLORS
                             [session_id.as_u32() as usize % colors::STREAM_COLORS.len()]
                         .to_value(),
                     ),
+                    (
+                        11,
+                        &if postgres.error.is_some()
+                            || postgres
+                                .login
+                                .as_ref()
+                                .map_or(false, |l| l.is_cleartext_password())
+                        {
+                            colors::ERROR_COLOR.to_value()
+                        } else {
+                            None::<&str>.to_value()
+                        },
+                    ),
+                    (
+                        12,
+                        &postgres.command_tag.as_deref().unwrap_or("").to_value(),
+                    ),
+                    (
+                        13,
+                        &postgres.rows_affected.map_or(-1, |r| r as i64).to_value(),
+                    ),
+                    (
+                        14,
+                        &get_transaction_desc(messages, start_idx + idx).to_value(),
+                    ),
+                    (15, &get_transaction_color(postgres).to_value()),
+                    (
+                        16,
+                        &postgres.transaction.map_or(-1, |t| t.id as i64).to_value(),
+                    ),
                 ],
             );
         }
@@ -511,11 +829,11 @@ impl CustomStreamsStore for PostgresStreamsStore {
     }
 
     fn supported_string_filter_keys(&self) -> &'static [&'static str] {
-        PostgresFilterKeys::VARIANTS
+        PostgresStringFilterKeys::VARIANTS
     }
 
     fn supported_numeric_filter_keys(&self) -> &'static [&'static str] {
-        &[]
+        PostgresNumericFilterKeys::VARIANTS
     }
 
     fn matches_filter(
@@ -525,15 +843,19 @@ impl CustomStreamsStore for PostgresStreamsStore {
         iter: &gtk::TreeIter,
     ) -> bool {
         let streams = &self.streams;
-        match (PostgresFilterKeys::from_str(filter.filter_key), &filter.op) {
-            (Ok(filter_key), SearchCriteria::Contains(filter_val)) => match filter_key {
-                PostgresFilterKeys::QueryString => model
+        match (
+            PostgresStringFilterKeys::from_str(filter.filter_key),
+            PostgresNumericFilterKeys::from_str(filter.filter_key),
+            &filter.op,
+        ) {
+            (Ok(filter_key), _, SearchCriteria::Contains(filter_val)) => match filter_key {
+                PostgresStringFilterKeys::QueryString => model
                     .value(iter, 0)
                     .get::<&str>()
                     .unwrap()
                     .to_lowercase()
                     .contains(&filter_val.to_lowercase()),
-                PostgresFilterKeys::ResultSet => {
+                PostgresStringFilterKeys::ResultSet => {
                     let fv = filter_val.to_lowercase();
                     get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
                         pg_msg.resultset_string_cols.iter().any(|v| {
@@ -543,7 +865,7 @@ impl CustomStreamsStore for PostgresStreamsStore {
                         })
                     })
                 }
-                PostgresFilterKeys::QueryParamValue => {
+                PostgresStringFilterKeys::QueryParamValue => {
                     let fv = filter_val.to_lowercase();
                     get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
                         pg_msg
@@ -552,7 +874,49 @@ impl CustomStreamsStore for PostgresStreamsStore {
                             .any(|(_type, v)| v.to_lowercase().contains(&fv))
                     })
                 }
+                PostgresStringFilterKeys::Error => {
+                    let fv = filter_val.to_lowercase();
+                    get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
+                        pg_msg.error.as_ref().map_or(false, |e| {
+                            [&e.message, &e.detail, &e.hint].iter().any(|f| {
+                                f.as_ref().map_or(false, |v| v.to_lowercase().contains(&fv))
+                            })
+                        })
+                    })
+                }
+                PostgresStringFilterKeys::SqlState => {
+                    let fv = filter_val.to_lowercase();
+                    get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
+                        pg_msg
+                            .error
+                            .as_ref()
+                            .and_then(|e| e.code.as_ref())
+                            .map_or(false, |c| c.to_lowercase().contains(&fv))
+                    })
+                }
+                PostgresStringFilterKeys::TransactionStatus => {
+                    let fv = filter_val.to_lowercase();
+                    get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
+                        pg_msg
+                            .transaction
+                            .filter(|t| t.status.desc().contains(&fv))
+                            .is_some()
+                    })
+                }
             },
+            (_, Ok(filter_key), SearchCriteria::GreaterThan(filter_val, decimals)) => {
+                match filter_key {
+                    PostgresNumericFilterKeys::RowsAffected => get_pg_message(streams, model, iter)
+                        .map_or(false, |pg_msg| {
+                            pg_msg
+                                .rows_affected
+                                .filter(|r| {
+                                    *r as usize * 10_usize.pow((*decimals).into()) > *filter_val
+                                })
+                                .is_some()
+                        }),
+                }
+            }
             _ => true,
         }
     }
@@ -583,7 +947,14 @@ impl CustomStreamsStore for PostgresStreamsStore {
                 resultset_bool_cols: vec![],
                 resultset_string_cols: vec![],
                 resultset_datetime_cols: vec![],
+                resultset_float_cols: vec![],
                 resultset_col_types: vec![],
+                error: None,
+                notices: vec![],
+                command_tag: None,
+                rows_affected: None,
+                transaction: None,
+                login: None,
             },
             win_msg_sender,
             bg_sender,
@@ -610,83 +981,156 @@ impl CustomStreamsStore for PostgresStreamsStore {
     }
 }
 
-fn decode_bool(val: &str) -> Option<bool> {
-    match hex_chars_to_string(val).as_deref() {
-        Some("t") => Some(true),
-        Some("1") => Some(true),
-        Some("null") => None,
-        Some("f") => Some(false),
-        Some("0") => Some(false),
-        _ => {
-            // eprintln!("expected bool value: {}", val,);
-            Some(true) // I've seen values such as 54525545 or 46414c5345.. maybe PG optimises "anything non-zero is true"?
+impl PostgresStreamsStore {
+    /// the top queries dialog was opened. The statistics are for
+    /// all the streams to the server of the stream, meaning the card.
+    pub fn display_statement_stats(&self, stream_id: TcpStreamId) {
+        if let Some(client_server) = self.stream_client_server(stream_id) {
+            let samples = postgres_statement_stats::statement_samples(
+                self.streams
+                    .values()
+                    .filter(|s| {
+                        s.client_server
+                            .filter(|cs| {
+                                cs.server_ip == client_server.server_ip
+                                    && cs.server_port == client_server.server_port
+                            })
+                            .is_some()
+                    })
+                    .flat_map(|s| s.messages.iter()),
+            );
+            self.component
+                .as_ref()
+                .unwrap()
+                .stream()
+                .emit(postgres_details_widget::Msg::ComputeStatementStats(samples));
         }
     }
 }
 
-fn decode_unknown(val: &str) -> String {
-    // try to detect string or binary/int, because
-    // the latter will contain \0
-    // maybe i rather should check:
-    //       <field name="pgsql.format" showname="Format: Binary (1)" size="2" pos="106" show="1" value="0001"/>
-    //       <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="108" show="0" value="0000"/>
-    // which I think is always present
-    if hex_chars_to_bytes(val)
-        .unwrap_or_else(Vec::new)
-        .iter()
-        .any(|b| b == &0)
-    {
-        decode_integer_as_str::<i32>(PostgresColType::Int8, val)
-    } else {
-        hex_chars_to_string(val).unwrap_or_else(|| val.to_string())
+fn decode_bool(format: PostgresFormat, val: &str) -> Option<bool> {
+    let bytes = hex_chars_to_bytes(val)?;
+    match format {
+        PostgresFormat::Binary => bytes.first().map(|b| *b != 0),
+        PostgresFormat::Text => match str::from_utf8(&bytes).ok()? {
+            "null" => None,
+            // 't' is what the server sends, clients may send 'true', 'yes', '1'...
+            v => Some(matches!(
+                v.chars().next().map(|c| c.to_ascii_lowercase()),
+                Some('t' | 'y' | '1')
+            )),
+        },
+    }
+}
+
+fn decode_unknown(format: PostgresFormat, val: &str) -> String {
+    let bytes = hex_chars_to_bytes(val).unwrap_or_default();
+    match format {
+        PostgresFormat::Text => hex_chars_to_string(val).unwrap_or_else(|| val.to_string()),
+        // we don't know the type, but strings are sent as-is even in binary format.
+        // integers on the other hand will contain \0 bytes
+        PostgresFormat::Binary => {
+            let int_type = match bytes.len() {
+                2 => Some(PostgresColType::Int2),
+                4 => Some(PostgresColType::Int4),
+                8 => Some(PostgresColType::Int8),
+                _ => None,
+            };
+            match int_type {
+                Some(typ) if bytes.contains(&0) => {
+                    postgres_value_decoding::decode_as_string(typ, format, &bytes)
+                }
+                _ => str::from_utf8(&bytes).ok().map(|s| s.to_string()),
+            }
+            .unwrap_or_else(|| val.to_string())
+        }
     }
 }
 
-fn decode_param(typ: PostgresColType, val: &str, length: i64) -> String {
+fn decode_param(typ: PostgresColType, format: PostgresFormat, val: &str, length: i64) -> String {
     if val == "null" || length == -1 {
         return "null".to_string();
     }
     match typ {
         PostgresColType::ByteArray => format!("Byte array ({} bytes): {}", length, val),
-        PostgresColType::Bool => match decode_bool(val) {
+        PostgresColType::Bool => match decode_bool(format, val) {
             Some(true) => "t",
             Some(false) => "f",
             None => "null",
         }
         .to_string(),
-        PostgresColType::Int4 | PostgresColType::Int2 => decode_integer_as_str::<i32>(typ, val),
-        PostgresColType::Int8 => decode_integer_as_str::<i64>(typ, val),
-        PostgresColType::Unknown => decode_unknown(val),
-        _ => hex_chars_to_string(val).unwrap_or_else(|| format!("Error decoding: {}", val)),
+        PostgresColType::Unknown => decode_unknown(format, val),
+        _ => hex_chars_to_bytes(val)
+            .and_then(|b| postgres_value_decoding::decode_as_string(typ, format, &b))
+            .unwrap_or_else(|| format!("Error decoding: {}", val)),
     }
 }
 
-fn decode_integer<T: num_traits::Num + ToString + FromStr>(
-    _typ: PostgresColType,
+fn decode_integer<T: FromStr>(
+    typ: PostgresColType,
+    format: PostgresFormat,
     val: &str,
 ) -> Option<T> {
-    if val.starts_with("00") {
-        T::from_str_radix(val, 16).ok()
-    } else {
-        hex_chars_to_bytes(val)
-            .and_then(|bytes| String::from_utf8(bytes).ok())
-            .and_then(|s| s.parse().ok())
-    }
+    let bytes = hex_chars_to_bytes(val)?;
+    let decode = |format| {
+        postgres_value_decoding::decode_as_string(typ, format, &bytes).and_then(|s| s.parse().ok())
+    };
+    decode(format).or_else(|| {
+        // the RowDescription can claim the text format for values which
+        // are sent in binary (for instance if it answered a statement Describe
+        // and we missed the Bind). Fall back to binary if the size matches.
+        let binary_len = match typ {
+            PostgresColType::Int2 => 2,
+            PostgresColType::Int4 => 4,
+            _ => 8,
+        };
+        Some(PostgresFormat::Binary)
+            .filter(|_| format == PostgresFormat::Text && bytes.len() == binary_len)
+            .and_then(decode)
+    })
 }
 
-// I was unable to find a logic in the PDML. Sometimes I would get hex-encoded
-// digits, sometimes the raw values. It seemed more often for int4 the hex-encoded
-// and for int8 the raw values but not always...
-// Would have to look in wireshark's source but... another time...
-// I don't know whether the type matters. Putting it in the type sig so I can assert
-// on known values
-fn decode_integer_as_str<T: num_traits::Num + ToString + FromStr>(
-    typ: PostgresColType,
-    val: &str,
-) -> String {
-    decode_integer(typ, val)
-        .map(|i: T| i.to_string())
-        .unwrap_or_else(|| val.to_string())
+/// an int column holding a value we can't decode: turn it into a string
+/// column, so that we can display the raw value instead of a made-up number
+fn demote_int_col_to_string(globals: &mut PostgresStreamGlobals, col_idx: usize) {
+    let previous_cols = &globals.cur_col_types[..col_idx];
+    let int_col_idx = previous_cols
+        .iter()
+        .filter(|t| matches!(t, PostgresColType::Int2 | PostgresColType::Int4))
+        .count();
+    let string_col_idx = previous_cols
+        .iter()
+        .filter(|t| {
+            !matches!(
+                t,
+                PostgresColType::Bool
+                    | PostgresColType::Int2
+                    | PostgresColType::Int4
+                    | PostgresColType::Timestamp
+                    | PostgresColType::Int8
+                    | PostgresColType::Float4
+                    | PostgresColType::Float8
+            )
+        })
+        .count();
+    let values = globals.cur_rs_int_cols.remove(int_col_idx);
+    globals.cur_rs_string_cols.insert(
+        string_col_idx,
+        values
+            .into_iter()
+            .map(|v| v.map(|i| i.to_string()))
+            .collect(),
+    );
+    globals.cur_col_types[col_idx] = PostgresColType::Unknown;
+}
+
+/// the format codes list can be empty (all text), contain a single
+/// format applying to all the values, or one format for each value
+fn format_for_idx(formats: &[PostgresFormat], idx: usize) -> PostgresFormat {
+    match formats {
+        [format] => *format,
+        _ => formats.get(idx).copied().unwrap_or(PostgresFormat::Text),
+    }
 }
 
 fn handle_pgsql_resultset_row(
@@ -699,6 +1143,7 @@ fn handle_pgsql_resultset_row(
     let mut bigint_col_idx = 0;
     let mut bool_col_idx = 0;
     let mut string_col_idx = 0;
+    let mut float_col_idx = 0;
     if globals.cur_col_types.is_empty() {
         // it's possible we don't have all the info about this query
         // default to String for all the columns instead of dropping the data.
@@ -708,68 +1153,101 @@ fn handle_pgsql_resultset_row(
             globals.cur_rs_string_cols.push(vec![]);
         }
     };
-    for (col_type, val) in globals.cur_col_types.iter().zip(cols) {
+    let undecodable_int_cols: Vec<_> = globals
+        .cur_col_types
+        .iter()
+        .zip(&cols)
+        .enumerate()
+        .filter(|(col_idx, (col_type, val))| {
+            matches!(col_type, PostgresColType::Int2 | PostgresColType::Int4)
+                && hex_chars_to_string(val).as_deref() != Some("null")
+                && decode_integer::<i32>(
+                    **col_type,
+                    format_for_idx(&globals.cur_col_formats, *col_idx),
+                    val,
+                )
+                .is_none()
+        })
+        .map(|(col_idx, _)| col_idx)
+        .collect();
+    for col_idx in undecodable_int_cols {
+        demote_int_col_to_string(globals, col_idx);
+    }
+    for (col_idx, (col_type, val)) in globals.cur_col_types.iter().zip(cols).enumerate() {
+        let format = &format_for_idx(&globals.cur_col_formats, col_idx);
+        let is_null = hex_chars_to_string(&val).as_deref() == Some("null");
         match col_type {
             PostgresColType::Bool => {
-                globals.cur_rs_bool_cols[bool_col_idx].push(decode_bool(&val));
+                globals.cur_rs_bool_cols[bool_col_idx].push(decode_bool(*format, &val));
                 bool_col_idx += 1;
             }
             PostgresColType::Int2 | PostgresColType::Int4 => {
-                let unhexed = hex_chars_to_string(&val);
-                globals.cur_rs_int_cols[int_col_idx].push(if unhexed.as_deref() == Some("null") {
+                globals.cur_rs_int_cols[int_col_idx].push(if is_null {
                     None
                 } else {
-                    Some(decode_integer::<i32>(*col_type, &val).unwrap_or(0))
+                    // undecodable values were moved to a string column
+                    decode_integer::<i32>(*col_type, *format, &val)
                 });
                 int_col_idx += 1;
             }
             PostgresColType::Timestamp => {
-                let val_str = hex_chars_to_string(&val);
-                globals.cur_rs_datetime_cols[datetime_col_idx].push(
-                    if val_str.as_deref() == Some("null") {
-                        None
+                globals.cur_rs_datetime_cols[datetime_col_idx].push(if is_null {
+                    None
+                } else {
+                    let parsed = hex_chars_to_bytes(&val)
+                        .and_then(|b| postgres_value_decoding::decode_timestamp(*format, &b));
+                    if parsed.is_some() {
+                        parsed
                     } else {
-                        let parsed = val_str.and_then(|v| {
-                            NaiveDateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S%.f").ok()
-                        });
-                        if parsed.is_some() {
-                            parsed
-                        } else {
-                            return Err(format!("expected datetime value: {}", val));
-                        }
-                    },
-                );
+                        return Err(format!("expected datetime value: {}", val));
+                    }
+                });
                 datetime_col_idx += 1;
             }
             PostgresColType::Int8 => {
-                let unhexed = hex_chars_to_string(&val);
-                globals.cur_rs_bigint_cols[bigint_col_idx].push(
-                    if unhexed.as_deref() == Some("null") {
-                        None
+                globals.cur_rs_bigint_cols[bigint_col_idx].push(if is_null {
+                    None
+                } else {
+                    let parsed = decode_integer::<i64>(*col_type, *format, &val);
+                    if parsed.is_some() {
+                        parsed
                     } else {
-                        let parsed = unhexed.and_then(|h| h.parse::<i64>().ok());
-                        if parsed.is_some() {
-                            parsed
-                        } else {
-                            return Err(format!("expected int8 value: {}", val));
-                        }
-                    },
-                );
+                        return Err(format!("expected int8 value: {}", val));
+                    }
+                });
                 bigint_col_idx += 1;
             }
             PostgresColType::Unknown => {
-                globals.cur_rs_string_cols[string_col_idx].push(
-                    if hex_chars_to_string(&val).as_deref() == Some("null") {
-                        None
-                    } else {
-                        Some(decode_unknown(&val))
-                    },
-                );
+                globals.cur_rs_string_cols[string_col_idx].push(if is_null {
+                    None
+                } else {
+                    Some(decode_unknown(*format, &val))
+                });
                 string_col_idx += 1;
             }
+            PostgresColType::Float4 | PostgresColType::Float8 => {
+                globals.cur_rs_float_cols[float_col_idx].push(if is_null {
+                    None
+                } else {
+                    let parsed = hex_chars_to_bytes(&val).and_then(|b| {
+                        postgres_value_decoding::decode_float(*col_type, *format, &b)
+                    });
+                    if parsed.is_some() {
+                        parsed
+                    } else {
+                        return Err(format!("expected float value: {}", val));
+                    }
+                });
+                float_col_idx += 1;
+            }
             _ => {
-                globals.cur_rs_string_cols[string_col_idx]
-                    .push(hex_chars_to_string(&val).filter(|v| v != "null"));
+                globals.cur_rs_string_cols[string_col_idx].push(if is_null {
+                    None
+                } else {
+                    hex_chars_to_bytes(&val).and_then(|b| {
+                        postgres_value_decoding::decode_as_string(*col_type, *format, &b)
+                    })
+                });
                 string_col_idx += 1;
             }
         }
@@ -777,68 +1255,471 @@ fn handle_pgsql_resultset_row(
     Ok(())
 }
 
-fn get_query_type_desc(query: &Option<Cow<'static, str>>) -> &'static str {
-    if query.as_ref().filter(|q| q.len() >= 5).is_none() {
-        "-"
-    } else {
-        let start_lower = query.as_ref().unwrap()[0..5].to_ascii_lowercase();
-        if start_lower.starts_with("inser") {
-            "insert"
-        } else if start_lower.starts_with("selec") {
-            "select"
-        } else if start_lower.starts_with("updat") {
-            "update"
-        } else if start_lower.starts_with("delet") {
-            "delete"
-        } else if start_lower.starts_with("commi") {
-            "commit"
-        } else if start_lower.starts_with("rollb") {
-            "rollback"
-        } else if start_lower.starts_with("set ") {
-            "system"
-        } else if start_lower.starts_with("drop ") {
-            "drop"
-        } else if start_lower.starts_with("creat") {
-            "create"
-        } else if start_lower.starts_with("alter") {
-            "alter"
-        } else if start_lower.starts_with("do ") {
-            "plsql"
-        } else if start_lower.starts_with("login") {
-            "login"
-        } else if start_lower.starts_with("copy ") {
-            // copy data
-            "copy"
-        } else if start_lower.starts_with("begin") {
-            "bookmark"
+/// move the resultset we accumulated in the globals to a message,
+/// leaving empty resultset data in the globals.
+fn take_current_resultset(
+    globals: &mut PostgresStreamGlobals,
+    query: Option<String>,
+    query_timestamp: NaiveDateTime,
+    result_timestamp: NaiveDateTime,
+) -> PostgresMessageData {
+    PostgresMessageData {
+        query: query.map(Cow::Owned),
+        query_timestamp,
+        result_timestamp,
+        parameter_values: std::mem::take(&mut globals.cur_parameter_values),
+        resultset_col_names: std::mem::take(&mut globals.cur_col_names),
+        resultset_row_count: std::mem::take(&mut globals.cur_rs_row_count),
+        resultset_bool_cols: std::mem::take(&mut globals.cur_rs_bool_cols),
+        resultset_string_cols: std::mem::take(&mut globals.cur_rs_string_cols),
+        resultset_int_cols: std::mem::take(&mut globals.cur_rs_int_cols),
+        resultset_bigint_cols: std::mem::take(&mut globals.cur_rs_bigint_cols),
+        resultset_datetime_cols: std::mem::take(&mut globals.cur_rs_datetime_cols),
+        resultset_float_cols: std::mem::take(&mut globals.cur_rs_float_cols),
+        resultset_col_types: std::mem::take(&mut globals.cur_col_types),
+        error: globals.cur_error.take(),
+        notices: std::mem::take(&mut globals.cur_notices),
+        rows_affected: globals
+            .cur_command_tag
+            .as_deref()
+            .and_then(parse_rows_affected),
+        command_tag: globals.cur_command_tag.take(),
+        transaction: None,
+        login: None,
+    }
+}
+
+/// assign the messages emitted since the previous ReadyForQuery to the
+/// current transaction, if any. When the transaction completes, we
+/// know its outcome and duration, and update all its messages.
+fn update_transactions(stream: &mut PostgresStreamData, state: PostgresTransactionState) {
+    let tracker = &mut stream.transaction_tracker;
+    let new_messages_idx = tracker.message_count_at_ready;
+    if tracker.cur_transaction_start_idx.is_none() && state != PostgresTransactionState::Idle {
+        // BEGIN, or the capture started in the middle of a transaction
+        tracker.transaction_count += 1;
+        tracker.cur_transaction_start_idx = Some(new_messages_idx);
+        tracker.cur_transaction_failed = false;
+    }
+    if state == PostgresTransactionState::Failed {
+        tracker.cur_transaction_failed = true;
+    }
+    let start_idx = match tracker.cur_transaction_start_idx {
+        Some(idx) => idx,
+        None => return,
+    };
+    let transaction_id = tracker.transaction_count;
+    for msg in &mut stream.messages[new_messages_idx..] {
+        msg.transaction = Some(PostgresTransactionInfo {
+            id: transaction_id,
+            status: PostgresTransactionStatus::InProgress,
+            duration: None,
+        });
+    }
+    if state == PostgresTransactionState::Idle {
+        let tx_messages = &mut stream.messages[start_idx..];
+        // COMMIT on a failed transaction also returns a ROLLBACK tag
+        let status = if tracker.cur_transaction_failed {
+            PostgresTransactionStatus::Failed
+        } else if tx_messages.last().and_then(|m| m.command_tag.as_deref()) == Some("ROLLBACK") {
+            PostgresTransactionStatus::RolledBack
         } else {
-            "other"
+            PostgresTransactionStatus::Committed
+        };
+        let duration = match (tx_messages.first(), tx_messages.last()) {
+            (Some(first), Some(last)) => Some(last.result_timestamp - first.query_timestamp),
+            _ => None,
+        };
+        for msg in tx_messages {
+            msg.transaction = Some(PostgresTransactionInfo {
+                id: transaction_id,
+                status,
+                duration,
+            });
         }
+        tracker.cur_transaction_start_idx = None;
     }
 }
 
-fn hex_chars_to_string(hex_chars: &str) -> Option<String> {
-    hex_chars_to_bytes(hex_chars)
-        .as_ref()
-        .and_then(|b| str::from_utf8(b).ok())
-        .map(|s| s.to_string())
+fn cur_login_message(stream: &mut PostgresStreamData) -> Option<&mut PostgresMessageData> {
+    let idx = stream.stream_globals.cur_login_idx?;
+    stream.messages.get_mut(idx)
 }
 
-fn hex_chars_to_bytes(hex_chars: &str) -> Option<Vec<u8>> {
-    let nocolons = hex_chars.replace(':', "");
-    hex::decode(&nocolons).ok().map(|c| c.into_iter().collect())
+/// the databases the client logged in, followed by some of the
+/// server parameters, for instance: "mydb (server_version: 13.4, TimeZone: UTC)"
+fn refresh_summary_details(stream: &mut PostgresStreamData) {
+    let params = SUMMARY_SERVER_PARAMETERS
+        .iter()
+        .filter_map(|p| {
+            stream
+                .server_parameters
+                .get(p)
+                .map(|v| format!("{}: {}", p, v))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let databases = stream.databases.join(", ");
+    stream.summary_details = match (databases.is_empty(), params.is_empty()) {
+        (true, true) => None,
+        (false, true) => Some(databases),
+        (true, false) => Some(format!("({})", params)),
+        (false, false) => Some(format!("{} ({})", databases, params)),
+    };
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct PostgresMessageData {
-    // for prepared queries, it's possible the declaration
-    // occured before we started recording the stream.
-    // in that case we won't be able to recover the query string.
-    pub query_timestamp: NaiveDateTime,
-    pub result_timestamp: NaiveDateTime,
-    pub query: Option<Cow<'static, str>>,
-    pub parameter_values: Vec<(PostgresColType, String)>,
-    pub resultset_col_names: Vec<String>,
+/// a CopyData message, we keep up to MAX_COPY_DATA_BYTES of the payload
+fn add_copy_data(globals: &mut PostgresStreamGlobals, data: &[u8]) {
+    if globals.copy_format.is_none()
+        && globals.copy_data_len == 0
+        && !globals.was_simple_query
+        && !globals.was_bind
+    {
+        // we missed the COPY query and the CopyIn/CopyOut response,
+        // probably because the capture started in the middle of the COPY
+        globals.is_unknown_copy = true;
+        globals.cur_query_with_fallback = Some("Unknown COPY".to_string());
+    }
+    let room = MAX_COPY_DATA_BYTES.saturating_sub(globals.copy_data.len());
+    globals
+        .copy_data
+        .extend_from_slice(&data[..data.len().min(room)]);
+    globals.copy_data_len += data.len();
+}
+
+/// the COPY payload is complete, present its rows as the resultset
+/// of the COPY statement. We only decode the text and CSV formats.
+fn finish_copy(globals: &mut PostgresStreamGlobals) {
+    let mut data = std::mem::take(&mut globals.copy_data);
+    let data_len = std::mem::take(&mut globals.copy_data_len);
+    // without CopyIn/CopyOut response, we assume the text format,
+    // which is the default
+    let format = globals.copy_format.take().unwrap_or(PostgresFormat::Text);
+    if data_len == 0 && !globals.is_unknown_copy {
+        return;
+    }
+    if data_len > data.len() {
+        globals.cur_notices.push(PostgresErrorInfo {
+            message: Some(format!(
+                "COPY data truncated: showing the first {} of {} bytes",
+                data.len(),
+                data_len
+            )),
+            ..PostgresErrorInfo::default()
+        });
+        // don't display a partial row
+        let complete_rows_len = data.iter().rposition(|b| *b == b'\n').map_or(0, |p| p + 1);
+        data.truncate(complete_rows_len);
+    }
+    if format == PostgresFormat::Binary {
+        globals.cur_col_names = vec!["Binary COPY data".to_string()];
+        globals.cur_col_types = vec![PostgresColType::Text];
+        globals.cur_col_formats = vec![];
+        globals.cur_rs_row_count = 1;
+        globals.cur_rs_string_cols = vec![vec![Some(format!("{} bytes", data_len))]];
+        return;
+    }
+    let query = if globals.was_simple_query {
+        simple_query_cur_statement(globals)
+    } else {
+        globals.cur_query_with_fallback.clone()
+    };
+    let copy_rs = postgres_copy_data::parse_copy_data(query.as_deref(), &data);
+    let col_count = copy_rs.col_names.len();
+    globals.cur_col_types = vec![PostgresColType::Text; col_count];
+    globals.cur_col_formats = vec![];
+    globals.cur_rs_row_count = copy_rs.rows.len();
+    globals.cur_rs_string_cols = vec![vec![]; col_count];
+    for row in copy_rs.rows {
+        for (col_idx, val) in row.into_iter().enumerate() {
+            globals.cur_rs_string_cols[col_idx].push(val);
+        }
+    }
+    globals.cur_col_names = copy_rs.col_names;
+}
+
+/// the statement of the simple query which is currently executing,
+/// or the whole query string if we couldn't split it in statements
+fn simple_query_cur_statement(globals: &PostgresStreamGlobals) -> Option<String> {
+    globals
+        .simple_query_statements
+        .get(globals.simple_query_completed_count)
+        .cloned()
+        .or_else(|| globals.cur_query_with_fallback.clone())
+}
+
+/// split a simple query string in its statements, so that we can match
+/// them with the CommandComplete messages we get from the server.
+/// We must not split on semicolons in string literals, quoted identifiers,
+/// dollar-quoted strings or comments.
+fn split_simple_query_statements(query: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut cur_start = 0;
+    let mut idx = 0;
+    let bytes = query.as_bytes();
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\'' | b'"' => {
+                let quote = bytes[idx];
+                idx += 1;
+                while idx < bytes.len() && bytes[idx] != quote {
+                    idx += 1;
+                }
+            }
+            b'-' if bytes.get(idx + 1) == Some(&b'-') => {
+                while idx < bytes.len() && bytes[idx] != b'\n' {
+                    idx += 1;
+                }
+            }
+            b'/' if bytes.get(idx + 1) == Some(&b'*') => {
+                idx += 2;
+                while idx < bytes.len() && !bytes[idx..].starts_with(b"*/") {
+                    idx += 1;
+                }
+                idx += 1;
+            }
+            b'$' => {
+                // $tag$ ... $tag$ (the tag may be empty)
+                let tag_len = query[idx + 1..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .filter(|l| query[idx + 1 + l..].starts_with('$'));
+                if let Some(l) = tag_len {
+                    let tag = &query[idx..idx + l + 2];
+                    idx += tag.len();
+                    idx += query[idx..]
+                        .find(tag)
+                        .map(|end| end + tag.len() - 1)
+                        .unwrap_or(query.len() - idx);
+                }
+            }
+            b';' => {
+                result.push(query[cur_start..idx].trim().to_string());
+                cur_start = idx + 1;
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+    if cur_start < query.len() {
+        result.push(query[cur_start..].trim().to_string());
+    }
+    result.retain(|s| !s.is_empty());
+    result
+}
+
+/// the rows before `start_idx` are already in the list store. If they belong
+/// to a transaction which ended in the new messages, their transaction
+/// description and color changed.
+fn ended_transaction_rows_to_refresh(
+    messages: &[PostgresMessageData],
+    start_idx: usize,
+) -> Range<usize> {
+    let prev_tx = start_idx
+        .checked_sub(1)
+        .and_then(|i| messages.get(i))
+        .and_then(|m| m.transaction);
+    let next_tx = messages.get(start_idx).and_then(|m| m.transaction);
+    match (prev_tx, next_tx) {
+        (Some(prev), Some(next))
+            if prev.id == next.id && prev.status != PostgresTransactionStatus::InProgress =>
+        {
+            let tx_start_idx = messages[..start_idx]
+                .iter()
+                .rposition(|m| m.transaction.map(|t| t.id) != Some(prev.id))
+                .map_or(0, |i| i + 1);
+            tx_start_idx..start_idx
+        }
+        _ => start_idx..start_idx,
+    }
+}
+
+fn refresh_transaction_rows(
+    ls: &gtk::ListStore,
+    session_id: TcpStreamId,
+    messages: &[PostgresMessageData],
+    msg_indices: Range<usize>,
+) {
+    let mut remaining = msg_indices.len();
+    if remaining == 0 {
+        return;
+    }
+    // these are recent messages, look for them from the end of the list
+    let row_count = ls.iter_n_children(None);
+    let mut iter = ls.iter_nth_child(None, row_count - 1);
+    while let Some(it) = iter.filter(|_| remaining > 0) {
+        let (stream_id, idx) =
+            custom_streams_store::get_message_helper(ls.upcast_ref::<gtk::TreeModel>(), &it);
+        let idx = idx as usize;
+        if stream_id == session_id && msg_indices.contains(&idx) {
+            ls.set_value(&it, 14, &get_transaction_desc(messages, idx).to_value());
+            ls.set_value(&it, 15, &get_transaction_color(&messages[idx]).to_value());
+            remaining -= 1;
+        }
+        iter = Some(it).filter(|i| ls.iter_previous(i));
+    }
+}
+
+fn get_transaction_color(postgres: &PostgresMessageData) -> Option<&'static str> {
+    Some(colors::ERROR_COLOR).filter(|_| {
+        postgres.transaction.map(|t| t.status) == Some(PostgresTransactionStatus::Failed)
+    })
+}
+
+/// we draw the transaction boundaries, and give the outcome of the
+/// transaction on its last message
+fn get_transaction_desc(messages: &[PostgresMessageData], idx: usize) -> String {
+    let tx = match messages[idx].transaction {
+        Some(tx) => tx,
+        None => return "".to_string(),
+    };
+    let tx_id_at = |i: Option<usize>| {
+        i.and_then(|i| messages.get(i))
+            .and_then(|m| m.transaction)
+            .map(|t| t.id)
+    };
+    let is_first = tx_id_at(idx.checked_sub(1)) != Some(tx.id);
+    let is_last = tx.status != PostgresTransactionStatus::InProgress
+        && tx_id_at(Some(idx + 1)) != Some(tx.id);
+    match (is_first, is_last, tx.duration) {
+        (_, true, Some(duration)) => format!(
+            "└ #{} {} ({} ms)",
+            tx.id,
+            tx.status.desc(),
+            duration.num_milliseconds()
+        ),
+        (_, true, None) => format!("└ #{} {}", tx.id, tx.status.desc()),
+        (true, false, _) => format!("┌ #{}", tx.id),
+        (false, false, _) => format!("│ #{}", tx.id),
+    }
+}
+
+fn get_message_desc(postgres: &PostgresMessageData) -> String {
+    match &postgres.login {
+        Some(login) => login.desc(),
+        None => postgres
+            .query
+            .as_deref()
+            .map(|q| if q.len() > 250 { &q[..250] } else { q })
+            .unwrap_or("couldn't get query")
+            .replace("\n", ""),
+    }
+}
+
+fn get_result_desc(postgres: &PostgresMessageData) -> String {
+    match (&postgres.error, &postgres.login) {
+        (Some(e), _) => format!(
+            "{} {}",
+            e.severity.as_deref().unwrap_or("ERROR"),
+            e.code.as_deref().unwrap_or("")
+        ),
+        (None, Some(login)) => match (login.authenticated, login.is_cleartext_password()) {
+            (Some(true), true) => "authenticated (cleartext password)".to_string(),
+            (Some(true), false) => "authenticated".to_string(),
+            _ => "-".to_string(),
+        },
+        (None, None) => format!("{} rows", postgres.resultset_row_count),
+    }
+}
+
+/// the number of rows affected by the statement, from the CommandComplete tag:
+/// "INSERT 0 5", "UPDATE 12", "SELECT 100"... Tags such as "CREATE TABLE"
+/// carry no row count.
+fn parse_rows_affected(command_tag: &str) -> Option<u64> {
+    command_tag
+        .rsplit(' ')
+        .next()
+        .and_then(|count| count.parse().ok())
+}
+
+/// the CommandComplete tag tells us for sure which command was executed,
+/// prefer it over guessing from the query string
+fn get_command_tag_type_desc(command_tag: &str) -> Option<&'static str> {
+    match command_tag.split(' ').next()? {
+        "INSERT" => Some("insert"),
+        "SELECT" => Some("select"),
+        "UPDATE" => Some("update"),
+        "DELETE" => Some("delete"),
+        "COMMIT" => Some("commit"),
+        "ROLLBACK" => Some("rollback"),
+        "SET" | "RESET" => Some("system"),
+        "DROP" => Some("drop"),
+        "CREATE" => Some("create"),
+        "ALTER" => Some("alter"),
+        "DO" => Some("plsql"),
+        "COPY" => Some("copy"),
+        "BEGIN" | "START" => Some("begin"),
+        "LISTEN" | "UNLISTEN" | "NOTIFY" => Some("notify"),
+        _ => None,
+    }
+}
+
+fn get_query_type_desc(query: &Option<Cow<'static, str>>) -> &'static str {
+    if query.as_ref().filter(|q| q.len() >= 5).is_none() {
+        "-"
+    } else {
+        let start_lower = query.as_ref().unwrap()[0..5].to_ascii_lowercase();
+        if start_lower.starts_with("inser") {
+            "insert"
+        } else if start_lower.starts_with("selec") {
+            "select"
+        } else if start_lower.starts_with("updat") {
+            "update"
+        } else if start_lower.starts_with("delet") {
+            "delete"
+        } else if start_lower.starts_with("commi") {
+            "commit"
+        } else if start_lower.starts_with("rollb") {
+            "rollback"
+        } else if start_lower.starts_with("set ") {
+            "system"
+        } else if start_lower.starts_with("drop ") {
+            "drop"
+        } else if start_lower.starts_with("creat") {
+            "create"
+        } else if start_lower.starts_with("alter") {
+            "alter"
+        } else if start_lower.starts_with("do ") {
+            "plsql"
+        } else if start_lower.starts_with("login") {
+            "login"
+        } else if start_lower.starts_with("copy ") {
+            // copy data
+            "copy"
+        } else if start_lower.starts_with("begin") {
+            "begin"
+        } else if start_lower.starts_with("notif")
+            || start_lower.starts_with("liste")
+            || start_lower.starts_with("unlis")
+        {
+            "notify"
+        } else {
+            "other"
+        }
+    }
+}
+
+fn hex_chars_to_string(hex_chars: &str) -> Option<String> {
+    hex_chars_to_bytes(hex_chars)
+        .as_ref()
+        .and_then(|b| str::from_utf8(b).ok())
+        .map(|s| s.to_string())
+}
+
+fn hex_chars_to_bytes(hex_chars: &str) -> Option<Vec<u8>> {
+    let nocolons = hex_chars.replace(':', "");
+    hex::decode(&nocolons).ok().map(|c| c.into_iter().collect())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostgresMessageData {
+    // for prepared queries, it's possible the declaration
+    // occured before we started recording the stream.
+    // in that case we won't be able to recover the query string.
+    pub query_timestamp: NaiveDateTime,
+    pub result_timestamp: NaiveDateTime,
+    pub query: Option<Cow<'static, str>>,
+    pub parameter_values: Vec<(PostgresColType, String)>,
+    pub resultset_col_names: Vec<String>,
     pub resultset_row_count: usize,
     pub resultset_col_types: Vec<PostgresColType>,
     pub resultset_string_cols: Vec<Vec<Option<String>>>,
@@ -846,25 +1727,107 @@ pub struct PostgresMessageData {
     pub resultset_int_cols: Vec<Vec<Option<i32>>>,
     pub resultset_bigint_cols: Vec<Vec<Option<i64>>>,
     pub resultset_datetime_cols: Vec<Vec<Option<NaiveDateTime>>>,
+    pub resultset_float_cols: Vec<Vec<Option<f64>>>,
+    pub error: Option<PostgresErrorInfo>,
+    pub notices: Vec<PostgresErrorInfo>,
+    pub command_tag: Option<String>,
+    pub rows_affected: Option<u64>,
+    pub transaction: Option<PostgresTransactionInfo>,
+    pub login: Option<PostgresLoginInfo>,
+}
+
+/// the startup message of the client, and the authentication
+/// exchange which follows it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PostgresLoginInfo {
+    pub username: String,
+    pub database: String,
+    pub application: Option<String>,
+    // the method the server requested, SASL continue/final are
+    // intermediate steps and don't overwrite it
+    pub auth_method: Option<PostgresAuthRequest>,
+    pub sasl_mechanisms: Vec<String>,
+    // None until we get AuthenticationOk or an error
+    pub authenticated: Option<bool>,
+    pub backend_pid: Option<u32>,
+    pub backend_key: Option<u32>,
+}
+
+impl PostgresLoginInfo {
+    pub fn desc(&self) -> String {
+        format!(
+            "LOGIN: user: {}, db: {}, app: {}",
+            self.username,
+            self.database,
+            self.application.as_deref().unwrap_or("-")
+        )
+    }
+
+    pub fn is_cleartext_password(&self) -> bool {
+        self.auth_method == Some(PostgresAuthRequest::CleartextPassword)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PostgresTransactionInfo {
+    pub id: u32,
+    pub status: PostgresTransactionStatus,
+    // known once the transaction completes
+    pub duration: Option<chrono::Duration>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostgresTransactionStatus {
+    InProgress,
+    Committed,
+    RolledBack,
+    Failed,
+}
+
+impl PostgresTransactionStatus {
+    pub fn desc(&self) -> &'static str {
+        match self {
+            PostgresTransactionStatus::InProgress => "in progress",
+            PostgresTransactionStatus::Committed => "committed",
+            PostgresTransactionStatus::RolledBack => "rolled back",
+            PostgresTransactionStatus::Failed => "failed",
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Debug, PartialEq, Default)]
 pub struct PostgresStreamGlobals {
     known_statements: HashMap<String, String>,
     cur_query: Option<String>,
     parse_param_types: Vec<PostgresColType>,
     cur_query_with_fallback: Option<String>,
     was_bind: bool,
+    was_simple_query: bool,
+    simple_query_statements: Vec<String>,
+    simple_query_completed_count: usize,
     query_timestamp: Option<NaiveDateTime>,
     cur_rs_row_count: usize,
     cur_col_names: Vec<String>,
     cur_col_types: Vec<PostgresColType>,
+    cur_col_formats: Vec<PostgresFormat>,
     cur_parameter_values: Vec<(PostgresColType, String)>,
     cur_rs_int_cols: Vec<Vec<Option<i32>>>,
     cur_rs_bigint_cols: Vec<Vec<Option<i64>>>,
     cur_rs_bool_cols: Vec<Vec<Option<bool>>>,
     cur_rs_string_cols: Vec<Vec<Option<String>>>,
     cur_rs_datetime_cols: Vec<Vec<Option<NaiveDateTime>>>,
+    cur_rs_float_cols: Vec<Vec<Option<f64>>>,
+    cur_error: Option<PostgresErrorInfo>,
+    cur_notices: Vec<PostgresErrorInfo>,
+    cur_command_tag: Option<String>,
+    copy_format: Option<PostgresFormat>,
+    copy_data: Vec<u8>,
+    // the total size of the COPY data, copy_data can be truncated
+    copy_data_len: usize,
+    // CopyData without the COPY query and CopyIn/CopyOut response
+    is_unknown_copy: bool,
+    // index in the messages of the login, while the authentication is in progress
+    cur_login_idx: Option<usize>,
 }
 
 #[cfg(test)]
@@ -918,11 +1881,18 @@ fn should_parse_simple_query() {
         resultset_int_cols: vec![],
         resultset_bigint_cols: vec![],
         resultset_datetime_cols: vec![],
+        resultset_float_cols: vec![],
         resultset_bool_cols: vec![],
         resultset_string_cols: vec![vec![
             Some("PostgreSQL".to_string()),
             Some("9.6.12 on x8".to_string()),
         ]],
+        error: None,
+        notices: vec![],
+        command_tag: None,
+        rows_affected: None,
+        transaction: None,
+        login: None,
     }];
     assert_eq!(expected, parsed);
 }
@@ -972,8 +1942,15 @@ fn should_parse_prepared_statement() {
             resultset_int_cols: vec![],
             resultset_bigint_cols: vec![],
             resultset_datetime_cols: vec![],
+            resultset_float_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![],
+            error: None,
+            notices: vec![],
+            command_tag: None,
+            rows_affected: None,
+            transaction: None,
+            login: None,
         },
         PostgresMessageData {
             query_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
@@ -986,8 +1963,15 @@ fn should_parse_prepared_statement() {
             resultset_int_cols: vec![],
             resultset_bigint_cols: vec![],
             resultset_datetime_cols: vec![],
+            resultset_float_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![vec![Some("PostgreSQL".to_string())]],
+            error: None,
+            notices: vec![],
+            command_tag: None,
+            rows_affected: None,
+            transaction: None,
+            login: None,
         },
     ];
     assert_eq!(expected, parsed);
@@ -1050,8 +2034,15 @@ fn should_parse_prepared_statement_with_parameters() {
             resultset_int_cols: vec![],
             resultset_bigint_cols: vec![],
             resultset_datetime_cols: vec![],
+            resultset_float_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![],
+            error: None,
+            notices: vec![],
+            command_tag: None,
+            rows_affected: None,
+            transaction: None,
+            login: None,
         },
         PostgresMessageData {
             query_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
@@ -1068,8 +2059,15 @@ fn should_parse_prepared_statement_with_parameters() {
             resultset_int_cols: vec![],
             resultset_bigint_cols: vec![],
             resultset_datetime_cols: vec![],
+            resultset_float_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![vec![Some("PostgreSQL".to_string())]],
+            error: None,
+            notices: vec![],
+            command_tag: None,
+            rows_affected: None,
+            transaction: None,
+            login: None,
         },
     ];
     assert_eq!(expected, parsed);
@@ -1146,8 +2144,15 @@ fn should_parse_query_with_multiple_columns_and_nulls() {
         resultset_string_cols: vec![],
         resultset_bigint_cols: vec![],
         resultset_datetime_cols: vec![],
+        resultset_float_cols: vec![],
         resultset_bool_cols: vec![],
         resultset_int_cols: vec![vec![Some(26)]],
+        error: None,
+        notices: vec![],
+        command_tag: None,
+        rows_affected: None,
+        transaction: None,
+        login: None,
     }];
     assert_eq!(expected, parsed);
 }
@@ -1248,21 +2253,835 @@ fn should_parse_query_with_no_parse_and_unknown_bind() {
         resultset_int_cols: vec![vec![Some(26)]],
         resultset_bigint_cols: vec![],
         resultset_datetime_cols: vec![],
+        resultset_float_cols: vec![],
         resultset_bool_cols: vec![],
         resultset_string_cols: vec![
             vec![None],
             vec![Some("GENERAL".to_string())],
             vec![Some("APPLICATION_TIMEZONE".to_string())],
         ],
+        error: None,
+        notices: vec![],
+        command_tag: None,
+        rows_affected: None,
+        transaction: None,
+        login: None,
     }];
     assert_eq!(expected, parsed);
 }
 
+#[test]
+fn should_use_the_bind_result_formats_over_the_row_description() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="25" pos="66">
+    <field name="pgsql.type" showname="Type: Parse" size="1" pos="66" show="Parse" value="50"/>
+    <field name="pgsql.query" show="select price, qty from items" />
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="13" pos="91">
+    <field name="pgsql.type" showname="Type: Bind" size="1" pos="91" show="Bind" value="42"/>
+    <field name="" show="Result formats: 1" size="2" pos="105" value="0001">
+      <field name="pgsql.format" showname="Format: Binary (1)" size="2" pos="107" show="1" value="0001"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="33" pos="76">
+    <field name="pgsql.type" showname="Type: Row description" size="1" pos="76" show="Row description" value="54"/>
+    <field name="pgsql.field.count" showname="Field count: 2" size="2" pos="81" show="2" value="0002">
+      <field name="pgsql.col.name" showname="Column name: price" size="6" pos="83" show="price" value="707269636500">
+        <field name="pgsql.oid.type" showname="Type OID: 701" size="4" pos="95" show="701" value="000002bd"/>
+        <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="105" show="0" value="0000"/>
+      </field>
+      <field name="pgsql.col.name" showname="Column name: qty" size="4" pos="107" show="qty" value="71747900">
+        <field name="pgsql.oid.type" showname="Type OID: 23" size="4" pos="115" show="23" value="00000017"/>
+        <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="125" show="0" value="0000"/>
+      </field>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="31" pos="127">
+    <field name="pgsql.type" showname="Type: Data row" size="1" pos="127" show="Data row" value="44"/>
+    <field name="pgsql.field.count" showname="Field count: 2" size="2" pos="132" show="2" value="0002">
+      <field name="pgsql.val.length" showname="Column length: 8" size="4" pos="134" show="8" value="00000008"/>
+      <field name="pgsql.val.data" size="8" pos="138" show="3f:f8:00:00:00:00:00:00" value="3ff8000000000000"/>
+      <field name="pgsql.val.length" showname="Column length: 4" size="4" pos="146" show="4" value="00000004"/>
+      <field name="pgsql.val.data" size="4" pos="150" show="00:00:01:00" value="00000100"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="158">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="158" show="Ready for query" value="5a"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(vec![vec![Some(1.5)]], parsed[0].resultset_float_cols);
+    assert_eq!(vec![vec![Some(256)]], parsed[0].resultset_int_cols);
+}
+
+#[test]
+fn should_show_undecodable_int_values_as_raw_strings() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="40" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: select id, qty from items" size="34" pos="71" show="select id, qty from items"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="33" pos="76">
+    <field name="pgsql.type" showname="Type: Row description" size="1" pos="76" show="Row description" value="54"/>
+    <field name="pgsql.field.count" showname="Field count: 2" size="2" pos="81" show="2" value="0002">
+      <field name="pgsql.col.name" showname="Column name: id" size="3" pos="83" show="id" value="696400">
+        <field name="pgsql.oid.type" showname="Type OID: 23" size="4" pos="92" show="23" value="00000017"/>
+        <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="102" show="0" value="0000"/>
+      </field>
+      <field name="pgsql.col.name" showname="Column name: qty" size="4" pos="104" show="qty" value="71747900">
+        <field name="pgsql.oid.type" showname="Type OID: 23" size="4" pos="114" show="23" value="00000017"/>
+        <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="124" show="0" value="0000"/>
+      </field>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="20" pos="126">
+    <field name="pgsql.type" showname="Type: Data row" size="1" pos="126" show="Data row" value="44"/>
+    <field name="pgsql.field.count" showname="Field count: 2" size="2" pos="131" show="2" value="0002">
+      <field name="pgsql.val.length" showname="Column length: 1" size="4" pos="133" show="1" value="00000001"/>
+      <field name="pgsql.val.data" size="1" pos="137" show="31" value="31"/>
+      <field name="pgsql.val.length" showname="Column length: 1" size="4" pos="138" show="1" value="00000001"/>
+      <field name="pgsql.val.data" size="1" pos="142" show="35" value="35"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="20" pos="143">
+    <field name="pgsql.type" showname="Type: Data row" size="1" pos="143" show="Data row" value="44"/>
+    <field name="pgsql.field.count" showname="Field count: 2" size="2" pos="148" show="2" value="0002">
+      <field name="pgsql.val.length" showname="Column length: 1" size="4" pos="150" show="1" value="00000001"/>
+      <field name="pgsql.val.data" size="1" pos="154" show="32" value="32"/>
+      <field name="pgsql.val.length" showname="Column length: 3" size="4" pos="155" show="3" value="00000003"/>
+      <field name="pgsql.val.data" size="3" pos="159" show="ff:fe:fd" value="fffefd"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="162">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="162" show="Ready for query" value="5a"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(
+        vec![PostgresColType::Int4, PostgresColType::Unknown],
+        parsed[0].resultset_col_types
+    );
+    assert_eq!(vec![vec![Some(1), Some(2)]], parsed[0].resultset_int_cols);
+    assert_eq!(
+        vec![vec![Some("5".to_string()), Some("fffefd".to_string())]],
+        parsed[0].resultset_string_cols
+    );
+}
+
 #[test]
 fn decode_23() {
     // known from SELECT typname FROM pg_catalog.pg_type WHERE oid=23
     assert_eq!(
         "23",
-        decode_integer_as_str::<i32>(PostgresColType::Int4, "3233")
+        decode_param(PostgresColType::Int4, PostgresFormat::Text, "3233", 2)
+    );
+}
+
+#[test]
+fn decode_binary_params() {
+    assert_eq!(
+        "42",
+        decode_param(PostgresColType::Int4, PostgresFormat::Binary, "0000002a", 4)
+    );
+    assert_eq!(
+        "-2",
+        decode_param(
+            PostgresColType::Int8,
+            PostgresFormat::Binary,
+            "fffffffffffffffe",
+            8
+        )
+    );
+    assert_eq!(
+        "t",
+        decode_param(PostgresColType::Bool, PostgresFormat::Binary, "01", 1)
+    );
+    assert_eq!(
+        "f",
+        decode_param(PostgresColType::Bool, PostgresFormat::Text, "66", 1)
+    );
+    // a text value containing only digits must not be decoded as an integer
+    assert_eq!(
+        "0042",
+        decode_param(
+            PostgresColType::Unknown,
+            PostgresFormat::Text,
+            "30303432",
+            4
+        )
+    );
+    assert_eq!(
+        "42",
+        decode_param(
+            PostgresColType::Unknown,
+            PostgresFormat::Binary,
+            "0000002a",
+            4
+        )
+    );
+}
+
+#[test]
+fn should_decode_mixed_text_and_binary_parameters() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="25" pos="66">
+    <field name="pgsql.type" showname="Type: Parse" size="1" pos="66" show="Parse" value="50"/>
+    <field name="pgsql.statement" show="S_1"/>
+    <field name="pgsql.query" show="select * from t where a=$1 and b=$2 and c=$3" />
+    <field name="" show="Parameters: 3" size="2" pos="120" value="0003">
+      <field name="pgsql.oid.type" showname="Type OID: 23" size="4" pos="122" show="23" value="00000017"/>
+      <field name="pgsql.oid.type" showname="Type OID: 23" size="4" pos="126" show="23" value="00000017"/>
+      <field name="pgsql.oid.type" showname="Type OID: 25" size="4" pos="130" show="25" value="00000019"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="13" pos="134">
+    <field name="pgsql.type" showname="Type: Bind" size="1" pos="134" show="Bind" value="42"/>
+    <field name="pgsql.statement" show="S_1" />
+    <field name="" show="Parameter formats: 3" size="2" pos="140" value="0003">
+      <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="142" show="0" value="0000"/>
+      <field name="pgsql.format" showname="Format: Binary (1)" size="2" pos="144" show="1" value="0001"/>
+      <field name="pgsql.format" showname="Format: Binary (1)" size="2" pos="146" show="1" value="0001"/>
+    </field>
+    <field name="" show="Parameter values: 3" size="2" pos="148" value="0003">
+      <field name="pgsql.val.length" showname="Column length: 2" size="4" pos="150" show="2" value="00000002"/>
+      <field name="pgsql.val.data" size="2" pos="154" show="32:33" value="3233"/>
+      <field name="pgsql.val.length" showname="Column length: 4" size="4" pos="156" show="4" value="00000004"/>
+      <field name="pgsql.val.data" size="4" pos="160" show="00:00:00:2a" value="0000002a"/>
+      <field name="pgsql.val.length" showname="Column length: 2" size="4" pos="164" show="2" value="00000002"/>
+      <field name="pgsql.val.data" size="2" pos="168" show="34:32" value="3432"/>
+    </field>
+    <field name="" show="Result formats: 1" size="2" pos="170" value="0001">
+      <field name="pgsql.format" showname="Format: Binary (1)" size="2" pos="172" show="1" value="0001"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="33" pos="174">
+    <field name="pgsql.type" showname="Type: Row description" size="1" pos="174" show="Row description" value="54"/>
+    <field name="pgsql.field.count" showname="Field count: 1" size="2" pos="179" show="1" value="0001">
+      <field name="pgsql.col.name" showname="Column name: a" size="2" pos="181" show="a" value="6100">
+        <field name="pgsql.oid.type" showname="Type OID: 23" size="4" pos="189" show="23" value="00000017"/>
+        <field name="pgsql.format" showname="Format: Binary (1)" size="2" pos="199" show="1" value="0001"/>
+      </field>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="15" pos="207">
+    <field name="pgsql.type" showname="Type: Data row" size="1" pos="207" show="Data row" value="44"/>
+    <field name="pgsql.field.count" showname="Field count: 1" size="2" pos="212" show="1" value="0001">
+      <field name="pgsql.val.length" showname="Column length: 4" size="4" pos="214" show="4" value="00000004"/>
+      <field name="pgsql.val.data" size="4" pos="218" show="00:00:31:32" value="00003132"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="222">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="222" show="Ready for query" value="5a"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(
+        vec![
+            (PostgresColType::Int4, "23".to_string()),
+            (PostgresColType::Int4, "42".to_string()),
+            (PostgresColType::Text, "42".to_string()),
+        ],
+        parsed[0].parameter_values
+    );
+    // in binary, not the "12" the text heuristics would have found
+    assert_eq!(vec![vec![Some(12594)]], parsed[0].resultset_int_cols);
+}
+
+#[test]
+fn should_parse_simple_query_protocol_with_multiple_statements() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="40" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.length" showname="Length: 39" size="4" pos="67" show="39" value="00000027"/>
+    <field name="pgsql.query" showname="Query: select 'a;b'; update t set a=1" size="34" pos="71" show="select &#x27;a;b&#x27;; update t set a=1"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="33" pos="76">
+    <field name="pgsql.type" showname="Type: Row description" size="1" pos="76" show="Row description" value="54"/>
+    <field name="pgsql.field.count" showname="Field count: 1" size="2" pos="81" show="1" value="0001">
+      <field name="pgsql.col.name" showname="Column name: ?column?" size="9" pos="83" show="?column?" value="3f636f6c756d6e3f00">
+        <field name="pgsql.oid.type" showname="Type OID: 25" size="4" pos="97" show="25" value="00000019"/>
+        <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="107" show="0" value="0000"/>
+      </field>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="109">
+    <field name="pgsql.type" showname="Type: Data row" size="1" pos="109" show="Data row" value="44"/>
+    <field name="pgsql.field.count" showname="Field count: 1" size="2" pos="114" show="1" value="0001">
+      <field name="pgsql.val.length" showname="Column length: 3" size="4" pos="116" show="3" value="00000003"/>
+      <field name="pgsql.val.data" size="3" pos="120" show="61:3b:62" value="613b62"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="123">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="123" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: SELECT 1" size="9" pos="128" show="SELECT 1" value="53454c454354203100"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="137">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="137" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: UPDATE 3" size="9" pos="142" show="UPDATE 3" value="55504441544520330"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="151">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="151" show="Ready for query" value="5a"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    let expected = vec![
+        PostgresMessageData {
+            query_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
+            result_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
+            query: Some(Cow::Borrowed("select 'a;b'")),
+            parameter_values: vec![],
+            resultset_col_names: vec!["?column?".to_string()],
+            resultset_row_count: 1,
+            resultset_col_types: vec![PostgresColType::Text],
+            resultset_int_cols: vec![],
+            resultset_bigint_cols: vec![],
+            resultset_datetime_cols: vec![],
+            resultset_float_cols: vec![],
+            resultset_bool_cols: vec![],
+            resultset_string_cols: vec![vec![Some("a;b".to_string())]],
+            error: None,
+            notices: vec![],
+            command_tag: Some("SELECT 1".to_string()),
+            rows_affected: Some(1),
+            transaction: None,
+            login: None,
+        },
+        PostgresMessageData {
+            query_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
+            result_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
+            query: Some(Cow::Borrowed("update t set a=1")),
+            parameter_values: vec![],
+            resultset_col_names: vec![],
+            resultset_row_count: 0,
+            resultset_col_types: vec![],
+            resultset_int_cols: vec![],
+            resultset_bigint_cols: vec![],
+            resultset_datetime_cols: vec![],
+            resultset_float_cols: vec![],
+            resultset_bool_cols: vec![],
+            resultset_string_cols: vec![],
+            error: None,
+            notices: vec![],
+            command_tag: Some("UPDATE 3".to_string()),
+            rows_affected: Some(3),
+            transaction: None,
+            login: None,
+        },
+    ];
+    assert_eq!(expected, parsed);
+}
+
+#[test]
+fn should_split_simple_query_statements() {
+    assert_eq!(
+        vec![
+            "select 'a;b', \"c;d\"",
+            "do $$ begin perform 1; end $$",
+            "select $1 -- comment; still comment\n from t",
+            "select /* ; */ 2",
+        ],
+        split_simple_query_statements(
+            "select 'a;b', \"c;d\"; do $$ begin perform 1; end $$;\nselect $1 -- comment; still comment\n from t; select /* ; */ 2;"
+        )
+    );
+}
+
+#[test]
+fn should_attach_error_to_failed_query() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="40" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: select 1; select * from missing" size="34" pos="71" show="select 1; select * from missing"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="123">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="123" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: SELECT 0" size="9" pos="128" show="SELECT 0"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="98" pos="137">
+    <field name="pgsql.type" showname="Type: Error" size="1" pos="137" show="Error" value="45"/>
+    <field name="pgsql.length" showname="Length: 97" size="4" pos="138" show="97" value="00000061"/>
+    <field name="pgsql.severity" showname="Severity: ERROR" size="6" pos="143" show="ERROR" value="4552524f5200"/>
+    <field name="pgsql.text_severity" showname="Text: ERROR" size="6" pos="150" show="ERROR" value="4552524f5200"/>
+    <field name="pgsql.code" showname="Code: 42P01" size="6" pos="157" show="42P01" value="343250303100"/>
+    <field name="pgsql.message" showname="Message: relation &quot;missing&quot; does not exist" size="35" pos="164" show="relation &quot;missing&quot; does not exist"/>
+    <field name="pgsql.position" showname="Position: 15" size="3" pos="200" show="15" value="313500"/>
+    <field name="pgsql.routine" showname="Routine: parserOpenTable" size="16" pos="220" show="parserOpenTable"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="235">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="235" show="Ready for query" value="5a"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(2, parsed.len());
+    assert_eq!(Some(Cow::Borrowed("select 1")), parsed[0].query);
+    assert_eq!(None, parsed[0].error);
+    assert_eq!(
+        Some(Cow::Borrowed("select * from missing")),
+        parsed[1].query
+    );
+    assert_eq!(
+        Some(PostgresErrorInfo {
+            severity: Some("ERROR".to_string()),
+            code: Some("42P01".to_string()),
+            message: Some("relation \"missing\" does not exist".to_string()),
+            detail: None,
+            hint: None,
+            position: Some(15),
+        }),
+        parsed[1].error
+    );
+}
+
+#[test]
+fn should_parse_rows_affected_from_command_tag() {
+    assert_eq!(Some(5), parse_rows_affected("INSERT 0 5"));
+    assert_eq!(Some(12), parse_rows_affected("UPDATE 12"));
+    assert_eq!(Some(100), parse_rows_affected("SELECT 100"));
+    assert_eq!(None, parse_rows_affected("CREATE TABLE"));
+    assert_eq!(None, parse_rows_affected("BEGIN"));
+}
+
+#[test]
+fn should_decode_float_and_binary_result_columns() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="40" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: select price, id from items" size="34" pos="71" show="select price, id from items"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="33" pos="76">
+    <field name="pgsql.type" showname="Type: Row description" size="1" pos="76" show="Row description" value="54"/>
+    <field name="pgsql.field.count" showname="Field count: 2" size="2" pos="81" show="2" value="0002">
+      <field name="pgsql.col.name" showname="Column name: price" size="6" pos="83" show="price" value="707269636500">
+        <field name="pgsql.oid.type" showname="Type OID: 701" size="4" pos="95" show="701" value="000002bd"/>
+        <field name="pgsql.format" showname="Format: Text (0)" size="2" pos="105" show="0" value="0000"/>
+      </field>
+      <field name="pgsql.col.name" showname="Column name: id" size="3" pos="107" show="id" value="696400">
+        <field name="pgsql.oid.type" showname="Type OID: 2950" size="4" pos="116" show="2950" value="00000b86"/>
+        <field name="pgsql.format" showname="Format: Binary (1)" size="2" pos="126" show="1" value="0001"/>
+      </field>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="33" pos="128">
+    <field name="pgsql.type" showname="Type: Data row" size="1" pos="128" show="Data row" value="44"/>
+    <field name="pgsql.field.count" showname="Field count: 2" size="2" pos="133" show="2" value="0002">
+      <field name="pgsql.val.length" showname="Column length: 3" size="4" pos="135" show="3" value="00000003"/>
+      <field name="pgsql.val.data" size="3" pos="139" show="33:2e:35" value="332e35"/>
+      <field name="pgsql.val.length" showname="Column length: 16" size="4" pos="142" show="16" value="00000010"/>
+      <field name="pgsql.val.data" size="16" pos="146" show="a0:ee:bc:99:9c:0b:4e:f8:bb:6d:6b:b9:bd:38:0a:11" value="a0eebc999c0b4ef8bb6d6bb9bd380a11"/>
+    </field>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="161">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="161" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: SELECT 1" size="9" pos="166" show="SELECT 1" value="53454c454354203100"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="175">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="175" show="Ready for query" value="5a"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(
+        vec![PostgresColType::Float8, PostgresColType::Uuid],
+        parsed[0].resultset_col_types
+    );
+    assert_eq!(vec![vec![Some(3.5)]], parsed[0].resultset_float_cols);
+    assert_eq!(
+        vec![vec![Some(
+            "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11".to_string()
+        )]],
+        parsed[0].resultset_string_cols
+    );
+}
+
+#[test]
+fn should_track_failed_transactions() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="11" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: begin" size="6" pos="71" show="begin"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="11" pos="77">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="77" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: BEGIN" size="6" pos="82" show="BEGIN" value="424547494e00"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="88">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="88" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Transaction (84)" size="1" pos="93" show="84" value="54"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="26" pos="94">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="94" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: update t set a=1" size="17" pos="99" show="update t set a=1"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="120">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="120" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: UPDATE 1" size="9" pos="125" show="UPDATE 1" value="5550444154452031"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="134">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="134" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Transaction (84)" size="1" pos="139" show="84" value="54"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="27" pos="140">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="140" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: select * from missing" size="22" pos="145" show="select * from missing"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="40" pos="167">
+    <field name="pgsql.type" showname="Type: Error" size="1" pos="167" show="Error" value="45"/>
+    <field name="pgsql.severity" showname="Severity: ERROR" size="6" pos="172" show="ERROR" value="4552524f5200"/>
+    <field name="pgsql.code" showname="Code: 42P01" size="6" pos="178" show="42P01" value="343250303100"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="207">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="207" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Error (69)" size="1" pos="212" show="69" value="45"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="213">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="213" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: commit" size="7" pos="218" show="commit"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="227">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="227" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: ROLLBACK" size="9" pos="232" show="ROLLBACK" value="524f4c4c4241434b00"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="241">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="241" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="246" show="73" value="49"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="247">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="247" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: select 1" size="9" pos="252" show="select 1"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="261">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="261" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: SELECT 1" size="9" pos="266" show="SELECT 1" value="53454c454354203100"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="275">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="275" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="280" show="73" value="49"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(5, parsed.len());
+    let failed_tx = Some(PostgresTransactionInfo {
+        id: 1,
+        status: PostgresTransactionStatus::Failed,
+        duration: Some(chrono::Duration::zero()),
+    });
+    assert_eq!(
+        vec![failed_tx, failed_tx, failed_tx, failed_tx, None],
+        parsed.iter().map(|m| m.transaction).collect::<Vec<_>>()
+    );
+    assert_eq!("┌ #1", get_transaction_desc(&parsed, 0));
+    assert_eq!("│ #1", get_transaction_desc(&parsed, 1));
+    assert_eq!("└ #1 failed (0 ms)", get_transaction_desc(&parsed, 3));
+    assert_eq!("", get_transaction_desc(&parsed, 4));
+    // the first rows were displayed before the transaction failed
+    assert_eq!(0..3, ended_transaction_rows_to_refresh(&parsed, 3));
+    assert!(ended_transaction_rows_to_refresh(&parsed, 4).is_empty());
+    assert!(ended_transaction_rows_to_refresh(&parsed, 0).is_empty());
+}
+
+#[test]
+fn should_decode_copy_data_as_resultset() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="38" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: COPY items (id, name) FROM STDIN" size="33" pos="71" show="COPY items (id, name) FROM STDIN"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="12" pos="104">
+    <field name="pgsql.type" showname="Type: CopyIn response" size="1" pos="104" show="CopyIn response" value="47"/>
+    <field name="pgsql.format" showname="Format: Text (0)" size="1" pos="109" show="0" value="00"/>
+    <field name="pgsql.field.count" showname="Columns: 2" size="2" pos="110" show="2" value="0002"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="18" pos="116">
+    <field name="pgsql.type" showname="Type: Copy data" size="1" pos="116" show="Copy data" value="64"/>
+    <field name="pgsql.copydata" showname="Copy data: 31096170706c650a32095c4e0a" size="13" pos="121" show="31:09:61:70:70:6c:65:0a:32:09:5c:4e:0a" value="31096170706c650a32095c4e0a"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="5" pos="134">
+    <field name="pgsql.type" showname="Type: Copy completion" size="1" pos="134" show="Copy completion" value="63"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="12" pos="139">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="139" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: COPY 2" size="7" pos="144" show="COPY 2" value="434f50592032"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="151">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="151" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="156" show="73" value="49"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    let copy = &parsed[0];
+    assert_eq!(
+        Some("COPY items (id, name) FROM STDIN"),
+        copy.query.as_deref()
+    );
+    assert_eq!(Some(2), copy.rows_affected);
+    assert_eq!(
+        vec!["id".to_string(), "name".to_string()],
+        copy.resultset_col_names
+    );
+    assert_eq!(2, copy.resultset_row_count);
+    assert_eq!(
+        vec![
+            vec![Some("1".to_string()), Some("2".to_string())],
+            vec![Some("apple".to_string()), None]
+        ],
+        copy.resultset_string_cols
+    );
+}
+
+#[test]
+fn should_keep_copy_data_when_the_copy_query_was_not_captured() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="18" pos="116">
+    <field name="pgsql.type" showname="Type: Copy data" size="1" pos="116" show="Copy data" value="64"/>
+    <field name="pgsql.copydata" showname="Copy data: 31096170706c650a32095c4e0a" size="13" pos="121" show="31:09:61:70:70:6c:65:0a:32:09:5c:4e:0a" value="31096170706c650a32095c4e0a"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="5" pos="134">
+    <field name="pgsql.type" showname="Type: Copy completion" size="1" pos="134" show="Copy completion" value="63"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="12" pos="139">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="139" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: COPY 2" size="7" pos="144" show="COPY 2" value="434f50592032"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="151">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="151" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="156" show="73" value="49"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(Some("Unknown COPY"), parsed[0].query.as_deref());
+    assert_eq!(Some(2), parsed[0].rows_affected);
+    assert_eq!(
+        vec![
+            vec![Some("1".to_string()), Some("2".to_string())],
+            vec![Some("apple".to_string()), None]
+        ],
+        parsed[0].resultset_string_cols
+    );
+}
+
+#[test]
+fn should_truncate_large_copy_data() {
+    let mut globals = PostgresStreamGlobals {
+        copy_format: Some(PostgresFormat::Text),
+        ..PostgresStreamGlobals::default()
+    };
+    let row = b"1\tapple\n";
+    let row_count = MAX_COPY_DATA_BYTES / row.len() + 10;
+    for _ in 0..row_count {
+        add_copy_data(&mut globals, row);
+    }
+    assert_eq!(MAX_COPY_DATA_BYTES, globals.copy_data.len());
+    finish_copy(&mut globals);
+    assert_eq!(MAX_COPY_DATA_BYTES / row.len(), globals.cur_rs_row_count);
+    assert_eq!(
+        Some(format!(
+            "COPY data truncated: showing the first {} of {} bytes",
+            MAX_COPY_DATA_BYTES,
+            row_count * row.len()
+        )),
+        globals.cur_notices[0].message
+    );
+}
+
+#[test]
+fn should_show_notifications_and_server_parameters() {
+    let mut parser = PostgresStreamsStore::default();
+    let sid = common_tests_parse_stream(
+        &mut parser,
+        parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="41" pos="66">
+    <field name="pgsql.type" showname="Type: Startup message" size="0" pos="66" show="Startup message"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: user" size="5" pos="74" show="user" value="7573657200"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: jobs" size="5" pos="79" show="jobs" value="6a6f627300"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: database" size="9" pos="84" show="database" value="646174616261736500"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: queue" size="6" pos="93" show="queue" value="717565756500"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="26" pos="66">
+    <field name="pgsql.type" showname="Type: Parameter status" size="1" pos="66" show="Parameter status" value="53"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: server_version" size="15" pos="71" show="server_version" value="7365727665725f76657273696f6e00"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: 13.4" size="5" pos="86" show="13.4" value="31332e3400"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="25" pos="92">
+    <field name="pgsql.type" showname="Type: Parameter status" size="1" pos="92" show="Parameter status" value="53"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: client_encoding" size="16" pos="97" show="client_encoding" value="636c69656e745f656e636f64696e6700"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: UTF8" size="5" pos="113" show="UTF8" value="5554463800"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="18" pos="117">
+    <field name="pgsql.type" showname="Type: Parameter status" size="1" pos="117" show="Parameter status" value="53"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: TimeZone" size="9" pos="122" show="TimeZone" value="54696d655a6f6e6500"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: UTC" size="4" pos="131" show="UTC" value="55544300"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="31" pos="135">
+    <field name="pgsql.type" showname="Type: Notification response" size="1" pos="135" show="Notification response" value="41"/>
+    <field name="pgsql.pid" showname="PID: 4242" size="4" pos="140" show="4242" value="00001092"/>
+    <field name="pgsql.channel" showname="Channel: jobs" size="5" pos="144" show="jobs" value="6a6f627300"/>
+    <field name="pgsql.payload" showname="Payload: job-17" size="7" pos="149" show="job-17" value="6a6f622d313700"/>
+  </proto>
+        "#,
+        ),
+    )
+    .unwrap();
+    let stream = parser.streams.get(&sid).unwrap();
+    assert_eq!(
+        Some("queue (server_version: 13.4, TimeZone: UTC)"),
+        stream.summary_details.as_deref()
+    );
+    assert_eq!(2, stream.messages.len());
+    assert_eq!(
+        Some("NOTIFICATION: channel: jobs, payload: job-17, pid: 4242"),
+        stream.messages[1].query.as_deref()
+    );
+    assert_eq!("notify", get_query_type_desc(&stream.messages[1].query));
+}
+
+#[test]
+fn should_parse_sasl_login() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="41" pos="66">
+    <field name="pgsql.type" showname="Type: Startup message" size="0" pos="66" show="Startup message"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: user" size="5" pos="74" show="user" value="7573657200"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: app" size="4" pos="79" show="app" value="61707000"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: database" size="9" pos="83" show="database" value="646174616261736500"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: shop" size="5" pos="92" show="shop" value="73686f7000"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="24" pos="66">
+    <field name="pgsql.type" showname="Type: Authentication request" size="1" pos="66" show="Authentication request" value="52"/>
+    <field name="pgsql.authtype" showname="Authentication type: SASL authentication (10)" size="4" pos="71" show="10" value="0000000a"/>
+    <field name="pgsql.auth.sasl.mech" showname="SASL authentication mechanism: SCRAM-SHA-256" size="14" pos="75" show="SCRAM-SHA-256" value="534352414d2d5348412d32353600"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="93" pos="90">
+    <field name="pgsql.type" showname="Type: Authentication request" size="1" pos="90" show="Authentication request" value="52"/>
+    <field name="pgsql.authtype" showname="Authentication type: SASL continue (11)" size="4" pos="95" show="11" value="0000000b"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="9" pos="183">
+    <field name="pgsql.type" showname="Type: Authentication request" size="1" pos="183" show="Authentication request" value="52"/>
+    <field name="pgsql.authtype" showname="Authentication type: Success (0)" size="4" pos="188" show="0" value="00000000"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="13" pos="192">
+    <field name="pgsql.type" showname="Type: Backend key data" size="1" pos="192" show="Backend key data" value="4b"/>
+    <field name="pgsql.pid" showname="PID: 4242" size="4" pos="197" show="4242" value="00001092"/>
+    <field name="pgsql.key" showname="Key: 123456" size="4" pos="201" show="123456" value="0001e240"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="205">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="205" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="210" show="73" value="49"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(None, parsed[0].query);
+    assert_eq!(
+        Some(PostgresLoginInfo {
+            username: "app".to_string(),
+            database: "shop".to_string(),
+            application: None,
+            auth_method: Some(PostgresAuthRequest::Sasl),
+            sasl_mechanisms: vec!["SCRAM-SHA-256".to_string()],
+            authenticated: Some(true),
+            backend_pid: Some(4242),
+            backend_key: Some(123456),
+        }),
+        parsed[0].login
+    );
+    assert_eq!("authenticated", get_result_desc(&parsed[0]));
+}
+
+#[test]
+fn should_flag_failed_cleartext_login() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="41" pos="66">
+    <field name="pgsql.type" showname="Type: Startup message" size="0" pos="66" show="Startup message"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: user" size="5" pos="74" show="user" value="7573657200"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: app" size="4" pos="79" show="app" value="61707000"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: database" size="9" pos="83" show="database" value="646174616261736500"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: shop" size="5" pos="92" show="shop" value="73686f7000"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="9" pos="66">
+    <field name="pgsql.type" showname="Type: Authentication request" size="1" pos="66" show="Authentication request" value="52"/>
+    <field name="pgsql.authtype" showname="Authentication type: Plaintext password (3)" size="4" pos="71" show="3" value="00000003"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="12" pos="97">
+    <field name="pgsql.type" showname="Type: Password message" size="1" pos="97" show="Password message" value="70"/>
+    <field name="pgsql.password" showname="Password: secret" size="7" pos="102" show="secret" value="73656372657400"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="40" pos="75">
+    <field name="pgsql.type" showname="Type: Error" size="1" pos="75" show="Error" value="45"/>
+    <field name="pgsql.severity" showname="Severity: FATAL" size="6" pos="80" show="FATAL" value="464154414c00"/>
+    <field name="pgsql.code" showname="Code: 28P01" size="6" pos="86" show="28P01" value="323850303100"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    assert_eq!(1, parsed.len());
+    let login = parsed[0].login.as_ref().unwrap();
+    assert!(login.is_cleartext_password());
+    assert_eq!(Some(false), login.authenticated);
+    assert_eq!("FATAL 28P01", get_result_desc(&parsed[0]));
+}
+
+#[test]
+fn should_aggregate_statement_stats() {
+    let parsed = tests_parse_stream(parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="34" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: select * from t where id = 1" size="29" pos="71" show="select * from t where id = 1"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="100">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="100" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: SELECT 0" size="9" pos="105" show="SELECT 0" value="53454c454354203000"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="114">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="114" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="119" show="73" value="49"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="34" pos="120">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="120" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: select * from t where id = 22" size="30" pos="125" show="select * from t where id = 22"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="14" pos="155">
+    <field name="pgsql.type" showname="Type: Command completion" size="1" pos="155" show="Command completion" value="43"/>
+    <field name="pgsql.tag" showname="Tag: SELECT 0" size="9" pos="160" show="SELECT 0" value="53454c454354203000"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="169">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="169" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="174" show="73" value="49"/>
+  </proto>
+        "#,
+        ))
+        .unwrap();
+    let stats = postgres_statement_stats::compute_statement_stats(
+        &postgres_statement_stats::statement_samples(parsed.iter()),
     );
+    assert_eq!(1, stats.len());
+    assert_eq!("select * from t where id = $1", stats[0].statement);
+    assert_eq!(2, stats[0].count);
+    assert_eq!(0.0, stats[0].max_duration_ms);
 }
@@ -0,0 +1,344 @@
+// https://www.postgresql.org/docs/12/protocol-overview.html#PROTOCOL-FORMAT-CODES
+// in text format, values are sent as their string representation.
+// in binary format, we must follow the "send" functions of the types,
+// from the postgres source (src/backend/utils/adt).
+use crate::pgsql::tshark_pgsql::{PostgresColType, PostgresFormat};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use itertools::Itertools;
+use std::convert::TryInto;
+use std::str;
+
+/// binary dates & timestamps are relative to 2000-01-01
+fn pg_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd(2000, 1, 1).and_hms(0, 0, 0)
+}
+
+fn read_i16(bytes: &[u8], offset: usize) -> Option<i16> {
+    Some(i16::from_be_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_i64(bytes: &[u8], offset: usize) -> Option<i64> {
+    Some(i64::from_be_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+pub fn decode_float(typ: PostgresColType, format: PostgresFormat, bytes: &[u8]) -> Option<f64> {
+    match (format, typ) {
+        (PostgresFormat::Binary, PostgresColType::Float4) => {
+            Some(f32::from_be_bytes(bytes.try_into().ok()?) as f64)
+        }
+        (PostgresFormat::Binary, PostgresColType::Float8) => {
+            Some(f64::from_be_bytes(bytes.try_into().ok()?))
+        }
+        (PostgresFormat::Binary, _) => None,
+        // rust parses "NaN" and "infinity", case-insensitively
+        (PostgresFormat::Text, _) => str::from_utf8(bytes).ok()?.parse().ok(),
+    }
+}
+
+pub fn decode_timestamp(format: PostgresFormat, bytes: &[u8]) -> Option<NaiveDateTime> {
+    match format {
+        PostgresFormat::Binary => {
+            pg_epoch().checked_add_signed(Duration::microseconds(read_i64(bytes, 0)?))
+        }
+        PostgresFormat::Text => {
+            NaiveDateTime::parse_from_str(str::from_utf8(bytes).ok()?, "%Y-%m-%d %H:%M:%S%.f").ok()
+        }
+    }
+}
+
+/// decode a value to its postgres text representation
+pub fn decode_as_string(
+    typ: PostgresColType,
+    format: PostgresFormat,
+    bytes: &[u8],
+) -> Option<String> {
+    match format {
+        PostgresFormat::Text => str::from_utf8(bytes).ok().map(|s| s.to_string()),
+        PostgresFormat::Binary => decode_binary_as_string(typ, bytes),
+    }
+}
+
+fn decode_binary_as_string(typ: PostgresColType, bytes: &[u8]) -> Option<String> {
+    match typ {
+        PostgresColType::Bool => Some(if bytes.first()? != &0 { "t" } else { "f" }.to_string()),
+        PostgresColType::ByteArray => Some(format!("\\x{}", hex::encode(bytes))),
+        PostgresColType::Int2 => read_i16(bytes, 0).map(|i| i.to_string()),
+        PostgresColType::Int4 => read_i32(bytes, 0).map(|i| i.to_string()),
+        PostgresColType::Oid => read_i32(bytes, 0).map(|i| (i as u32).to_string()),
+        PostgresColType::Int8 => read_i64(bytes, 0).map(|i| i.to_string()),
+        PostgresColType::Float4 | PostgresColType::Float8 => {
+            decode_float(typ, PostgresFormat::Binary, bytes).map(|f| f.to_string())
+        }
+        PostgresColType::Numeric => decode_binary_numeric(bytes),
+        PostgresColType::Date => match read_i32(bytes, 0)? {
+            i32::MAX => Some("infinity".to_string()),
+            i32::MIN => Some("-infinity".to_string()),
+            days => pg_epoch()
+                .date()
+                .checked_add_signed(Duration::days(days.into()))
+                .map(|d| d.to_string()),
+        },
+        PostgresColType::Time => {
+            let micros = read_i64(bytes, 0)?;
+            NaiveTime::from_num_seconds_from_midnight_opt(
+                (micros / 1_000_000) as u32,
+                (micros % 1_000_000 * 1000) as u32,
+            )
+            .map(|t| t.to_string())
+        }
+        PostgresColType::Timestamp | PostgresColType::TimestampTz => {
+            let suffix = if typ == PostgresColType::TimestampTz {
+                "+00"
+            } else {
+                ""
+            };
+            match read_i64(bytes, 0)? {
+                i64::MAX => Some("infinity".to_string()),
+                i64::MIN => Some("-infinity".to_string()),
+                _ => decode_timestamp(PostgresFormat::Binary, bytes)
+                    .map(|ts| format!("{}{}", ts, suffix)),
+            }
+        }
+        PostgresColType::Interval => decode_binary_interval(bytes),
+        PostgresColType::Uuid => {
+            if bytes.len() != 16 {
+                return None;
+            }
+            let hex = hex::encode(bytes);
+            Some(format!(
+                "{}-{}-{}-{}-{}",
+                &hex[0..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..32]
+            ))
+        }
+        // jsonb is prefixed by a version number, currently always 1
+        PostgresColType::Jsonb => str::from_utf8(bytes.get(1..)?).ok().map(|s| s.to_string()),
+        PostgresColType::Inet => decode_binary_inet(bytes),
+        PostgresColType::Array(elt_oid) => decode_binary_array(elt_oid, bytes),
+        _ => str::from_utf8(bytes).ok().map(|s| s.to_string()),
+    }
+}
+
+/// numerics are sent as base-10000 digits
+fn decode_binary_numeric(bytes: &[u8]) -> Option<String> {
+    let ndigits = read_i16(bytes, 0)? as usize;
+    let weight = read_i16(bytes, 2)? as isize;
+    let sign = read_i16(bytes, 4)? as u16;
+    let dscale = read_i16(bytes, 6)? as usize;
+    let digits = (0..ndigits)
+        .map(|i| read_i16(bytes, 8 + i * 2))
+        .collect::<Option<Vec<_>>>()?;
+    let digit_at = |idx: isize| {
+        usize::try_from(idx)
+            .ok()
+            .and_then(|i| digits.get(i))
+            .copied()
+            .unwrap_or(0)
+    };
+    let mut result = match sign {
+        0x0000 => String::new(),
+        0x4000 => "-".to_string(),
+        0xC000 => return Some("NaN".to_string()),
+        0xD000 => return Some("Infinity".to_string()),
+        0xF000 => return Some("-Infinity".to_string()),
+        _ => return None,
+    };
+    if weight < 0 {
+        result.push('0');
+    } else {
+        result.push_str(&digit_at(0).to_string());
+        for idx in 1..=weight {
+            result.push_str(&format!("{:04}", digit_at(idx)));
+        }
+    }
+    if dscale > 0 {
+        let fraction = (0..dscale)
+            .step_by(4)
+            .enumerate()
+            .map(|(i, _)| format!("{:04}", digit_at(weight + 1 + i as isize)))
+            .collect::<String>();
+        result.push('.');
+        result.push_str(&fraction[..dscale]);
+    }
+    Some(result)
+}
+
+/// formatted like the default "postgres" IntervalStyle
+fn decode_binary_interval(bytes: &[u8]) -> Option<String> {
+    let micros = read_i64(bytes, 0)?;
+    let days = read_i32(bytes, 8)?;
+    let months = read_i32(bytes, 12)?;
+    let plural = |count: i32, unit: &str| {
+        format!(
+            "{} {}{}",
+            count,
+            unit,
+            if count.abs() == 1 { "" } else { "s" }
+        )
+    };
+    let mut parts = vec![];
+    if months / 12 != 0 {
+        parts.push(plural(months / 12, "year"));
+    }
+    if months % 12 != 0 {
+        parts.push(plural(months % 12, "mon"));
+    }
+    if days != 0 {
+        parts.push(plural(days, "day"));
+    }
+    if micros != 0 || parts.is_empty() {
+        let abs_micros = micros.unsigned_abs();
+        let secs = abs_micros / 1_000_000;
+        let mut time = format!(
+            "{}{:02}:{:02}:{:02}",
+            if micros < 0 { "-" } else { "" },
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        );
+        if abs_micros % 1_000_000 != 0 {
+            time.push_str(format!(".{:06}", abs_micros % 1_000_000).trim_end_matches('0'));
+        }
+        parts.push(time);
+    }
+    Some(parts.join(" "))
+}
+
+/// family, bits, is_cidr, address length, address
+fn decode_binary_inet(bytes: &[u8]) -> Option<String> {
+    let (family, bits, addr) = (*bytes.first()?, *bytes.get(1)?, bytes.get(4..)?);
+    let (ip, max_bits) = match (family, addr.len()) {
+        (2, 4) => (std::net::IpAddr::from(<[u8; 4]>::try_from(addr).ok()?), 32),
+        (3, 16) => (
+            std::net::IpAddr::from(<[u8; 16]>::try_from(addr).ok()?),
+            128,
+        ),
+        _ => return None,
+    };
+    Some(if bits == max_bits {
+        ip.to_string()
+    } else {
+        format!("{}/{}", ip, bits)
+    })
+}
+
+/// only one-dimensional arrays are supported
+fn decode_binary_array(elt_oid: u32, bytes: &[u8]) -> Option<String> {
+    let ndim = read_i32(bytes, 0)?;
+    if ndim == 0 {
+        return Some("{}".to_string());
+    }
+    if ndim != 1 || read_i32(bytes, 8)? as u32 != elt_oid {
+        return None;
+    }
+    let elt_count = read_i32(bytes, 12)?;
+    let elt_type = PostgresColType::from_pg_oid(elt_oid);
+    let mut offset = 20;
+    let mut elts = vec![];
+    for _ in 0..elt_count {
+        let length = read_i32(bytes, offset)?;
+        offset += 4;
+        if length < 0 {
+            elts.push("NULL".to_string());
+        } else {
+            let elt_bytes = bytes.get(offset..offset + length as usize)?;
+            offset += length as usize;
+            elts.push(quote_array_element(&decode_binary_as_string(
+                elt_type, elt_bytes,
+            )?));
+        }
+    }
+    Some(format!("{{{}}}", elts.iter().join(",")))
+}
+
+fn quote_array_element(elt: &str) -> String {
+    let needs_quotes = elt.is_empty()
+        || elt.eq_ignore_ascii_case("null")
+        || elt
+            .chars()
+            .any(|c| matches!(c, '{' | '}' | ',' | '"' | '\\') || c.is_whitespace());
+    if needs_quotes {
+        format!("\"{}\"", elt.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        elt.to_string()
+    }
+}
+
+#[cfg(test)]
+fn decode_binary_hex(typ: PostgresColType, hex_val: &str) -> Option<String> {
+    decode_as_string(typ, PostgresFormat::Binary, &hex::decode(hex_val).unwrap())
+}
+
+#[test]
+fn should_decode_binary_numerics() {
+    assert_eq!(
+        Some("123.45".to_string()),
+        decode_binary_hex(PostgresColType::Numeric, "0002000000000002007b1194")
+    );
+    assert_eq!(
+        Some("-0.0001".to_string()),
+        decode_binary_hex(PostgresColType::Numeric, "0001ffff400000040001")
+    );
+    assert_eq!(
+        Some("10000000".to_string()),
+        decode_binary_hex(PostgresColType::Numeric, "000100010000000003e8")
+    );
+}
+
+#[test]
+fn should_decode_binary_dates_and_times() {
+    assert_eq!(
+        Some("2021-03-05".to_string()),
+        decode_binary_hex(PostgresColType::Date, "00001e36")
+    );
+    assert_eq!(
+        Some("2021-03-05 08:49:52.736275+00".to_string()),
+        decode_binary_hex(PostgresColType::TimestampTz, "00025fc4eface813")
+    );
+    assert_eq!(
+        Some("1 year 2 mons 3 days 04:05:06.5".to_string()),
+        decode_binary_hex(
+            PostgresColType::Interval,
+            "000000036c9361a0000000030000000e"
+        )
+    );
+}
+
+#[test]
+fn should_decode_binary_uuid_inet_and_arrays() {
+    assert_eq!(
+        Some("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11".to_string()),
+        decode_binary_hex(PostgresColType::Uuid, "a0eebc999c0b4ef8bb6d6bb9bd380a11")
+    );
+    assert_eq!(
+        Some("192.168.0.0/24".to_string()),
+        decode_binary_hex(PostgresColType::Inet, "02180004c0a80000")
+    );
+    assert_eq!(
+        Some("{1,NULL,3}".to_string()),
+        decode_binary_hex(
+            PostgresColType::Array(23),
+            "00000001000000010000001700000003000000010000000400000001ffffffff0000000400000003"
+        )
+    );
+    assert_eq!(
+        Some(r#"{"a b",c}"#.to_string()),
+        decode_binary_hex(
+            PostgresColType::Array(25),
+            "0000000100000000000000190000000200000001000000036120620000000163"
+        )
+    );
+}
@@ -16,7 +16,20 @@ pub enum PostgresColType {
     Int2,
     Int4,
     Int8,
+    Float4,
+    Float8,
+    Numeric,
+    Date,
+    Time,
     Timestamp,
+    TimestampTz,
+    Interval,
+    Uuid,
+    Json,
+    Jsonb,
+    Inet,
+    // one-dimensional array, with the OID of the element type
+    Array(u32),
     Other,
 
     // for prepared queries, it's possible the declaration
@@ -28,25 +41,159 @@ pub enum PostgresColType {
 }
 
 impl PostgresColType {
-    /// select * from postgres.pg_catalog.pg_type
     fn from_pg_oid_type(typ: &str) -> PostgresColType {
-        match typ.parse() {
-            Ok(16) => PostgresColType::Bool,
-            Ok(17) => PostgresColType::ByteArray,
-            Ok(18) => PostgresColType::Char,
-            Ok(19) => PostgresColType::Name,
-            Ok(20) => PostgresColType::Int8,
-            Ok(21) => PostgresColType::Int2,
-            Ok(23) => PostgresColType::Int4,
-            Ok(25) => PostgresColType::Text,
-            Ok(26) => PostgresColType::Oid,
-            Ok(1043) => PostgresColType::Varchar,
-            Ok(1114) => PostgresColType::Timestamp,
+        typ.parse()
+            .map(PostgresColType::from_pg_oid)
+            .unwrap_or(PostgresColType::Other)
+    }
+
+    /// select * from postgres.pg_catalog.pg_type
+    pub fn from_pg_oid(oid: u32) -> PostgresColType {
+        match oid {
+            16 => PostgresColType::Bool,
+            17 => PostgresColType::ByteArray,
+            18 => PostgresColType::Char,
+            19 => PostgresColType::Name,
+            20 => PostgresColType::Int8,
+            21 => PostgresColType::Int2,
+            23 => PostgresColType::Int4,
+            25 => PostgresColType::Text,
+            26 => PostgresColType::Oid,
+            114 => PostgresColType::Json,
+            700 => PostgresColType::Float4,
+            701 => PostgresColType::Float8,
+            869 => PostgresColType::Inet,
+            1043 => PostgresColType::Varchar,
+            1082 => PostgresColType::Date,
+            1083 => PostgresColType::Time,
+            1114 => PostgresColType::Timestamp,
+            1184 => PostgresColType::TimestampTz,
+            1186 => PostgresColType::Interval,
+            1700 => PostgresColType::Numeric,
+            2950 => PostgresColType::Uuid,
+            3802 => PostgresColType::Jsonb,
+            // arrays: pg_type.typarray of the element types above
+            1000 => PostgresColType::Array(16),
+            1001 => PostgresColType::Array(17),
+            1002 => PostgresColType::Array(18),
+            1003 => PostgresColType::Array(19),
+            1016 => PostgresColType::Array(20),
+            1005 => PostgresColType::Array(21),
+            1007 => PostgresColType::Array(23),
+            1009 => PostgresColType::Array(25),
+            1028 => PostgresColType::Array(26),
+            199 => PostgresColType::Array(114),
+            1021 => PostgresColType::Array(700),
+            1022 => PostgresColType::Array(701),
+            1041 => PostgresColType::Array(869),
+            1015 => PostgresColType::Array(1043),
+            1182 => PostgresColType::Array(1082),
+            1183 => PostgresColType::Array(1083),
+            1115 => PostgresColType::Array(1114),
+            1185 => PostgresColType::Array(1184),
+            1187 => PostgresColType::Array(1186),
+            1231 => PostgresColType::Array(1700),
+            2951 => PostgresColType::Array(2950),
+            3807 => PostgresColType::Array(3802),
             _ => PostgresColType::Other,
         }
     }
 }
 
+/// the transaction status indicator of a ReadyForQuery message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostgresTransactionState {
+    Idle,
+    InTransaction,
+    Failed,
+}
+
+impl PostgresTransactionState {
+    fn from_status_byte(status: u8) -> Option<PostgresTransactionState> {
+        match status {
+            b'I' => Some(PostgresTransactionState::Idle),
+            b'T' => Some(PostgresTransactionState::InTransaction),
+            b'E' => Some(PostgresTransactionState::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// the format code of a parameter or column value
+/// https://www.postgresql.org/docs/12/protocol-overview.html#PROTOCOL-FORMAT-CODES
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostgresFormat {
+    Text,
+    Binary,
+}
+
+impl PostgresFormat {
+    fn from_format_code(code: i32) -> PostgresFormat {
+        if code == 1 {
+            PostgresFormat::Binary
+        } else {
+            PostgresFormat::Text
+        }
+    }
+}
+
+/// the request of an AuthenticationXXX message from the server
+/// https://www.postgresql.org/docs/12/protocol-message-formats.html
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostgresAuthRequest {
+    Ok,
+    CleartextPassword,
+    Md5Password,
+    Gss,
+    Sspi,
+    Sasl,
+    SaslContinue,
+    SaslFinal,
+    Other,
+}
+
+impl PostgresAuthRequest {
+    fn from_auth_type(auth_type: u32) -> PostgresAuthRequest {
+        match auth_type {
+            0 => PostgresAuthRequest::Ok,
+            3 => PostgresAuthRequest::CleartextPassword,
+            5 => PostgresAuthRequest::Md5Password,
+            7 | 8 => PostgresAuthRequest::Gss,
+            9 => PostgresAuthRequest::Sspi,
+            10 => PostgresAuthRequest::Sasl,
+            11 => PostgresAuthRequest::SaslContinue,
+            12 => PostgresAuthRequest::SaslFinal,
+            _ => PostgresAuthRequest::Other,
+        }
+    }
+
+    pub fn desc(&self) -> &'static str {
+        match self {
+            PostgresAuthRequest::Ok => "ok",
+            PostgresAuthRequest::CleartextPassword => "cleartext password",
+            PostgresAuthRequest::Md5Password => "MD5 password",
+            PostgresAuthRequest::Gss => "GSSAPI",
+            PostgresAuthRequest::Sspi => "SSPI",
+            PostgresAuthRequest::Sasl
+            | PostgresAuthRequest::SaslContinue
+            | PostgresAuthRequest::SaslFinal => "SASL",
+            PostgresAuthRequest::Other => "other",
+        }
+    }
+}
+
+/// the fields of an ErrorResponse or NoticeResponse message
+/// https://www.postgresql.org/docs/12/protocol-error-fields.html
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PostgresErrorInfo {
+    pub severity: Option<String>,
+    pub code: Option<String>, // SQLSTATE
+    pub message: Option<String>,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    pub position: Option<u32>,
+}
+
 #[derive(Debug)]
 pub enum PostgresWireMessage {
     Startup {
@@ -54,7 +201,26 @@ pub enum PostgresWireMessage {
         database: Option<String>,
         application: Option<String>,
     },
-    CopyData,
+    // COPY ... FROM STDIN / TO STDOUT: the server answers the query with
+    // CopyInResponse or CopyOutResponse, then the rows are streamed in
+    // CopyData messages, until CopyDone (or CopyFail).
+    CopyInResponse {
+        format: PostgresFormat,
+    },
+    CopyOutResponse {
+        format: PostgresFormat,
+    },
+    CopyData {
+        data: Vec<u8>,
+    },
+    CopyDone,
+    CopyFail,
+    // simple query protocol: the query string comes in one message,
+    // there may be several statements in it, each completed by a
+    // CommandComplete, and then one ReadyForQuery at the end.
+    SimpleQuery {
+        query: Option<String>,
+    },
     Parse {
         query: Option<String>,
         statement: Option<String>,
@@ -65,16 +231,46 @@ pub enum PostgresWireMessage {
     // we can then recover the query from the statement id in post-processing.
     Bind {
         statement: Option<String>,
+        parameter_formats: Vec<PostgresFormat>,
         parameter_lengths_and_vals: Vec<(i64, String)>,
+        result_formats: Vec<PostgresFormat>,
     },
     RowDescription {
         col_names: Vec<String>,
         col_types: Vec<PostgresColType>,
+        col_formats: Vec<PostgresFormat>,
     },
     ResultSetRow {
         col_lengths_and_vals: Vec<(i64, String)>,
     },
-    ReadyForQuery,
+    CommandComplete {
+        tag: Option<String>,
+    },
+    ErrorResponse(PostgresErrorInfo),
+    NoticeResponse(PostgresErrorInfo),
+    ReadyForQuery {
+        transaction_state: Option<PostgresTransactionState>,
+    },
+    // the server answers the startup message with authentication requests,
+    // until AuthenticationOk (or an ErrorResponse), then BackendKeyData
+    AuthenticationRequest {
+        request: PostgresAuthRequest,
+        sasl_mechanisms: Vec<String>,
+    },
+    BackendKeyData {
+        pid: Option<u32>,
+        key: Option<u32>,
+    },
+    // asynchronous messages, the server may send them at any time
+    ParameterStatus {
+        name: Option<String>,
+        value: Option<String>,
+    },
+    NotificationResponse {
+        pid: Option<u32>,
+        channel: Option<String>,
+        payload: Option<String>,
+    },
 }
 
 pub fn parse_pgsql_info<B: BufRead>(
@@ -91,18 +287,56 @@ pub fn parse_pgsql_info<B: BufRead>(
                         Some("Startup message") => {
                             return Ok(Some(parse_startup_message(xml_reader)?));
                         }
-                        Some("Copy data") => return Ok(Some(PostgresWireMessage::CopyData)),
+                        Some("CopyIn response") => {
+                            let format = parse_copy_response_format(xml_reader)?;
+                            return Ok(Some(PostgresWireMessage::CopyInResponse { format }));
+                        }
+                        Some("CopyOut response") => {
+                            let format = parse_copy_response_format(xml_reader)?;
+                            return Ok(Some(PostgresWireMessage::CopyOutResponse { format }));
+                        }
+                        Some("Copy data") => return Ok(Some(parse_copy_data_message(xml_reader)?)),
+                        Some("Copy completion") => return Ok(Some(PostgresWireMessage::CopyDone)),
+                        Some("Copy failure") => return Ok(Some(PostgresWireMessage::CopyFail)),
+                        Some("Simple query") => {
+                            return Ok(Some(parse_simple_query_message(xml_reader)?));
+                        }
                         Some("Parse") => {
                             return Ok(Some(parse_parse_message(xml_reader)?));
                         },
                         Some("Bind") => return Ok(Some(parse_bind_message(xml_reader)?)),
                         Some("Ready for query") => {
-                            return Ok(Some(PostgresWireMessage::ReadyForQuery));
+                            return Ok(Some(parse_ready_for_query_message(xml_reader)?));
                         }
                         Some("Row description") => {
                             return Ok(Some(parse_row_description_message(xml_reader)?));
                         }
                         Some("Data row") => return Ok(Some(parse_data_row_message(xml_reader)?)),
+                        Some("Command completion") => {
+                            return Ok(Some(parse_command_complete_message(xml_reader)?));
+                        }
+                        Some("Error") => {
+                            return Ok(Some(PostgresWireMessage::ErrorResponse(
+                                parse_error_info(xml_reader)?,
+                            )));
+                        }
+                        Some("Notice") => {
+                            return Ok(Some(PostgresWireMessage::NoticeResponse(
+                                parse_error_info(xml_reader)?,
+                            )));
+                        }
+                        Some("Authentication request") => {
+                            return Ok(Some(parse_authentication_request_message(xml_reader)?));
+                        }
+                        Some("Backend key data") => {
+                            return Ok(Some(parse_backend_key_data_message(xml_reader)?));
+                        }
+                        Some("Parameter status") => {
+                            return Ok(Some(parse_parameter_status_message(xml_reader)?));
+                        }
+                        Some("Notification response") => {
+                            return Ok(Some(parse_notification_response_message(xml_reader)?));
+                        }
                         _ => {}
                     }
                 }
@@ -162,6 +396,139 @@ fn parse_startup_message<B: BufRead>(
     )
 }
 
+fn parse_authentication_request_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut request = PostgresAuthRequest::Other;
+    let mut sasl_mechanisms = vec![];
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                match name.as_deref() {
+                    Some(b"pgsql.authtype") => {
+                        if let Some(auth_type) =
+                            tshark_communication::element_attr_val_number(e, b"show")?
+                        {
+                            request = PostgresAuthRequest::from_auth_type(auth_type);
+                        }
+                    }
+                    Some(b"pgsql.auth.sasl.mech") => {
+                        if let Some(mech) = tshark_communication::element_attr_val_string(e, b"show")? {
+                            sasl_mechanisms.push(mech);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::AuthenticationRequest {
+                    request,
+                    sasl_mechanisms,
+                });
+            }
+        }
+    )
+}
+
+fn parse_backend_key_data_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut pid = None;
+    let mut key = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                match name.as_deref() {
+                    Some(b"pgsql.pid") => {
+                        pid = tshark_communication::element_attr_val_number(e, b"show")?
+                    }
+                    Some(b"pgsql.key") => {
+                        key = tshark_communication::element_attr_val_number(e, b"show")?
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::BackendKeyData { pid, key });
+            }
+        }
+    )
+}
+
+fn parse_parameter_status_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut name = None;
+    let mut value = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let field_name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                match field_name.as_deref() {
+                    Some(b"pgsql.parameter_name") => {
+                        name = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    Some(b"pgsql.parameter_value") => {
+                        value = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::ParameterStatus { name, value });
+            }
+        }
+    )
+}
+
+fn parse_notification_response_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut pid = None;
+    let mut channel = None;
+    let mut payload = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                match name.as_deref() {
+                    Some(b"pgsql.pid") => {
+                        pid = tshark_communication::element_attr_val_number(e, b"show")?
+                    }
+                    Some(b"pgsql.channel") => {
+                        channel = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    Some(b"pgsql.payload") => {
+                        payload = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::NotificationResponse {
+                    pid,
+                    channel,
+                    payload,
+                });
+            }
+        }
+    )
+}
+
 fn parse_parse_message<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
 ) -> Result<PostgresWireMessage, String> {
@@ -203,6 +570,152 @@ fn parse_parse_message<B: BufRead>(
     )
 }
 
+/// the overall format of the COPY comes first, then the per-column formats,
+/// which must all be the same as the overall format.
+fn parse_copy_response_format<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresFormat, String> {
+    let mut format = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" && format.is_none() {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.format") {
+                    format = tshark_communication::element_attr_val_number(e, b"show")?
+                        .map(PostgresFormat::from_format_code);
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(format.unwrap_or(PostgresFormat::Text));
+            }
+        }
+    )
+}
+
+fn parse_copy_data_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut data = vec![];
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.copydata") {
+                    if let Some(val) = tshark_communication::element_attr_val_string(e, b"value")? {
+                        data = hex::decode(&val).map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::CopyData { data });
+            }
+        }
+    )
+}
+
+fn parse_ready_for_query_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut transaction_state = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.status") {
+                    // wireshark gives us the status byte as a number: 73 for 'I'
+                    transaction_state = tshark_communication::element_attr_val_number(e, b"show")?
+                        .and_then(PostgresTransactionState::from_status_byte);
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::ReadyForQuery { transaction_state });
+            }
+        }
+    )
+}
+
+fn parse_simple_query_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut query = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.query") {
+                    query = tshark_communication::element_attr_val_string(e, b"show")?;
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::SimpleQuery { query });
+            }
+        }
+    )
+}
+
+fn parse_command_complete_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut tag = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.tag") {
+                    tag = tshark_communication::element_attr_val_string(e, b"show")?;
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::CommandComplete { tag });
+            }
+        }
+    )
+}
+
+fn parse_error_info<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresErrorInfo, String> {
+    let mut info = PostgresErrorInfo::default();
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                let val = tshark_communication::element_attr_val_string(e, b"show")?;
+                match name.as_deref() {
+                    Some(b"pgsql.severity") => info.severity = val,
+                    Some(b"pgsql.code") => info.code = val,
+                    Some(b"pgsql.message") => info.message = val,
+                    Some(b"pgsql.detail") => info.detail = val,
+                    Some(b"pgsql.hint") => info.hint = val,
+                    Some(b"pgsql.position") => info.position = val.and_then(|p| p.parse().ok()),
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(info);
+            }
+        }
+    )
+}
+
 fn parse_param_types<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
 ) -> Result<Vec<PostgresColType>, String> {
@@ -231,7 +744,9 @@ fn parse_bind_message<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
 ) -> Result<PostgresWireMessage, String> {
     let mut statement = None;
+    let mut parameter_formats = vec![];
     let mut parameter_lengths_and_vals = vec![];
+    let mut result_formats = vec![];
     let buf = &mut vec![];
     xml_event_loop!(xml_reader, buf,
         Ok(Event::Empty(ref e)) => {
@@ -249,8 +764,17 @@ fn parse_bind_message<B: BufRead>(
                 if name.as_deref() == Some(b"") {
                     let show =
                         tshark_communication::element_attr_val_string(e, b"show")?;
-                    if show.filter(|s| s.starts_with("Parameter values")).is_some() {
-                        parameter_lengths_and_vals = parse_parameter_values(xml_reader)?;
+                    match show.as_deref() {
+                        Some(s) if s.starts_with("Parameter formats") => {
+                            parameter_formats = parse_format_codes(xml_reader)?;
+                        }
+                        Some(s) if s.starts_with("Parameter values") => {
+                            parameter_lengths_and_vals = parse_parameter_values(xml_reader)?;
+                        }
+                        Some(s) if s.starts_with("Result formats") => {
+                            result_formats = parse_format_codes(xml_reader)?;
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -259,13 +783,39 @@ fn parse_bind_message<B: BufRead>(
             if e.name() == b"proto" {
                 return Ok(PostgresWireMessage::Bind {
                     statement,
+                    parameter_formats,
                     parameter_lengths_and_vals,
+                    result_formats,
                 });
             }
         }
     )
 }
 
+fn parse_format_codes<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<Vec<PostgresFormat>, String> {
+    let mut result = vec![];
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.format") {
+                    if let Some(code) = tshark_communication::element_attr_val_number(e, b"show")? {
+                        result.push(PostgresFormat::from_format_code(code));
+                    }
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"field" {
+                return Ok(result);
+            }
+        }
+    )
+}
+
 fn parse_parameter_values<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
 ) -> Result<Vec<(i64, String)>, String> {
@@ -317,15 +867,24 @@ fn parse_row_description_message<B: BufRead>(
 ) -> Result<PostgresWireMessage, String> {
     let mut col_names = vec![];
     let mut col_types = vec![];
+    let mut col_formats = vec![];
     let buf = &mut vec![];
     xml_event_loop!(xml_reader, buf,
         Ok(Event::Empty(ref e)) => {
             if e.name() == b"field" {
                 let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
-                if name.as_deref() == Some(b"pgsql.oid.type")  {
-                    if let Some(oid) = tshark_communication::element_attr_val_string(e, b"show")? {
-                        col_types.push(PostgresColType::from_pg_oid_type(&oid));
+                match name.as_deref() {
+                    Some(b"pgsql.oid.type") => {
+                        if let Some(oid) = tshark_communication::element_attr_val_string(e, b"show")? {
+                            col_types.push(PostgresColType::from_pg_oid_type(&oid));
+                        }
+                    }
+                    Some(b"pgsql.format") => {
+                        if let Some(code) = tshark_communication::element_attr_val_number(e, b"show")? {
+                            col_formats.push(PostgresFormat::from_format_code(code));
+                        }
                     }
+                    _ => {}
                 }
             }
         }
@@ -344,6 +903,7 @@ fn parse_row_description_message<B: BufRead>(
                 return Ok(PostgresWireMessage::RowDescription {
                     col_names,
                     col_types,
+                    col_formats,
                 });
             }
         }
@@ -1,12 +1,17 @@
 use crate::{
     custom_streams_store::{ClientServerInfo, CustomStreamsStore},
-    http::http_streams_store::HttpStreamsStore,
+    http::http_streams_store::{HttpMessageData, HttpStreamsStore},
     http2::http2_streams_store::Http2StreamsStore,
     pgsql::postgres_streams_store::PostgresStreamsStore,
     tshark_communication::{TSharkPacket, TcpStreamId},
+    websocket::websocket_streams_store,
+    websocket::websocket_streams_store::WebSocketStreamsStore,
 };
 use itertools::Itertools;
 
+#[cfg(test)]
+use crate::tshark_communication::parse_test_xml_no_wrapper;
+
 pub struct Streams {
     // this field name is 200% wrong
     streams: Vec<Box<dyn CustomStreamsStore>>,
@@ -22,6 +27,9 @@ impl Default for Streams {
     fn default() -> Streams {
         Streams {
             streams: vec![
+                // before the HTTP store, which would otherwise claim the upgrade
+                // responses on the connections it didn't see so far
+                Box::new(WebSocketStreamsStore::default()),
                 Box::new(HttpStreamsStore::default()),
                 Box::new(PostgresStreamsStore::default()),
                 Box::new(Http2StreamsStore::default()),
@@ -50,6 +58,19 @@ impl Streams {
         self.streams.get(store_index).unwrap()
     }
 
+    /// the store of a given type, to call the methods which are specific to it
+    pub fn get_store<T: 'static>(&self) -> Option<&T> {
+        self.streams
+            .iter()
+            .find_map(|s| s.as_any().downcast_ref::<T>())
+    }
+
+    fn get_store_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.streams
+            .iter_mut()
+            .find_map(|s| s.as_any_mut().downcast_mut::<T>())
+    }
+
     pub fn tcp_stream_ids(&self) -> Vec<TcpStreamId> {
         self.streams
             .iter()
@@ -69,6 +90,59 @@ impl Streams {
             .find_map(|s| s.stream_summary_details(stream_id))
     }
 
+    /// the HTTP exchanges for the HAR export. `include` gets the store index,
+    /// the stream id, the client/server info and the index of the message in the stream.
+    pub fn http_exchanges<F>(
+        &self,
+        include: F,
+    ) -> Vec<(TcpStreamId, ClientServerInfo, &HttpMessageData)>
+    where
+        F: Fn(usize, TcpStreamId, ClientServerInfo, usize) -> bool,
+    {
+        let mut result = vec![];
+        for (store_index, store) in self.streams.iter().enumerate() {
+            for stream_id in store.tcp_stream_ids() {
+                if let (Some(messages), Some(cs)) = (
+                    store_http_messages(store.as_ref(), stream_id),
+                    store.stream_client_server(stream_id),
+                ) {
+                    result.extend(
+                        messages
+                            .iter()
+                            .enumerate()
+                            .filter(|(idx, _)| include(store_index, stream_id, cs, *idx))
+                            .map(|(_, msg)| (stream_id, cs, msg)),
+                    );
+                }
+            }
+        }
+        result
+    }
+
+    /// the HTTP exchanges of the stream, if it's a HTTP or HTTP2 stream
+    pub fn stream_http_messages(&self, stream_id: TcpStreamId) -> Option<&[HttpMessageData]> {
+        let store_index = self.get_store_index(stream_id)?;
+        store_http_messages(self.get_streams_store(store_index).as_ref(), stream_id)
+    }
+
+    /// in live captures, event streams get new events after we displayed them
+    pub fn display_new_server_sent_events(
+        &self,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+        displayed_count: usize,
+    ) {
+        let store = match self.get_store_index(stream_id) {
+            Some(idx) => self.get_streams_store(idx).as_any(),
+            None => return,
+        };
+        if let Some(http) = store.downcast_ref::<HttpStreamsStore>() {
+            http.display_new_server_sent_events(stream_id, msg_idx, displayed_count);
+        } else if let Some(http2) = store.downcast_ref::<Http2StreamsStore>() {
+            http2.display_new_server_sent_events(stream_id, msg_idx, displayed_count);
+        }
+    }
+
     pub fn get_store_index(&self, stream_id: TcpStreamId) -> Option<usize> {
         self.streams.iter().position(|s| s.has_stream_id(stream_id))
     }
@@ -118,6 +192,33 @@ impl Streams {
             .join(" || ")
     }
 
+    /// the HTTP store has the connection until the server accepts
+    /// to switch it to WebSocket, then the WebSocket store takes it over
+    fn hand_over_upgraded_stream(&mut self, p: &TSharkPacket) -> bool {
+        let is_upgrade = p
+            .http
+            .as_ref()
+            .filter(|h| {
+                h.iter()
+                    .any(websocket_streams_store::is_websocket_upgrade_response)
+            })
+            .is_some();
+        if !is_upgrade {
+            return false;
+        }
+        let stream_id = p.basic_info.tcp_stream_id;
+        let http_stream = self
+            .get_store_mut::<HttpStreamsStore>()
+            .and_then(|http| http.remove_upgraded_stream(stream_id));
+        match (http_stream, self.get_store_mut::<WebSocketStreamsStore>()) {
+            (Some((client_server, messages)), Some(ws)) => {
+                ws.take_over_http_stream(stream_id, client_server, messages);
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn get_stream_store_for_packet(
         &mut self,
         p: &TSharkPacket,
@@ -136,9 +237,15 @@ impl Streams {
         &mut self,
         p: TSharkPacket,
     ) -> Result<Option<PacketAddedData>, String> {
+        let is_handed_over = self.hand_over_upgraded_stream(&p);
         if let Some((store_index, store)) = self.get_stream_store_for_packet(&p) {
             let packet_stream_id = p.basic_info.tcp_stream_id;
-            let message_count_before = store.stream_message_count(packet_stream_id).unwrap_or(0);
+            // a stream which changed stores is new to the store, including its first messages
+            let message_count_before = if is_handed_over {
+                0
+            } else {
+                store.stream_message_count(packet_stream_id).unwrap_or(0)
+            };
             let session_change_type = if message_count_before > 0 {
                 // existing stream
                 SessionChangeType::NewDataInSession
@@ -161,3 +268,112 @@ impl Streams {
         }
     }
 }
+
+fn store_http_messages(
+    store: &dyn CustomStreamsStore,
+    stream_id: TcpStreamId,
+) -> Option<&[HttpMessageData]> {
+    let store = store.as_any();
+    match store.downcast_ref::<HttpStreamsStore>() {
+        Some(http) => http.stream_http_messages(stream_id),
+        None => store
+            .downcast_ref::<Http2StreamsStore>()
+            .and_then(|http2| http2.stream_http_messages(stream_id)),
+    }
+}
+
+#[cfg(test)]
+fn test_upgrade_packets(response_first_line: &str) -> Vec<TSharkPacket> {
+    parse_test_xml_no_wrapper(&format!(
+        r#"
+      <pdml>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.1" />
+              <field name="ip.dst" show="10.0.0.2" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="53092" />
+            <field name="tcp.dstport" show="80" />
+          </proto>
+          <proto name="http">
+            <field name="" show="GET /chat HTTP/1.1\r\n"></field>
+            <field name="http.request.line" show="Host: example.com&#x0d;&#x0a;" />
+            <field name="http.request.line" show="Upgrade: websocket&#x0d;&#x0a;" />
+          </proto>
+        </packet>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.2" />
+              <field name="ip.dst" show="10.0.0.1" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+          </proto>
+          <proto name="http">
+            <field name="" show="{}\r\n"></field>
+            <field name="http.response.line" show="Upgrade: websocket&#x0d;&#x0a;" />
+          </proto>
+        </packet>
+      </pdml>
+        "#,
+        response_first_line
+    ))
+    .unwrap()
+}
+
+#[test]
+fn should_hand_over_accepted_websocket_upgrades() {
+    let mut streams = Streams::default();
+    let mut packets = test_upgrade_packets("HTTP/1.1 101 Switching Protocols").into_iter();
+    let request_added = streams
+        .handle_got_packet(packets.next().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        "HTTP",
+        streams
+            .get_streams_store(request_added.store_index)
+            .protocol_name()
+    );
+    let response_added = streams
+        .handle_got_packet(packets.next().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        "WebSocket",
+        streams
+            .get_streams_store(response_added.store_index)
+            .protocol_name()
+    );
+    assert_eq!(0, response_added.message_count_before);
+    assert!(response_added.session_change_type == SessionChangeType::NewSession);
+    assert_eq!(Some(2), streams.stream_message_count(TcpStreamId(0)));
+    assert_eq!(
+        Some("example.com/chat"),
+        streams.stream_summary_details(TcpStreamId(0))
+    );
+    assert!(!streams
+        .get_store::<HttpStreamsStore>()
+        .unwrap()
+        .has_stream_id(TcpStreamId(0)));
+}
+
+#[test]
+fn should_keep_refused_websocket_upgrades_in_http() {
+    let mut streams = Streams::default();
+    for p in test_upgrade_packets("HTTP/1.1 400 Bad Request") {
+        streams.handle_got_packet(p).unwrap();
+    }
+    assert_eq!(
+        Some("HTTP"),
+        streams
+            .get_store_index(TcpStreamId(0))
+            .map(|idx| streams.get_streams_store(idx).protocol_name())
+    );
+    assert_eq!(
+        1,
+        streams.stream_http_messages(TcpStreamId(0)).unwrap().len()
+    );
+}
@@ -1,6 +1,7 @@
 use crate::http::tshark_http;
 use crate::http2::tshark_http2;
 use crate::pgsql::tshark_pgsql;
+use crate::websocket::tshark_websocket;
 use chrono::NaiveDateTime;
 use quick_xml::events::attributes::Attributes;
 use quick_xml::events::Event;
@@ -94,6 +95,7 @@ pub struct TSharkPacket {
     pub http: Option<Vec<tshark_http::TSharkHttp>>,
     pub http2: Option<Vec<tshark_http2::TSharkHttp2Message>>,
     pub pgsql: Option<Vec<tshark_pgsql::PostgresWireMessage>>,
+    pub websocket: Option<Vec<tshark_websocket::TSharkWebSocketFrame>>,
     pub tcp_payload: Option<Vec<u8>>,
     pub is_malformed: bool,
 }
@@ -113,6 +115,7 @@ pub fn parse_packet<B: BufRead>(
     let mut http = None::<Vec<tshark_http::TSharkHttp>>;
     let mut http2 = None::<Vec<tshark_http2::TSharkHttp2Message>>;
     let mut pgsql = None::<Vec<tshark_pgsql::PostgresWireMessage>>;
+    let mut websocket = None::<Vec<tshark_websocket::TSharkWebSocketFrame>>;
     let mut is_malformed = false;
     temp_tcp_payload.clear();
     let buf = &mut vec![];
@@ -168,6 +171,15 @@ pub fn parse_packet<B: BufRead>(
                             }
                         }
                     }
+                    Some(b"websocket") => {
+                        let frame = tshark_websocket::parse_websocket_info(xml_reader)?;
+                        if let Some(mut sofar) = websocket {
+                            sofar.push(frame);
+                            websocket = Some(sofar);
+                        } else {
+                            websocket = Some(vec![frame]);
+                        }
+                    }
                     Some(b"icmp") => {
                         // need to skip ICMP info, because it also contains IP info that can confuse us
                         skip_until_proto_end(xml_reader)?;
@@ -181,7 +193,7 @@ pub fn parse_packet<B: BufRead>(
         }
         Ok(Event::End(ref e)) => {
             let tcp_payload =
-                if !temp_tcp_payload.is_empty() && http1_streams.contains(&tcp_stream_id) && http.is_none() {
+                if !temp_tcp_payload.is_empty() && http1_streams.contains(&tcp_stream_id) && http.is_none() && websocket.is_none() {
                     hex::decode(&temp_tcp_payload).ok()
                 } else {
                     None
@@ -200,6 +212,7 @@ pub fn parse_packet<B: BufRead>(
                     http,
                     http2,
                     pgsql,
+                    websocket,
                     tcp_payload,
                     is_malformed
                 });
@@ -0,0 +1,3 @@
+pub mod tshark_websocket;
+mod websocket_details_widget;
+pub mod websocket_streams_store;
@@ -0,0 +1,101 @@
+// https://datatracker.ietf.org/doc/html/rfc6455#section-5.2
+use crate::tshark_communication;
+use quick_xml::events::Event;
+use std::io::BufRead;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TSharkWebSocketFrame {
+    pub fin: bool,
+    // RSV1 is set for frames compressed with permessage-deflate
+    pub is_compressed: bool,
+    pub opcode: u8,
+    pub is_masked: bool,
+    // unmasked
+    pub payload: Vec<u8>,
+}
+
+pub fn parse_websocket_info<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<TSharkWebSocketFrame, String> {
+    let mut fin = false;
+    let mut is_compressed = false;
+    let mut opcode = 0;
+    let mut is_masked = false;
+    let mut masking_key = None;
+    let mut masked_payload = None;
+    let mut payload = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                match name.as_deref() {
+                    Some(b"websocket.fin") => {
+                        fin = tshark_communication::element_attr_val_number(e, b"show")? == Some(1_u8);
+                    }
+                    Some(b"websocket.rsv") => {
+                        // the three reserved bits, RSV1 first
+                        is_compressed |= tshark_communication::element_attr_val_string(e, b"value")?
+                            .and_then(|v| u8::from_str_radix(&v, 16).ok())
+                            .filter(|rsv| rsv & 0x4 != 0)
+                            .is_some();
+                    }
+                    Some(b"websocket.pmc") => {
+                        is_compressed |= tshark_communication::element_attr_val_number(e, b"show")? == Some(1_u8);
+                    }
+                    Some(b"websocket.opcode") => {
+                        opcode = tshark_communication::element_attr_val_number(e, b"show")?.unwrap_or(0);
+                    }
+                    Some(b"websocket.mask") => {
+                        is_masked = tshark_communication::element_attr_val_number(e, b"show")? == Some(1_u8);
+                    }
+                    Some(b"websocket.masking_key") => {
+                        masking_key = tshark_communication::element_attr_val_string(e, b"value")?
+                            .and_then(|v| hex::decode(v).ok());
+                    }
+                    Some(b"websocket.masked_payload") => {
+                        masked_payload = tshark_communication::element_attr_val_string(e, b"value")?
+                            .and_then(|v| hex::decode(v).ok());
+                    }
+                    Some(b"websocket.payload")
+                    | Some(b"websocket.unmasked_payload")
+                    | Some(b"websocket.payload.text")
+                    | Some(b"websocket.payload.binary") if payload.is_none() => {
+                        // the text and binary fields are children of the payload one,
+                        // some tshark versions only give us those
+                        payload = tshark_communication::element_attr_val_string(e, b"value")?
+                            .and_then(|v| hex::decode(v).ok());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                // depending on the version, tshark may only give us the masked payload
+                let payload = payload
+                    .or_else(|| Some(unmask(&masked_payload?, &masking_key?)))
+                    .unwrap_or_default();
+                return Ok(TSharkWebSocketFrame {
+                    fin,
+                    is_compressed,
+                    opcode,
+                    is_masked,
+                    payload,
+                });
+            }
+        }
+    )
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc6455#section-5.3
+fn unmask(masked: &[u8], masking_key: &[u8]) -> Vec<u8> {
+    if masking_key.is_empty() {
+        return masked.to_vec();
+    }
+    masked
+        .iter()
+        .zip(masking_key.iter().cycle())
+        .map(|(b, k)| b ^ k)
+        .collect()
+}
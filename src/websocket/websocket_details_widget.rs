@@ -0,0 +1,176 @@
+use super::websocket_streams_store;
+use super::websocket_streams_store::{WebSocketMessageData, WebSocketMessageKind};
+use crate::colors;
+use crate::http::code_formatting;
+use crate::tshark_communication::TcpStreamId;
+use crate::widgets::comm_info_header;
+use crate::widgets::comm_info_header::CommInfoHeader;
+use crate::widgets::hex_viewer;
+use crate::widgets::hex_viewer::HexViewer;
+use crate::widgets::win;
+use gtk::prelude::*;
+use relm::Widget;
+use relm_derive::{widget, Msg};
+use std::net::IpAddr;
+
+pub struct Model {
+    win_msg_sender: relm::StreamHandle<win::Msg>,
+    stream_id: TcpStreamId,
+    client_ip: IpAddr,
+    data: WebSocketMessageData,
+}
+
+#[derive(Msg, Debug)]
+pub enum Msg {
+    DisplayDetails(IpAddr, TcpStreamId, WebSocketMessageData),
+}
+
+#[widget]
+impl Widget for WebSocketCommEntry {
+    fn model(
+        _relm: &relm::Relm<Self>,
+        params: (
+            TcpStreamId,
+            IpAddr,
+            WebSocketMessageData,
+            relm::StreamHandle<win::Msg>,
+        ),
+    ) -> Model {
+        let (stream_id, client_ip, data, win_msg_sender) = params;
+        Model {
+            win_msg_sender,
+            stream_id,
+            client_ip,
+            data,
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::DisplayDetails(client_ip, stream_id, message_data) => {
+                self.streams
+                    .comm_info_header
+                    .emit(comm_info_header::Msg::Update(client_ip, stream_id));
+                self.model.stream_id = stream_id;
+                self.model.client_ip = client_ip;
+                if let Some(bytes) = Self::binary_payload(&message_data) {
+                    self.streams
+                        .hex_viewer
+                        .emit(hex_viewer::Msg::DisplayBytes(bytes.to_vec()));
+                }
+                self.model.data = message_data;
+            }
+        }
+    }
+
+    /// the payload of binary frames, which we display in the hex viewer
+    fn binary_payload(data: &WebSocketMessageData) -> Option<&[u8]> {
+        match &data.kind {
+            WebSocketMessageKind::Frame(f)
+                if f.payload_text().is_none()
+                    && f.close_status().is_none()
+                    && !f.payload.is_empty() =>
+            {
+                Some(&f.payload)
+            }
+            _ => None,
+        }
+    }
+
+    fn info_markup(data: &WebSocketMessageData) -> String {
+        let mut result = format!("<b>{}</b>, {}", data.type_desc(), data.direction.desc());
+        if let WebSocketMessageKind::Frame(f) = &data.kind {
+            let flags = f.flags_desc();
+            if !flags.is_empty() {
+                result.push_str(&format!(" ({})", flags));
+            }
+            result.push_str(&format!(", {} bytes", f.payload.len()));
+            if let Some((code, reason)) = f.close_status() {
+                let close_desc = glib::markup_escape_text(&format!(
+                    "Close code {} {} {}",
+                    code,
+                    websocket_streams_store::close_code_desc(code).unwrap_or(""),
+                    reason
+                ))
+                .to_string();
+                result.push_str(&format!(
+                    "\n{}",
+                    if data.is_error() {
+                        format!(
+                            "<span foreground=\"{}\">{}</span>",
+                            colors::ERROR_COLOR,
+                            close_desc
+                        )
+                    } else {
+                        close_desc
+                    }
+                ));
+            }
+        }
+        result
+    }
+
+    /// text payloads are formatted if they are JSON
+    fn contents_markup(data: &WebSocketMessageData) -> String {
+        match &data.kind {
+            WebSocketMessageKind::Handshake {
+                first_line,
+                headers,
+            } => format!(
+                "<b>{}</b>\n{}",
+                glib::markup_escape_text(first_line),
+                headers
+                    .iter()
+                    .map(|(k, v)| format!(
+                        "<b>{}</b>: {}",
+                        glib::markup_escape_text(k),
+                        glib::markup_escape_text(v)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            WebSocketMessageKind::Frame(f) => match f.payload_text() {
+                Some(txt) => code_formatting::highlight_indent_truncate(
+                    true,
+                    txt,
+                    Some("application/json")
+                        .filter(|_| serde_json::from_str::<serde_json::Value>(txt).is_ok()),
+                ),
+                None => "".to_string(),
+            },
+        }
+    }
+
+    view! {
+        gtk::Box {
+            orientation: gtk::Orientation::Vertical,
+            margin_top: 10,
+            margin_bottom: 10,
+            margin_start: 10,
+            margin_end: 10,
+            spacing: 10,
+            #[name="comm_info_header"]
+            CommInfoHeader(self.model.client_ip, self.model.stream_id) {
+            },
+            gtk::Label {
+                markup: &Self::info_markup(&self.model.data),
+                xalign: 0.0,
+                selectable: true,
+            },
+            gtk::Label {
+                markup: &Self::contents_markup(&self.model.data),
+                visible: Self::binary_payload(&self.model.data).is_none(),
+                line_wrap: true,
+                xalign: 0.0,
+                yalign: 0.0,
+                selectable: true,
+            },
+            gtk::Box {
+                visible: Self::binary_payload(&self.model.data).is_some(),
+                #[name="hex_viewer"]
+                HexViewer(self.model.win_msg_sender.clone()) {
+                },
+            },
+        }
+    }
+}
@@ -0,0 +1,1100 @@
+use super::tshark_websocket::TSharkWebSocketFrame;
+use super::websocket_details_widget;
+use super::websocket_details_widget::WebSocketCommEntry;
+use crate::colors;
+use crate::custom_streams_store;
+use crate::custom_streams_store::{ClientServerInfo, CustomStreamsStore};
+use crate::http::http_streams_store;
+use crate::http::http_streams_store::HttpMessageData;
+use crate::http::tshark_http::{HttpType, TSharkHttp};
+use crate::icons::Icon;
+use crate::search_expr;
+use crate::search_expr::SearchCriteria;
+use crate::tshark_communication::{TSharkPacket, TSharkPacketBasicInfo, TcpStreamId};
+use crate::widgets::win;
+use crate::BgFunc;
+use chrono::{NaiveDateTime, Utc};
+use gtk::prelude::*;
+use itertools::Itertools;
+use relm::ContainerWidget;
+use std::any::Any;
+use std::collections::HashMap;
+use std::io::Write;
+use std::str;
+use std::str::FromStr;
+use std::sync::mpsc;
+use strum::VariantNames;
+use strum_macros::{EnumString, EnumVariantNames};
+
+#[cfg(test)]
+use {
+    crate::custom_streams_store::common_tests_parse_stream,
+    crate::tshark_communication::parse_test_xml_no_wrapper,
+};
+
+const SUMMARY_MAX_CHARS: usize = 200;
+
+#[derive(Default)]
+pub struct WebSocketStreamData {
+    pub client_server: Option<ClientServerInfo>,
+    pub messages: Vec<WebSocketMessageData>,
+    pub summary_details: Option<String>,
+    client_inflater: MessageInflater,
+    server_inflater: MessageInflater,
+}
+
+impl WebSocketStreamData {
+    fn inflater(&mut self, direction: WebSocketDirection) -> &mut MessageInflater {
+        match direction {
+            WebSocketDirection::ClientToServer => &mut self.client_inflater,
+            WebSocketDirection::ServerToClient => &mut self.server_inflater,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WebSocketStreamsStore {
+    streams: HashMap<TcpStreamId, WebSocketStreamData>,
+    component: Option<relm::Component<WebSocketCommEntry>>,
+}
+
+impl WebSocketStreamsStore {
+    /// the HTTP store had the connection until the server accepted the
+    /// upgrade: its exchanges so far become the handshake messages
+    pub fn take_over_http_stream(
+        &mut self,
+        stream_id: TcpStreamId,
+        client_server: Option<ClientServerInfo>,
+        http_messages: Vec<HttpMessageData>,
+    ) {
+        let stream = self.streams.entry(stream_id).or_default();
+        stream.client_server = client_server;
+        for msg in http_messages {
+            if let Some(req) = &msg.request {
+                if stream.summary_details.is_none() {
+                    let path = req.first_line.split(' ').nth(1).unwrap_or("");
+                    stream.summary_details = Some(format!(
+                        "{}{}",
+                        http_streams_store::get_http_header_value(&req.headers, "Host")
+                            .map(String::as_str)
+                            .unwrap_or(""),
+                        path
+                    ));
+                }
+            }
+            let req_resp = msg
+                .request
+                .into_iter()
+                .map(|r| (WebSocketDirection::ClientToServer, r))
+                .chain(
+                    msg.response
+                        .into_iter()
+                        .map(|r| (WebSocketDirection::ServerToClient, r)),
+                );
+            for (direction, r) in req_resp {
+                stream.messages.push(WebSocketMessageData {
+                    timestamp: r.timestamp,
+                    direction,
+                    kind: WebSocketMessageKind::Handshake {
+                        first_line: r.first_line,
+                        headers: r.headers,
+                    },
+                });
+            }
+        }
+    }
+
+    fn get_msg_info(
+        &self,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+    ) -> Option<(&WebSocketMessageData, ClientServerInfo)> {
+        let stream = self.streams.get(&stream_id)?;
+        let msg = stream.messages.get(msg_idx)?;
+        Some((msg, stream.client_server?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebSocketDirection {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl WebSocketDirection {
+    pub fn desc(self) -> &'static str {
+        match self {
+            WebSocketDirection::ClientToServer => "Client → Server",
+            WebSocketDirection::ServerToClient => "Server → Client",
+        }
+    }
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc6455#section-11.8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebSocketOpcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+    Other(u8),
+}
+
+impl WebSocketOpcode {
+    fn from_u8(opcode: u8) -> WebSocketOpcode {
+        match opcode {
+            0x0 => WebSocketOpcode::Continuation,
+            0x1 => WebSocketOpcode::Text,
+            0x2 => WebSocketOpcode::Binary,
+            0x8 => WebSocketOpcode::Close,
+            0x9 => WebSocketOpcode::Ping,
+            0xa => WebSocketOpcode::Pong,
+            o => WebSocketOpcode::Other(o),
+        }
+    }
+
+    pub fn desc(self) -> String {
+        match self {
+            WebSocketOpcode::Continuation => "Continuation".to_string(),
+            WebSocketOpcode::Text => "Text".to_string(),
+            WebSocketOpcode::Binary => "Binary".to_string(),
+            WebSocketOpcode::Close => "Close".to_string(),
+            WebSocketOpcode::Ping => "Ping".to_string(),
+            WebSocketOpcode::Pong => "Pong".to_string(),
+            WebSocketOpcode::Other(o) => format!("Reserved (0x{:x})", o),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketFrame {
+    pub fin: bool,
+    pub opcode: WebSocketOpcode,
+    pub is_masked: bool,
+    pub is_compressed: bool,
+    // unmasked, and decompressed when we could
+    pub payload: Vec<u8>,
+}
+
+impl WebSocketFrame {
+    /// binary frames, and fragments which are not valid UTF-8, have no text
+    pub fn payload_text(&self) -> Option<&str> {
+        match self.opcode {
+            WebSocketOpcode::Text
+            | WebSocketOpcode::Continuation
+            | WebSocketOpcode::Ping
+            | WebSocketOpcode::Pong => str::from_utf8(&self.payload).ok(),
+            _ => None,
+        }
+    }
+
+    /// the status code and reason of a close frame
+    /// https://datatracker.ietf.org/doc/html/rfc6455#section-5.5.1
+    pub fn close_status(&self) -> Option<(u16, String)> {
+        match (self.opcode, self.payload.as_slice()) {
+            (WebSocketOpcode::Close, [b1, b2, reason @ ..]) => Some((
+                u16::from_be_bytes([*b1, *b2]),
+                String::from_utf8_lossy(reason).to_string(),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn flags_desc(&self) -> String {
+        [
+            Some("FIN").filter(|_| self.fin),
+            Some("masked").filter(|_| self.is_masked),
+            Some("compressed").filter(|_| self.is_compressed),
+        ]
+        .iter()
+        .flatten()
+        .join(", ")
+    }
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc6455#section-7.4.1
+pub fn close_code_desc(code: u16) -> Option<&'static str> {
+    match code {
+        1000 => Some("Normal closure"),
+        1001 => Some("Going away"),
+        1002 => Some("Protocol error"),
+        1003 => Some("Unsupported data"),
+        1005 => Some("No status received"),
+        1006 => Some("Abnormal closure"),
+        1007 => Some("Invalid payload data"),
+        1008 => Some("Policy violation"),
+        1009 => Some("Message too big"),
+        1010 => Some("Mandatory extension"),
+        1011 => Some("Internal error"),
+        1012 => Some("Service restart"),
+        1013 => Some("Try again later"),
+        1014 => Some("Bad gateway"),
+        1015 => Some("TLS handshake failure"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebSocketMessageKind {
+    // the HTTP/1.1 upgrade request, or the response to it
+    Handshake {
+        first_line: String,
+        headers: Vec<(String, String)>,
+    },
+    Frame(WebSocketFrame),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketMessageData {
+    pub timestamp: NaiveDateTime,
+    pub direction: WebSocketDirection,
+    pub kind: WebSocketMessageKind,
+}
+
+impl WebSocketMessageData {
+    pub fn type_desc(&self) -> String {
+        match &self.kind {
+            WebSocketMessageKind::Handshake { .. } => "Handshake".to_string(),
+            WebSocketMessageKind::Frame(f) => f.opcode.desc(),
+        }
+    }
+
+    pub fn payload_length(&self) -> usize {
+        match &self.kind {
+            WebSocketMessageKind::Handshake { .. } => 0,
+            WebSocketMessageKind::Frame(f) => f.payload.len(),
+        }
+    }
+
+    /// a failed handshake, or a close frame with an abnormal status code
+    pub fn is_error(&self) -> bool {
+        match &self.kind {
+            WebSocketMessageKind::Handshake { first_line, .. } => {
+                self.direction == WebSocketDirection::ServerToClient
+                    && first_line.split(' ').nth(1) != Some("101")
+            }
+            WebSocketMessageKind::Frame(f) => f
+                .close_status()
+                .filter(|(code, _)| *code != 1000 && *code != 1001)
+                .is_some(),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let summary = match &self.kind {
+            WebSocketMessageKind::Handshake { first_line, .. } => first_line.clone(),
+            WebSocketMessageKind::Frame(f) => match (f.close_status(), f.payload_text()) {
+                (Some((code, reason)), _) => format!(
+                    "{} {} {}",
+                    code,
+                    close_code_desc(code).unwrap_or(""),
+                    reason
+                ),
+                (_, Some(txt)) => txt.to_string(),
+                _ => hex::encode(&f.payload[..f.payload.len().min(32)]),
+            },
+        };
+        summary
+            .split_whitespace()
+            .join(" ")
+            .chars()
+            .take(SUMMARY_MAX_CHARS)
+            .collect()
+    }
+}
+
+#[derive(EnumString, EnumVariantNames)]
+enum WebSocketStringFilterKeys {
+    #[strum(serialize = "ws.payload")]
+    Payload,
+    #[strum(serialize = "ws.opcode")]
+    Opcode,
+}
+
+#[derive(EnumString, EnumVariantNames)]
+enum WebSocketNumericFilterKeys {
+    #[strum(serialize = "ws.payload_length")]
+    PayloadLength,
+}
+
+fn get_ws_message<'a, 'b>(
+    streams: &'a HashMap<TcpStreamId, WebSocketStreamData>,
+    model: &'b gtk::TreeModel,
+    iter: &'b gtk::TreeIter,
+) -> Option<&'a WebSocketMessageData> {
+    let (stream_id, idx) = custom_streams_store::get_message_helper(model, iter);
+    streams
+        .get(&stream_id)
+        .and_then(|s| s.messages.get(idx as usize))
+}
+
+/// the server accepted to switch the connection to WebSocket. We don't take
+/// the connection on the upgrade request: the server may refuse it, and
+/// the connection then goes on with HTTP
+pub fn is_websocket_upgrade_response(http: &TSharkHttp) -> bool {
+    http.http_type == Some(HttpType::Response)
+        && http.first_line.split(' ').nth(1) == Some("101")
+        && http_streams_store::get_http_header_value(
+            &http_streams_store::parse_headers(&http.other_lines),
+            "Upgrade",
+        )
+        .filter(|v| v.eq_ignore_ascii_case("websocket"))
+        .is_some()
+}
+
+fn packet_direction(
+    client_server: Option<ClientServerInfo>,
+    basic_info: &TSharkPacketBasicInfo,
+) -> WebSocketDirection {
+    match client_server {
+        Some(cs) if (cs.server_ip, cs.server_port) == (basic_info.ip_src, basic_info.port_src) => {
+            WebSocketDirection::ServerToClient
+        }
+        _ => WebSocketDirection::ClientToServer,
+    }
+}
+
+/// permessage-deflate, https://datatracker.ietf.org/doc/html/rfc7692#section-7.2.2
+/// Unless the peers negotiated "no context takeover", the messages of a
+/// direction share the compression context: a message can refer to the
+/// data of the previous ones, so we keep one decoder per direction.
+struct MessageInflater {
+    decoder: flate2::write::DeflateDecoder<Vec<u8>>,
+    no_context_takeover: bool,
+    // the compressed payload of a message split in several frames, so far
+    fragments: Option<Vec<u8>>,
+}
+
+impl Default for MessageInflater {
+    fn default() -> Self {
+        MessageInflater {
+            decoder: flate2::write::DeflateDecoder::new(vec![]),
+            no_context_takeover: false,
+            fragments: None,
+        }
+    }
+}
+
+impl MessageInflater {
+    fn inflate(&mut self, payload: &[u8]) -> Option<Vec<u8>> {
+        if self.no_context_takeover {
+            self.decoder = flate2::write::DeflateDecoder::new(vec![]);
+        }
+        let result = Self::write_message(&mut self.decoder, payload);
+        if result.is_none() {
+            // the context is lost (we missed a message?), start over
+            self.decoder = flate2::write::DeflateDecoder::new(vec![]);
+        }
+        result
+    }
+
+    fn write_message(
+        decoder: &mut flate2::write::DeflateDecoder<Vec<u8>>,
+        payload: &[u8],
+    ) -> Option<Vec<u8>> {
+        // there is no final deflate block, we must flush rather than finish the stream
+        decoder.write_all(payload).ok()?;
+        decoder.write_all(&[0x00, 0x00, 0xff, 0xff]).ok()?;
+        decoder.flush().ok()?;
+        Some(std::mem::take(decoder.get_mut()))
+    }
+}
+
+/// the permessage-deflate parameters the server accepted
+/// https://datatracker.ietf.org/doc/html/rfc7692#section-7.1
+fn negotiated_deflate_params(headers: &[(String, String)]) -> Vec<String> {
+    http_streams_store::get_http_header_value(headers, "Sec-WebSocket-Extensions")
+        .into_iter()
+        .flat_map(|v| v.split(','))
+        .map(|ext| {
+            ext.split(';')
+                .map(|p| p.trim().to_ascii_lowercase())
+                .collect::<Vec<_>>()
+        })
+        .find(|params| params.first().map(String::as_str) == Some("permessage-deflate"))
+        .unwrap_or_default()
+}
+
+/// only the first frame of a message has the compressed flag, and the
+/// message must be reassembled before it's decompressed. We show the
+/// decompressed message on its last frame.
+fn prepare_frame(inflater: &mut MessageInflater, frame: TSharkWebSocketFrame) -> WebSocketFrame {
+    let opcode = WebSocketOpcode::from_u8(frame.opcode);
+    let is_compressed_fragment =
+        opcode == WebSocketOpcode::Continuation && inflater.fragments.is_some();
+    let payload = if frame.is_compressed || is_compressed_fragment {
+        let mut message = inflater.fragments.take().unwrap_or_default();
+        message.extend_from_slice(&frame.payload);
+        if frame.fin {
+            inflater.inflate(&message).unwrap_or(frame.payload)
+        } else {
+            inflater.fragments = Some(message);
+            frame.payload
+        }
+    } else {
+        frame.payload
+    };
+    WebSocketFrame {
+        fin: frame.fin,
+        opcode,
+        is_masked: frame.is_masked,
+        is_compressed: frame.is_compressed,
+        payload,
+    }
+}
+
+impl CustomStreamsStore for WebSocketStreamsStore {
+    fn is_my_message(&self, msg: &TSharkPacket) -> bool {
+        msg.websocket.is_some()
+            || msg
+                .http
+                .as_ref()
+                .filter(|h| h.iter().any(is_websocket_upgrade_response))
+                .is_some()
+    }
+
+    fn tshark_filter_string(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn protocol_icon(&self) -> Icon {
+        Icon::HTTP
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "WebSocket"
+    }
+
+    fn tcp_stream_ids(&self) -> Vec<TcpStreamId> {
+        self.streams.keys().copied().collect()
+    }
+
+    fn has_stream_id(&self, stream_id: TcpStreamId) -> bool {
+        self.streams.contains_key(&stream_id)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    fn stream_client_server(&self, stream_id: TcpStreamId) -> Option<ClientServerInfo> {
+        self.streams.get(&stream_id).and_then(|s| s.client_server)
+    }
+
+    fn reset(&mut self) {
+        self.streams = HashMap::new();
+    }
+
+    fn stream_message_count(&self, stream_id: TcpStreamId) -> Option<usize> {
+        self.streams.get(&stream_id).map(|s| s.messages.len())
+    }
+
+    fn stream_summary_details(&self, stream_id: TcpStreamId) -> Option<&str> {
+        self.streams
+            .get(&stream_id)
+            .and_then(|s| s.summary_details.as_deref())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn add_to_stream(
+        &mut self,
+        stream_id: TcpStreamId,
+        new_packet: TSharkPacket,
+    ) -> Result<Option<ClientServerInfo>, String> {
+        let stream = self.streams.entry(stream_id).or_default();
+        let basic_info = new_packet.basic_info;
+        for http in new_packet.http.into_iter().flatten() {
+            let headers = http_streams_store::parse_headers(&http.other_lines);
+            if http.http_type == Some(HttpType::Request) {
+                if stream.client_server.is_none() {
+                    stream.client_server = Some(ClientServerInfo {
+                        client_ip: basic_info.ip_src,
+                        server_ip: basic_info.ip_dst,
+                        server_port: basic_info.port_dst,
+                    });
+                }
+                if stream.summary_details.is_none() {
+                    let path = http.first_line.split(' ').nth(1).unwrap_or("");
+                    stream.summary_details = Some(format!(
+                        "{}{}",
+                        http.http_host.as_deref().unwrap_or(""),
+                        path
+                    ));
+                }
+            }
+            if http.http_type == Some(HttpType::Response) {
+                let deflate_params = negotiated_deflate_params(&headers);
+                let has_param = |p: &str| deflate_params.iter().any(|dp| dp == p);
+                stream.client_inflater.no_context_takeover =
+                    has_param("client_no_context_takeover");
+                stream.server_inflater.no_context_takeover =
+                    has_param("server_no_context_takeover");
+            }
+            stream.messages.push(WebSocketMessageData {
+                timestamp: basic_info.frame_time,
+                direction: packet_direction(stream.client_server, &basic_info),
+                kind: WebSocketMessageKind::Handshake {
+                    first_line: http.first_line,
+                    headers,
+                },
+            });
+        }
+        for frame in new_packet.websocket.into_iter().flatten() {
+            if stream.client_server.is_none() {
+                // we missed the handshake. Only the client masks its frames
+                stream.client_server = Some(if frame.is_masked {
+                    ClientServerInfo {
+                        client_ip: basic_info.ip_src,
+                        server_ip: basic_info.ip_dst,
+                        server_port: basic_info.port_dst,
+                    }
+                } else {
+                    ClientServerInfo {
+                        client_ip: basic_info.ip_dst,
+                        server_ip: basic_info.ip_src,
+                        server_port: basic_info.port_src,
+                    }
+                });
+            }
+            let direction = packet_direction(stream.client_server, &basic_info);
+            let frame = prepare_frame(stream.inflater(direction), frame);
+            stream.messages.push(WebSocketMessageData {
+                timestamp: basic_info.frame_time,
+                direction,
+                kind: WebSocketMessageKind::Frame(frame),
+            });
+        }
+        Ok(stream.client_server)
+    }
+
+    fn finish_stream(&mut self, _stream_id: TcpStreamId) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn prepare_treeview(&self, tv: &gtk::TreeView) {
+        let streamcolor_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("S")
+            .fixed_width(10)
+            .sort_column_id(2)
+            .build();
+        let cell_s_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        streamcolor_col.pack_start(&cell_s_txt, true);
+        streamcolor_col.add_attribute(&cell_s_txt, "background", 10);
+        tv.append_column(&streamcolor_col);
+
+        let timestamp_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Timestamp")
+            .resizable(true)
+            .sort_column_id(5)
+            .build();
+        let cell_t_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        timestamp_col.pack_start(&cell_t_txt, true);
+        timestamp_col.add_attribute(&cell_t_txt, "text", 4);
+        tv.append_column(&timestamp_col);
+
+        let direction_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Direction")
+            .resizable(true)
+            .sort_column_id(1)
+            .build();
+        let cell_d_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        direction_col.pack_start(&cell_d_txt, true);
+        direction_col.add_attribute(&cell_d_txt, "text", 1);
+        tv.append_column(&direction_col);
+
+        let opcode_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Type")
+            .resizable(true)
+            .sort_column_id(6)
+            .build();
+        let cell_o_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        opcode_col.pack_start(&cell_o_txt, true);
+        opcode_col.add_attribute(&cell_o_txt, "text", 6);
+        tv.append_column(&opcode_col);
+
+        let flags_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Flags")
+            .resizable(true)
+            .sort_column_id(7)
+            .build();
+        let cell_f_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        flags_col.pack_start(&cell_f_txt, true);
+        flags_col.add_attribute(&cell_f_txt, "text", 7);
+        tv.append_column(&flags_col);
+
+        let length_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Length")
+            .resizable(true)
+            .sort_column_id(9)
+            .build();
+        let cell_l_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        length_col.pack_start(&cell_l_txt, true);
+        length_col.add_attribute(&cell_l_txt, "text", 8);
+        tv.append_column(&length_col);
+
+        let payload_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Payload")
+            .expand(true)
+            .resizable(true)
+            .sort_column_id(0)
+            .build();
+        let cell_p_txt = gtk::builders::CellRendererTextBuilder::new()
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        payload_col.pack_start(&cell_p_txt, true);
+        payload_col.add_attribute(&cell_p_txt, "text", 0);
+        payload_col.add_attribute(&cell_p_txt, "foreground", 11);
+        tv.append_column(&payload_col);
+    }
+
+    fn get_empty_liststore(&self) -> gtk::ListStore {
+        gtk::ListStore::new(&[
+            String::static_type(), // payload summary
+            String::static_type(), // direction
+            u32::static_type(),    // stream_id
+            u32::static_type(),    // index of the comm in the model vector
+            String::static_type(), // timestamp (string)
+            i64::static_type(),    // timestamp (integer, for sorting)
+            String::static_type(), // opcode
+            String::static_type(), // flags
+            String::static_type(), // payload length display
+            i64::static_type(),    // payload length, for sorting
+            String::static_type(), // stream color
+            String::static_type(), // summary color (errors)
+        ])
+    }
+
+    fn populate_treeview(
+        &self,
+        ls: &gtk::ListStore,
+        session_id: TcpStreamId,
+        start_idx: usize,
+        item_count: usize,
+    ) {
+        let messages = &self.streams.get(&session_id).unwrap().messages;
+        for (idx, ws) in messages.iter().skip(start_idx).take(item_count).enumerate() {
+            let flags = match &ws.kind {
+                WebSocketMessageKind::Handshake { .. } => "".to_string(),
+                WebSocketMessageKind::Frame(f) => f.flags_desc(),
+            };
+            ls.insert_with_values(
+                None,
+                &[
+                    (0, &ws.summary().to_value()),
+                    (1, &ws.direction.desc().to_value()),
+                    (
+                        custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX,
+                        &session_id.as_u32().to_value(),
+                    ),
+                    (
+                        custom_streams_store::TREE_STORE_MESSAGE_INDEX_COL_IDX,
+                        &((start_idx + idx) as i32).to_value(),
+                    ),
+                    (4, &ws.timestamp.to_string().to_value()),
+                    (5, &ws.timestamp.timestamp_nanos().to_value()),
+                    (6, &ws.type_desc().to_value()),
+                    (7, &flags.to_value()),
+                    (8, &ws.payload_length().to_string().to_value()),
+                    (9, &(ws.payload_length() as i64).to_value()),
+                    (
+                        10,
+                        &colors::STREAM_COLORS
+                            [session_id.as_u32() as usize % colors::STREAM_COLORS.len()]
+                        .to_value(),
+                    ),
+                    (
+                        11,
+                        &if ws.is_error() {
+                            colors::ERROR_COLOR.to_value()
+                        } else {
+                            None::<&str>.to_value()
+                        },
+                    ),
+                ],
+            );
+        }
+    }
+
+    fn end_populate_treeview(&self, tv: &gtk::TreeView, ls: &gtk::ListStore) {
+        let model_sort = gtk::TreeModelSort::new(ls);
+        model_sort.set_sort_column_id(gtk::SortColumn::Index(5), gtk::SortType::Ascending);
+        tv.set_model(Some(&model_sort));
+    }
+
+    fn supported_string_filter_keys(&self) -> &'static [&'static str] {
+        WebSocketStringFilterKeys::VARIANTS
+    }
+
+    fn supported_numeric_filter_keys(&self) -> &'static [&'static str] {
+        WebSocketNumericFilterKeys::VARIANTS
+    }
+
+    fn matches_filter(
+        &self,
+        filter: &search_expr::SearchOpExpr,
+        model: &gtk::TreeModel,
+        iter: &gtk::TreeIter,
+    ) -> bool {
+        let streams = &self.streams;
+        match (
+            WebSocketStringFilterKeys::from_str(filter.filter_key),
+            WebSocketNumericFilterKeys::from_str(filter.filter_key),
+            &filter.op,
+        ) {
+            (Ok(filter_key), _, SearchCriteria::Contains(filter_val)) => {
+                let fv = filter_val.to_lowercase();
+                match filter_key {
+                    WebSocketStringFilterKeys::Payload => get_ws_message(streams, model, iter)
+                        .and_then(|ws| match &ws.kind {
+                            WebSocketMessageKind::Frame(f) => f.payload_text(),
+                            WebSocketMessageKind::Handshake { .. } => None,
+                        })
+                        .filter(|txt| txt.to_lowercase().contains(&fv))
+                        .is_some(),
+                    WebSocketStringFilterKeys::Opcode => model
+                        .value(iter, 6)
+                        .get::<&str>()
+                        .unwrap()
+                        .to_lowercase()
+                        .contains(&fv),
+                }
+            }
+            (_, Ok(filter_key), SearchCriteria::GreaterThan(filter_val, decimals)) => {
+                match filter_key {
+                    WebSocketNumericFilterKeys::PayloadLength => {
+                        get_ws_message(streams, model, iter)
+                            .filter(|ws| {
+                                ws.payload_length() * 10_usize.pow((*decimals).into()) > *filter_val
+                            })
+                            .is_some()
+                    }
+                }
+            }
+            _ => true,
+        }
+    }
+
+    fn requests_details_overlay(&self) -> bool {
+        false
+    }
+
+    fn add_details_to_scroll(
+        &mut self,
+        parent: &gtk::ScrolledWindow,
+        _overlay: Option<&gtk::Overlay>,
+        _bg_sender: mpsc::Sender<BgFunc>,
+        win_msg_sender: relm::StreamHandle<win::Msg>,
+    ) {
+        let component = parent.add_widget::<WebSocketCommEntry>((
+            TcpStreamId(0),
+            "0.0.0.0".parse().unwrap(),
+            WebSocketMessageData {
+                timestamp: Utc::now().naive_local(),
+                direction: WebSocketDirection::ClientToServer,
+                kind: WebSocketMessageKind::Handshake {
+                    first_line: "".to_string(),
+                    headers: vec![],
+                },
+            },
+            win_msg_sender,
+        ));
+        self.component = Some(component);
+    }
+
+    fn display_in_details_widget(
+        &self,
+        _bg_sender: mpsc::Sender<BgFunc>,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+    ) {
+        if let Some((ws_msg, client_server)) = self.get_msg_info(stream_id, msg_idx) {
+            self.component.as_ref().unwrap().stream().emit(
+                websocket_details_widget::Msg::DisplayDetails(
+                    client_server.client_ip,
+                    stream_id,
+                    ws_msg.clone(),
+                ),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+fn tests_parse_stream(
+    packets: Result<Vec<TSharkPacket>, String>,
+) -> Result<Vec<WebSocketMessageData>, String> {
+    let mut parser = WebSocketStreamsStore::default();
+    let sid = common_tests_parse_stream(&mut parser, packets)?;
+    Ok(parser.streams.get(&sid).unwrap().messages.clone())
+}
+
+#[test]
+fn should_recognize_accepted_websocket_upgrades() {
+    let packets = parse_test_xml_no_wrapper(
+        r#"
+      <pdml>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.1" />
+              <field name="ip.dst" show="10.0.0.2" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="53092" />
+            <field name="tcp.dstport" show="80" />
+          </proto>
+          <proto name="http">
+            <field name="" show="GET /chat HTTP/1.1\r\n"></field>
+            <field name="http.request.line" show="Upgrade: websocket" />
+          </proto>
+        </packet>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.2" />
+              <field name="ip.dst" show="10.0.0.1" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+          </proto>
+          <proto name="http">
+            <field name="" show="HTTP/1.1 101 Switching Protocols\r\n"></field>
+            <field name="http.response.line" show="Upgrade: websocket" />
+          </proto>
+        </packet>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.2" />
+              <field name="ip.dst" show="10.0.0.1" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53093" />
+          </proto>
+          <proto name="http">
+            <field name="" show="HTTP/1.1 426 Upgrade Required\r\n"></field>
+            <field name="http.response.line" show="Upgrade: websocket" />
+          </proto>
+        </packet>
+      </pdml>
+        "#,
+    )
+    .unwrap();
+    let store = WebSocketStreamsStore::default();
+    // the server may still refuse the upgrade
+    assert!(!store.is_my_message(&packets[0]));
+    assert!(store.is_my_message(&packets[1]));
+    assert!(!store.is_my_message(&packets[2]));
+}
+
+#[test]
+fn should_parse_handshake_and_frames() {
+    let parsed = tests_parse_stream(parse_test_xml_no_wrapper(
+        r#"
+      <pdml>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.1" />
+              <field name="ip.dst" show="10.0.0.2" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="53092" />
+            <field name="tcp.dstport" show="80" />
+          </proto>
+          <proto name="http">
+            <field name="" show="GET /chat HTTP/1.1\r\n"></field>
+            <field name="http.request.line" show="Host: example.com&#x0d;&#x0a;" />
+            <field name="http.request.line" show="Upgrade: websocket&#x0d;&#x0a;" />
+            <field name="http.host" show="example.com" />
+          </proto>
+        </packet>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.2" />
+              <field name="ip.dst" show="10.0.0.1" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+          </proto>
+          <proto name="http">
+            <field name="" show="HTTP/1.1 101 Switching Protocols\r\n"></field>
+            <field name="http.response.line" show="Upgrade: websocket" />
+          </proto>
+        </packet>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.1" />
+              <field name="ip.dst" show="10.0.0.2" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="53092" />
+            <field name="tcp.dstport" show="80" />
+          </proto>
+          <proto name="websocket" showname="WebSocket" size="11" pos="66">
+            <field name="websocket.fin" showname="1... .... = Fin: True" size="1" pos="66" show="1" value="1" unmaskedvalue="81"/>
+            <field name="websocket.rsv" showname=".000 .... = Reserved: 0x0" size="1" pos="66" show="0x00" value="0" unmaskedvalue="81"/>
+            <field name="websocket.opcode" showname=".... 0001 = Opcode: Text (1)" size="1" pos="66" show="1" value="1" unmaskedvalue="81"/>
+            <field name="websocket.mask" showname="1... .... = Mask: True" size="1" pos="67" show="1" value="1" unmaskedvalue="85"/>
+            <field name="websocket.payload_length" showname=".000 0101 = Payload length: 5" size="1" pos="67" show="5" value="5" unmaskedvalue="85"/>
+            <field name="websocket.masking_key" showname="Masking-Key: 37fa213d" size="4" pos="68" show="37:fa:21:3d" value="37fa213d"/>
+            <field name="websocket.masked_payload" showname="Masked payload" size="5" pos="72" show="7f:9f:4d:51:58" value="7f9f4d5158"/>
+          </proto>
+        </packet>
+        <packet>
+          <proto name="ip">
+              <field name="ip.src" show="10.0.0.2" />
+              <field name="ip.dst" show="10.0.0.1" />
+          </proto>
+          <proto name="tcp">
+            <field name="tcp.srcport" show="80" />
+            <field name="tcp.dstport" show="53092" />
+          </proto>
+          <proto name="websocket" showname="WebSocket" size="13" pos="66">
+            <field name="websocket.fin" show="1" value="1"/>
+            <field name="websocket.rsv" show="0x00" value="0"/>
+            <field name="websocket.opcode" show="1" value="1"/>
+            <field name="websocket.mask" show="0" value="0"/>
+            <field name="websocket.payload" showname="Payload" size="11" pos="68" show="7b:22:61:22:3a:20:31:7d" value="7b2261223a20317d">
+              <field name="websocket.payload.text" show="{&quot;a&quot;: 1}" />
+            </field>
+          </proto>
+          <proto name="websocket" showname="WebSocket" size="6" pos="79">
+            <field name="websocket.fin" show="1" value="1"/>
+            <field name="websocket.rsv" show="0x00" value="0"/>
+            <field name="websocket.opcode" show="8" value="8"/>
+            <field name="websocket.mask" show="0" value="0"/>
+            <field name="websocket.payload" showname="Payload" size="4" pos="81" show="03:f3:62:79" value="03f36279"/>
+          </proto>
+        </packet>
+      </pdml>
+        "#,
+    ))
+    .unwrap();
+    assert_eq!(5, parsed.len());
+    assert_eq!(
+        WebSocketMessageKind::Handshake {
+            first_line: "GET /chat HTTP/1.1".to_string(),
+            headers: vec![
+                ("Host".to_string(), "example.com".to_string()),
+                ("Upgrade".to_string(), "websocket".to_string())
+            ],
+        },
+        parsed[0].kind
+    );
+    assert_eq!(WebSocketDirection::ServerToClient, parsed[1].direction);
+    assert!(!parsed[1].is_error());
+    assert_eq!(
+        WebSocketMessageData {
+            timestamp: parsed[2].timestamp,
+            direction: WebSocketDirection::ClientToServer,
+            kind: WebSocketMessageKind::Frame(WebSocketFrame {
+                fin: true,
+                opcode: WebSocketOpcode::Text,
+                is_masked: true,
+                is_compressed: false,
+                payload: b"Hello".to_vec(),
+            }),
+        },
+        parsed[2]
+    );
+    assert_eq!(WebSocketDirection::ServerToClient, parsed[3].direction);
+    assert_eq!("{\"a\": 1}", parsed[3].summary());
+    match &parsed[4].kind {
+        WebSocketMessageKind::Frame(f) => {
+            assert_eq!(Some((1011, "by".to_string())), f.close_status())
+        }
+        k => panic!("expected a close frame, got {:?}", k),
+    }
+    assert!(parsed[4].is_error());
+}
+
+#[cfg(test)]
+fn test_frame(fin: bool, opcode: u8, is_compressed: bool, payload: &[u8]) -> TSharkWebSocketFrame {
+    TSharkWebSocketFrame {
+        fin,
+        is_compressed,
+        opcode,
+        is_masked: false,
+        payload: payload.to_vec(),
+    }
+}
+
+#[test]
+fn should_inflate_compressed_payloads() {
+    // "Hello" compressed with permessage-deflate, from RFC 7692 section 7.2.3.1
+    let mut inflater = MessageInflater {
+        no_context_takeover: true,
+        ..MessageInflater::default()
+    };
+    for _ in 0..2 {
+        assert_eq!(
+            b"Hello".to_vec(),
+            prepare_frame(
+                &mut inflater,
+                test_frame(true, 1, true, &[0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00])
+            )
+            .payload
+        );
+    }
+}
+
+#[test]
+fn should_inflate_messages_sharing_the_compression_context() {
+    // RFC 7692 section 7.2.3.2: the second "Hello" refers to the first one
+    let mut inflater = MessageInflater::default();
+    assert_eq!(
+        b"Hello".to_vec(),
+        prepare_frame(
+            &mut inflater,
+            test_frame(true, 1, true, &[0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00])
+        )
+        .payload
+    );
+    assert_eq!(
+        b"Hello".to_vec(),
+        prepare_frame(
+            &mut inflater,
+            test_frame(true, 1, true, &[0xf2, 0x00, 0x11, 0x00, 0x00])
+        )
+        .payload
+    );
+}
+
+#[test]
+fn should_reassemble_fragmented_compressed_messages() {
+    // RFC 7692 section 7.2.3.1, with a ping in the middle of the message
+    let mut inflater = MessageInflater::default();
+    let first = prepare_frame(
+        &mut inflater,
+        test_frame(false, 1, true, &[0xf2, 0x48, 0xcd]),
+    );
+    assert_eq!(vec![0xf2, 0x48, 0xcd], first.payload);
+    let ping = prepare_frame(&mut inflater, test_frame(true, 9, false, b"ping"));
+    assert_eq!(b"ping".to_vec(), ping.payload);
+    let last = prepare_frame(
+        &mut inflater,
+        test_frame(true, 0, false, &[0xc9, 0xc9, 0x07, 0x00]),
+    );
+    assert_eq!(b"Hello".to_vec(), last.payload);
+    assert_eq!(None, inflater.fragments);
+}
+
+#[test]
+fn should_read_the_negotiated_deflate_params() {
+    assert_eq!(
+        vec!["permessage-deflate", "client_no_context_takeover"],
+        negotiated_deflate_params(&[(
+            "Sec-WebSocket-Extensions".to_string(),
+            "x-webkit-deflate-frame, permessage-deflate; Client_No_Context_Takeover".to_string()
+        )])
+    );
+}
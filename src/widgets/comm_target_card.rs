@@ -77,6 +77,14 @@ impl CommTargetCardData {
         }
     }
 
+    /// the details of the stream which changed last win: the server parameters
+    /// can change during the session, for instance with SET application_name
+    pub fn update_summary_details(&mut self, details: &str) {
+        if let Some(d) = SummaryDetails::new(details.to_string(), self.to_key()) {
+            self.summary_details = Some(d);
+        }
+    }
+
     pub fn increase_incoming_session_count(&mut self) {
         self.incoming_session_count += 1;
     }
@@ -167,6 +175,7 @@ impl Widget for CommTargetCard {
                         margin_start: 2,
                         label: self.model.summary_details.as_ref().map(|d| d.details.as_str()).unwrap_or(""),
                         ellipsize: pango::EllipsizeMode::End,
+                        tooltip_text: self.model.summary_details.as_ref().map(|d| d.details.as_str()),
                         visible: self.model.summary_details.is_some(),
                     }
                 },
@@ -174,3 +183,28 @@ impl Widget for CommTargetCard {
         }
     }
 }
+
+#[test]
+fn should_replace_the_summary_details_when_parameters_change() {
+    let mut card = CommTargetCardData::new(
+        "10.0.0.1".parse().unwrap(),
+        NetworkPort(5432),
+        0,
+        BTreeSet::new(),
+        Icon::DATABASE,
+        None,
+        1,
+    );
+    card.update_summary_details("mydb (application_name: psql)");
+    card.update_summary_details("mydb (application_name: batch)");
+    assert_eq!(
+        Some("mydb (application_name: batch)"),
+        card.summary_details.as_ref().map(|d| d.details.as_str())
+    );
+    // the IP, we already display it
+    card.update_summary_details("10.0.0.1");
+    assert_eq!(
+        Some("mydb (application_name: batch)"),
+        card.summary_details.as_ref().map(|d| d.details.as_str())
+    );
+}
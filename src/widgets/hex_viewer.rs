@@ -0,0 +1,417 @@
+// a hex and ASCII dump of binary contents, with offsets. Used for binary
+// HTTP bodies and for postgres bytea values.
+use crate::http::http_streams_store::ContentEncoding;
+use crate::widgets::win;
+use gtk::prelude::*;
+use itertools::{Itertools, MinMaxResult};
+use relm::Widget;
+use relm_derive::{widget, Msg};
+use std::ops::Range;
+
+#[cfg(test)]
+use std::io::Write;
+
+const BYTES_PER_LINE: usize = 16;
+// "00000010  " then the hex bytes, with an extra space after the eighth one
+const HEX_START_COL: usize = 10;
+const HEX_WIDTH: usize = BYTES_PER_LINE * 3 + 1;
+// " |" between the hex bytes and the ASCII column
+const ASCII_START_COL: usize = HEX_START_COL + HEX_WIDTH + 2;
+const LINE_LENGTH: usize = ASCII_START_COL + BYTES_PER_LINE + 1;
+
+// the dump is about four times larger than the data
+pub const HEX_DUMP_LIMIT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Pdf,
+    Zip,
+    Gzip,
+    Zstd,
+    Png,
+    Jpeg,
+    Gif,
+    Protobuf,
+}
+
+impl FileType {
+    /// from the magic bytes at the start of the contents
+    pub fn guess(bytes: &[u8]) -> Option<FileType> {
+        const MAGIC_BYTES: &[(&[u8], FileType)] = &[
+            (b"%PDF-", FileType::Pdf),
+            (b"PK\x03\x04", FileType::Zip),
+            // an empty archive
+            (b"PK\x05\x06", FileType::Zip),
+            (b"\x1f\x8b", FileType::Gzip),
+            (b"\x28\xb5\x2f\xfd", FileType::Zstd),
+            (b"\x89PNG\r\n\x1a\n", FileType::Png),
+            (b"\xff\xd8\xff", FileType::Jpeg),
+            (b"GIF8", FileType::Gif),
+        ];
+        MAGIC_BYTES
+            .iter()
+            .find(|(magic, _)| bytes.starts_with(magic))
+            .map(|(_, t)| *t)
+            .or_else(|| Some(FileType::Protobuf).filter(|_| looks_like_protobuf(bytes)))
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            FileType::Pdf => "PDF document",
+            FileType::Zip => "ZIP archive",
+            FileType::Gzip => "gzip compressed data",
+            FileType::Zstd => "zstd compressed data",
+            FileType::Png => "PNG image",
+            FileType::Jpeg => "JPEG image",
+            FileType::Gif => "GIF image",
+            FileType::Protobuf => "probably a protobuf message",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileType::Pdf => "pdf",
+            FileType::Zip => "zip",
+            FileType::Gzip => "gz",
+            FileType::Zstd => "zst",
+            FileType::Png => "png",
+            FileType::Jpeg => "jpg",
+            FileType::Gif => "gif",
+            FileType::Protobuf => "bin",
+        }
+    }
+
+    fn compression(self) -> Option<ContentEncoding> {
+        match self {
+            FileType::Gzip => Some(ContentEncoding::Gzip),
+            FileType::Zstd => Some(ContentEncoding::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// gzip or zstd contents which tshark didn't decode, for instance because
+/// they're a payload of their own and not a content-encoding.
+pub fn decompress(bytes: &[u8]) -> Option<Vec<u8>> {
+    FileType::guess(bytes)?.compression()?.decode(bytes)
+}
+
+/// the protobuf wire format has no magic bytes: we check that the contents
+/// can be read as a sequence of fields with valid keys and lengths.
+fn looks_like_protobuf(bytes: &[u8]) -> bool {
+    fn read_varint(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+        let mut result = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = *bytes.get(*pos)?;
+            *pos += 1;
+            result |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+        None
+    }
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let key = match read_varint(bytes, &mut pos) {
+            Some(k) if k >> 3 > 0 => k,
+            _ => return false,
+        };
+        let field_len = match key & 7 {
+            0 => read_varint(bytes, &mut pos).map(|_| 0),
+            1 => Some(8),
+            2 => read_varint(bytes, &mut pos),
+            5 => Some(4),
+            _ => None,
+        };
+        match field_len {
+            Some(len) if len <= (bytes.len() - pos) as u64 => pos += len as usize,
+            _ => return false,
+        }
+    }
+    !bytes.is_empty()
+}
+
+pub fn hex_dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(line_idx, line)| {
+            let hex: String = line
+                .iter()
+                .enumerate()
+                .map(|(i, b)| format!("{}{:02x} ", if i == 8 { " " } else { "" }, b))
+                .collect();
+            let ascii: String = line
+                .iter()
+                .map(|b| {
+                    if b.is_ascii_graphic() || *b == b' ' {
+                        *b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$} |{}|",
+                line_idx * BYTES_PER_LINE,
+                hex,
+                ascii,
+                width = HEX_WIDTH
+            )
+        })
+        .join("\n")
+}
+
+/// the index in its line of the byte displayed at this column, if any
+fn column_byte(col: usize, in_ascii: bool) -> Option<usize> {
+    if in_ascii {
+        return Some(col)
+            .filter(|c| (ASCII_START_COL..ASCII_START_COL + BYTES_PER_LINE).contains(c))
+            .map(|c| c - ASCII_START_COL);
+    }
+    (0..BYTES_PER_LINE).find(|i| {
+        let start = HEX_START_COL + i * 3 + if *i >= 8 { 1 } else { 0 };
+        col == start || col == start + 1
+    })
+}
+
+/// the bytes of which a character is displayed between these two (line, column)
+/// positions of the dump, the end being excluded. A selection spanning several
+/// lines covers both the hex and the ASCII columns, we only consider the one
+/// where it starts.
+pub fn selected_bytes(start: (usize, usize), end: (usize, usize)) -> Option<Range<usize>> {
+    let in_ascii = start.1 >= ASCII_START_COL;
+    let selected = (start.0..=end.0).flat_map(|line| {
+        let from = if line == start.0 { start.1 } else { 0 };
+        let to = if line == end.0 { end.1 } else { LINE_LENGTH };
+        (from..to)
+            .filter_map(move |col| column_byte(col, in_ascii))
+            .map(move |b| line * BYTES_PER_LINE + b)
+    });
+    match selected.minmax() {
+        MinMaxResult::NoElements => None,
+        MinMaxResult::OneElement(b) => Some(b..b + 1),
+        MinMaxResult::MinMax(from, to) => Some(from..to + 1),
+    }
+}
+
+#[derive(Msg, Debug)]
+pub enum Msg {
+    DisplayBytes(Vec<u8>),
+    CopyAsHex,
+    CopyAsBase64,
+    Decompress,
+    ShowCompressed,
+}
+
+pub struct Model {
+    win_msg_sender: relm::StreamHandle<win::Msg>,
+    // the original contents, then the result of each decompression
+    layers: Vec<Vec<u8>>,
+}
+
+#[widget]
+impl Widget for HexViewer {
+    fn model(_relm: &relm::Relm<Self>, win_msg_sender: relm::StreamHandle<win::Msg>) -> Model {
+        Model {
+            win_msg_sender,
+            layers: vec![],
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::DisplayBytes(bytes) => {
+                self.model.layers = vec![bytes];
+                self.refresh_dump();
+            }
+            Msg::CopyAsHex => {
+                let hex = hex::encode(self.selected_or_all_bytes());
+                self.copy_to_clipboard(&hex);
+            }
+            Msg::CopyAsBase64 => {
+                let b64 = base64::encode(self.selected_or_all_bytes());
+                self.copy_to_clipboard(&b64);
+            }
+            Msg::Decompress => match self.model.layers.last().and_then(|b| decompress(b)) {
+                Some(decompressed) => {
+                    self.model.layers.push(decompressed);
+                    self.refresh_dump();
+                }
+                None => self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                    Some("Failed decompressing the contents".to_string()),
+                    win::InfobarOptions::ShowCloseButton,
+                )),
+            },
+            Msg::ShowCompressed => {
+                self.model.layers.pop();
+                self.refresh_dump();
+            }
+        }
+    }
+
+    fn refresh_dump(&self) {
+        let bytes = Self::current_bytes(&self.model.layers);
+        if let Some(buffer) = self.widgets.hex_textview.buffer() {
+            buffer.set_text(&hex_dump(
+                &bytes[..std::cmp::min(bytes.len(), HEX_DUMP_LIMIT_BYTES)],
+            ));
+        }
+    }
+
+    fn current_bytes(layers: &[Vec<u8>]) -> &[u8] {
+        layers.last().map(|b| b.as_slice()).unwrap_or(&[])
+    }
+
+    /// all the bytes if there's no selection, including those over the display limit
+    fn selected_or_all_bytes(&self) -> &[u8] {
+        let bytes = Self::current_bytes(&self.model.layers);
+        let range = self
+            .widgets
+            .hex_textview
+            .buffer()
+            .and_then(|b| b.selection_bounds())
+            .and_then(|(start, end)| {
+                selected_bytes(
+                    (start.line() as usize, start.line_offset() as usize),
+                    (end.line() as usize, end.line_offset() as usize),
+                )
+            });
+        match range {
+            Some(r) => {
+                &bytes[std::cmp::min(r.start, bytes.len())..std::cmp::min(r.end, bytes.len())]
+            }
+            None => bytes,
+        }
+    }
+
+    fn copy_to_clipboard(&self, contents: &str) {
+        if let Some(clip) = gtk::Clipboard::default(&self.widgets.hex_textview.display()) {
+            clip.set_text(contents);
+            self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                Some("Copied to the clipboard".to_string()),
+                win::InfobarOptions::TimeLimitedWithCloseButton,
+            ));
+        }
+    }
+
+    fn info_markup(layers: &[Vec<u8>]) -> String {
+        let bytes = Self::current_bytes(layers);
+        let mut result = format!("{} bytes", bytes.len());
+        if let Some(file_type) = FileType::guess(bytes) {
+            result = format!(
+                "<b>{}</b>, {}",
+                glib::markup_escape_text(file_type.description()),
+                result
+            );
+        }
+        if layers.len() > 1 {
+            result.push_str(", decompressed");
+        }
+        if bytes.len() > HEX_DUMP_LIMIT_BYTES {
+            result.push_str(&format!(
+                ", <i>only the first {} are displayed</i>",
+                HEX_DUMP_LIMIT_BYTES
+            ));
+        }
+        result
+    }
+
+    fn can_decompress(layers: &[Vec<u8>]) -> bool {
+        FileType::guess(Self::current_bytes(layers))
+            .and_then(FileType::compression)
+            .is_some()
+    }
+
+    view! {
+        gtk::Box {
+            orientation: gtk::Orientation::Vertical,
+            spacing: 5,
+            gtk::Box {
+                spacing: 5,
+                gtk::Label {
+                    markup: &Self::info_markup(&self.model.layers),
+                    hexpand: true,
+                    xalign: 0.0,
+                },
+                gtk::Button {
+                    label: "Decompress and view",
+                    visible: Self::can_decompress(&self.model.layers),
+                    clicked => Msg::Decompress,
+                },
+                gtk::Button {
+                    label: "Show compressed",
+                    visible: self.model.layers.len() > 1,
+                    clicked => Msg::ShowCompressed,
+                },
+                gtk::Button {
+                    label: "Copy as hex",
+                    clicked => Msg::CopyAsHex,
+                },
+                gtk::Button {
+                    label: "Copy as base64",
+                    clicked => Msg::CopyAsBase64,
+                },
+            },
+            gtk::ScrolledWindow {
+                height_request: 300,
+                vexpand: true,
+                #[name="hex_textview"]
+                gtk::TextView {
+                    editable: false,
+                    cursor_visible: false,
+                    monospace: true,
+                },
+            },
+        }
+    }
+}
+
+#[test]
+fn should_format_hex_dump() {
+    assert_eq!(
+        "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|\n\
+         00000010  41                                                |A|",
+        hex_dump(b"Hello, world!\n\x00\xffA")
+    );
+}
+
+#[test]
+fn should_map_selection_to_bytes() {
+    // "6c 6c" in the hex part: bytes 2 and 3
+    assert_eq!(Some(2..4), selected_bytes((0, 16), (0, 21)));
+    // the separator between the two groups of eight bytes is not a byte
+    assert_eq!(Some(7..9), selected_bytes((0, 31), (0, 36)));
+    // "lo" in the ASCII part
+    assert_eq!(Some(3..5), selected_bytes((0, 64), (0, 66)));
+    // from the end of the first line to the start of the second one
+    assert_eq!(Some(15..17), selected_bytes((0, 57), (1, 12)));
+    assert_eq!(Some(14..18), selected_bytes((0, 75), (1, 63)));
+    // only the offset
+    assert_eq!(None, selected_bytes((1, 0), (1, 8)));
+}
+
+#[test]
+fn should_guess_file_types() {
+    assert_eq!(Some(FileType::Pdf), FileType::guess(b"%PDF-1.7\n..."));
+    assert_eq!(Some(FileType::Zip), FileType::guess(b"PK\x03\x04\x14\x00"));
+    // field 1, varint 150; field 2, string "testing"
+    assert_eq!(
+        Some(FileType::Protobuf),
+        FileType::guess(b"\x08\x96\x01\x12\x07testing")
+    );
+    // the length of the string goes past the end
+    assert_eq!(None, FileType::guess(b"\x08\x96\x01\x12\x09testing"));
+    assert_eq!(None, FileType::guess(b""));
+}
+
+#[test]
+fn should_decompress_nested_gzip() {
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(b"nested payload").unwrap();
+    let gzipped = encoder.finish().unwrap();
+    assert_eq!(Some(FileType::Gzip), FileType::guess(&gzipped));
+    assert_eq!(Some(b"nested payload".to_vec()), decompress(&gzipped));
+    assert_eq!(None, decompress(b"%PDF-1.7"));
+}
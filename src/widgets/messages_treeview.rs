@@ -35,11 +35,14 @@ pub struct MessagesTreeviewState {
     message_treeviews: Vec<(gtk::TreeView, TreeViewSignals)>,
     details_adjustments: Vec<gtk::Adjustment>,
     cur_liststore: Option<(CommTargetCardKey, gtk::ListStore)>,
+    // stream id, message index and the number of server-sent events we displayed
+    displayed_details: Option<(TcpStreamId, usize, usize)>,
 }
 
 impl MessagesTreeviewState {
     pub fn file_closed(&mut self) {
         self.cur_liststore = None;
+        self.displayed_details = None;
     }
 }
 
@@ -68,6 +71,7 @@ pub fn init_grids_and_panes(
         message_treeviews,
         details_adjustments,
         cur_liststore: None,
+        displayed_details: None,
     }
 }
 
@@ -358,6 +362,27 @@ fn get_store_holding_model(
     (tv, store_holding_model)
 }
 
+/// the stream id & message index of the rows currently displayed in the grid,
+/// taking into account the search filter
+pub fn visible_messages(
+    tv_state: &MessagesTreeviewState,
+    store_index: usize,
+) -> HashSet<(TcpStreamId, u32)> {
+    let (ref tv, ref _signals) = tv_state.message_treeviews.get(store_index).unwrap();
+    let mut result = HashSet::new();
+    if let Some(model) = tv.model() {
+        if let Some(iter) = model.iter_first() {
+            loop {
+                result.insert(custom_streams_store::get_message_helper(&model, &iter));
+                if !model.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+    }
+    result
+}
+
 fn matches_filter(
     mp: &dyn CustomStreamsStore,
     f: &search_expr::SearchExpr,
@@ -578,7 +603,7 @@ fn packets_added_trigger_events(
 }
 
 pub fn handle_display_details(
-    state: &MessagesTreeviewState,
+    state: &mut MessagesTreeviewState,
     bg_sender: mpsc::Sender<BgFunc>,
     streams: &Streams,
     stream_id: TcpStreamId,
@@ -590,5 +615,39 @@ pub fn handle_display_details(
     if let Some(store_index) = streams.get_store_index(stream_id) {
         let store = streams.get_streams_store(store_index);
         store.display_in_details_widget(bg_sender, stream_id, msg_idx);
+        state.displayed_details = Some((
+            stream_id,
+            msg_idx,
+            server_sent_events_count(streams, stream_id, msg_idx),
+        ));
+    }
+}
+
+fn server_sent_events_count(streams: &Streams, stream_id: TcpStreamId, msg_idx: usize) -> usize {
+    streams
+        .stream_http_messages(stream_id)
+        .and_then(|msgs| msgs.get(msg_idx))
+        .and_then(|m| m.response.as_ref())
+        .map(|r| r.server_sent_events.len())
+        .unwrap_or(0)
+}
+
+/// in live captures, event streams get new events after we displayed
+/// them. Send the new ones to the details, keeping the scroll position.
+pub fn refresh_details_new_events(
+    state: &mut MessagesTreeviewState,
+    streams: &Streams,
+    stream_id: TcpStreamId,
+) {
+    let (msg_idx, event_count) = match state.displayed_details {
+        Some((displayed_stream_id, msg_idx, event_count)) if displayed_stream_id == stream_id => {
+            (msg_idx, event_count)
+        }
+        _ => return,
+    };
+    let new_event_count = server_sent_events_count(streams, stream_id, msg_idx);
+    if new_event_count > event_count {
+        streams.display_new_server_sent_events(stream_id, msg_idx, event_count);
+        state.displayed_details = Some((stream_id, msg_idx, new_event_count));
     }
 }
@@ -1,6 +1,7 @@
 pub mod comm_info_header;
 mod comm_target_card;
 pub mod headerbar_search;
+pub mod hex_viewer;
 mod ips_and_streams_treeview;
 mod messages_treeview;
 mod preferences;
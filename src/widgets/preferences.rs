@@ -11,12 +11,16 @@ pub enum Msg {
     CustomTcpDumpBufferSizeToggled(bool),
     CustomTcpDumpBufferSizeValueChanged,
     TcpdumpUsePkexecChanged(bool),
+    PickGrpcProtoPath(gtk::FileChooserAction),
+    ClearGrpcProtoPath,
 }
 
 pub struct Model {
+    win_msg_sender: relm::StreamHandle<win::Msg>,
     prefer_dark_theme: bool,
     custom_tcpdump_buffer_size_kib: Option<usize>,
     tcpdump_use_pkexec_if_possible: bool,
+    grpc_proto_path: Option<String>,
 }
 
 #[widget]
@@ -35,12 +39,14 @@ impl Widget for Preferences {
             .set_increments(128.0, 128.0);
     }
 
-    fn model() -> Model {
+    fn model(_relm: &relm::Relm<Self>, win_msg_sender: relm::StreamHandle<win::Msg>) -> Model {
         let config = Config::read_config();
         Model {
+            win_msg_sender,
             prefer_dark_theme: config.prefer_dark_theme,
             custom_tcpdump_buffer_size_kib: config.custom_tcpdump_buffer_size_kib,
             tcpdump_use_pkexec_if_possible: config.tcpdump_use_pkexec_if_possible,
+            grpc_proto_path: config.grpc_proto_path,
         }
     }
 
@@ -49,6 +55,7 @@ impl Widget for Preferences {
             prefer_dark_theme: self.model.prefer_dark_theme,
             custom_tcpdump_buffer_size_kib: self.model.custom_tcpdump_buffer_size_kib,
             tcpdump_use_pkexec_if_possible: self.model.tcpdump_use_pkexec_if_possible,
+            grpc_proto_path: self.model.grpc_proto_path.clone(),
         }
     }
 
@@ -74,6 +81,35 @@ impl Widget for Preferences {
                 self.model.tcpdump_use_pkexec_if_possible = t;
                 self.get_config().save_config(&self.widgets.prefs_window);
             }
+            Msg::PickGrpcProtoPath(action) => {
+                let dialog = gtk::builders::FileChooserNativeBuilder::new()
+                    .action(action)
+                    .title(if action == gtk::FileChooserAction::SelectFolder {
+                        "Select the folder containing the .proto files"
+                    } else {
+                        "Select a protobuf descriptor set"
+                    })
+                    .modal(true)
+                    .transient_for(&self.widgets.prefs_window)
+                    .build();
+                if dialog.run() == gtk::ResponseType::Accept {
+                    self.model.grpc_proto_path =
+                        dialog.filename().map(|f| f.to_string_lossy().to_string());
+                    self.get_config().save_config(&self.widgets.prefs_window);
+                    self.model
+                        .win_msg_sender
+                        .emit(win::Msg::GrpcProtoPathChanged(
+                            self.model.grpc_proto_path.clone(),
+                        ));
+                }
+            }
+            Msg::ClearGrpcProtoPath => {
+                self.model.grpc_proto_path = None;
+                self.get_config().save_config(&self.widgets.prefs_window);
+                self.model
+                    .win_msg_sender
+                    .emit(win::Msg::GrpcProtoPathChanged(None));
+            }
         }
     }
 
@@ -123,6 +159,36 @@ impl Widget for Preferences {
                     toggled(t) => Msg::TcpdumpUsePkexecChanged(t.is_active()),
                     visible: cfg!(target_os = "linux") && !win::is_flatpak()
                 },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 5,
+                    gtk::Label {
+                        label: "gRPC protobuf definitions:",
+                    },
+                    gtk::Label {
+                        label: self.model.grpc_proto_path.as_deref().unwrap_or("none (field numbers only)"),
+                        hexpand: true,
+                        xalign: 0.0,
+                        ellipsize: pango::EllipsizeMode::Start,
+                    },
+                    gtk::Button {
+                        label: "Folder...",
+                        tooltip_text: Some("A folder containing .proto files"),
+                        clicked => Msg::PickGrpcProtoPath(gtk::FileChooserAction::SelectFolder),
+                    },
+                    gtk::Button {
+                        label: "Descriptor set...",
+                        tooltip_text: Some("A file generated by protoc --include_imports --descriptor_set_out"),
+                        clicked => Msg::PickGrpcProtoPath(gtk::FileChooserAction::Open),
+                    },
+                    gtk::Button {
+                        always_show_image: true,
+                        image: Some(&gtk::Image::from_icon_name(
+                            Some("edit-clear-symbolic"), gtk::IconSize::Menu)),
+                        sensitive: self.model.grpc_proto_path.is_some(),
+                        clicked => Msg::ClearGrpcProtoPath,
+                    },
+                },
             }
         }
     }
@@ -11,9 +11,14 @@ use super::recent_file_item::RecentFileItem;
 use crate::config;
 use crate::config::Config;
 use crate::custom_streams_store::ClientServerInfo;
+use crate::http::har;
+use crate::http::http_streams_store::HttpStreamsStore;
+use crate::http2::http2_streams_store::Http2StreamsStore;
+use crate::http2::proto_schema::ConfiguredProtoSchema;
 use crate::icons::Icon;
 use crate::packets_read;
 use crate::packets_read::{InputStep, ParseInputStep, TSharkInputType};
+use crate::pgsql::postgres_streams_store::PostgresStreamsStore;
 use crate::search_expr;
 use crate::streams::{SessionChangeType, Streams};
 use crate::tshark_communication;
@@ -36,6 +41,7 @@ use std::path::PathBuf;
 use std::process::Child;
 use std::process::Command;
 use std::sync::mpsc;
+use std::sync::Arc;
 
 const CSS_DATA: &[u8] = include_bytes!("../../resources/style.css");
 const SHORTCUTS_UI: &str = include_str!("shortcuts.ui");
@@ -45,6 +51,14 @@ const LOADING_STACK_NAME: &str = "loading";
 const NORMAL_STACK_NAME: &str = "normal";
 
 const PCAP_MIME_TYPE: &str = "application/vnd.tcpdump.pcap";
+const HAR_MIME_TYPE: &str = "application/x-har+json";
+
+fn is_har_file(fname: &Path) -> bool {
+    fname
+        .extension()
+        .filter(|e| e.eq_ignore_ascii_case("har"))
+        .is_some()
+}
 
 pub fn is_flatpak() -> bool {
     // The Flatpak environment can be detected at runtime by looking for a file named /.flatpak-info. https://github.com/flathub/flathub/wiki/App-Maintenance
@@ -59,6 +73,13 @@ pub enum InfobarOptions {
     TimeLimitedWithCloseButton,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum HarExportScope {
+    SelectedServer,
+    VisibleRows,
+    WholeCapture,
+}
+
 #[derive(Msg, Debug)]
 pub enum Msg {
     SearchClicked,
@@ -69,6 +90,7 @@ pub enum Msg {
     DisplayShortcuts,
     CaptureToggled,
     SaveCapture,
+    ExportHar(HarExportScope),
     ChildProcessDied,
 
     DragDataReceived(gdk::DragContext, gtk::SelectionData),
@@ -89,6 +111,10 @@ pub enum Msg {
     SelectCardFromRemoteIpsAndStreams(CommTargetCardData, Vec<IpAddr>, Vec<TcpStreamId>),
 
     DisplayDetails(TcpStreamId, u32),
+    DisplayStatementStats(TcpStreamId),
+
+    GrpcProtoPathChanged(Option<String>),
+    GotGrpcProtoSchema(Option<Arc<ConfiguredProtoSchema>>),
 
     Quit,
 }
@@ -125,6 +151,9 @@ pub struct Model {
     _loaded_data_channel: relm::Channel<ParseInputStep>,
     loaded_data_sender: relm::Sender<ParseInputStep>,
 
+    _grpc_schema_channel: relm::Channel<Option<Arc<ConfiguredProtoSchema>>>,
+    grpc_schema_sender: relm::Sender<Option<Arc<ConfiguredProtoSchema>>>,
+
     comm_targets_components: HashMap<CommTargetCardKey, Component<CommTargetCard>>,
     _recent_file_item_components: Vec<Component<RecentFileItem>>,
 
@@ -218,6 +247,10 @@ impl Widget for Win {
             &mut self.model.streams,
         ));
 
+        self.model.relm.stream().emit(Msg::GrpcProtoPathChanged(
+            Config::read_config().grpc_proto_path,
+        ));
+
         self.model.ips_and_streams_treeview_state =
             Some(ips_and_streams_treeview::init_remote_ip_streams_tv(
                 &self.widgets.remote_ips_streams_treeview,
@@ -283,7 +316,8 @@ impl Widget for Win {
                 i.last_application().map(|a| a.to_string()) == Some("hotwire".to_string())
                     // if we don't also filter by mimetype, we get also the files we saved (for instance
                     // when saving http bodies to files on disk)
-                    && i.mime_type() == Some(PCAP_MIME_TYPE.into())
+                    && (i.mime_type() == Some(PCAP_MIME_TYPE.into())
+                        || i.mime_type() == Some(HAR_MIME_TYPE.into()))
             })
             .take(5)
             .flat_map(|fi| fi.uri())
@@ -335,6 +369,13 @@ impl Widget for Win {
             })
         };
 
+        let (_grpc_schema_channel, grpc_schema_sender) = {
+            let stream = relm.stream().clone();
+            relm::Channel::new(move |schema| {
+                stream.emit(Msg::GotGrpcProtoSchema(schema));
+            })
+        };
+
         // the problem i'm trying to fix is the user triggering
         // a capture... so we call pkexec to launch tcpdump.. but the user closes pkexec and
         // so tcpdump will never be launched.
@@ -369,6 +410,8 @@ impl Widget for Win {
             selected_card: None,
             loaded_data_sender,
             _loaded_data_channel,
+            grpc_schema_sender,
+            _grpc_schema_channel,
             messages_treeview_state: None,
             ips_and_streams_treeview_state: None,
             sidebar_selection_change_signal_id: None,
@@ -433,6 +476,9 @@ impl Widget for Win {
             Msg::SaveCapture => {
                 self.handle_save_capture();
             }
+            Msg::ExportHar(scope) => {
+                self.handle_export_har(scope);
+            }
             Msg::ChildProcessDied => {
                 // the problem i'm trying to fix is the user triggering
                 // a capture... so we call pkexec to launch tcpdump.. but the user closes pkexec and
@@ -564,13 +610,41 @@ impl Widget for Win {
             }
             Msg::DisplayDetails(stream_id, idx) => {
                 messages_treeview::handle_display_details(
-                    self.model.messages_treeview_state.as_ref().unwrap(),
+                    self.model.messages_treeview_state.as_mut().unwrap(),
                     self.model.bg_sender.clone(),
                     &self.model.streams,
                     stream_id,
                     idx as usize,
                 );
             }
+            Msg::DisplayStatementStats(stream_id) => {
+                if let Some(store) = self.model.streams.get_store::<PostgresStreamsStore>() {
+                    store.display_statement_stats(stream_id);
+                }
+            }
+            Msg::GrpcProtoPathChanged(path) => {
+                // loading the .proto files can take a while
+                let sender = self.model.grpc_schema_sender.clone();
+                self.model
+                    .bg_sender
+                    .send(BgFunc::new(move || {
+                        sender
+                            .send(
+                                path.clone()
+                                    .map(|p| Arc::new(ConfiguredProtoSchema::load(p))),
+                            )
+                            .unwrap();
+                    }))
+                    .unwrap();
+            }
+            Msg::GotGrpcProtoSchema(schema) => {
+                if let Some(store) = self.model.streams.get_store::<HttpStreamsStore>() {
+                    store.set_grpc_schema(schema.clone());
+                }
+                if let Some(store) = self.model.streams.get_store::<Http2StreamsStore>() {
+                    store.set_grpc_schema(schema);
+                }
+            }
             Msg::Quit => {
                 // needed for the pcap save temp files at least
                 if let Err(e) =
@@ -798,6 +872,11 @@ impl Widget for Win {
                 &self.model.streams,
                 follow_packets,
             );
+            messages_treeview::refresh_details_new_events(
+                tv_state,
+                &self.model.streams,
+                tcp_stream_id,
+            );
 
             if let Some(cs) = client_server_info {
                 let protocol_icon = self
@@ -960,10 +1039,8 @@ impl Widget for Win {
                 card.increase_incoming_session_count();
             }
             card.remote_hosts.insert(client_server_info.client_ip);
-            if card.summary_details.is_none() {
-                if let Some(details) = summary_details {
-                    card.summary_details = SummaryDetails::new(details.to_string(), card_key);
-                }
+            if let Some(details) = summary_details {
+                card.update_summary_details(details);
             }
             self.model
                 .comm_targets_components
@@ -1026,8 +1103,10 @@ impl Widget for Win {
     }
 
     fn display_preferences(&mut self) {
-        self.model.prefs_win =
-            Some(relm::init::<Preferences>(()).expect("Error initializing the preferences window"));
+        self.model.prefs_win = Some(
+            relm::init::<Preferences>(self.model.relm.stream().clone())
+                .expect("Error initializing the preferences window"),
+        );
         let prefs_win = self.model.prefs_win.as_ref().unwrap();
         prefs_win
             .widget()
@@ -1199,6 +1278,63 @@ impl Widget for Win {
         }
     }
 
+    fn handle_export_har(&mut self, scope: HarExportScope) {
+        let selected_card = self.model.selected_card.as_ref();
+        let exchanges = match (scope, selected_card) {
+            (HarExportScope::SelectedServer, Some(card)) => {
+                let card_key = card.to_key();
+                self.model
+                    .streams
+                    .http_exchanges(|store_index, _stream_id, cs, _idx| {
+                        store_index == card_key.store_index && card_key.matches_server(cs)
+                    })
+            }
+            (HarExportScope::VisibleRows, Some(card)) => {
+                let visible = messages_treeview::visible_messages(
+                    self.model.messages_treeview_state.as_ref().unwrap(),
+                    card.store_index,
+                );
+                self.model
+                    .streams
+                    .http_exchanges(|store_index, stream_id, _cs, idx| {
+                        store_index == card.store_index
+                            && visible.contains(&(stream_id, idx as u32))
+                    })
+            }
+            (HarExportScope::WholeCapture, _) => {
+                self.model.streams.http_exchanges(|_, _, _, _| true)
+            }
+            (_, None) => vec![],
+        };
+        if exchanges.is_empty() {
+            Self::display_error_block(
+                "Nothing to export",
+                Some("There are no HTTP exchanges to export"),
+            );
+            return;
+        }
+        let dialog = gtk::builders::FileChooserNativeBuilder::new()
+            .action(gtk::FileChooserAction::Save)
+            .title("Select file")
+            .modal(true)
+            .do_overwrite_confirmation(true)
+            .build();
+        let filter = gtk::FileFilter::new();
+        filter.add_pattern("*.har");
+        dialog.set_filter(&filter);
+        dialog.set_current_name("capture.har");
+        if dialog.run() == gtk::ResponseType::Accept {
+            if let Some(fname) = dialog.filename() {
+                if let Err(e) = har::export_har(&exchanges)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| std::fs::write(fname, contents).map_err(|e| e.to_string()))
+                {
+                    Self::display_error_block("Error exporting to HAR", Some(&e));
+                }
+            }
+        }
+    }
+
     fn open_file(&mut self) {
         let dialog = gtk::builders::FileChooserNativeBuilder::new()
             .action(gtk::FileChooserAction::Open)
@@ -1208,6 +1344,7 @@ impl Widget for Win {
         let filter = gtk::FileFilter::new();
         filter.add_pattern("*.pcap");
         filter.add_pattern("*.pcapng");
+        filter.add_pattern("*.har");
         dialog.set_filter(&filter);
         if dialog.run() == gtk::ResponseType::Accept {
             if let Some(fname) = dialog.filename() {
@@ -1294,7 +1431,12 @@ impl Widget for Win {
                 let recent_data = gtk::RecentData {
                     display_name: None,
                     description: None,
-                    mime_type: PCAP_MIME_TYPE.to_string(),
+                    mime_type: if is_har_file(fname) {
+                        HAR_MIME_TYPE
+                    } else {
+                        PCAP_MIME_TYPE
+                    }
+                    .to_string(),
                     app_name: "hotwire".to_string(),
                     app_exec: "hotwire".to_string(),
                     groups: vec![],
@@ -1348,6 +1490,18 @@ impl Widget for Win {
         }
 
         let s = self.model.loaded_data_sender.clone();
+        if is_har_file(&fname) {
+            self.widgets.loading_tshark_label.set_visible(false);
+            self.widgets.loading_parsing_label.set_visible(true);
+            self.model
+                .bg_sender
+                .send(BgFunc::new(move || {
+                    packets_read::read_har_file(&fname, s.clone());
+                }))
+                .unwrap();
+            self.refresh_recent_files();
+            return;
+        }
         let filter = self.model.streams.tshark_filter_string();
         // self.init_remote_ips_streams_tree();
         self.model
@@ -1460,6 +1614,21 @@ impl Widget for Win {
                                     margin_start: 10,
                                     margin_end: 10,
                                     margin_bottom: 10,
+                                    gtk::ModelButton {
+                                        label: "Export Server as HAR...",
+                                        hexpand: true,
+                                        clicked => Msg::ExportHar(HarExportScope::SelectedServer),
+                                    },
+                                    gtk::ModelButton {
+                                        label: "Export Visible Rows as HAR...",
+                                        hexpand: true,
+                                        clicked => Msg::ExportHar(HarExportScope::VisibleRows),
+                                    },
+                                    gtk::ModelButton {
+                                        label: "Export Capture as HAR...",
+                                        hexpand: true,
+                                        clicked => Msg::ExportHar(HarExportScope::WholeCapture),
+                                    },
                                     gtk::ModelButton {
                                         label: "Preferences",
                                         hexpand: true,